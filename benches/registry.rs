@@ -0,0 +1,107 @@
+//! Benchmarks for concurrent registry lookups across many tenants.
+//!
+//! `rwlock_hashmap` reproduces the previous `RwLock<HashMap>` registry state as a baseline for
+//! the sharded map now backing [`Registry`].
+
+// std
+use std::{collections::HashMap, hint::black_box, sync::Arc};
+// crates.io
+use criterion::{Criterion, criterion_group, criterion_main};
+use jwks_cache::{IdentityProviderRegistration, Registry};
+use tokio::{runtime::Runtime, sync::RwLock};
+
+const TENANTS: usize = 4_096;
+const TASKS: usize = 32;
+const LOOKUPS_PER_TASK: usize = 512;
+
+type Baseline = RwLock<HashMap<(String, String), Arc<IdentityProviderRegistration>>>;
+
+fn registration(index: usize) -> IdentityProviderRegistration {
+	IdentityProviderRegistration::new(
+		format!("tenant-{index}"),
+		"idp",
+		format!("https://tenant-{index}.example.com/.well-known/jwks.json"),
+	)
+	.expect("registration")
+}
+
+fn concurrent_lookups(c: &mut Criterion) {
+	let runtime = Runtime::new().expect("runtime");
+	let registry = Registry::new();
+	let baseline: Arc<Baseline> = Arc::new(RwLock::new(HashMap::new()));
+
+	runtime.block_on(async {
+		for index in 0..TENANTS {
+			let effective = registry.register(registration(index)).await.expect("register");
+
+			baseline
+				.write()
+				.await
+				.insert((effective.tenant_id.clone(), effective.provider_id.clone()), effective);
+		}
+	});
+
+	let tenants: Arc<Vec<String>> = Arc::new((0..TENANTS).map(|i| format!("tenant-{i}")).collect());
+	let mut group = c.benchmark_group("concurrent_lookups");
+
+	group.bench_function("registry", |b| {
+		b.to_async(&runtime).iter(|| {
+			let registry = registry.clone();
+			let tenants = tenants.clone();
+
+			async move {
+				let tasks = (0..TASKS).map(|task| {
+					let registry = registry.clone();
+					let tenants = tenants.clone();
+
+					tokio::spawn(async move {
+						for lookup in 0..LOOKUPS_PER_TASK {
+							let tenant = &tenants[(task * LOOKUPS_PER_TASK + lookup) % TENANTS];
+
+							black_box(
+								registry
+									.effective_registration(tenant, "idp")
+									.await
+									.expect("lookup"),
+							);
+						}
+					})
+				});
+
+				for task in tasks.collect::<Vec<_>>() {
+					task.await.expect("join");
+				}
+			}
+		});
+	});
+	group.bench_function("rwlock_hashmap", |b| {
+		b.to_async(&runtime).iter(|| {
+			let baseline = baseline.clone();
+			let tenants = tenants.clone();
+
+			async move {
+				let tasks = (0..TASKS).map(|task| {
+					let baseline = baseline.clone();
+					let tenants = tenants.clone();
+
+					tokio::spawn(async move {
+						for lookup in 0..LOOKUPS_PER_TASK {
+							let tenant = &tenants[(task * LOOKUPS_PER_TASK + lookup) % TENANTS];
+							let key = (tenant.clone(), "idp".to_owned());
+
+							black_box(baseline.read().await.get(&key).cloned().expect("lookup"));
+						}
+					})
+				});
+
+				for task in tasks.collect::<Vec<_>>() {
+					task.await.expect("join");
+				}
+			}
+		});
+	});
+	group.finish();
+}
+
+criterion_group!(benches, concurrent_lookups);
+criterion_main!(benches);
@@ -0,0 +1,26 @@
+//! Build script generating the `grpc` feature's tonic bindings.
+
+fn main() {
+	#[cfg(feature = "grpc")]
+	grpc::compile();
+}
+
+#[cfg(feature = "grpc")]
+mod grpc {
+	const PROTO: &str = "proto/jwks_cache/v1/cache_control.proto";
+
+	pub fn compile() {
+		println!("cargo:rerun-if-changed={PROTO}");
+
+		// Use the vendored `protoc` so building the feature needs no system protobuf install.
+		let protoc = protoc_bin_vendored::protoc_bin_path().expect("vendored protoc");
+		let include = protoc_bin_vendored::include_path().expect("vendored protobuf includes");
+
+		// SAFETY: build scripts are single-threaded, so nothing reads the environment concurrently.
+		unsafe { std::env::set_var("PROTOC", protoc) };
+
+		tonic_prost_build::configure()
+			.compile_protos(&[PROTO.into()], &[std::path::PathBuf::from("proto"), include])
+			.expect("compile jwks_cache.v1 protos");
+	}
+}
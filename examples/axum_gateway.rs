@@ -0,0 +1,104 @@
+//! API gateway that verifies bearer tokens with keys cached in a [`Registry`].
+//!
+//! ```sh
+//! export JWKS_URL=https://www.googleapis.com/oauth2/v3/certs
+//! cargo run --example axum_gateway --features jwks-cache-axum
+//! curl -H "Authorization: Bearer $TOKEN" http://127.0.0.1:3000/me
+//! ```
+//!
+//! `/readyz` answers `200` once the provider's keys are cached, so an orchestrator only routes
+//! traffic to the gateway after [`Registry::warm_up`] succeeded, and `/healthz` reports
+//! [`Registry::health`]. `Ctrl-C` drains background
+//! refreshes before exiting.
+
+// std
+use std::{env, net::SocketAddr};
+// crates.io
+use axum::{Json, Router, extract::State, http::StatusCode, routing::get};
+use jsonwebtoken::{Algorithm, Validation};
+use jwks_cache::{
+	AggregateStatus, HealthReport, IdentityProviderRegistration, Registry, ShutdownOptions,
+	middleware::{JwtAuthLayer, StaticProvider, ValidatedClaims},
+};
+use serde::Deserialize;
+use tokio::net::TcpListener;
+
+const TENANT: &str = "default";
+const PROVIDER: &str = "primary";
+
+#[derive(Debug, Deserialize)]
+struct Claims {
+	sub: String,
+	iss: Option<String>,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+	tracing_subscriber::fmt().with_env_filter("info,jwks_cache=debug").init();
+
+	let jwks_url = env::var("JWKS_URL")
+		.unwrap_or_else(|_| "https://www.googleapis.com/oauth2/v3/certs".into());
+	let addr: SocketAddr =
+		env::var("LISTEN_ADDR").unwrap_or_else(|_| "127.0.0.1:3000".into()).parse()?;
+	let registry = Registry::new();
+
+	registry.register(IdentityProviderRegistration::new(TENANT, PROVIDER, &jwks_url)?).await?;
+
+	let warm_up = registry.warm_up().await;
+
+	if !warm_up.all_ready() {
+		tracing::warn!(failed = ?warm_up.failed, "starting with cold providers");
+	}
+
+	let mut validation = Validation::new(Algorithm::RS256);
+
+	validation.algorithms = vec![Algorithm::RS256, Algorithm::ES256];
+	// Accept any audience; a real gateway pins the audience it serves.
+	validation.validate_aud = false;
+
+	let protected = Router::new().route("/me", get(me)).layer(JwtAuthLayer::new(
+		registry.clone(),
+		StaticProvider::new(TENANT, PROVIDER),
+		validation,
+	));
+	let app = Router::new()
+		.route("/readyz", get(readyz))
+		.route("/healthz", get(healthz))
+		.merge(protected)
+		.with_state(registry.clone());
+
+	tracing::info!(%addr, %jwks_url, "gateway listening");
+
+	axum::serve(TcpListener::bind(addr).await?, app)
+		.with_graceful_shutdown(async {
+			let _ = tokio::signal::ctrl_c().await;
+		})
+		.await?;
+
+	let report = registry.shutdown_gracefully(&ShutdownOptions::default()).await?;
+
+	tracing::info!(?report, "gateway stopped");
+
+	Ok(())
+}
+
+async fn me(ValidatedClaims(claims): ValidatedClaims<Claims>) -> String {
+	format!("subject {} issued by {}\n", claims.sub, claims.iss.as_deref().unwrap_or("unknown"))
+}
+
+async fn healthz(State(registry): State<Registry>) -> (StatusCode, Json<HealthReport>) {
+	let report = registry.health().await;
+
+	(report.status_code(), Json(report))
+}
+
+async fn readyz(State(registry): State<Registry>) -> (StatusCode, Json<AggregateStatus>) {
+	let status = registry.aggregate_status().await;
+	let code = if status.time_to_all_ready.is_some() {
+		StatusCode::OK
+	} else {
+		StatusCode::SERVICE_UNAVAILABLE
+	};
+
+	(code, Json(status))
+}
@@ -0,0 +1,87 @@
+//! Multi-tenant service that loads providers from a configuration file and exports Prometheus
+//! metrics.
+//!
+//! ```sh
+//! cargo run --example config_loader --features prometheus -- examples/registrations.json
+//! ```
+//!
+//! Providers are registered from the file, warmed up, and then kept fresh by the registry's
+//! background refreshes; edits to the file are applied by [`Registry::watch_config`]. Every
+//! cache event is logged, and the Prometheus exposition is printed on a fixed interval where a
+//! real service would serve it from `/metrics`.
+
+// std
+use std::{env, path::PathBuf, time::Duration};
+// crates.io
+use jwks_cache::{Registry, ShutdownOptions, metrics};
+use tokio::{sync::broadcast::error::RecvError, time};
+
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+const REPORT_INTERVAL: Duration = Duration::from_secs(30);
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+	tracing_subscriber::fmt().with_env_filter("info,jwks_cache=debug").init();
+	metrics::install_default_exporter()?;
+
+	let path = env::args_os()
+		.nth(1)
+		.map(PathBuf::from)
+		.unwrap_or_else(|| PathBuf::from("examples/registrations.json"));
+	let registry = Registry::new();
+	let mut events = registry.subscribe();
+
+	tokio::spawn(async move {
+		loop {
+			match events.recv().await {
+				Ok(event) => tracing::info!(
+					tenant = %event.tenant_id,
+					provider = %event.provider_id,
+					kind = ?event.kind,
+					"cache event"
+				),
+				Err(RecvError::Lagged(skipped)) => tracing::warn!(skipped, "cache events dropped"),
+				Err(RecvError::Closed) => break,
+			}
+		}
+	});
+
+	let reload = registry.reload_from_path(&path).await?;
+
+	tracing::info!(?reload, path = %path.display(), "registrations loaded");
+
+	let warm_up = registry.warm_up().await;
+
+	tracing::info!(ready = warm_up.ready.len(), failed = ?warm_up.failed, "registry warmed up");
+
+	let watch = registry.watch_config(&path, WATCH_INTERVAL);
+	let mut report = time::interval(REPORT_INTERVAL);
+
+	loop {
+		tokio::select! {
+			_ = report.tick() => {
+				let status = registry.aggregate_status().await;
+
+				tracing::info!(
+					providers = status.total_providers,
+					ready = status.by_state.ready,
+					errors = status.total_error_count,
+					"registry status"
+				);
+
+				if let Some(handle) = metrics::prometheus_handle() {
+					println!("{}", handle.render());
+				}
+			},
+			_ = tokio::signal::ctrl_c() => break,
+		}
+	}
+
+	watch.abort();
+
+	let shutdown = registry.shutdown_gracefully(&ShutdownOptions::default()).await?;
+
+	tracing::info!(?shutdown, "stopped");
+
+	Ok(())
+}
@@ -0,0 +1,60 @@
+//! Warm restarts from snapshots persisted in Redis.
+//!
+//! ```sh
+//! docker run --rm -p 6379:6379 redis
+//! cargo run --example redis_warm_restart --features redis
+//! cargo run --example redis_warm_restart --features redis
+//! ```
+//!
+//! The first run fetches keys upstream and persists them on shutdown. The second run restores
+//! them from Redis during registration, so the provider is ready before any upstream request
+//! completes; only a conditional revalidation follows in the background.
+
+// std
+use std::{env, time::Instant};
+// crates.io
+use jwks_cache::{IdentityProviderRegistration, Registry, RestorePolicy, ShutdownOptions};
+
+const TENANT: &str = "acme";
+const PROVIDER: &str = "google";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+	tracing_subscriber::fmt().with_env_filter("info,jwks_cache=debug").init();
+
+	let redis_url = env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".into());
+	let jwks_url = env::var("JWKS_URL")
+		.unwrap_or_else(|_| "https://www.googleapis.com/oauth2/v3/certs".into());
+	let registry = Registry::builder()
+		.with_redis_client(redis::Client::open(redis_url)?)
+		.redis_namespace("jwks-cache-example")
+		.restore_policy(RestorePolicy::Inline)
+		.build();
+	let started = Instant::now();
+
+	registry.register(IdentityProviderRegistration::new(TENANT, PROVIDER, &jwks_url)?).await?;
+
+	let restored = registry.provider_status(TENANT, PROVIDER).await?;
+
+	match restored.age_seconds {
+		Some(age) if restored.last_refresh.is_some() =>
+			tracing::info!(age_seconds = age, "warm start: keys restored from Redis"),
+		_ => tracing::info!("cold start: no snapshot in Redis"),
+	}
+
+	let warm_up = registry.warm_up().await;
+	let jwks = registry.resolve(TENANT, PROVIDER, None).await?;
+
+	tracing::info!(
+		elapsed = ?started.elapsed(),
+		keys = jwks.keys.len(),
+		ready = warm_up.all_ready(),
+		"keys available"
+	);
+
+	let report = registry.shutdown_gracefully(&ShutdownOptions::default()).await?;
+
+	tracing::info!(persisted = report.persisted, "snapshot written; run again for a warm start");
+
+	Ok(())
+}
@@ -0,0 +1,23 @@
+//! Fuzz allowlist normalisation and host matching.
+//!
+//! The first input line is the host; the remaining lines are allowlist entries.
+
+#![no_main]
+
+// crates.io
+use jwks_cache::security;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+	let Ok(input) = std::str::from_utf8(data) else {
+		return;
+	};
+	let mut lines = input.split('\n');
+	let host = lines.next().unwrap_or_default();
+	let entries = lines.map(str::to_owned).collect::<Vec<_>>();
+	let normalized = security::normalize_allowlist(entries);
+
+	assert!(normalized.iter().all(|entry| !entry.is_empty()));
+
+	let _ = security::host_is_allowed(host, &normalized);
+});
@@ -0,0 +1,28 @@
+//! Fuzz ETag handling through cache policy evaluation and revalidation.
+
+#![no_main]
+
+// crates.io
+use http::{
+	HeaderValue, Request, Response,
+	header::{CACHE_CONTROL, ETAG},
+};
+use jwks_cache::{clock, http::semantics};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+	let Ok(etag) = HeaderValue::from_bytes(data) else {
+		return;
+	};
+	let request = Request::get("https://idp.example.com/.well-known/jwks.json")
+		.body(())
+		.expect("static request must build");
+	let response = Response::builder()
+		.status(200)
+		.header(CACHE_CONTROL, "max-age=0")
+		.header(ETAG, etag)
+		.body(())
+		.expect("response with a valid header value must build");
+	let policy = semantics::cache_policy(&request, &response);
+	let _ = policy.before_request(&request, clock::system_now());
+});
@@ -0,0 +1,26 @@
+//! Fuzz JWKS body parsing, activation metadata, and key conversion.
+
+#![no_main]
+
+// crates.io
+use jsonwebtoken::{DecodingKey, jwk::JwkSet};
+use jwks_cache::{KeyRotation, cache::activation};
+use libfuzzer_sys::fuzz_target;
+use serde::Deserialize;
+use serde_json::Value;
+
+fuzz_target!(|data: &[u8]| {
+	let Ok(document) = serde_json::from_slice::<Value>(data) else {
+		return;
+	};
+	let _ = activation::collect_activations(&activation::NotBeforeField::default(), &document);
+	let Ok(jwks) = JwkSet::deserialize(&document) else {
+		return;
+	};
+
+	for jwk in &jwks.keys {
+		let _ = DecodingKey::from_jwk(jwk);
+	}
+
+	assert!(KeyRotation::between("tenant", "provider", &jwks, &jwks).is_none());
+});
@@ -0,0 +1,27 @@
+//! Fuzz persisted snapshot deserialisation, migration, and validation.
+
+#![no_main]
+
+// crates.io
+use jsonwebtoken::jwk::JwkSet;
+use jwks_cache::{IdentityProviderRegistration, PersistentSnapshot};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+	let Ok(json) = std::str::from_utf8(data) else {
+		return;
+	};
+	let Ok(snapshot) = PersistentSnapshot::from_json(json) else {
+		return;
+	};
+	let registration = IdentityProviderRegistration::new(
+		"tenant",
+		"provider",
+		"https://idp.example.com/.well-known/jwks.json",
+	)
+	.expect("static registration must be valid");
+
+	if snapshot.validate(&registration).is_ok() {
+		let _ = serde_json::from_str::<JwkSet>(&snapshot.jwks_json);
+	}
+});
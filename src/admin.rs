@@ -0,0 +1,230 @@
+//! Axum router exposing registry administration over HTTP.
+//!
+//! [`router`] serves these routes. Statuses are rendered in the registry's
+//! [`StatusFormat`](crate::StatusFormat), and errors as RFC 7807 problem details:
+//!
+//! | Method | Path | Action |
+//! | --- | --- | --- |
+//! | `GET` | `/providers` | Page through statuses; see [`ListQuery`]. Streamed, and gzip-compressed when the client accepts it and the `gzip` feature is enabled. |
+//! | `POST` | `/providers` | Register the [`IdentityProviderRegistration`] in the body. |
+//! | `GET` | `/providers/{tenant_id}/{provider_id}` | Fetch one provider's status. |
+//! | `GET` | `/providers/{tenant_id}/{provider_id}/exchanges` | List the provider's recent redacted [`CapturedExchange`]s. |
+//! | `POST` | `/providers/{tenant_id}/{provider_id}/refresh` | Trigger a refresh. |
+//! | `POST` | `/providers/{tenant_id}/{provider_id}/invalidate` | Invalidate cached keys; see [`InvalidateQuery`]. |
+//!
+//! Every route sits behind the authentication layer supplied by the caller.
+
+// std
+use std::convert::Infallible;
+// crates.io
+use axum::{
+	Json, Router,
+	body::Body,
+	extract::{Path, Query, Request, State},
+	http::{HeaderMap, HeaderValue, StatusCode, header},
+	response::{IntoResponse, Response},
+	routing::{Route, get, post},
+};
+use futures_util::{StreamExt, stream};
+use serde::Deserialize;
+use tower_layer::Layer;
+use tower_service::Service;
+// self
+use crate::{
+	_prelude::*,
+	http::capture::CapturedExchange,
+	problem::{self, ProblemDetails},
+	registry::{
+		ChunkEncoding, IdentityProviderRegistration, ProviderState, Registry, StatusDocument,
+		StatusFilter,
+	},
+};
+
+/// Page size used by `GET /providers` when the query sets no `limit`.
+pub const DEFAULT_PAGE_LIMIT: usize = 100;
+/// Largest page `GET /providers` returns, regardless of the requested `limit`.
+pub const MAX_PAGE_LIMIT: usize = 1_000;
+
+/// Build the admin router for `registry`, guarding every route with `auth`.
+///
+/// `auth` runs before any handler, so rejecting a request there keeps it from reaching the
+/// registry. Pass `tower_layer::Identity` only when the router is mounted behind equivalent
+/// protection.
+pub fn router<L>(registry: Registry, auth: L) -> Router
+where
+	L: Layer<Route> + Clone + Send + Sync + 'static,
+	L::Service: Service<Request> + Clone + Send + Sync + 'static,
+	<L::Service as Service<Request>>::Response: IntoResponse + 'static,
+	<L::Service as Service<Request>>::Error: Into<Infallible> + 'static,
+	<L::Service as Service<Request>>::Future: Send + 'static,
+{
+	Router::new()
+		.route("/providers", get(list_providers).post(register_provider))
+		.route("/providers/{tenant_id}/{provider_id}", get(provider_status))
+		.route("/providers/{tenant_id}/{provider_id}/exchanges", get(provider_exchanges))
+		.route("/providers/{tenant_id}/{provider_id}/refresh", post(refresh_provider))
+		.route("/providers/{tenant_id}/{provider_id}/invalidate", post(invalidate_provider))
+		.layer(auth)
+		.with_state(registry)
+}
+
+/// Query parameters accepted by `GET /providers`.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ListQuery {
+	/// Only providers of this tenant.
+	pub tenant_id: Option<String>,
+	/// Only providers in this lifecycle state.
+	pub state: Option<ProviderState>,
+	/// Only providers with consecutive refresh errors.
+	pub failing: bool,
+	/// Number of matching providers to skip.
+	pub offset: usize,
+	/// Page size, defaulting to [`DEFAULT_PAGE_LIMIT`] and capped at [`MAX_PAGE_LIMIT`].
+	pub limit: Option<usize>,
+}
+
+/// Query parameters accepted by `POST /providers/{tenant_id}/{provider_id}/invalidate`.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct InvalidateQuery {
+	/// Delete the persisted snapshot too.
+	pub purge_snapshot: bool,
+}
+
+// Crate errors rendered as problem details.
+struct ApiError(Error);
+impl From<Error> for ApiError {
+	fn from(err: Error) -> Self {
+		Self(err)
+	}
+}
+impl IntoResponse for ApiError {
+	fn into_response(self) -> Response {
+		let problem = ProblemDetails::from(&self.0);
+
+		(problem.status_code(), [(header::CONTENT_TYPE, problem::CONTENT_TYPE)], Json(problem))
+			.into_response()
+	}
+}
+
+type ApiResult<T> = std::result::Result<T, ApiError>;
+
+async fn list_providers(
+	State(registry): State<Registry>,
+	headers: HeaderMap,
+	Query(query): Query<ListQuery>,
+) -> ApiResult<Response> {
+	let filter =
+		StatusFilter { tenant_id: query.tenant_id, state: query.state, failing: query.failing };
+	let limit = query.limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(MAX_PAGE_LIMIT);
+	let page = registry.statuses_page(&filter, query.offset, limit).await;
+	let suffix = format!(
+		r#"],"total":{},"next_offset":{}}}"#,
+		page.total,
+		page.next_offset.map_or_else(|| "null".into(), |offset| offset.to_string())
+	);
+	let (encoding, content_encoding) = negotiate_encoding(&headers);
+	let mut document = StatusDocument::new(registry.status_format(), encoding);
+	let head = document.open(br#"{"statuses":["#)?;
+	// Each status is serialised only when the body is polled for it.
+	let rest = stream::unfold(
+		Some((document, page.statuses.into_iter(), suffix)),
+		|state| async move {
+			let (mut document, mut statuses, suffix) = state?;
+
+			match statuses.next() {
+				Some(status) => Some((document.push(&status), Some((document, statuses, suffix)))),
+				None => Some((document.close(suffix.as_bytes()), None)),
+			}
+		},
+	);
+	let body = Body::from_stream(stream::once(async move { Ok(head) }).chain(rest));
+	let mut response = ([(header::CONTENT_TYPE, "application/json")], body).into_response();
+
+	if let Some(content_encoding) = content_encoding {
+		response.headers_mut().insert(header::CONTENT_ENCODING, content_encoding);
+	}
+
+	response.headers_mut().insert(header::VARY, header::ACCEPT_ENCODING.into());
+
+	Ok(response)
+}
+
+// Pick the body encoding for `GET /providers` from the request's `Accept-Encoding`.
+fn negotiate_encoding(headers: &HeaderMap) -> (ChunkEncoding, Option<HeaderValue>) {
+	#[cfg(feature = "gzip")]
+	if accepts_gzip(headers) {
+		return (ChunkEncoding::gzip(), Some(HeaderValue::from_static("gzip")));
+	}
+	#[cfg(not(feature = "gzip"))]
+	let _ = headers;
+
+	(ChunkEncoding::Identity, None)
+}
+
+#[cfg(feature = "gzip")]
+fn accepts_gzip(headers: &HeaderMap) -> bool {
+	headers
+		.get_all(header::ACCEPT_ENCODING)
+		.iter()
+		.filter_map(|value| value.to_str().ok())
+		.flat_map(|value| value.split(','))
+		.any(|coding| {
+			let mut parts = coding.split(';').map(str::trim);
+			let name = parts.next().unwrap_or_default();
+			let refused = parts.any(|param| {
+				param.strip_prefix("q=").and_then(|q| q.parse::<f32>().ok()) == Some(0.0)
+			});
+
+			name.eq_ignore_ascii_case("gzip") && !refused
+		})
+}
+
+async fn register_provider(
+	State(registry): State<Registry>,
+	Json(registration): Json<IdentityProviderRegistration>,
+) -> ApiResult<(StatusCode, Json<serde_json::Value>)> {
+	let registration = registry.register(registration).await?;
+	let status = registry
+		.provider_status(&registration.tenant_id, &registration.provider_id)
+		.await?
+		.to_json(registry.status_format())?;
+
+	Ok((StatusCode::CREATED, Json(status)))
+}
+
+async fn provider_status(
+	State(registry): State<Registry>,
+	Path((tenant_id, provider_id)): Path<(String, String)>,
+) -> ApiResult<Json<serde_json::Value>> {
+	let format = registry.status_format();
+
+	Ok(Json(registry.provider_status(&tenant_id, &provider_id).await?.to_json(format)?))
+}
+
+async fn provider_exchanges(
+	State(registry): State<Registry>,
+	Path((tenant_id, provider_id)): Path<(String, String)>,
+) -> ApiResult<Json<Vec<CapturedExchange>>> {
+	Ok(Json(registry.recent_exchanges(&tenant_id, &provider_id).await?))
+}
+
+async fn refresh_provider(
+	State(registry): State<Registry>,
+	Path((tenant_id, provider_id)): Path<(String, String)>,
+) -> ApiResult<StatusCode> {
+	registry.refresh(&tenant_id, &provider_id).await?;
+
+	Ok(StatusCode::ACCEPTED)
+}
+
+async fn invalidate_provider(
+	State(registry): State<Registry>,
+	Path((tenant_id, provider_id)): Path<(String, String)>,
+	Query(query): Query<InvalidateQuery>,
+) -> ApiResult<StatusCode> {
+	registry.invalidate(&tenant_id, &provider_id, query.purge_snapshot).await?;
+
+	Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,284 @@
+//! Operations CLI for jwks-cache.
+//!
+//! ```sh
+//! jwks-cache fetch https://login.example.com/.well-known/jwks.json --max-ttl 1h
+//! jwks-cache status https://admin.internal:8080 --tenant acme --header "authorization: Bearer …"
+//! jwks-cache seed https://login.example.com/.well-known/jwks.json --tenant acme --provider okta
+//! ```
+//!
+//! `fetch` and `seed` go through a [`Registry`], so TTL clamping, the response size guard, HTTPS
+//! enforcement, and the domain allowlist behave exactly as in the cache.
+
+// std
+use std::{fs, process::ExitCode, sync::Arc};
+// crates.io
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use jwks_cache::{
+	ByteSize, IdentityProviderRegistration, MemorySnapshotStore, Registry, RegistryBuilder, Result,
+	SnapshotStore, StatusFormat, TtlSeconds,
+};
+use reqwest::{
+	Client,
+	header::{HeaderMap, HeaderName, HeaderValue},
+};
+use url::Url;
+
+// Identifiers used for the throwaway registration behind `fetch`.
+const FETCH_TENANT: &str = "cli";
+const FETCH_PROVIDER: &str = "fetch";
+
+#[tokio::main]
+async fn main() -> ExitCode {
+	let matches = command().get_matches();
+	let outcome = match matches.subcommand() {
+		Some(("fetch", args)) => fetch(args).await,
+		Some(("status", args)) => status(args).await,
+		Some(("seed", args)) => seed(args).await,
+		_ => unreachable!("clap requires a subcommand"),
+	};
+
+	match outcome {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(err) => {
+			eprintln!("error: {err}");
+
+			ExitCode::FAILURE
+		},
+	}
+}
+
+fn command() -> Command {
+	let fetch_args = [
+		Arg::new("url").required(true).help("JWKS URL to fetch"),
+		Arg::new("allow-http")
+			.long("allow-http")
+			.action(ArgAction::SetTrue)
+			.help("Allow plain-HTTP URLs, for local testing only"),
+		Arg::new("allowed-domain")
+			.long("allowed-domain")
+			.action(ArgAction::Append)
+			.help("Domain the JWKS URL may be served from; repeat to allow several"),
+		Arg::new("max-response-bytes")
+			.long("max-response-bytes")
+			.help("Largest accepted JWKS body, such as 512KiB"),
+		Arg::new("min-ttl").long("min-ttl").help("Lower bound applied to the upstream TTL"),
+		Arg::new("max-ttl").long("max-ttl").help("Upper bound applied to the upstream TTL"),
+	];
+
+	Command::new("jwks-cache")
+		.about("Inspect JWKS endpoints and jwks-cache deployments")
+		.version(env!("CARGO_PKG_VERSION"))
+		.subcommand_required(true)
+		.subcommand(
+			Command::new("fetch")
+				.about("Fetch and validate a JWKS URL with the cache's semantics")
+				.args(fetch_args.clone()),
+		)
+		.subcommand(
+			Command::new("status")
+				.about("Print provider statuses from a running admin API")
+				.arg(Arg::new("admin-url").required(true).help("Base URL of the admin router"))
+				.arg(Arg::new("tenant").long("tenant").help("Only this tenant's providers"))
+				.arg(
+					Arg::new("provider")
+						.long("provider")
+						.requires("tenant")
+						.help("Only this provider of the tenant"),
+				)
+				.arg(
+					Arg::new("header")
+						.long("header")
+						.action(ArgAction::Append)
+						.help("Request header as `name: value`, such as credentials"),
+				),
+		)
+		.subcommand(
+			Command::new("seed")
+				.about("Fetch a JWKS URL and write a persistence snapshot for it")
+				.args(fetch_args)
+				.arg(Arg::new("tenant").long("tenant").required(true))
+				.arg(Arg::new("provider").long("provider").required(true))
+				.arg(
+					Arg::new("out")
+						.long("out")
+						.help("Write the snapshot JSON here instead of stdout"),
+				)
+				.args(redis_args()),
+		)
+}
+
+#[cfg(feature = "redis")]
+fn redis_args() -> Vec<Arg> {
+	vec![
+		Arg::new("redis").long("redis").help("Persist the snapshot into this Redis server instead"),
+		Arg::new("namespace")
+			.long("namespace")
+			.requires("redis")
+			.help("Redis key namespace used by the registries that restore it"),
+	]
+}
+
+#[cfg(not(feature = "redis"))]
+fn redis_args() -> Vec<Arg> {
+	Vec::new()
+}
+
+async fn fetch(args: &ArgMatches) -> Result<()> {
+	let registry = builder(args).build();
+	let registration = registration(args, FETCH_TENANT, FETCH_PROVIDER)?;
+
+	registry.register(registration).await?;
+
+	let jwks = registry.resolve(FETCH_TENANT, FETCH_PROVIDER, None).await?;
+	let status = registry.provider_status(FETCH_TENANT, FETCH_PROVIDER).await?;
+	let kids: Vec<_> = jwks.keys.iter().filter_map(|key| key.common.key_id.as_deref()).collect();
+
+	print_json(&serde_json::json!({
+		"jwks_url": arg(args, "url"),
+		"key_count": jwks.keys.len(),
+		"kids": kids,
+		"expires_at": status.expires_at,
+		"next_refresh": status.next_refresh,
+		"age_seconds": status.age_seconds,
+	}))
+}
+
+async fn status(args: &ArgMatches) -> Result<()> {
+	let mut url = Url::parse(arg(args, "admin-url"))?;
+	let tenant = args.get_one::<String>("tenant");
+
+	{
+		let mut segments =
+			url.path_segments_mut().map_err(|_| invalid_url(arg(args, "admin-url")))?;
+
+		segments.pop_if_empty().push("providers");
+
+		if let (Some(tenant), Some(provider)) = (tenant, args.get_one::<String>("provider")) {
+			segments.push(tenant).push(provider);
+		}
+	}
+
+	if let (Some(tenant), None) = (tenant, args.get_one::<String>("provider")) {
+		url.query_pairs_mut().append_pair("tenant_id", tenant);
+	}
+
+	let mut headers = HeaderMap::new();
+
+	for header in args.get_many::<String>("header").into_iter().flatten() {
+		let (name, value) = header.split_once(':').ok_or_else(|| invalid("header", header))?;
+		let name = HeaderName::try_from(name.trim()).map_err(|_| invalid("header", header))?;
+		let mut value =
+			HeaderValue::try_from(value.trim()).map_err(|_| invalid("header", header))?;
+
+		value.set_sensitive(true);
+		headers.append(name, value);
+	}
+
+	let response = Client::new().get(url).headers(headers).send().await?;
+	let status = response.status();
+	let body = response.json::<serde_json::Value>().await?;
+
+	print_json(&body)?;
+
+	if !status.is_success() {
+		return Err(jwks_cache::Error::Cache(format!("Admin API answered {status}.")));
+	}
+
+	Ok(())
+}
+
+async fn seed(args: &ArgMatches) -> Result<()> {
+	let tenant = arg(args, "tenant");
+	let provider = arg(args, "provider");
+	let memory = Arc::new(MemorySnapshotStore::new());
+	let builder = builder(args);
+	#[cfg(feature = "redis")]
+	let builder = match args.get_one::<String>("redis") {
+		Some(url) => {
+			let builder = builder.with_redis_client(redis::Client::open(url.as_str())?);
+
+			match args.get_one::<String>("namespace") {
+				Some(namespace) => builder.redis_namespace(namespace),
+				None => builder,
+			}
+		},
+		None => builder.with_snapshot_store(memory.clone()),
+	};
+	#[cfg(not(feature = "redis"))]
+	let builder = builder.with_snapshot_store(memory.clone());
+	let registry = builder.build();
+
+	registry.register(registration(args, tenant, provider)?).await?;
+	registry.resolve(tenant, provider, None).await?;
+	registry.persist_all().await?;
+
+	let Some(snapshot) = memory.load(tenant, provider).await? else {
+		eprintln!("snapshot for {tenant}/{provider} persisted to Redis");
+
+		return Ok(());
+	};
+	let json = serde_json::to_string_pretty(&snapshot)?;
+
+	match args.get_one::<String>("out") {
+		Some(path) => {
+			fs::write(path, json)?;
+			eprintln!("snapshot for {tenant}/{provider} written to {path}");
+		},
+		None => println!("{json}"),
+	}
+
+	Ok(())
+}
+
+fn builder(args: &ArgMatches) -> RegistryBuilder {
+	let builder = Registry::builder()
+		.require_https(!args.get_flag("allow-http"))
+		.status_format(StatusFormat::V1);
+
+	match args.get_many::<String>("allowed-domain") {
+		Some(domains) => builder.allowed_domains(domains.cloned()),
+		None => builder,
+	}
+}
+
+fn registration(
+	args: &ArgMatches,
+	tenant: &str,
+	provider: &str,
+) -> Result<IdentityProviderRegistration> {
+	let mut registration = IdentityProviderRegistration::new(tenant, provider, arg(args, "url"))?
+		.with_require_https(!args.get_flag("allow-http"));
+
+	if let Some(value) = args.get_one::<String>("max-response-bytes") {
+		registration.max_response_bytes = ByteSize::parse(value)?;
+	}
+	if let Some(value) = args.get_one::<String>("min-ttl") {
+		registration.min_ttl = TtlSeconds::parse(value)?;
+	}
+	if let Some(value) = args.get_one::<String>("max-ttl") {
+		registration.max_ttl = TtlSeconds::parse(value)?;
+	}
+
+	Ok(registration)
+}
+
+fn arg<'a>(args: &'a ArgMatches, name: &str) -> &'a str {
+	args.get_one::<String>(name).map(String::as_str).unwrap_or_default()
+}
+
+fn invalid(field: &'static str, value: &str) -> jwks_cache::Error {
+	jwks_cache::Error::Validation { field, reason: format!("`{value}` is not `name: value`.") }
+}
+
+fn invalid_url(value: &str) -> jwks_cache::Error {
+	jwks_cache::Error::Validation {
+		field: "admin_url",
+		reason: format!("`{value}` cannot be a base URL."),
+	}
+}
+
+fn print_json(value: &serde_json::Value) -> Result<()> {
+	println!("{}", serde_json::to_string_pretty(value)?);
+
+	Ok(())
+}
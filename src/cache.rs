@@ -1,5 +1,15 @@
 //! Cache module containing state machine and manager implementations.
 
+pub mod activation;
+pub mod breaker;
 pub mod entry;
+pub mod history;
+pub mod latency;
 pub mod manager;
+pub mod recovery;
+pub mod residency;
+pub mod rotation;
+pub mod scheduler;
+pub mod single_flight;
 pub mod state;
+pub mod storage;
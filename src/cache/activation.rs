@@ -0,0 +1,92 @@
+//! Pluggable activation rules for keys published ahead of use.
+//!
+//! Some identity providers pre-publish signing keys together with vendor-specific "not before"
+//! metadata. A [`KeyActivation`] hook reads that metadata from the raw JWKS document when it is
+//! fetched; keys that are not yet active stay cached but are skipped by `resolve_key`, and left out
+//! of resolved key sets, until their activation time, so activation needs no upstream round trip.
+
+// std
+use std::{collections::BTreeMap, fmt::Debug};
+// crates.io
+use serde_json::{Map, Value};
+// self
+use crate::_prelude::*;
+
+/// Activation times keyed by `kid`.
+pub type KeyActivations = BTreeMap<String, DateTime<Utc>>;
+
+/// Decides when a published key becomes usable.
+pub trait KeyActivation
+where
+	Self: Debug + Send + Sync,
+{
+	/// Return the time `key` becomes active, or `None` when it is active immediately.
+	///
+	/// `key` is the raw JWK object, including fields `jsonwebtoken` does not model.
+	fn activates_at(&self, key: &Map<String, Value>) -> Option<DateTime<Utc>>;
+}
+
+/// Reads activation times from a numeric (Unix seconds) or RFC 3339 field on each JWK.
+#[derive(Clone, Debug)]
+pub struct NotBeforeField {
+	field: String,
+}
+impl NotBeforeField {
+	/// Read activation times from `field`.
+	pub fn new(field: impl Into<String>) -> Self {
+		Self { field: field.into() }
+	}
+}
+impl Default for NotBeforeField {
+	fn default() -> Self {
+		Self::new("nbf")
+	}
+}
+impl KeyActivation for NotBeforeField {
+	fn activates_at(&self, key: &Map<String, Value>) -> Option<DateTime<Utc>> {
+		match key.get(&self.field)? {
+			Value::Number(number) => DateTime::from_timestamp(number.as_i64()?, 0),
+			Value::String(raw) =>
+				DateTime::parse_from_rfc3339(raw).ok().map(|value| value.with_timezone(&Utc)),
+			_ => None,
+		}
+	}
+}
+
+/// Evaluate `hook` against every key with a `kid` in a raw JWKS document.
+pub fn collect_activations(hook: &dyn KeyActivation, document: &Value) -> KeyActivations {
+	let Some(keys) = document.get("keys").and_then(Value::as_array) else {
+		return KeyActivations::new();
+	};
+
+	keys.iter()
+		.filter_map(Value::as_object)
+		.filter_map(|key| {
+			let kid = key.get("kid")?.as_str()?;
+
+			Some((kid.to_owned(), hook.activates_at(key)?))
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn not_before_field_accepts_epoch_seconds_and_rfc3339() {
+		let document = serde_json::json!({
+			"keys": [
+				{ "kid": "epoch", "kty": "oct", "nbf": 1_700_000_000 },
+				{ "kid": "rfc3339", "kty": "oct", "nbf": "2030-01-01T00:00:00Z" },
+				{ "kid": "plain", "kty": "oct" },
+				{ "kty": "oct", "nbf": 1_700_000_000 }
+			]
+		});
+		let activations = collect_activations(&NotBeforeField::default(), &document);
+
+		assert_eq!(activations.len(), 2);
+		assert_eq!(activations["epoch"].timestamp(), 1_700_000_000);
+		assert_eq!(activations["rfc3339"].to_rfc3339(), "2030-01-01T00:00:00+00:00");
+	}
+}
@@ -0,0 +1,168 @@
+//! Per-provider circuit breaker guarding upstream JWKS fetches.
+//!
+//! Once a provider fails [`CircuitBreakerPolicy::failure_threshold`] refreshes in a row, the
+//! breaker opens and refreshes are rejected without touching the network, so callers fall back to
+//! stale keys instead of burning the retry budget on every resolve. After
+//! [`CircuitBreakerPolicy::open_duration`] the breaker half-opens and lets trial fetches through;
+//! it closes once enough of them succeed and reopens on the first failure.
+
+// crates.io
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+// self
+use crate::{_prelude::*, registry::CircuitBreakerPolicy};
+
+/// Externally visible breaker state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+	/// Fetches flow normally.
+	Closed,
+	/// Fetches are rejected until the open window elapses.
+	Open,
+	/// Trial fetches are allowed to decide whether the upstream recovered.
+	HalfOpen,
+}
+
+/// Point-in-time view of a [`CircuitBreaker`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BreakerSnapshot {
+	/// Current breaker state.
+	pub state: CircuitState,
+	/// Instant at which an open breaker starts admitting trial fetches.
+	pub open_until: Option<Instant>,
+}
+
+/// Failure-counting circuit breaker for a single provider.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+	policy: CircuitBreakerPolicy,
+	inner: Mutex<BreakerInner>,
+}
+impl CircuitBreaker {
+	/// Create a closed breaker governed by `policy`.
+	pub fn new(policy: CircuitBreakerPolicy) -> Self {
+		Self { policy, inner: Mutex::new(BreakerInner::Closed { failures: 0 }) }
+	}
+
+	/// Decide whether a fetch may proceed at `now`.
+	///
+	/// Returns the instant the breaker will next admit a fetch when it rejects this one.
+	pub async fn admit(&self, now: Instant) -> std::result::Result<(), Instant> {
+		let mut inner = self.inner.lock().await;
+
+		match *inner {
+			BreakerInner::Open { until } if now < until => Err(until),
+			BreakerInner::Open { .. } => {
+				tracing::info!("circuit breaker half-open; probing upstream");
+
+				*inner = BreakerInner::HalfOpen { successes: 0 };
+
+				Ok(())
+			},
+			BreakerInner::Closed { .. } | BreakerInner::HalfOpen { .. } => Ok(()),
+		}
+	}
+
+	/// Record a successful fetch.
+	pub async fn record_success(&self) {
+		let mut inner = self.inner.lock().await;
+
+		*inner = match *inner {
+			BreakerInner::HalfOpen { successes }
+				if successes + 1 < self.policy.half_open_probes =>
+				BreakerInner::HalfOpen { successes: successes + 1 },
+			BreakerInner::HalfOpen { .. } => {
+				tracing::info!("circuit breaker closed");
+
+				BreakerInner::Closed { failures: 0 }
+			},
+			BreakerInner::Closed { .. } | BreakerInner::Open { .. } =>
+				BreakerInner::Closed { failures: 0 },
+		};
+	}
+
+	/// Record a fetch that failed after exhausting its retries.
+	pub async fn record_failure(&self, now: Instant) {
+		let mut inner = self.inner.lock().await;
+		let open = BreakerInner::Open { until: now + self.policy.open_duration };
+
+		*inner = match *inner {
+			BreakerInner::Closed { failures } if failures + 1 < self.policy.failure_threshold =>
+				BreakerInner::Closed { failures: failures + 1 },
+			BreakerInner::Closed { .. } | BreakerInner::HalfOpen { .. } => {
+				tracing::warn!(
+					open_for = ?self.policy.open_duration,
+					"circuit breaker opened after repeated refresh failures"
+				);
+
+				open
+			},
+			BreakerInner::Open { .. } => open,
+		};
+	}
+
+	/// Capture the current breaker state.
+	pub async fn snapshot(&self) -> BreakerSnapshot {
+		match *self.inner.lock().await {
+			BreakerInner::Closed { .. } =>
+				BreakerSnapshot { state: CircuitState::Closed, open_until: None },
+			BreakerInner::Open { until } =>
+				BreakerSnapshot { state: CircuitState::Open, open_until: Some(until) },
+			BreakerInner::HalfOpen { .. } =>
+				BreakerSnapshot { state: CircuitState::HalfOpen, open_until: None },
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug)]
+enum BreakerInner {
+	Closed { failures: u32 },
+	Open { until: Instant },
+	HalfOpen { successes: u32 },
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn breaker_opens_half_opens_and_closes() {
+		let breaker = CircuitBreaker::new(CircuitBreakerPolicy {
+			failure_threshold: 2,
+			open_duration: Duration::from_secs(30),
+			half_open_probes: 2,
+		});
+		let now = Instant::now();
+
+		breaker.record_failure(now).await;
+
+		assert!(breaker.admit(now).await.is_ok());
+
+		breaker.record_failure(now).await;
+
+		assert_eq!(breaker.admit(now).await, Err(now + Duration::from_secs(30)));
+
+		let later = now + Duration::from_secs(31);
+
+		assert!(breaker.admit(later).await.is_ok());
+		assert_eq!(breaker.snapshot().await.state, CircuitState::HalfOpen);
+
+		breaker.record_success().await;
+
+		assert_eq!(breaker.snapshot().await.state, CircuitState::HalfOpen);
+
+		breaker.record_failure(later).await;
+
+		assert_eq!(breaker.snapshot().await.state, CircuitState::Open);
+
+		let recovered = later + Duration::from_secs(31);
+
+		assert!(breaker.admit(recovered).await.is_ok());
+
+		breaker.record_success().await;
+		breaker.record_success().await;
+
+		assert_eq!(breaker.snapshot().await.state, CircuitState::Closed);
+	}
+}
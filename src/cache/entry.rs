@@ -4,6 +4,8 @@
 use crate::{
 	_prelude::*,
 	cache::state::{CachePayload, CacheState},
+	clock::ClockDrift,
+	registry::{ClockJumpPolicy, ProviderError},
 };
 
 /// Represents a cached JWKS entry for a tenant/provider pair.
@@ -83,10 +85,15 @@ impl CacheEntry {
 	/// When a backoff is provided the next refresh instant is shifted forward
 	/// by that duration, effectively treating it as a cooldown on top of the
 	/// previously scheduled refresh window.
-	pub fn refresh_failure(&mut self, now: Instant, next_backoff: Option<Duration>) {
+	pub fn refresh_failure(
+		&mut self,
+		now: Instant,
+		next_backoff: Option<Duration>,
+		error: ProviderError,
+	) {
 		self.state = match std::mem::replace(&mut self.state, CacheState::Empty) {
 			CacheState::Refreshing(mut payload) => {
-				payload.bump_error(next_backoff);
+				payload.bump_error(next_backoff, error);
 
 				if let Some(delay) = next_backoff {
 					payload.next_refresh_at = now + delay;
@@ -102,11 +109,50 @@ impl CacheEntry {
 		};
 	}
 
+	/// Abandon a refresh that was never attempted, postponing the next one until `until`.
+	///
+	/// Unlike [`Self::refresh_failure`], the error count is left untouched.
+	pub fn defer_refresh(&mut self, now: Instant, until: Instant) {
+		self.state = match std::mem::replace(&mut self.state, CacheState::Empty) {
+			CacheState::Refreshing(mut payload) => {
+				payload.next_refresh_at = until;
+
+				if payload.can_serve_stale(now) {
+					CacheState::Ready(payload)
+				} else {
+					CacheState::Empty
+				}
+			},
+			CacheState::Loading => CacheState::Empty,
+			state => state,
+		};
+	}
+
+	/// Abandon a background refresh that was cancelled, returning the payload to `Ready`.
+	///
+	/// The refresh schedule is left untouched, so the next resolve schedules it again.
+	pub fn cancel_refresh(&mut self) {
+		if let CacheState::Refreshing(payload) = &self.state {
+			self.state = CacheState::Ready(payload.clone());
+		}
+	}
+
 	/// Invalidate the cached payload, returning to Empty state.
 	pub fn invalidate(&mut self) {
 		self.state = CacheState::Empty;
 	}
 
+	/// Reconcile the payload's deadlines after a clock jump; see
+	/// [`CachePayload::reconcile_clocks`].
+	pub fn reconcile_clocks(
+		&mut self,
+		policy: &ClockJumpPolicy,
+		now: Instant,
+		wall: SystemTime,
+	) -> Option<ClockDrift> {
+		self.state.payload_mut()?.reconcile_clocks(policy, now, wall)
+	}
+
 	/// Retrieve a clone of the cached payload if present.
 	pub fn snapshot(&self) -> Option<CachePayload> {
 		self.state.payload().cloned()
@@ -117,10 +163,10 @@ impl CacheEntry {
 mod tests {
 	// crates.io
 	use http::{Request, Response, StatusCode};
-	use http_cache_semantics::CachePolicy;
 	use jsonwebtoken::jwk::JwkSet;
 	// self
 	use super::*;
+	use crate::{clock, http::semantics, registry::ClockReconciliation};
 
 	fn sample_payload(now: Instant) -> CachePayload {
 		let request = Request::builder()
@@ -129,19 +175,24 @@ mod tests {
 			.body(())
 			.expect("request");
 		let response = Response::builder().status(StatusCode::OK).body(()).expect("response");
-		let policy = CachePolicy::new(&request, &response);
+		let policy = semantics::cache_policy(&request, &response);
 
 		CachePayload {
-			jwks: Arc::new(JwkSet { keys: Vec::new() }),
+			activations: Default::default(),
+			decoding_keys: Default::default(),
+			jwks: Arc::new(JwkSet { keys: Vec::new() }).into(),
 			policy,
 			etag: Some("v1".to_string()),
 			last_modified: None,
-			last_refresh_at: Utc::now(),
+			last_refresh_at: clock::utc_now(),
 			expires_at: now + Duration::from_secs(60),
 			next_refresh_at: now + Duration::from_secs(30),
 			stale_deadline: Some(now + Duration::from_secs(120)),
+			revalidate_deadline: None,
 			retry_backoff: None,
 			error_count: 0,
+			last_error: None,
+			clock_anchor: clock::ClockAnchor { monotonic: now, wall: clock::system_now() },
 		}
 	}
 
@@ -195,8 +246,63 @@ mod tests {
 
 		assert!(entry.begin_refresh(now + Duration::from_secs(31)));
 
-		entry.refresh_failure(now + Duration::from_secs(90), None);
+		entry.refresh_failure(
+			now + Duration::from_secs(90),
+			None,
+			ProviderError::from_error(&Error::Cache("upstream down".into()), Utc::now()),
+		);
 
 		assert!(matches!(entry.state(), CacheState::Empty));
 	}
+
+	#[test]
+	fn clock_jumps_are_reconciled_by_policy() {
+		let now = Instant::now();
+		let payload = sample_payload(now);
+		let resumed = now + Duration::from_secs(10);
+		let wall = payload.clock_anchor.wall + Duration::from_secs(3_610);
+		let policy =
+			|reconciliation| ClockJumpPolicy { threshold: Duration::from_secs(60), reconciliation };
+		let reconcile = |reconciliation| {
+			let mut entry = CacheEntry::new("tenant", "provider");
+
+			entry.load_success(payload.clone());
+
+			let drift = entry.reconcile_clocks(&policy(reconciliation), resumed, wall);
+
+			assert_eq!(drift, Some(ClockDrift::Ahead(Duration::from_secs(3_600))));
+			assert_eq!(entry.reconcile_clocks(&policy(reconciliation), resumed, wall), None);
+
+			entry.snapshot().expect("payload")
+		};
+
+		let mut entry = CacheEntry::new("tenant", "provider");
+
+		entry.load_success(payload.clone());
+
+		assert_eq!(
+			entry.reconcile_clocks(
+				&policy(ClockReconciliation::Revalidate),
+				resumed,
+				payload.clock_anchor.wall + Duration::from_secs(30),
+			),
+			None,
+			"drift below the threshold is ignored"
+		);
+
+		let monotonic = reconcile(ClockReconciliation::TrustMonotonic);
+
+		assert_eq!(monotonic.expires_at, payload.expires_at);
+		assert!(!monotonic.is_expired(resumed));
+
+		let wall_clock = reconcile(ClockReconciliation::TrustWallClock);
+
+		assert!(wall_clock.is_expired(resumed));
+		assert!(!wall_clock.can_serve_stale(resumed));
+
+		let revalidate = reconcile(ClockReconciliation::Revalidate);
+
+		assert!(revalidate.is_expired(resumed));
+		assert_eq!(revalidate.stale_deadline, payload.stale_deadline);
+	}
 }
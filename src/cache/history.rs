@@ -0,0 +1,111 @@
+//! Bounded per-provider status history sampled on refresh events.
+
+// std
+use std::collections::VecDeque;
+// crates.io
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+// self
+use crate::{_prelude::*, registry::ProviderState};
+
+/// Default number of samples retained per provider.
+pub const DEFAULT_STATUS_HISTORY_CAPACITY: usize = 64;
+
+/// Point-in-time provider status captured after an upstream refresh.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StatusSample {
+	/// UTC timestamp when the sample was recorded.
+	pub recorded_at: DateTime<Utc>,
+	/// Lifecycle state after the refresh completed.
+	pub state: ProviderState,
+	/// Whether the refresh that produced this sample succeeded.
+	pub refresh_succeeded: bool,
+	/// Consecutive error count after the refresh.
+	pub error_count: u32,
+	/// Cumulative ratio of cache hits to total requests.
+	#[cfg(feature = "metrics")]
+	pub hit_rate: f64,
+	/// Cumulative ratio of served responses that were stale.
+	#[cfg(feature = "metrics")]
+	pub stale_serve_ratio: f64,
+}
+
+/// Fixed-capacity ring of [`StatusSample`]s, oldest first.
+#[derive(Debug)]
+pub struct StatusHistory {
+	capacity: usize,
+	samples: Mutex<VecDeque<StatusSample>>,
+}
+impl StatusHistory {
+	/// Create a history retaining at most `capacity` samples; zero disables recording.
+	pub fn new(capacity: usize) -> Self {
+		Self { capacity, samples: Mutex::new(VecDeque::with_capacity(capacity)) }
+	}
+
+	/// Append a sample, evicting the oldest one when full.
+	pub async fn record(&self, sample: StatusSample) {
+		if self.capacity == 0 {
+			return;
+		}
+
+		let mut samples = self.samples.lock().await;
+
+		while samples.len() >= self.capacity {
+			samples.pop_front();
+		}
+
+		samples.push_back(sample);
+	}
+
+	/// Copy the retained samples, oldest first.
+	pub async fn samples(&self) -> Vec<StatusSample> {
+		self.samples.lock().await.iter().cloned().collect()
+	}
+}
+impl Default for StatusHistory {
+	fn default() -> Self {
+		Self::new(DEFAULT_STATUS_HISTORY_CAPACITY)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::clock;
+
+	fn sample(error_count: u32) -> StatusSample {
+		StatusSample {
+			recorded_at: clock::utc_now(),
+			state: ProviderState::Ready,
+			refresh_succeeded: error_count == 0,
+			error_count,
+			#[cfg(feature = "metrics")]
+			hit_rate: 0.0,
+			#[cfg(feature = "metrics")]
+			stale_serve_ratio: 0.0,
+		}
+	}
+
+	#[tokio::test]
+	async fn evicts_oldest_sample_when_full() {
+		let history = StatusHistory::new(2);
+
+		for error_count in 0..3 {
+			history.record(sample(error_count)).await;
+		}
+
+		let counts: Vec<u32> =
+			history.samples().await.into_iter().map(|sample| sample.error_count).collect();
+
+		assert_eq!(counts, vec![1, 2]);
+	}
+
+	#[tokio::test]
+	async fn zero_capacity_disables_recording() {
+		let history = StatusHistory::new(0);
+
+		history.record(sample(0)).await;
+
+		assert!(history.samples().await.is_empty());
+	}
+}
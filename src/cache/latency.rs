@@ -0,0 +1,86 @@
+//! Rolling upstream fetch latency used to widen the proactive refresh lead.
+
+// std
+use std::{collections::VecDeque, sync::Mutex};
+// self
+use crate::_prelude::*;
+
+/// Number of recent fetch latencies retained per provider.
+pub const LATENCY_WINDOW: usize = 32;
+/// Multiple of the p95 latency the refresh lead must cover.
+pub const LEAD_LATENCY_FACTOR: u32 = 2;
+
+/// Fixed-size window of successful fetch latencies, oldest first.
+#[derive(Debug, Default)]
+pub struct LatencyWindow {
+	samples: Mutex<VecDeque<Duration>>,
+}
+impl LatencyWindow {
+	/// Record the latency of a successful fetch, evicting the oldest sample when full.
+	pub fn record(&self, latency: Duration) {
+		let mut samples = self.samples.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+		while samples.len() >= LATENCY_WINDOW {
+			samples.pop_front();
+		}
+
+		samples.push_back(latency);
+	}
+
+	/// 95th percentile of the retained latencies, or `None` before the first fetch.
+	pub fn p95(&self) -> Option<Duration> {
+		let mut sorted = self
+			.samples
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+			.iter()
+			.copied()
+			.collect::<Vec<_>>();
+
+		sorted.sort_unstable();
+
+		let index = (sorted.len() * 95).div_ceil(100).checked_sub(1)?;
+
+		sorted.get(index).copied()
+	}
+
+	/// Refresh lead covering [`LEAD_LATENCY_FACTOR`] times the p95 latency, capped at `cap`.
+	///
+	/// Returns `None` while `refresh_early` already covers it, so callers only act on adaptation.
+	pub fn widened_lead(&self, refresh_early: Duration, cap: Duration) -> Option<Duration> {
+		let needed = self.p95()?.saturating_mul(LEAD_LATENCY_FACTOR).min(cap);
+
+		(needed > refresh_early).then_some(needed)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn lead_widens_with_slow_fetches_up_to_the_cap() {
+		let window = LatencyWindow::default();
+		let refresh_early = Duration::from_secs(30);
+		let cap = Duration::from_secs(60);
+
+		assert_eq!(window.widened_lead(refresh_early, cap), None);
+
+		for _ in 0..LATENCY_WINDOW {
+			window.record(Duration::from_millis(200));
+		}
+
+		assert_eq!(window.widened_lead(refresh_early, cap), None);
+
+		window.record(Duration::from_secs(20));
+		window.record(Duration::from_secs(20));
+
+		assert_eq!(window.p95(), Some(Duration::from_secs(20)));
+		assert_eq!(window.widened_lead(refresh_early, cap), Some(Duration::from_secs(40)));
+
+		window.record(Duration::from_secs(45));
+		window.record(Duration::from_secs(45));
+
+		assert_eq!(window.widened_lead(refresh_early, cap), Some(cap));
+	}
+}
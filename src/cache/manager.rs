@@ -1,36 +1,75 @@
 //! Cache manager handling JWKS retrieval and lifecycle.
 
+// std
+use std::{
+	collections::HashMap,
+	future::Future,
+	sync::{
+		Mutex as StdMutex, OnceLock, RwLock as StdRwLock,
+		atomic::{AtomicBool, AtomicUsize, Ordering},
+	},
+};
 // crates.io
 use http::{
-	HeaderName, HeaderValue, Request, Response,
-	header::{ETAG, IF_NONE_MATCH, LAST_MODIFIED},
+	HeaderName, HeaderValue, Request, Response, StatusCode,
+	header::{AGE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
 };
-use http_cache_semantics::BeforeRequest;
-#[cfg(feature = "redis")] use http_cache_semantics::CachePolicy;
-use jsonwebtoken::jwk::JwkSet;
-use rand::Rng;
-use reqwest::{Client, redirect::Policy};
+use http_cache_semantics::{BeforeRequest, CachePolicy};
+use jsonwebtoken::{
+	DecodingKey,
+	jwk::{Jwk, JwkSet},
+};
+use reqwest::Client;
 use tokio::{
-	sync::{Mutex, RwLock},
+	sync::{Mutex, RwLock, SemaphorePermit, broadcast, oneshot, watch},
 	time,
 };
+use url::Url;
 // self
-#[cfg(feature = "metrics")] use crate::metrics::{self, ProviderMetrics};
-#[cfg(feature = "redis")] use crate::registry::PersistentSnapshot;
+#[cfg(feature = "zstd")] use crate::cache::storage::ParsedJwksCache;
+#[cfg(feature = "metrics")] use crate::metrics::{self, ProviderMetrics, ResolveSource};
 use crate::{
 	_prelude::*,
 	cache::{
+		activation::{self, KeyActivation},
+		breaker::{BreakerSnapshot, CircuitBreaker},
 		entry::CacheEntry,
+		history::{StatusHistory, StatusSample},
+		latency::LatencyWindow,
+		recovery,
+		residency::ResidentSet,
+		rotation::{self, KeyRotation, KeyRotationHook},
+		scheduler::RefreshScheduler,
+		single_flight::SingleFlight,
 		state::{CachePayload, CacheState},
+		storage::StoredJwks,
 	},
+	clock::{self, ClockAnchor},
+	events::{CacheEvent, CacheEventKind},
 	http::{
-		client::fetch_jwks,
+		capture::{self, CapturedExchange, ExchangeLog},
+		client::{self as http_client, HttpFetch, fetch_jwks, load_static_jwks},
+		connection::ConnectionTracker,
+		discovery::{self, CachedDiscovery},
+		rate_limit::HostRateLimiter,
 		retry::{AttemptBudget, RetryExecutor},
-		semantics::{Freshness, base_request, evaluate_freshness, evaluate_revalidation},
+		semantics::{self, Freshness, base_request, evaluate_freshness, evaluate_revalidation},
+	},
+	jitter,
+	persistence::{RefreshLock, SnapshotStore},
+	registry::{
+		HttpClientConfig, IdentityProviderRegistration, PersistentSnapshot, ProviderError,
+		ProviderState, SNAPSHOT_VERSION,
 	},
-	registry::IdentityProviderRegistration,
+	security::HostAllowlist,
+	tasks::TaskHandle,
 };
 
+const MAX_MISSING_KIDS: usize = 1_024;
+const FORCED_REVALIDATION_INTERVAL: Duration = Duration::from_secs(1);
+const LEASE_POLL_MIN: Duration = Duration::from_millis(50);
+const LEASE_POLL_MAX: Duration = Duration::from_secs(1);
+
 /// Coordinates fetching, caching, and background refresh for a registration.
 ///
 /// Instances are scoped per tenant/provider pair; the single-flight guard only
@@ -38,22 +77,66 @@ use crate::{
 #[derive(Clone, Debug)]
 pub struct CacheManager {
 	registration: Arc<IdentityProviderRegistration>,
+	// Registration used for upstream requests; differs once discovery remaps `jwks_url`.
+	upstream: Arc<StdRwLock<Arc<IdentityProviderRegistration>>>,
 	client: Arc<Client>,
 	entry: Arc<RwLock<CacheEntry>>,
-	single_flight: Arc<Mutex<()>>,
+	single_flight: Arc<SingleFlight>,
+	resolve_timeout: Option<Duration>,
+	in_flight: Arc<StdMutex<Option<watch::Receiver<Option<SharedRefresh>>>>>,
+	discovery: Arc<RwLock<Option<CachedDiscovery>>>,
+	missing_kids: Arc<Mutex<MissingKids>>,
+	history: Arc<StatusHistory>,
+	exchanges: Arc<ExchangeLog>,
+	connections: Arc<ConnectionTracker>,
+	latency: Arc<LatencyWindow>,
+	rotation_hooks: Arc<Vec<KeyRotationHook>>,
+	events: Option<broadcast::Sender<CacheEvent>>,
+	breaker: Option<Arc<CircuitBreaker>>,
+	tasks: Option<TaskHandle>,
+	retired: Arc<AtomicBool>,
+	recovering: Arc<AtomicBool>,
+	accept_full_rotation: Arc<AtomicBool>,
+	// Explicit revalidations in progress, which fetch upstream rather than adopt shared snapshots.
+	forced_revalidations: Arc<AtomicUsize>,
+	key_activation: Option<Arc<dyn KeyActivation>>,
+	rate_limiter: Option<Arc<HostRateLimiter>>,
+	host_allowlist: HostAllowlist,
+	scheduler: Option<Arc<RefreshScheduler>>,
+	snapshot_store: Option<Arc<dyn SnapshotStore>>,
+	write_through: bool,
+	refresh_lock: Option<Arc<dyn RefreshLock>>,
+	// Shared with the registry so persists from the refresh path never outlive `unregister`'s
+	// delete.
+	persistence: Arc<Mutex<()>>,
+	// Registry-wide LRU bounding resident payloads, with this manager's id in it.
+	residency: Option<(Arc<ResidentSet<CacheManager>>, u64)>,
+	// Set once an eviction dropped the payload, so the next load tries the snapshot store first.
+	evicted: Arc<AtomicBool>,
+	#[cfg(feature = "zstd")]
+	parsed_jwks: Option<Arc<ParsedJwksCache>>,
+	created_at: Instant,
+	first_ready_at: Arc<OnceLock<Instant>>,
+	jitter_seed: u64,
 	#[cfg(feature = "metrics")]
 	metrics: Arc<ProviderMetrics>,
 }
 impl CacheManager {
-	/// Build a new cache manager with the default reqwest client.
+	/// Build a new cache manager with the registration's HTTP client or proxy, or the default
+	/// client.
 	pub fn new(registration: IdentityProviderRegistration) -> Result<Self> {
 		registration.validate()?;
 
-		let client = Client::builder()
-			.redirect(Policy::limited(10))
-			.user_agent(format!("jwks-cache/{}", env!("CARGO_PKG_VERSION")))
-			.connect_timeout(Duration::from_secs(5))
-			.build()?;
+		let client = http_client::build_client(
+			registration.http_client.as_ref(),
+			registration.proxy.as_ref(),
+			&registration.trusted_root_certificates()?,
+			&registration.pinned_spki,
+			registration.client_identity.as_ref(),
+			registration.block_private_networks,
+			None,
+			&HttpClientConfig::default(),
+		)?;
 
 		#[cfg(feature = "metrics")]
 		let manager = Self::with_parts(registration, client, ProviderMetrics::new());
@@ -81,12 +164,51 @@ impl CacheManager {
 	) -> Self {
 		let tenant = registration.tenant_id.clone();
 		let provider = registration.provider_id.clone();
+		let jitter_seed = jitter::seed_for(&tenant, &provider);
+		let breaker = registration
+			.circuit_breaker
+			.clone()
+			.map(|policy| Arc::new(CircuitBreaker::new(policy)));
+
+		let registration = Arc::new(registration);
 
 		Self {
-			registration: Arc::new(registration),
+			upstream: Arc::new(StdRwLock::new(registration.clone())),
+			registration,
 			client: Arc::new(client),
 			entry: Arc::new(RwLock::new(CacheEntry::new(tenant, provider))),
-			single_flight: Arc::new(Mutex::new(())),
+			single_flight: Arc::new(SingleFlight::default()),
+			resolve_timeout: None,
+			in_flight: Arc::new(StdMutex::new(None)),
+			discovery: Arc::new(RwLock::new(None)),
+			missing_kids: Arc::new(Mutex::new(MissingKids::default())),
+			history: Arc::new(StatusHistory::default()),
+			exchanges: Arc::new(ExchangeLog::default()),
+			connections: Arc::new(ConnectionTracker::new()),
+			latency: Arc::new(LatencyWindow::default()),
+			rotation_hooks: Arc::new(Vec::new()),
+			events: None,
+			breaker,
+			tasks: None,
+			retired: Arc::new(AtomicBool::new(false)),
+			recovering: Arc::new(AtomicBool::new(false)),
+			accept_full_rotation: Arc::new(AtomicBool::new(false)),
+			forced_revalidations: Arc::new(AtomicUsize::new(0)),
+			key_activation: None,
+			rate_limiter: None,
+			host_allowlist: HostAllowlist::default(),
+			scheduler: None,
+			snapshot_store: None,
+			write_through: false,
+			refresh_lock: None,
+			persistence: Arc::new(Mutex::new(())),
+			residency: None,
+			evicted: Arc::new(AtomicBool::new(false)),
+			#[cfg(feature = "zstd")]
+			parsed_jwks: None,
+			created_at: Instant::now(),
+			first_ready_at: Arc::new(OnceLock::new()),
+			jitter_seed,
 			metrics,
 		}
 	}
@@ -95,13 +217,213 @@ impl CacheManager {
 	fn with_parts(registration: IdentityProviderRegistration, client: Client) -> Self {
 		let tenant = registration.tenant_id.clone();
 		let provider = registration.provider_id.clone();
+		let jitter_seed = jitter::seed_for(&tenant, &provider);
+		let breaker = registration
+			.circuit_breaker
+			.clone()
+			.map(|policy| Arc::new(CircuitBreaker::new(policy)));
+
+		let registration = Arc::new(registration);
 
 		Self {
-			registration: Arc::new(registration),
+			upstream: Arc::new(StdRwLock::new(registration.clone())),
+			registration,
 			client: Arc::new(client),
 			entry: Arc::new(RwLock::new(CacheEntry::new(tenant, provider))),
-			single_flight: Arc::new(Mutex::new(())),
+			single_flight: Arc::new(SingleFlight::default()),
+			resolve_timeout: None,
+			in_flight: Arc::new(StdMutex::new(None)),
+			discovery: Arc::new(RwLock::new(None)),
+			missing_kids: Arc::new(Mutex::new(MissingKids::default())),
+			history: Arc::new(StatusHistory::default()),
+			exchanges: Arc::new(ExchangeLog::default()),
+			connections: Arc::new(ConnectionTracker::new()),
+			latency: Arc::new(LatencyWindow::default()),
+			rotation_hooks: Arc::new(Vec::new()),
+			events: None,
+			breaker,
+			tasks: None,
+			retired: Arc::new(AtomicBool::new(false)),
+			recovering: Arc::new(AtomicBool::new(false)),
+			accept_full_rotation: Arc::new(AtomicBool::new(false)),
+			forced_revalidations: Arc::new(AtomicUsize::new(0)),
+			key_activation: None,
+			rate_limiter: None,
+			host_allowlist: HostAllowlist::default(),
+			scheduler: None,
+			snapshot_store: None,
+			write_through: false,
+			refresh_lock: None,
+			persistence: Arc::new(Mutex::new(())),
+			residency: None,
+			evicted: Arc::new(AtomicBool::new(false)),
+			#[cfg(feature = "zstd")]
+			parsed_jwks: None,
+			created_at: Instant::now(),
+			first_ready_at: Arc::new(OnceLock::new()),
+			jitter_seed,
+		}
+	}
+
+	/// Replace the status history with one retaining `capacity` samples.
+	pub fn with_history_capacity(mut self, capacity: usize) -> Self {
+		self.history = Arc::new(StatusHistory::new(capacity));
+
+		self
+	}
+
+	/// Status samples recorded after upstream refreshes, oldest first.
+	pub async fn status_history(&self) -> Vec<StatusSample> {
+		self.history.samples().await
+	}
+
+	/// Bound how long [`Self::resolve`] waits on upstream fetches; see
+	/// [`Self::resolve_with_timeout`].
+	pub fn with_resolve_timeout(mut self, timeout: Option<Duration>) -> Self {
+		self.resolve_timeout = timeout;
+
+		self
+	}
+
+	/// Replace the exchange log with one retaining `capacity` fetches.
+	pub fn with_exchange_capacity(mut self, capacity: usize) -> Self {
+		self.exchanges = Arc::new(ExchangeLog::new(capacity));
+
+		self
+	}
+
+	/// Invoke `hooks` whenever a refresh changes the set of key ids.
+	pub fn with_rotation_hooks(mut self, hooks: Vec<KeyRotationHook>) -> Self {
+		self.rotation_hooks = Arc::new(hooks);
+
+		self
+	}
+
+	/// Publish lifecycle events for this provider on `sender`.
+	pub fn with_event_sender(mut self, sender: broadcast::Sender<CacheEvent>) -> Self {
+		self.events = Some(sender);
+
+		self
+	}
+
+	/// Wait on `limiter`'s per-host budget before every upstream JWKS and discovery fetch.
+	pub fn with_rate_limiter(mut self, limiter: Option<Arc<HostRateLimiter>>) -> Self {
+		self.rate_limiter = limiter;
+
+		self
+	}
+
+	/// Reject redirects to hosts outside the registry-wide `allowlist`.
+	pub fn with_host_allowlist(mut self, allowlist: HostAllowlist) -> Self {
+		self.host_allowlist = allowlist;
+
+		self
+	}
+
+	/// Smear proactive refreshes and queue background refreshes through `scheduler`.
+	pub fn with_refresh_scheduler(mut self, scheduler: Option<Arc<RefreshScheduler>>) -> Self {
+		self.scheduler = scheduler;
+
+		self
+	}
+
+	/// Share refreshed keys through `store`; see [`Self::with_write_through`] and
+	/// [`Self::with_refresh_lock`].
+	///
+	/// Writes hold `persistence`, the lock the registry takes around bulk persists and snapshot
+	/// deletion, and are skipped once the manager is retired.
+	pub fn with_snapshot_store(
+		mut self,
+		store: Arc<dyn SnapshotStore>,
+		persistence: Arc<Mutex<()>>,
+	) -> Self {
+		self.snapshot_store = Some(store);
+		self.persistence = persistence;
+
+		self
+	}
+
+	/// Persist a snapshot to the snapshot store after every successful refresh.
+	pub fn with_write_through(mut self, enabled: bool) -> Self {
+		self.write_through = enabled;
+
+		self
+	}
+
+	/// Fetch upstream only while holding `lock`'s lease for this provider, and otherwise adopt
+	/// the snapshot the lease holder persists to the snapshot store.
+	///
+	/// Fetches without the lease when the lock is unreachable or the holder's lease runs out
+	/// before it persists newer keys.
+	pub fn with_refresh_lock(mut self, lock: Option<Arc<dyn RefreshLock>>) -> Self {
+		self.refresh_lock = lock;
+
+		self
+	}
+
+	/// Keep the cached payload only while this provider is among the `set`'s most recently used.
+	///
+	/// Payloads pushed out of the set are dropped by [`Self::evict`].
+	pub fn with_resident_set(mut self, set: Option<Arc<ResidentSet<CacheManager>>>) -> Self {
+		self.residency = set.map(|set| {
+			let id = set.next_id();
+
+			(set, id)
+		});
+
+		self
+	}
+
+	/// Spawn background refreshes into the task set behind `handle` instead of detaching them.
+	pub fn with_task_handle(mut self, handle: TaskHandle) -> Self {
+		self.tasks = Some(handle);
+
+		self
+	}
+
+	/// Take over the cache and runtime state of `previous`, a manager for the same provider.
+	///
+	/// The cached payload, status history, captured exchanges, fetch latencies, negative `kid`
+	/// cache, time to first usable keys, and metrics carry over, and refreshes coordinate with
+	/// those still running on `previous`. The circuit breaker starts closed under this manager's
+	/// registration.
+	pub fn with_state_of(mut self, previous: &CacheManager) -> Self {
+		self.entry = previous.entry.clone();
+		self.single_flight = previous.single_flight.clone();
+		self.in_flight = previous.in_flight.clone();
+		self.missing_kids = previous.missing_kids.clone();
+		self.history = previous.history.clone();
+		self.exchanges = previous.exchanges.clone();
+		self.latency = previous.latency.clone();
+		self.created_at = previous.created_at;
+		self.first_ready_at = previous.first_ready_at.clone();
+		self.evicted = previous.evicted.clone();
+		#[cfg(feature = "metrics")]
+		{
+			self.metrics = previous.metrics.clone();
 		}
+
+		self
+	}
+
+	/// Store fetched key sets zstd-compressed, parsing them on demand through `parsed`.
+	#[cfg(feature = "zstd")]
+	pub fn with_compressed_storage(mut self, parsed: Option<Arc<ParsedJwksCache>>) -> Self {
+		self.parsed_jwks = parsed;
+
+		self
+	}
+
+	/// Evaluate `hook` on every fetched JWKS to defer keys published ahead of use.
+	pub fn with_key_activation(mut self, hook: Option<Arc<dyn KeyActivation>>) -> Self {
+		self.key_activation = hook;
+
+		self
+	}
+
+	/// Redacted headers and timing of the most recent upstream fetches, oldest first.
+	pub async fn recent_exchanges(&self) -> Vec<CapturedExchange> {
+		self.exchanges.entries().await
 	}
 
 	/// Access the per-provider metrics accumulator.
@@ -110,18 +432,86 @@ impl CacheManager {
 		self.metrics.clone()
 	}
 
+	/// Registration the manager was built with.
+	pub fn registration(&self) -> Arc<IdentityProviderRegistration> {
+		self.registration.clone()
+	}
+
+	/// Registration currently used for upstream fetches.
+	///
+	/// Matches the registration the manager was built with until discovery remaps `jwks_url`.
+	pub fn effective_registration(&self) -> Arc<IdentityProviderRegistration> {
+		self.upstream()
+	}
+
+	/// Cached OpenID discovery document, when the registration uses discovery.
+	pub async fn discovery(&self) -> Option<CachedDiscovery> {
+		self.discovery.read().await.clone()
+	}
+
+	/// Seed the discovery cache with a document fetched during registration.
+	pub async fn store_discovery(&self, document: CachedDiscovery) {
+		*self.discovery.write().await = Some(document);
+	}
+
 	/// Capture the current cache state for status reporting.
 	pub async fn snapshot(&self) -> CacheSnapshot {
 		let captured_at = Instant::now();
-		let captured_at_wallclock = Utc::now();
+		let captured_at_wallclock = clock::utc_now();
 		let state = { self.entry.read().await.state().clone() };
+		let circuit = match &self.breaker {
+			Some(breaker) => Some(breaker.snapshot().await),
+			None => None,
+		};
+
+		CacheSnapshot {
+			captured_at,
+			captured_at_wallclock,
+			state,
+			circuit,
+			time_to_ready: self.time_to_ready(),
+		}
+	}
+
+	/// When the cache first held usable keys, fetched or restored from a snapshot.
+	pub fn first_ready_at(&self) -> Option<Instant> {
+		self.first_ready_at.get().copied()
+	}
+
+	/// Time from creating the manager, normally at registration, until it first held usable keys.
+	pub fn time_to_ready(&self) -> Option<Duration> {
+		self.first_ready_at().map(|ready_at| ready_at.saturating_duration_since(self.created_at))
+	}
 
-		CacheSnapshot { captured_at, captured_at_wallclock, state }
+	/// Detach this manager from its registry once the provider is unregistered or replaced.
+	///
+	/// A retired manager discards the results of refreshes still in flight, produces no
+	/// persistence snapshots, spawns no background work, and emits no further events. Retirement
+	/// is permanent and shared by every clone.
+	pub async fn retire(&self) {
+		// Taking the entry lock waits out a commit that already passed its retirement check.
+		let _entry = self.entry.write().await;
+
+		self.retired.store(true, Ordering::Release);
+
+		if let Some((set, id)) = &self.residency {
+			set.remove(*id);
+		}
+	}
+
+	/// Whether [`Self::retire`] has been called.
+	pub fn is_retired(&self) -> bool {
+		self.retired.load(Ordering::Acquire)
 	}
 
-	#[cfg(feature = "redis")]
 	/// Build a persistence payload capturing the current cache contents.
+	///
+	/// Returns `None` once the manager is retired.
 	pub async fn persistent_snapshot(&self) -> Result<Option<PersistentSnapshot>> {
+		if self.is_retired() {
+			return Ok(None);
+		}
+
 		let snapshot = self.snapshot().await;
 		let payload = match snapshot.state {
 			CacheState::Ready(ref payload) | CacheState::Refreshing(ref payload) => payload.clone(),
@@ -131,9 +521,10 @@ impl CacheManager {
 			Some(dt) => dt,
 			None => return Ok(None),
 		};
-		let jwks_json = serde_json::to_string(&*payload.jwks)?;
-		let persisted_at = Utc::now();
+		let jwks_json = serde_json::to_string(&*payload.jwks.load()?)?;
+		let persisted_at = clock::utc_now();
 		let snapshot = PersistentSnapshot {
+			version: SNAPSHOT_VERSION,
 			tenant_id: self.registration.tenant_id.clone(),
 			provider_id: self.registration.provider_id.clone(),
 			jwks_json,
@@ -141,33 +532,71 @@ impl CacheManager {
 			last_modified: payload.last_modified,
 			expires_at,
 			persisted_at,
+			age_seconds: Some(payload.policy.age(clock::system_now()).as_secs()),
+			key_activations: (*payload.activations).clone(),
 		};
 
 		Ok(Some(snapshot))
 	}
 
-	#[cfg(feature = "redis")]
 	/// Restore cache state from a previously persisted snapshot.
+	///
+	/// When the snapshot carries an `ETag` or `Last-Modified` validator, a conditional
+	/// revalidation is started in the background right away instead of waiting for expiry, so a
+	/// fleet restart costs upstream a round of `304 Not Modified` responses rather than full
+	/// downloads.
 	pub async fn restore_snapshot(&self, snapshot: PersistentSnapshot) -> Result<()> {
+		self.restore(snapshot, true).await
+	}
+
+	/// Like [`Self::restore_snapshot`], but leave keys a completed fetch already cached in place.
+	///
+	/// Used when restores run concurrently with resolves, where the snapshot may be older than
+	/// what the cache already holds.
+	pub async fn restore_snapshot_if_empty(&self, snapshot: PersistentSnapshot) -> Result<()> {
+		self.restore(snapshot, false).await
+	}
+
+	// Rebuild a cache payload from `snapshot`, carrying its age forward.
+	fn snapshot_payload(&self, snapshot: PersistentSnapshot) -> Result<CachePayload> {
 		snapshot.validate(&self.registration)?;
 
-		let PersistentSnapshot { jwks_json, etag, last_modified, expires_at, persisted_at, .. } =
-			snapshot;
+		let PersistentSnapshot {
+			jwks_json,
+			etag,
+			last_modified,
+			expires_at,
+			persisted_at,
+			age_seconds,
+			key_activations,
+			..
+		} = snapshot;
 		let jwks: JwkSet = serde_json::from_str(&jwks_json)?;
 		let jwks = Arc::new(jwks);
 		let ttl = (expires_at - persisted_at)
 			.to_std()
 			.unwrap_or_default()
-			.max(self.registration.min_ttl)
-			.min(self.registration.max_ttl);
-		let request = base_request(&self.registration)?;
+			.max(self.registration.min_ttl.as_duration())
+			.min(self.registration.max_ttl.as_duration());
+		// Carry the age forward so status reports keep showing how old the keys really are.
+		let age = age_seconds.map(|age| {
+			age.saturating_add((clock::utc_now() - persisted_at).num_seconds().max(0) as u64)
+		});
+		let request = base_request(&self.upstream())?;
 		let mut response = Response::builder()
 			.status(200)
-			.header("cache-control", format!("public, max-age={}", ttl.as_secs()))
+			.header(
+				"cache-control",
+				format!("public, max-age={}", ttl.as_secs().saturating_add(age.unwrap_or(0))),
+			)
 			.header("content-type", "application/json")
 			.body(())
 			.map_err(Error::from)?;
 
+		if let Some(age) = age {
+			response.headers_mut().insert(AGE, HeaderValue::from(age));
+		}
+
 		if let Some(ref etag_value) = etag {
 			let value = HeaderValue::from_str(etag_value).map_err(|err| Error::Validation {
 				field: "etag",
@@ -186,27 +615,166 @@ impl CacheManager {
 			response.headers_mut().insert(LAST_MODIFIED, value);
 		}
 
-		let policy = CachePolicy::new(&request, &response);
-		let freshness = Freshness { ttl, policy };
+		let policy = semantics::cache_policy(&request, &response);
+		let freshness = Freshness::new(ttl, policy);
 		let now = Instant::now();
-		let payload = self.build_payload(jwks, freshness, etag, last_modified, now, persisted_at);
+		let mut payload = self.build_payload(
+			self.store_jwks(jwks),
+			freshness,
+			etag,
+			last_modified,
+			now,
+			persisted_at,
+		);
+
+		payload.activations = Arc::new(key_activations);
+
+		Ok(payload)
+	}
+
+	async fn restore(&self, snapshot: PersistentSnapshot, replace_ready: bool) -> Result<()> {
+		let mut payload = self.snapshot_payload(snapshot)?;
+		let has_validators = payload.etag.is_some() || payload.last_modified.is_some();
+
+		if has_validators {
+			payload.next_refresh_at = Instant::now();
+		}
 
 		{
 			let mut entry = self.entry.write().await;
 
-			entry.load_success(payload.clone());
+			// A fetch already in flight will produce fresher data than the snapshot.
+			if matches!(entry.state(), CacheState::Loading | CacheState::Refreshing(_))
+				|| (!replace_ready && matches!(entry.state(), CacheState::Ready(_)))
+			{
+				tracing::debug!("cache already loading or loaded; skipping snapshot restore");
+
+				return Ok(());
+			}
+
+			entry.load_success(payload);
+			self.mark_ready();
+			self.mark_resident();
 		}
 
 		tracing::debug!(
 			tenant = %self.registration.tenant_id,
 			provider = %self.registration.provider_id,
+			has_validators,
 			"restored cache entry from persistent snapshot"
 		);
 
+		if has_validators {
+			self.trigger_refresh().await?;
+		}
+
 		Ok(())
 	}
 
 	/// Resolve JWKS for the registration, fetching upstream when necessary.
+	///
+	/// Waits at most the timeout set by [`Self::with_resolve_timeout`], if any. `kid`s are
+	/// rewritten by the registration's [`KidNamespace`](crate::KidNamespace), if any. Keys the
+	/// [`KeyActivation`] hook reports as not yet active are left out, as with
+	/// [`Self::resolve_key`].
+	pub async fn resolve(&self, kid: Option<&str>) -> Result<Arc<JwkSet>> {
+		let jwks = self.resolve_upstream(kid).await?;
+
+		Ok(self.namespaced(self.active_keys(jwks).await))
+	}
+
+	/// Resolve like [`Self::resolve`], but wait at most `timeout` for an upstream fetch.
+	///
+	/// The fetch keeps running in the background after the wait times out, so later resolves still
+	/// benefit from it. The caller then receives the cached payload if it is within its
+	/// `stale_while_error` window, and [`Error::ResolveTimeout`] otherwise.
+	pub async fn resolve_with_timeout(
+		&self,
+		kid: Option<&str>,
+		timeout: Duration,
+	) -> Result<Arc<JwkSet>> {
+		let jwks = self.resolve_bounded(kid, timeout).await?;
+
+		Ok(self.namespaced(self.active_keys(jwks).await))
+	}
+
+	/// Resolve like [`Self::resolve`], with per-call [`ResolveOptions`].
+	pub async fn resolve_with(
+		&self,
+		kid: Option<&str>,
+		options: &ResolveOptions,
+	) -> Result<Arc<JwkSet>> {
+		let timeout = options.timeout.or(self.resolve_timeout);
+		// The budget only applies when this call would queue behind a running refresh.
+		let budget = options.wait_budget.filter(|_| self.single_flight.is_busy());
+		let bound = match (timeout, budget) {
+			(Some(timeout), Some(budget)) => Some(timeout.min(budget)),
+			(timeout, budget) => timeout.or(budget),
+		};
+		let jwks = match bound {
+			Some(bound) => self.resolve_bounded(kid, bound).await?,
+			None => self.resolve_unbounded(kid).await?,
+		};
+
+		Ok(self.namespaced(self.active_keys(jwks).await))
+	}
+
+	async fn resolve_upstream(&self, kid: Option<&str>) -> Result<Arc<JwkSet>> {
+		match self.resolve_timeout {
+			Some(timeout) => self.resolve_bounded(kid, timeout).await,
+			None => self.resolve_unbounded(kid).await,
+		}
+	}
+
+	async fn resolve_bounded(&self, kid: Option<&str>, timeout: Duration) -> Result<Arc<JwkSet>> {
+		#[cfg(feature = "metrics")]
+		let started = Instant::now();
+		let snapshot = self.resolve_snapshot().await;
+
+		// Fresh payloads never wait on upstream, so skip the task spawn on the hot path. Retired
+		// managers cannot spawn the task at all.
+		if self.is_retired() || snapshot.is_some_and(|payload| !payload.is_expired(Instant::now()))
+		{
+			return self.resolve_unbounded(kid).await;
+		}
+
+		let (sender, receiver) = oneshot::channel();
+		let manager = self.clone();
+		let owned_kid = kid.map(str::to_owned);
+
+		self.spawn(async move {
+			let _ = sender.send(manager.resolve_unbounded(owned_kid.as_deref()).await);
+		});
+
+		match time::timeout(timeout, receiver).await {
+			Ok(Ok(outcome)) => outcome,
+			Ok(Err(_)) => Err(Error::Cache("Resolve was cancelled before it completed.".into())),
+			Err(_) => {
+				let snapshot = { self.entry.read().await.snapshot() };
+
+				match snapshot {
+					Some(payload) if payload.can_serve_stale(Instant::now()) => {
+						tracing::warn!(timeout = ?timeout, "resolve timed out, serving stale data");
+
+						#[cfg(feature = "metrics")]
+						{
+							self.observe_hit(true);
+							self.observe_resolve(ResolveSource::Stale, started);
+						}
+						self.emit(CacheEventKind::StaleServed);
+
+						payload.jwks.load()
+					},
+					_ => Err(Error::ResolveTimeout {
+						tenant: self.registration.tenant_id.clone(),
+						provider: self.registration.provider_id.clone(),
+						timeout,
+					}),
+				}
+			},
+		}
+	}
+
 	#[tracing::instrument(
 		skip(self, kid),
 		fields(
@@ -215,16 +783,19 @@ impl CacheManager {
 			kid = kid.unwrap_or_default()
 		)
 	)]
-	pub async fn resolve(&self, kid: Option<&str>) -> Result<Arc<JwkSet>> {
+	async fn resolve_unbounded(&self, kid: Option<&str>) -> Result<Arc<JwkSet>> {
+		#[cfg(feature = "metrics")]
+		let started = Instant::now();
+
 		loop {
-			let snapshot = { self.entry.read().await.snapshot() };
+			let snapshot = self.resolve_snapshot().await;
 			let now = Instant::now();
 
 			match snapshot {
 				None => {
 					tracing::debug!("cache empty; performing initial fetch");
 
-					match self.refresh_blocking(true).await? {
+					match self.refresh_coalesced(true).await? {
 						RefreshOutcome::Updated { jwks, from_cache } => {
 							if from_cache {
 								#[cfg(feature = "metrics")]
@@ -233,23 +804,40 @@ impl CacheManager {
 								#[cfg(feature = "metrics")]
 								self.observe_miss();
 							}
+							#[cfg(feature = "metrics")]
+							self.observe_resolve(ResolveSource::Refresh, started);
 
 							return Ok(jwks);
 						},
 						RefreshOutcome::Stale(jwks) => {
 							#[cfg(feature = "metrics")]
-							self.observe_hit(true);
+							{
+								self.observe_hit(true);
+								self.observe_resolve(ResolveSource::Stale, started);
+							}
+							self.emit(CacheEventKind::StaleServed);
 
 							return Ok(jwks);
 						},
 					}
 				},
 				Some(payload) => {
-					if !payload.is_expired(now) {
-						let jwks = payload.jwks.clone();
+					if !payload.is_expired(now) || payload.can_revalidate_in_background(now) {
+						let jwks = payload.jwks.load()?;
+						let stale = payload.is_expired(now);
 
 						#[cfg(feature = "metrics")]
-						self.observe_hit(false);
+						{
+							self.observe_hit(stale);
+							self.observe_resolve(
+								if stale { ResolveSource::Stale } else { ResolveSource::Memory },
+								started,
+							);
+						}
+						if stale {
+							self.emit(CacheEventKind::StaleServed);
+						}
+						self.mark_resident();
 
 						if now >= payload.next_refresh_at {
 							self.schedule_background_refresh(now).await;
@@ -261,7 +849,7 @@ impl CacheManager {
 					if payload.can_serve_stale(now) {
 						// TODO(refactor): consolidate stale fallback with perform_fetch_with_retry
 						// once the helper can orchestrate stale responses directly.
-						match self.refresh_blocking(false).await {
+						match self.refresh_coalesced(false).await {
 							Ok(RefreshOutcome::Updated { jwks, from_cache }) => {
 								if from_cache {
 									#[cfg(feature = "metrics")]
@@ -270,12 +858,18 @@ impl CacheManager {
 									#[cfg(feature = "metrics")]
 									self.observe_miss();
 								}
+								#[cfg(feature = "metrics")]
+								self.observe_resolve(ResolveSource::Refresh, started);
 
 								return Ok(jwks);
 							},
 							Ok(RefreshOutcome::Stale(jwks)) => {
 								#[cfg(feature = "metrics")]
-								self.observe_hit(true);
+								{
+									self.observe_hit(true);
+									self.observe_resolve(ResolveSource::Stale, started);
+								}
+								self.emit(CacheEventKind::StaleServed);
 
 								return Ok(jwks);
 							},
@@ -284,15 +878,19 @@ impl CacheManager {
 									tracing::warn!(error = %err, "refresh failed, serving stale data");
 
 									#[cfg(feature = "metrics")]
-									self.observe_hit(true);
+									{
+										self.observe_hit(true);
+										self.observe_resolve(ResolveSource::Stale, started);
+									}
+									self.emit(CacheEventKind::StaleServed);
 
-									return Ok(payload.jwks.clone());
+									return payload.jwks.load();
 								} else {
 									return Err(err);
 								},
 						}
 					} else if let RefreshOutcome::Updated { jwks, from_cache } =
-						self.refresh_blocking(true).await?
+						self.refresh_coalesced(true).await?
 					{
 						if from_cache {
 							#[cfg(feature = "metrics")]
@@ -301,6 +899,8 @@ impl CacheManager {
 							#[cfg(feature = "metrics")]
 							self.observe_miss();
 						}
+						#[cfg(feature = "metrics")]
+						self.observe_resolve(ResolveSource::Refresh, started);
 						return Ok(jwks);
 					}
 				},
@@ -308,6 +908,171 @@ impl CacheManager {
 		}
 	}
 
+	// Snapshot the payload, first reconciling its deadlines if the clocks jumped since they were
+	// computed.
+	async fn resolve_snapshot(&self) -> Option<CachePayload> {
+		let snapshot = { self.entry.read().await.snapshot() };
+		let policy = &self.registration.clock_jump;
+		let jumped = snapshot.as_ref().is_some_and(|payload| {
+			payload.clock_anchor.drift(Instant::now(), clock::system_now()).magnitude()
+				>= policy.threshold
+		});
+		let snapshot = if jumped { self.reconcile_clocks().await } else { snapshot };
+
+		#[cfg(feature = "metrics")]
+		if let Some(payload) = &snapshot {
+			self.observe_freshness(payload);
+		}
+
+		snapshot
+	}
+
+	async fn reconcile_clocks(&self) -> Option<CachePayload> {
+		let policy = &self.registration.clock_jump;
+		let mut entry = self.entry.write().await;
+
+		if let Some(drift) = entry.reconcile_clocks(policy, Instant::now(), clock::system_now()) {
+			tracing::warn!(
+				tenant = %self.registration.tenant_id,
+				provider = %self.registration.provider_id,
+				drift = ?drift,
+				reconciliation = ?policy.reconciliation,
+				"monotonic and wall clocks diverged"
+			);
+
+			#[cfg(feature = "metrics")]
+			metrics::record_clock_jump(
+				&self.registration.tenant_id,
+				&self.registration.provider_id,
+				drift,
+			);
+		}
+
+		entry.snapshot()
+	}
+
+	/// Resolve a single key by `kid`, forcing one revalidation when the kid is unknown.
+	///
+	/// Misses are remembered for [`IdentityProviderRegistration::missing_kid_ttl`], revalidations
+	/// triggered by concurrent misses are coalesced, and a new one starts at most once a second, so
+	/// floods of unknown kids do not turn into floods of upstream requests.
+	#[tracing::instrument(
+		skip(self),
+		fields(tenant = %self.registration.tenant_id, provider = %self.registration.provider_id)
+	)]
+	pub async fn resolve_key(&self, kid: &str) -> Result<Arc<Jwk>> {
+		let jwk = self.resolve_upstream_key(kid).await?;
+
+		match &self.registration.kid_namespace {
+			Some(_) => {
+				let mut jwk = (*jwk).clone();
+
+				jwk.common.key_id = Some(kid.to_owned());
+
+				Ok(Arc::new(jwk))
+			},
+			None => Ok(jwk),
+		}
+	}
+
+	async fn resolve_upstream_key(&self, kid: &str) -> Result<Arc<Jwk>> {
+		let started = Instant::now();
+		let kid = self.upstream_kid(kid)?;
+		let jwks = self.resolve_upstream(Some(kid)).await?;
+
+		if let Some(jwk) = jwks.find(kid) {
+			self.ensure_key_active(kid).await?;
+
+			return Ok(Arc::new(jwk.clone()));
+		}
+		if !self.claim_missing_kid_revalidation(kid).await {
+			tracing::debug!("kid recently missing; skipping revalidation");
+
+			return Err(self.key_not_found(kid));
+		}
+
+		tracing::debug!("unknown kid; forcing revalidation");
+
+		// The revalidation shares the resolve timeout with the lookup that preceded it.
+		let jwks = match self.resolve_timeout {
+			Some(timeout) =>
+				self.revalidate_missing_kid_bounded(timeout.saturating_sub(started.elapsed()))
+					.await?,
+			None => self.revalidate_missing_kid().await?,
+		};
+
+		match jwks.find(kid) {
+			Some(jwk) => {
+				self.missing_kids.lock().await.kids.remove(kid);
+				self.ensure_key_active(kid).await?;
+
+				Ok(Arc::new(jwk.clone()))
+			},
+			None => Err(self.key_not_found(kid)),
+		}
+	}
+
+	async fn revalidate_missing_kid(&self) -> Result<Arc<JwkSet>> {
+		let _forced = ForcedRevalidation::begin(&self.forced_revalidations);
+
+		// Concurrent misses for different kids share one upstream fetch.
+		match self.refresh_coalesced(true).await? {
+			RefreshOutcome::Updated { jwks, .. } | RefreshOutcome::Stale(jwks) => Ok(jwks),
+		}
+	}
+
+	// Like `resolve_bounded`, the revalidation keeps running after the wait times out, so a
+	// rotated key still lands in the cache for later calls.
+	async fn revalidate_missing_kid_bounded(&self, timeout: Duration) -> Result<Arc<JwkSet>> {
+		if self.is_retired() {
+			return self.revalidate_missing_kid().await;
+		}
+
+		let (sender, receiver) = oneshot::channel();
+		let manager = self.clone();
+
+		self.spawn(async move {
+			let _ = sender.send(manager.revalidate_missing_kid().await);
+		});
+
+		match time::timeout(timeout, receiver).await {
+			Ok(Ok(outcome)) => outcome,
+			Ok(Err(_)) =>
+				Err(Error::Cache("Revalidation was cancelled before it completed.".into())),
+			Err(_) => Err(Error::ResolveTimeout {
+				tenant: self.registration.tenant_id.clone(),
+				provider: self.registration.provider_id.clone(),
+				timeout: self.resolve_timeout.unwrap_or(timeout),
+			}),
+		}
+	}
+
+	/// Resolve the [`DecodingKey`] for `kid`, reusing the conversion cached on the payload.
+	///
+	/// Lookup follows [`Self::resolve_key`]; the conversion is redone only after a refresh
+	/// replaces the payload.
+	pub async fn decoding_key(&self, kid: &str) -> Result<Arc<DecodingKey>> {
+		let jwk = self.resolve_upstream_key(kid).await?;
+		let kid = self.upstream_kid(kid)?;
+		let cached = {
+			let entry = self.entry.read().await;
+
+			entry.state().payload().and_then(|payload| {
+				// Cache only against the payload the key came from, not one swapped in since.
+				let jwks = payload.jwks.load().ok()?;
+
+				jwks.find(kid)
+					.filter(|current| **current == *jwk)
+					.map(|_| payload.decoding_keys.clone())
+			})
+		};
+
+		match cached {
+			Some(keys) => keys.get_or_convert(kid, &jwk),
+			None => Ok(Arc::new(DecodingKey::from_jwk(&jwk)?)),
+		}
+	}
+
 	/// Trigger a manual refresh asynchronously; used by the control plane.
 	#[tracing::instrument(
 		skip(self),
@@ -336,11 +1101,14 @@ impl CacheManager {
 		match action {
 			RefreshTrigger::Background => {
 				let manager = self.clone();
+				let pending = PendingRefresh(Some(self.entry.clone()));
 
-				tokio::spawn(async move {
+				self.spawn(async move {
 					if let Err(err) = manager.refresh_blocking(true).await {
 						tracing::warn!(error = %err, "manual refresh failed");
 					}
+
+					pending.finish();
 				});
 			},
 			RefreshTrigger::Blocking => {
@@ -352,35 +1120,242 @@ impl CacheManager {
 		Ok(())
 	}
 
+	/// Start probing the upstream connection when the registration sets `keepalive_interval`.
+	///
+	/// Once a fetch has opened a connection, a `HEAD` request to the JWKS URL is sent whenever no
+	/// upstream traffic happened for the interval. The probe runs until the manager is retired or
+	/// its task set is dropped.
+	pub fn start_keepalive(&self) {
+		// Static key sets have no connection to keep warm.
+		let Some(interval) =
+			self.registration.keepalive_interval.filter(|_| !self.registration.is_static())
+		else {
+			return;
+		};
+
+		let manager = self.clone();
+
+		self.spawn_daemon(async move {
+			loop {
+				let idle = manager.connections.idle_for(Instant::now()).unwrap_or_default();
+
+				time::sleep(interval.saturating_sub(idle)).await;
+
+				if manager.is_retired() {
+					break;
+				}
+
+				manager.probe_connection(interval).await;
+			}
+		});
+	}
+
+	/// Revalidate against upstream now, sharing any refresh already in flight, and wait for it.
+	pub async fn revalidate(&self) -> Result<()> {
+		let _forced = ForcedRevalidation::begin(&self.forced_revalidations);
+
+		self.refresh_coalesced(true).await.map(|_| ())
+	}
+
+	/// Refresh now, accepting a key set that replaces every `kid` despite
+	/// [`IdentityProviderRegistration::reject_full_rotation`].
+	pub async fn force_refresh(&self) -> Result<()> {
+		self.accept_full_rotation.store(true, Ordering::Release);
+
+		let _override = FullRotationOverride(&self.accept_full_rotation);
+		let _forced = ForcedRevalidation::begin(&self.forced_revalidations);
+
+		self.refresh_blocking(true).await.map(|_| ())
+	}
+
+	/// Drop the cached payload so the next resolve fetches fresh keys from upstream.
+	///
+	/// Waits for any in-flight refresh so it cannot repopulate the entry afterwards. The negative
+	/// `kid` cache is cleared as well.
 	#[tracing::instrument(
 		skip(self),
 		fields(tenant = %self.registration.tenant_id, provider = %self.registration.provider_id)
 	)]
-	async fn schedule_background_refresh(&self, now: Instant) {
-		let should_spawn = {
-			let mut entry = self.entry.write().await;
+	pub async fn invalidate(&self) {
+		let _guard = self.enter_single_flight().await;
 
-			entry.begin_refresh(now)
-		};
-		if should_spawn {
-			let manager = self.clone();
+		self.entry.write().await.invalidate();
+		self.evicted.store(false, Ordering::Release);
+		*self.missing_kids.lock().await = MissingKids::default();
+		self.emit(CacheEventKind::Invalidated);
 
-			tokio::spawn(async move {
-				if let Err(err) = manager.refresh_blocking(true).await {
-					tracing::debug!(error = %err, "background refresh failed");
-				}
-			});
-		}
+		tracing::info!("cache entry invalidated");
 	}
 
+	/// Drop the cached payload to free memory, keeping the registration and its runtime state.
+	///
+	/// The payload is persisted first when a snapshot store is configured, and the next resolve
+	/// restores it from there if it is still fresh, fetching upstream otherwise. Nothing is
+	/// dropped while a load is in flight, or when the provider was used again after the
+	/// registry's resident set pushed it out.
 	#[tracing::instrument(
-		skip(self, force_revalidation),
-		fields(tenant = %self.registration.tenant_id, provider = %self.registration.provider_id, force_revalidation)
+		skip(self),
+		fields(tenant = %self.registration.tenant_id, provider = %self.registration.provider_id)
 	)]
-	async fn refresh_blocking(&self, force_revalidation: bool) -> Result<RefreshOutcome> {
-		let _guard = self.single_flight.lock().await;
-		let now = Instant::now();
-		let (existing, mode) = {
+	pub async fn evict(&self) {
+		let _guard = self.enter_single_flight().await;
+
+		if self.residency.as_ref().is_some_and(|(set, id)| set.contains(*id)) {
+			return;
+		}
+		if !self.write_through {
+			self.persist_now().await;
+		}
+
+		{
+			let mut entry = self.entry.write().await;
+
+			if self.is_retired() || !matches!(entry.state(), CacheState::Ready(_)) {
+				return;
+			}
+
+			entry.invalidate();
+			self.evicted.store(true, Ordering::Release);
+		}
+
+		self.emit(CacheEventKind::Evicted);
+
+		tracing::debug!("cold cache entry evicted");
+	}
+
+	// Keep fetching in the background after failures left the entry empty, unless a probe is
+	// already running. The probe stops once keys are cached again or the manager is retired.
+	//
+	// Synchronous on purpose: awaiting it from `refresh_blocking` would make that future's `Send`
+	// bound depend on itself through the spawned probe.
+	fn start_recovery_probe(&self) {
+		let Some(policy) = self.registration.recovery_probe.clone() else {
+			return;
+		};
+
+		if self.recovering.swap(true, Ordering::AcqRel) {
+			return;
+		}
+
+		let manager = self.clone();
+
+		self.spawn_daemon(async move {
+			let mut attempt = 0;
+
+			loop {
+				time::sleep(recovery::probe_delay(&policy, attempt, manager.jitter_seed)).await;
+
+				if manager.is_retired()
+					|| !matches!(manager.entry.read().await.state(), CacheState::Empty)
+				{
+					break;
+				}
+
+				tracing::debug!(attempt, "probing upstream to recover an empty cache entry");
+
+				if manager.refresh_blocking(false).await.is_ok() {
+					tracing::info!(attempt, "recovery probe restored the cache entry");
+
+					break;
+				}
+
+				attempt = attempt.saturating_add(1);
+			}
+
+			manager.recovering.store(false, Ordering::Release);
+		});
+	}
+
+	#[tracing::instrument(
+		skip(self),
+		fields(tenant = %self.registration.tenant_id, provider = %self.registration.provider_id)
+	)]
+	async fn schedule_background_refresh(&self, now: Instant) {
+		let should_spawn = {
+			let mut entry = self.entry.write().await;
+
+			entry.begin_refresh(now)
+		};
+		if should_spawn {
+			let manager = self.clone();
+			// Moved into the task, so it also fires when the spawn is rejected.
+			let pending = PendingRefresh(Some(self.entry.clone()));
+
+			self.spawn(async move {
+				let _slot = manager.enter_refresh_queue().await;
+
+				if let Err(err) = manager.refresh_blocking(true).await {
+					tracing::debug!(error = %err, "background refresh failed");
+				}
+
+				pending.finish();
+			});
+		}
+	}
+
+	#[tracing::instrument(
+		skip(self, force_revalidation),
+		fields(tenant = %self.registration.tenant_id, provider = %self.registration.provider_id, force_revalidation)
+	)]
+	// Run a foreground refresh, or wait for the one another caller already started and share its
+	// outcome, so concurrent resolves issue a single upstream fetch. When callers shared a refresh,
+	// each of them receives its failure as `Error::Shared`.
+	async fn refresh_coalesced(&self, force_revalidation: bool) -> Result<RefreshOutcome> {
+		loop {
+			let joined = {
+				let mut in_flight =
+					self.in_flight.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+				match in_flight.as_ref() {
+					Some(receiver) => Ok(receiver.clone()),
+					None => {
+						let (sender, receiver) = watch::channel(None);
+
+						*in_flight = Some(receiver);
+
+						Err(sender)
+					},
+				}
+			};
+
+			match joined {
+				Ok(mut receiver) => {
+					// The leader was cancelled before finishing; take over.
+					let Ok(shared) = receiver.wait_for(Option::is_some).await else {
+						continue;
+					};
+
+					if let Some(outcome) = shared.clone() {
+						return outcome.map_err(Error::Shared);
+					}
+				},
+				Err(sender) => {
+					let leader = InFlightGuard(&self.in_flight);
+					let outcome = self.refresh_blocking(force_revalidation).await;
+
+					drop(leader);
+
+					if sender.receiver_count() == 0 {
+						return outcome;
+					}
+
+					let outcome = outcome.map_err(Arc::new);
+
+					sender.send_replace(Some(outcome.clone()));
+
+					return outcome.map_err(Error::Shared);
+				},
+			}
+		}
+	}
+
+	async fn refresh_blocking(&self, force_revalidation: bool) -> Result<RefreshOutcome> {
+		let _guard = self.enter_single_flight().await;
+
+		self.refresh_discovery_if_expired().await;
+
+		let now = Instant::now();
+		let (existing, mode) = {
 			let mut entry = self.entry.write().await;
 			let snapshot = entry.snapshot();
 			let mode = if snapshot.is_some() {
@@ -396,11 +1371,488 @@ impl CacheManager {
 			(snapshot, mode)
 		};
 
+		if let Some(jwks) = self.rehydrate(mode).await {
+			return Ok(RefreshOutcome::Updated { jwks, from_cache: true });
+		}
+
 		match self.prepare_request(existing.as_ref(), force_revalidation)? {
 			PreparedRequest::UseCached { jwks } =>
 				Ok(RefreshOutcome::Updated { jwks, from_cache: true }),
-			PreparedRequest::Send(request) =>
-				self.perform_fetch_with_retry(*request, existing, mode, force_revalidation).await,
+			PreparedRequest::Send(request) => {
+				if let Some(breaker) = &self.breaker
+					&& let Err(open_until) = breaker.admit(now).await
+				{
+					return self
+						.reject_open_circuit(existing, force_revalidation, now, open_until)
+						.await;
+				}
+
+				let lease = match self.follow_refresh_lease(existing.as_ref(), mode).await {
+					LeaseDecision::Adopted(jwks) =>
+						return Ok(RefreshOutcome::Updated { jwks, from_cache: true }),
+					LeaseDecision::Fetch(lease) => lease,
+				};
+
+				self.emit(CacheEventKind::RefreshStarted);
+
+				let outcome = self
+					.perform_fetch_with_retry(*request, existing, mode, force_revalidation)
+					.await;
+
+				if let Some(lock) = &self.refresh_lock {
+					self.share_refresh(lock.as_ref(), lease, &outcome).await;
+				}
+
+				if let Some(breaker) = &self.breaker {
+					// Stale fallbacks are failures too; only a completed fetch counts as success.
+					match &outcome {
+						Ok(RefreshOutcome::Updated { .. }) => breaker.record_success().await,
+						Ok(RefreshOutcome::Stale(_)) | Err(_) =>
+							breaker.record_failure(Instant::now()).await,
+					}
+				}
+
+				self.record_history(outcome.is_ok()).await;
+				self.emit(match &outcome {
+					Ok(_) => CacheEventKind::RefreshSucceeded,
+					Err(err) => CacheEventKind::RefreshFailed {
+						error: err.redacted(),
+						code: err.code().into(),
+					},
+				});
+
+				if outcome.is_err() && matches!(self.entry.read().await.state(), CacheState::Empty)
+				{
+					self.start_recovery_probe();
+				}
+
+				outcome
+			},
+		}
+	}
+
+	// With a refresh lock, only the lease holder fetches and the other replicas adopt the
+	// snapshot it persists. Replicas fetch without the lease once the holder's lease runs out, or
+	// when the lock is unreachable. Forced revalidations never adopt: a snapshot persisted before
+	// a new `kid` was published would hide it, so they take the lease if it is free and fetch
+	// either way.
+	async fn follow_refresh_lease(
+		&self,
+		existing: Option<&CachePayload>,
+		mode: FetchMode,
+	) -> LeaseDecision {
+		let (Some(lock), Some(store)) = (&self.refresh_lock, &self.snapshot_store) else {
+			return LeaseDecision::Fetch(None);
+		};
+		let tenant = &self.registration.tenant_id;
+		let provider = &self.registration.provider_id;
+
+		if self.is_forced_revalidation() {
+			return LeaseDecision::Fetch(lock.try_acquire(tenant, provider).await.ok().flatten());
+		}
+
+		let lease_duration = lock.lease_duration();
+		let deadline = Instant::now() + lease_duration;
+		let poll = (lease_duration / 10).clamp(LEASE_POLL_MIN, LEASE_POLL_MAX);
+
+		loop {
+			if let Some(jwks) = self.adopt_persisted(store.as_ref(), existing, mode).await {
+				tracing::debug!("adopted keys persisted by the refresh lease holder");
+
+				return LeaseDecision::Adopted(jwks);
+			}
+
+			match lock.try_acquire(tenant, provider).await {
+				Ok(Some(token)) => return LeaseDecision::Fetch(Some(token)),
+				Ok(None) => {},
+				Err(err) => {
+					tracing::warn!(error = %err, "refresh lock unavailable; fetching locally");
+
+					return LeaseDecision::Fetch(None);
+				},
+			}
+
+			if Instant::now() >= deadline {
+				tracing::warn!("refresh lease holder persisted no keys in time; fetching locally");
+
+				return LeaseDecision::Fetch(None);
+			}
+
+			time::sleep(poll).await;
+		}
+	}
+
+	fn is_forced_revalidation(&self) -> bool {
+		self.forced_revalidations.load(Ordering::Acquire) > 0
+	}
+
+	// Reload an evicted payload from the snapshot store instead of fetching it again.
+	async fn rehydrate(&self, mode: FetchMode) -> Option<Arc<JwkSet>> {
+		if !matches!(mode, FetchMode::Initial)
+			|| !self.evicted.swap(false, Ordering::AcqRel)
+			|| self.is_forced_revalidation()
+		{
+			return None;
+		}
+
+		let jwks = self.adopt_persisted(self.snapshot_store.as_deref()?, None, mode).await?;
+
+		tracing::debug!("rehydrated evicted cache entry from the snapshot store");
+
+		Some(jwks)
+	}
+
+	// Commit the persisted snapshot when it holds keys fresher than the cached ones.
+	async fn adopt_persisted(
+		&self,
+		store: &dyn SnapshotStore,
+		existing: Option<&CachePayload>,
+		mode: FetchMode,
+	) -> Option<Arc<JwkSet>> {
+		let snapshot = store
+			.load(&self.registration.tenant_id, &self.registration.provider_id)
+			.await
+			.inspect_err(|err| tracing::debug!(error = %err, "failed to load shared snapshot"))
+			.ok()??;
+		let payload = self
+			.snapshot_payload(snapshot)
+			.inspect_err(|err| tracing::debug!(error = %err, "ignoring invalid shared snapshot"))
+			.ok()?;
+
+		if payload.is_expired(Instant::now())
+			|| existing.is_some_and(|existing| payload.expires_at <= existing.expires_at)
+		{
+			return None;
+		}
+
+		let jwks = payload.jwks.load().ok()?;
+
+		self.commit_success(mode, payload).await;
+
+		Some(jwks)
+	}
+
+	// Publish a completed fetch to the other replicas, then release the lease if this replica
+	// held it.
+	async fn share_refresh(
+		&self,
+		lock: &dyn RefreshLock,
+		lease: Option<String>,
+		outcome: &Result<RefreshOutcome>,
+	) {
+		if let Ok(RefreshOutcome::Updated { from_cache: false, .. }) = outcome {
+			self.persist_now().await;
+		}
+
+		if let Some(token) = lease
+			&& let Err(err) = lock
+				.release(&self.registration.tenant_id, &self.registration.provider_id, &token)
+				.await
+		{
+			tracing::warn!(error = %err, "failed to release refresh lease");
+		}
+	}
+
+	async fn reject_open_circuit(
+		&self,
+		existing: Option<CachePayload>,
+		force_revalidation: bool,
+		now: Instant,
+		open_until: Instant,
+	) -> Result<RefreshOutcome> {
+		tracing::debug!("circuit open; skipping upstream fetch");
+
+		self.entry.write().await.defer_refresh(now, open_until);
+
+		if !force_revalidation
+			&& let Some(payload) = existing
+			&& payload.can_serve_stale(now)
+		{
+			return Ok(RefreshOutcome::Stale(payload.jwks.load()?));
+		}
+
+		let retry_at = TimeDelta::from_std(open_until.saturating_duration_since(now))
+			.map(|delta| clock::utc_now() + delta)
+			.unwrap_or_else(|_| clock::utc_now());
+
+		Err(Error::CircuitOpen {
+			tenant: self.registration.tenant_id.clone(),
+			provider: self.registration.provider_id.clone(),
+			retry_at,
+		})
+	}
+
+	async fn record_history(&self, refresh_succeeded: bool) {
+		let state = { self.entry.read().await.state().clone() };
+		let error_count = match &state {
+			CacheState::Ready(payload) | CacheState::Refreshing(payload) => payload.error_count,
+			CacheState::Empty | CacheState::Loading => 0,
+		};
+		#[cfg(feature = "metrics")]
+		let metrics = self.metrics.snapshot();
+
+		self.history
+			.record(StatusSample {
+				recorded_at: clock::utc_now(),
+				state: ProviderState::from(&state),
+				refresh_succeeded,
+				error_count,
+				#[cfg(feature = "metrics")]
+				hit_rate: metrics.hit_rate(),
+				#[cfg(feature = "metrics")]
+				stale_serve_ratio: metrics.stale_ratio(),
+			})
+			.await;
+	}
+
+	async fn refresh_discovery_if_expired(&self) {
+		let Some(settings) = &self.registration.discovery else {
+			return;
+		};
+		let expired = self
+			.discovery
+			.read()
+			.await
+			.as_ref()
+			.is_none_or(|cached| cached.is_expired(Instant::now()));
+
+		if !expired {
+			return;
+		}
+
+		self.throttle(&settings.issuer).await;
+
+		match discovery::fetch_discovery(
+			&self.client,
+			&self.registration,
+			&self.host_allowlist,
+			settings,
+		)
+		.await
+		{
+			Ok(document) => {
+				self.follow_jwks_uri(&document.metadata.jwks_uri);
+				self.store_discovery(document).await;
+			},
+			Err(err) => {
+				tracing::warn!(error = %err, "discovery refresh failed; keeping previous document");
+			},
+		}
+	}
+
+	// Wait for the registry-wide budget of `url`'s host, if one is configured.
+	async fn throttle(&self, url: &Url) {
+		let Some(limiter) = &self.rate_limiter else {
+			return;
+		};
+		let wait = limiter.acquire(url).await;
+
+		if wait.is_zero() {
+			return;
+		}
+
+		tracing::debug!(host = url.host_str(), ?wait, "upstream request delayed by rate limit");
+
+		#[cfg(feature = "metrics")]
+		metrics::record_rate_limit_wait(
+			&self.registration.tenant_id,
+			&self.registration.provider_id,
+			wait,
+		);
+	}
+
+	// Point upstream fetches at a relocated `jwks_uri`, provided it passes the same HTTPS and
+	// allowlist checks as a registered `jwks_url`.
+	fn follow_jwks_uri(&self, advertised: &Url) {
+		let current = self.upstream();
+
+		if &current.jwks_url == advertised {
+			return;
+		}
+
+		let mut remapped = (*current).clone();
+
+		remapped.jwks_url = advertised.clone();
+
+		if let Err(err) = remapped.validate() {
+			tracing::warn!(
+				tenant = %self.registration.tenant_id,
+				provider = %self.registration.provider_id,
+				advertised = %advertised,
+				error = %err,
+				"discovery document advertises a disallowed jwks_uri; keeping the previous one"
+			);
+			self.emit(CacheEventKind::JwksUriRejected {
+				advertised: advertised.to_string(),
+				error: err.to_string(),
+			});
+
+			return;
+		}
+
+		tracing::info!(
+			tenant = %self.registration.tenant_id,
+			provider = %self.registration.provider_id,
+			previous = %current.jwks_url,
+			current = %advertised,
+			"discovery document moved the jwks_uri; following it"
+		);
+
+		*self.upstream.write().unwrap_or_else(|poisoned| poisoned.into_inner()) =
+			Arc::new(remapped);
+
+		self.emit(CacheEventKind::JwksUriChanged {
+			previous: current.jwks_url.to_string(),
+			current: advertised.to_string(),
+		});
+	}
+
+	fn upstream(&self) -> Arc<IdentityProviderRegistration> {
+		self.upstream.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+	}
+
+	/// Record a revalidation attempt for `kid`, returning `false` while it is negatively cached or
+	/// the provider revalidated too recently.
+	///
+	/// Forced revalidations start at most once per `FORCED_REVALIDATION_INTERVAL`, whatever the
+	/// `kid`, so random kids cannot amplify upstream traffic. Misses refused by that limit are not
+	/// negatively cached, so a rotated `kid` is picked up by the next window.
+	async fn claim_missing_kid_revalidation(&self, kid: &str) -> bool {
+		let ttl = self.registration.missing_kid_ttl;
+
+		if ttl.is_zero() {
+			return true;
+		}
+
+		let now = Instant::now();
+		let mut missing = self.missing_kids.lock().await;
+
+		missing.kids.retain(|_, expires_at| *expires_at > now);
+
+		if missing.kids.contains_key(kid) {
+			return false;
+		}
+		// Joining a revalidation already in flight costs upstream nothing.
+		if !self.revalidation_in_flight() {
+			if missing.next_revalidation.is_some_and(|next| now < next) {
+				return false;
+			}
+
+			missing.next_revalidation = Some(now + FORCED_REVALIDATION_INTERVAL);
+		}
+		// Make room by forgetting the miss closest to expiring rather than refusing the kid.
+		if missing.kids.len() >= MAX_MISSING_KIDS
+			&& let Some(oldest) = missing
+				.kids
+				.iter()
+				.min_by_key(|(_, expires_at)| **expires_at)
+				.map(|(kid, _)| kid.clone())
+		{
+			missing.kids.remove(&oldest);
+		}
+
+		missing.kids.insert(kid.to_owned(), now + ttl);
+
+		true
+	}
+
+	fn revalidation_in_flight(&self) -> bool {
+		self.in_flight.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).is_some()
+	}
+
+	async fn ensure_key_active(&self, kid: &str) -> Result<()> {
+		let pending = {
+			let entry = self.entry.read().await;
+
+			entry
+				.state()
+				.payload()
+				.and_then(|payload| payload.pending_activation(kid, clock::utc_now()))
+		};
+
+		match pending {
+			Some(activates_at) => Err(Error::KeyNotActive {
+				tenant: self.registration.tenant_id.clone(),
+				provider: self.registration.provider_id.clone(),
+				kid: kid.to_owned(),
+				activates_at,
+			}),
+			None => Ok(()),
+		}
+	}
+
+	fn check_upstream_age(&self, policy: &CachePolicy) {
+		let Some(threshold) = self.registration.upstream_age_warning else {
+			return;
+		};
+		let age = policy.age(clock::system_now());
+
+		if age <= threshold {
+			return;
+		}
+
+		tracing::warn!(
+			tenant = %self.registration.tenant_id,
+			provider = %self.registration.provider_id,
+			age = ?age,
+			threshold = ?threshold,
+			"upstream served an aged JWKS copy"
+		);
+		#[cfg(feature = "metrics")]
+		metrics::record_upstream_age_exceeded(
+			&self.registration.tenant_id,
+			&self.registration.provider_id,
+			age,
+		);
+	}
+
+	// Lookups outside the namespace never reach upstream, so foreign kids cannot select a key.
+	fn upstream_kid<'a>(&self, kid: &'a str) -> Result<&'a str> {
+		match &self.registration.kid_namespace {
+			Some(namespace) => namespace.strip(kid).ok_or_else(|| self.key_not_found(kid)),
+			None => Ok(kid),
+		}
+	}
+
+	// Leave out keys whose activation time lies ahead, so callers of whole sets cannot verify with
+	// a key `resolve_key` would refuse.
+	async fn active_keys(&self, jwks: Arc<JwkSet>) -> Arc<JwkSet> {
+		let activations = {
+			let entry = self.entry.read().await;
+
+			entry.state().payload().map(|payload| payload.activations.clone())
+		};
+		let Some(activations) = activations.filter(|activations| !activations.is_empty()) else {
+			return jwks;
+		};
+		let now = clock::utc_now();
+		let pending = |jwk: &Jwk| {
+			jwk.common
+				.key_id
+				.as_ref()
+				.and_then(|kid| activations.get(kid))
+				.is_some_and(|activates_at| *activates_at > now)
+		};
+
+		if !jwks.keys.iter().any(pending) {
+			return jwks;
+		}
+
+		Arc::new(JwkSet { keys: jwks.keys.iter().filter(|jwk| !pending(jwk)).cloned().collect() })
+	}
+
+	fn namespaced(&self, jwks: Arc<JwkSet>) -> Arc<JwkSet> {
+		match &self.registration.kid_namespace {
+			Some(namespace) => Arc::new(namespace.apply_to_set(&jwks)),
+			None => jwks,
+		}
+	}
+
+	fn key_not_found(&self, kid: &str) -> Error {
+		Error::KeyNotFound {
+			tenant: self.registration.tenant_id.clone(),
+			provider: self.registration.provider_id.clone(),
+			kid: kid.to_owned(),
 		}
 	}
 
@@ -409,14 +1861,14 @@ impl CacheManager {
 		existing: Option<&CachePayload>,
 		force_revalidation: bool,
 	) -> Result<PreparedRequest> {
-		let mut request = base_request(&self.registration)?;
+		let mut request = base_request(&self.upstream())?;
 
 		if let Some(payload) = existing {
 			let mut send_conditional = force_revalidation;
 
-			match payload.policy.before_request(&request, SystemTime::now()) {
+			match payload.policy.before_request(&request, clock::system_now()) {
 				BeforeRequest::Fresh(_) if !force_revalidation => {
-					return Ok(PreparedRequest::UseCached { jwks: payload.jwks.clone() });
+					return Ok(PreparedRequest::UseCached { jwks: payload.jwks.load()? });
 				},
 				BeforeRequest::Stale { request: parts, matches } if matches => {
 					request = Request::from_parts(parts, ());
@@ -431,6 +1883,14 @@ impl CacheManager {
 			{
 				request.headers_mut().insert(IF_NONE_MATCH, value);
 			}
+			if send_conditional
+				&& !request.headers().contains_key(IF_MODIFIED_SINCE)
+				&& let Some(last_modified) = payload.last_modified
+				&& let Ok(value) =
+					HeaderValue::from_str(&httpdate::fmt_http_date(last_modified.into()))
+			{
+				request.headers_mut().insert(IF_MODIFIED_SINCE, value);
+			}
 		}
 
 		Ok(PreparedRequest::Send(Box::new(request)))
@@ -443,32 +1903,69 @@ impl CacheManager {
 		mode: FetchMode,
 		force_revalidation: bool,
 	) -> Result<RefreshOutcome> {
-		let mut executor = RetryExecutor::new(&self.registration.retry_policy);
+		let mut executor =
+			RetryExecutor::new(&self.registration.retry_policy).with_jitter_seed(self.jitter_seed);
 		let mut last_error: Option<Error> = None;
 		let mut last_backoff: Option<Duration> = None;
 		let request = request;
+		let upstream = self.upstream();
 
 		while let AttemptBudget::Granted { timeout } = executor.attempt_budget() {
-			#[cfg(feature = "metrics")]
 			let attempt_started = Instant::now();
-			let fetch = fetch_jwks(&self.client, &self.registration, &request, timeout).await;
+			let fetch = if upstream.is_static() {
+				load_static_jwks(&upstream, &request).await
+			} else {
+				self.fetch_with_failover(&upstream, &request, timeout).await
+			};
 
 			match fetch {
 				Ok(fetch) => {
 					let now = Instant::now();
+
+					self.observe_connection(fetch.connection_reused);
+					self.latency.record(attempt_started.elapsed());
+
+					// Refetching would return the same set, so a rejection is not retried.
+					if let (Some(fresh_jwks), Some(previous)) = (&fetch.jwks, existing.as_ref())
+						&& self.registration.reject_full_rotation
+						&& !self.accept_full_rotation.load(Ordering::Acquire)
+						&& let Ok(previous_jwks) = previous.jwks.load()
+						&& rotation::replaces_every_kid(&previous_jwks, fresh_jwks)
+					{
+						tracing::warn!("refresh replaced every kid; keeping the cached key set");
+
+						last_error = Some(Error::KeySetReplaced {
+							tenant: self.registration.tenant_id.clone(),
+							provider: self.registration.provider_id.clone(),
+						});
+
+						break;
+					}
+
 					let payload = match (&fetch.jwks, existing.as_ref()) {
 						(Some(fresh_jwks), _) => {
 							let freshness =
 								evaluate_freshness(&self.registration, &fetch.exchange)?;
 
-							self.build_payload(
-								fresh_jwks.clone(),
+							let mut payload = self.build_payload(
+								self.store_jwks(fresh_jwks.clone()),
 								freshness,
 								fetch.etag.clone(),
 								fetch.last_modified,
 								now,
-								Utc::now(),
-							)
+								clock::utc_now(),
+							);
+
+							if let (Some(hook), Some(document)) =
+								(&self.key_activation, &fetch.document)
+							{
+								payload.activations = Arc::new(activation::collect_activations(
+									hook.as_ref(),
+									document,
+								));
+							}
+
+							payload
 						},
 						(None, Some(previous)) => {
 							let revalidation = evaluate_revalidation(
@@ -480,15 +1977,19 @@ impl CacheManager {
 							let updated_etag = extract_header(&revalidation.response, &ETAG)
 								.or_else(|| previous.etag.clone());
 
-							self.build_payload(
+							let mut payload = self.build_payload(
 								previous.jwks.clone(),
 								revalidation.freshness,
 								updated_etag,
 								extract_last_modified(&revalidation.response)
 									.or(previous.last_modified),
 								now,
-								Utc::now(),
-							)
+								clock::utc_now(),
+							);
+
+							payload.activations = previous.activations.clone();
+
+							payload
 						},
 						(None, None) => {
 							return Err(Error::Cache(
@@ -497,22 +1998,39 @@ impl CacheManager {
 						},
 					};
 
-					let jwks = payload.jwks.clone();
+					let jwks = payload.jwks.load()?;
 
+					self.check_upstream_age(&payload.policy);
 					self.commit_success(mode, payload).await;
+
+					if let Some(previous) = &existing
+						&& let Ok(previous_jwks) = previous.jwks.load()
+					{
+						self.notify_rotation(&previous_jwks, &jwks);
+					}
 					#[cfg(feature = "metrics")]
 					self.observe_refresh_success(attempt_started.elapsed());
 
 					return Ok(RefreshOutcome::Updated { jwks, from_cache: false });
 				},
 				Err(err) => {
-					last_error = Some(err);
-
-					if !executor.can_retry() {
-						break;
+					let hint = err.retry_after();
+					// A rejected credential was invalidated, so the next attempt authenticates
+					// afresh.
+					let reauthenticate = self.registration.auth.is_some()
+						&& matches!(
+							err,
+							Error::HttpStatus { status: StatusCode::UNAUTHORIZED, .. }
+						);
+					let retryable = err.is_retryable() || reauthenticate;
+
+					if !retryable {
+						tracing::debug!(error = %err, "terminal upstream error; not retrying");
 					}
 
-					if let Some(delay) = executor.next_backoff() {
+					last_error = Some(err);
+
+					if retryable && let Some(delay) = executor.next_backoff_with_hint(hint) {
 						last_backoff = Some(delay);
 
 						if !delay.is_zero() {
@@ -520,6 +2038,11 @@ impl CacheManager {
 						}
 						continue;
 					}
+					// Without a retry left in this window, the hint still postpones the next
+					// background refresh.
+					if hint.is_some() {
+						last_backoff = hint;
+					}
 
 					break;
 				},
@@ -527,6 +2050,13 @@ impl CacheManager {
 		}
 
 		let now = Instant::now();
+		let err = last_error
+			.unwrap_or_else(|| Error::Cache("Refresh attempts exhausted.".into()))
+			.with_provider(
+				&self.registration.tenant_id,
+				&self.registration.provider_id,
+				&capture::redact_url(&upstream.jwks_url),
+			);
 
 		match mode {
 			FetchMode::Initial => {
@@ -537,7 +2067,11 @@ impl CacheManager {
 			FetchMode::Refresh => {
 				let mut entry = self.entry.write().await;
 
-				entry.refresh_failure(now, last_backoff);
+				entry.refresh_failure(
+					now,
+					last_backoff,
+					ProviderError::from_error(&err, clock::utc_now()),
+				);
 			},
 		}
 
@@ -548,24 +2082,309 @@ impl CacheManager {
 			&& let Some(payload) = existing
 			&& payload.can_serve_stale(now)
 		{
-			return Ok(RefreshOutcome::Stale(payload.jwks));
+			return Ok(RefreshOutcome::Stale(payload.jwks.load()?));
 		}
 
-		Err(last_error.unwrap_or_else(|| Error::Cache("Refresh attempts exhausted.".into())))
+		Err(err)
+	}
+
+	// Fetch from `jwks_url`, then from each mirror in order, returning the first success or the
+	// primary's error.
+	//
+	// `timeout` bounds the whole attempt: each URL gets an even share of what is left for it and
+	// the URLs after it, so a hung primary cannot starve the mirrors and failover cannot multiply
+	// the attempt timeout.
+	async fn fetch_with_failover(
+		&self,
+		upstream: &IdentityProviderRegistration,
+		request: &Request<()>,
+		timeout: Duration,
+	) -> Result<HttpFetch> {
+		let deadline = Instant::now() + timeout;
+		let share = |urls_left: usize| {
+			deadline.saturating_duration_since(Instant::now())
+				/ u32::try_from(urls_left).unwrap_or(u32::MAX)
+		};
+
+		self.throttle(&upstream.jwks_url).await;
+
+		let primary_err = match fetch_jwks(
+			&self.client,
+			upstream,
+			&self.host_allowlist,
+			request,
+			share(1 + upstream.mirror_urls.len()),
+			&self.exchanges,
+			&self.connections,
+		)
+		.await
+		{
+			Ok(fetch) => return Ok(fetch),
+			Err(err) => err,
+		};
+		let mut mirrored = upstream.clone();
+
+		for (index, mirror) in upstream.mirror_urls.iter().enumerate() {
+			let timeout = share(upstream.mirror_urls.len() - index);
+
+			if timeout.is_zero() {
+				tracing::debug!("attempt timeout spent; skipping remaining jwks mirrors");
+
+				break;
+			}
+
+			let redacted = capture::redact_url(mirror);
+
+			tracing::warn!(mirror = %redacted, error = %primary_err, "trying jwks mirror");
+
+			mirrored.jwks_url = mirror.clone();
+			self.throttle(mirror).await;
+
+			match fetch_jwks(
+				&self.client,
+				&mirrored,
+				&self.host_allowlist,
+				request,
+				timeout,
+				&self.exchanges,
+				&self.connections,
+			)
+			.await
+			{
+				Ok(fetch) => {
+					self.emit(CacheEventKind::MirrorUsed {
+						mirror: redacted.to_string(),
+						error: primary_err.to_string(),
+					});
+
+					return Ok(fetch);
+				},
+				Err(err) => tracing::debug!(mirror = %redacted, error = %err, "jwks mirror failed"),
+			}
+		}
+
+		Err(primary_err)
 	}
 
 	async fn commit_success(&self, mode: FetchMode, payload: CachePayload) {
 		let mut entry = self.entry.write().await;
 
+		// Checked under the entry lock so nothing is committed once `retire` returns.
+		if self.is_retired() {
+			tracing::debug!("manager retired; discarding refreshed payload");
+
+			return;
+		}
+
+		#[cfg(feature = "metrics")]
+		self.observe_freshness(&payload);
+
 		match mode {
 			FetchMode::Initial => entry.load_success(payload),
 			FetchMode::Refresh => entry.refresh_success(payload),
 		}
+
+		self.mark_ready();
+		self.mark_resident();
+		self.persist_committed();
+	}
+
+	// Write the committed payload through to the snapshot store in the background. Each write
+	// snapshots the entry when it runs, so the last one always stores the newest keys. With a
+	// refresh lock, the refresh path already persisted before releasing the lease.
+	fn persist_committed(&self) {
+		if !self.write_through || self.refresh_lock.is_some() {
+			return;
+		}
+
+		let manager = self.clone();
+
+		self.spawn(async move { manager.persist_now().await });
+	}
+
+	async fn persist_now(&self) {
+		let Some(store) = &self.snapshot_store else {
+			return;
+		};
+		let _persistence = self.persistence.lock().await;
+		let persisted = match self.persistent_snapshot().await {
+			Ok(Some(snapshot)) => store.persist(&[snapshot]).await,
+			Ok(None) => Ok(()),
+			Err(err) => Err(err),
+		};
+
+		if let Err(err) = persisted {
+			tracing::warn!(
+				tenant = %self.registration.tenant_id,
+				provider = %self.registration.provider_id,
+				error = %err,
+				"snapshot persist failed"
+			);
+		}
+	}
+
+	// Move this provider to the front of the resident set and evict whatever it pushed out. The
+	// evictions are spawned since callers may hold this entry's lock.
+	fn mark_resident(&self) {
+		let Some((set, id)) = &self.residency else {
+			return;
+		};
+
+		for victim in set.touch(*id, self) {
+			let manager = victim.clone();
+
+			victim.spawn(async move { manager.evict().await });
+		}
+	}
+
+	// Record the first time the entry held usable keys; later calls are no-ops.
+	fn mark_ready(&self) {
+		let now = Instant::now();
+
+		if self.first_ready_at.set(now).is_err() {
+			return;
+		}
+
+		let elapsed = now.saturating_duration_since(self.created_at);
+
+		tracing::info!(
+			tenant = %self.registration.tenant_id,
+			provider = %self.registration.provider_id,
+			time_to_ready = ?elapsed,
+			"first usable keys cached"
+		);
+
+		#[cfg(feature = "metrics")]
+		metrics::record_time_to_ready(
+			&self.registration.tenant_id,
+			&self.registration.provider_id,
+			elapsed,
+		);
+	}
+
+	fn spawn<F>(&self, task: F)
+	where
+		F: Future<Output = ()> + Send + 'static,
+	{
+		if self.is_retired() {
+			return;
+		}
+
+		match &self.tasks {
+			Some(tasks) => tasks.spawn(task),
+			None => {
+				tokio::spawn(task);
+			},
+		}
+	}
+
+	// Like `spawn`, for loops that run until the manager is retired; draining aborts them.
+	fn spawn_daemon<F>(&self, task: F)
+	where
+		F: Future<Output = ()> + Send + 'static,
+	{
+		if self.is_retired() {
+			return;
+		}
+
+		match &self.tasks {
+			Some(tasks) => tasks.spawn_daemon(task),
+			None => {
+				tokio::spawn(task);
+			},
+		}
+	}
+
+	fn emit(&self, kind: CacheEventKind) {
+		let Some(sender) = &self.events else {
+			return;
+		};
+
+		if self.is_retired() {
+			return;
+		}
+
+		// Skip building the event when nobody is listening; send only fails without receivers.
+		if sender.receiver_count() > 0 {
+			let _ = sender.send(CacheEvent::new(
+				&self.registration.tenant_id,
+				&self.registration.provider_id,
+				kind,
+			));
+		}
+	}
+
+	fn notify_rotation(&self, previous: &Arc<JwkSet>, current: &Arc<JwkSet>) {
+		if self.is_retired() {
+			return;
+		}
+		// A 304 revalidation reuses the cached set, so there is nothing to compare.
+		if Arc::ptr_eq(previous, current) {
+			return;
+		}
+
+		let Some(rotation) = KeyRotation::between(
+			&self.registration.tenant_id,
+			&self.registration.provider_id,
+			previous,
+			current,
+		) else {
+			return;
+		};
+
+		tracing::info!(
+			tenant = %rotation.tenant_id,
+			provider = %rotation.provider_id,
+			added = ?rotation.added,
+			removed = ?rotation.removed,
+			"signing keys rotated"
+		);
+
+		for hook in self.rotation_hooks.iter() {
+			hook.notify(&rotation);
+		}
+	}
+
+	// The configured lead, widened towards `max_refresh_early` when fetches have become slow enough
+	// that a proactive refresh could still be running at expiry.
+	fn refresh_early(&self) -> Duration {
+		let refresh_early = self.registration.effective_refresh_early();
+		let Some(cap) = self.registration.max_refresh_early else {
+			return refresh_early;
+		};
+		let Some(lead) = self.latency.widened_lead(refresh_early, cap) else {
+			return refresh_early;
+		};
+
+		tracing::debug!(
+			tenant = %self.registration.tenant_id,
+			provider = %self.registration.provider_id,
+			configured = ?refresh_early,
+			lead = ?lead,
+			"widened refresh lead for upstream latency"
+		);
+		#[cfg(feature = "metrics")]
+		metrics::record_refresh_lead_adapted(
+			&self.registration.tenant_id,
+			&self.registration.provider_id,
+			lead,
+		);
+
+		lead
+	}
+
+	fn store_jwks(&self, jwks: Arc<JwkSet>) -> StoredJwks {
+		#[cfg(feature = "zstd")]
+		let stored = StoredJwks::new(jwks, self.parsed_jwks.as_ref());
+		#[cfg(not(feature = "zstd"))]
+		let stored = StoredJwks::from(jwks);
+
+		stored
 	}
 
 	fn build_payload(
 		&self,
-		jwks: Arc<JwkSet>,
+		jwks: StoredJwks,
 		freshness: Freshness,
 		etag: Option<String>,
 		last_modified: Option<DateTime<Utc>>,
@@ -574,28 +2393,37 @@ impl CacheManager {
 	) -> CachePayload {
 		let ttl = freshness.ttl;
 		let expires_at = now + ttl;
-		let mut refresh_at = if self.registration.refresh_early >= ttl {
-			now
-		} else {
-			expires_at - self.registration.refresh_early
+		let refresh_early = self.refresh_early();
+		// Upstream directives may narrow the windows the registration allows, never widen them.
+		let revalidate_window = freshness
+			.stale_while_revalidate
+			.map_or(Duration::ZERO, |window| window.min(refresh_early));
+		let stale_while_error = match freshness.stale_if_error {
+			Some(window) => window.min(self.registration.effective_stale_while_error()),
+			None => self.registration.effective_stale_while_error(),
 		};
+		// Time spent revalidating in the background past expiry comes out of the refresh lead.
+		let lead = refresh_early - revalidate_window;
+		let mut refresh_at = if lead >= ttl { now } else { expires_at - lead };
 
 		if !self.registration.prefetch_jitter.is_zero() {
-			let jitter = random_jitter(self.registration.prefetch_jitter);
+			let offset = jitter::up_to(self.registration.prefetch_jitter, self.jitter_seed);
 
-			if refresh_at > now + jitter {
-				refresh_at -= jitter;
+			if refresh_at > now + offset {
+				refresh_at -= offset;
 			}
 		}
+		if let Some(scheduler) = &self.scheduler {
+			refresh_at = scheduler.smear(now, refresh_at, self.jitter_seed);
+		}
 
-		let stale_deadline = if self.registration.stale_while_error.is_zero() {
-			None
-		} else {
-			Some(expires_at + self.registration.stale_while_error)
-		};
+		let stale_deadline =
+			if stale_while_error.is_zero() { None } else { Some(expires_at + stale_while_error) };
 
 		CachePayload {
 			jwks,
+			activations: Default::default(),
+			decoding_keys: Default::default(),
 			policy: freshness.policy,
 			etag,
 			last_modified,
@@ -603,9 +2431,56 @@ impl CacheManager {
 			expires_at,
 			next_refresh_at: refresh_at,
 			stale_deadline,
+			revalidate_deadline: (!revalidate_window.is_zero())
+				.then(|| expires_at + revalidate_window),
 			retry_backoff: None,
 			error_count: 0,
+			last_error: None,
+			clock_anchor: ClockAnchor { monotonic: now, wall: clock::system_now() },
+		}
+	}
+
+	// Wait for a registry-wide background refresh slot, if a scheduler is configured.
+	async fn enter_refresh_queue(&self) -> Option<SemaphorePermit<'_>> {
+		let scheduler = self.scheduler.as_ref()?;
+		let (permit, wait) = scheduler.acquire().await;
+
+		if !wait.is_zero() {
+			tracing::debug!(?wait, "background refresh queued behind the concurrency cap");
+
+			#[cfg(feature = "metrics")]
+			metrics::record_refresh_queue_wait(
+				&self.registration.tenant_id,
+				&self.registration.provider_id,
+				wait,
+			);
+		}
+
+		Some(permit)
+	}
+
+	async fn enter_single_flight(&self) -> SemaphorePermit<'_> {
+		#[cfg(feature = "metrics")]
+		let (tenant, provider) = (&self.registration.tenant_id, &self.registration.provider_id);
+		let (permit, wait) = self
+			.single_flight
+			.acquire(|waiters| {
+				#[cfg(feature = "metrics")]
+				metrics::record_single_flight_waiters(tenant, provider, waiters);
+				#[cfg(not(feature = "metrics"))]
+				let _ = waiters;
+			})
+			.await;
+
+		#[cfg(feature = "metrics")]
+		{
+			metrics::record_single_flight_wait(tenant, provider, wait);
+			metrics::record_single_flight_waiters(tenant, provider, self.single_flight.waiters());
 		}
+		#[cfg(not(feature = "metrics"))]
+		let _ = wait;
+
+		permit
 	}
 
 	#[cfg(feature = "metrics")]
@@ -618,6 +2493,32 @@ impl CacheManager {
 		self.metrics.record_hit(stale);
 	}
 
+	#[cfg(feature = "metrics")]
+	fn observe_resolve(&self, source: ResolveSource, started: Instant) {
+		metrics::record_resolve_duration(
+			&self.registration.tenant_id,
+			&self.registration.provider_id,
+			source,
+			started.elapsed(),
+		);
+	}
+
+	#[cfg(feature = "metrics")]
+	fn observe_freshness(&self, payload: &CachePayload) {
+		let now = Instant::now();
+		let until_expiry = match payload.expires_at.checked_duration_since(now) {
+			Some(remaining) => remaining.as_secs_f64(),
+			None => -now.saturating_duration_since(payload.expires_at).as_secs_f64(),
+		};
+
+		metrics::record_payload_freshness(
+			&self.registration.tenant_id,
+			&self.registration.provider_id,
+			until_expiry,
+			payload.policy.age(clock::system_now()),
+		);
+	}
+
 	#[cfg(feature = "metrics")]
 	fn observe_miss(&self) {
 		let tenant = &self.registration.tenant_id;
@@ -628,6 +2529,49 @@ impl CacheManager {
 		self.metrics.record_miss();
 	}
 
+	#[tracing::instrument(
+		skip(self),
+		fields(tenant = %self.registration.tenant_id, provider = %self.registration.provider_id)
+	)]
+	async fn probe_connection(&self, interval: Duration) {
+		// Nothing to keep warm before the first fetch, and recent traffic already did the job.
+		if !self.connections.idle_for(Instant::now()).is_some_and(|idle| idle >= interval) {
+			return;
+		}
+
+		let probe = self
+			.client
+			.head(self.upstream().jwks_url.clone())
+			.timeout(self.registration.retry_policy.attempt_timeout)
+			.send()
+			.await;
+
+		match probe {
+			Ok(response) => {
+				let reused = self.connections.observe(&response);
+
+				self.observe_connection(reused);
+
+				tracing::debug!(status = %response.status(), reused = ?reused, "keepalive probe sent");
+			},
+			Err(err) => tracing::debug!(error = %err, "keepalive probe failed"),
+		}
+	}
+
+	fn observe_connection(&self, reused: Option<bool>) {
+		#[cfg(feature = "metrics")]
+		if let Some(reused) = reused {
+			let tenant = &self.registration.tenant_id;
+			let provider = &self.registration.provider_id;
+
+			metrics::record_upstream_connection(tenant, provider, reused);
+
+			self.metrics.record_connection(reused);
+		}
+		#[cfg(not(feature = "metrics"))]
+		let _ = reused;
+	}
+
 	#[cfg(feature = "metrics")]
 	fn observe_refresh_success(&self, duration: Duration) {
 		let tenant = &self.registration.tenant_id;
@@ -649,6 +2593,33 @@ impl CacheManager {
 	}
 }
 
+/// Per-call overrides for [`CacheManager::resolve_with`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ResolveOptions {
+	/// Maximum time to wait for an upstream fetch, replacing the registry's resolve timeout.
+	pub timeout: Option<Duration>,
+	/// Maximum time to queue behind a refresh that is already running for the provider.
+	///
+	/// Once it elapses, the call falls back like a timed-out resolve: to stale keys within the
+	/// `stale_while_error` window, or [`Error::ResolveTimeout`]. The refresh keeps running.
+	pub wait_budget: Option<Duration>,
+}
+impl ResolveOptions {
+	/// Wait at most `timeout` for an upstream fetch.
+	pub fn with_timeout(mut self, timeout: Duration) -> Self {
+		self.timeout = Some(timeout);
+
+		self
+	}
+
+	/// Queue at most `budget` behind a refresh that is already running.
+	pub fn with_wait_budget(mut self, budget: Duration) -> Self {
+		self.wait_budget = Some(budget);
+
+		self
+	}
+}
+
 /// Snapshot of cache state captured for status reporting.
 #[derive(Clone, Debug)]
 pub struct CacheSnapshot {
@@ -658,6 +2629,10 @@ pub struct CacheSnapshot {
 	pub captured_at_wallclock: DateTime<Utc>,
 	/// Cache state recorded at capture time.
 	pub state: CacheState,
+	/// Circuit breaker state, when the registration configures one.
+	pub circuit: Option<BreakerSnapshot>,
+	/// Time from registration until the cache first held usable keys.
+	pub time_to_ready: Option<Duration>,
 }
 impl CacheSnapshot {
 	/// Convert a monotonic instant drawn from the cached payload into UTC.
@@ -676,18 +2651,90 @@ impl CacheSnapshot {
 	}
 }
 
+// Negative `kid` cache, and when the next forced revalidation may start.
+#[derive(Debug, Default)]
+struct MissingKids {
+	kids: HashMap<String, Instant>,
+	next_revalidation: Option<Instant>,
+}
+
 #[derive(Clone, Copy, Debug)]
 enum FetchMode {
 	Initial,
 	Refresh,
 }
 
-#[derive(Debug)]
+type SharedRefresh = std::result::Result<RefreshOutcome, Arc<Error>>;
+
+// Clears the in-flight refresh slot even when the leading caller is cancelled, so waiting callers
+// wake up and one of them takes over.
+struct InFlightGuard<'a>(&'a StdMutex<Option<watch::Receiver<Option<SharedRefresh>>>>);
+impl Drop for InFlightGuard<'_> {
+	fn drop(&mut self) {
+		*self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+	}
+}
+
+// Returns an entry to `Ready` when its background refresh is aborted or never spawned, so it does
+// not stay `Refreshing` with nothing left to finish it.
+struct PendingRefresh(Option<Arc<RwLock<CacheEntry>>>);
+impl PendingRefresh {
+	// The refresh ran to completion and settled the entry itself.
+	fn finish(mut self) {
+		self.0 = None;
+	}
+}
+impl Drop for PendingRefresh {
+	fn drop(&mut self) {
+		let Some(entry) = self.0.take() else {
+			return;
+		};
+
+		if let Ok(mut entry) = entry.try_write() {
+			entry.cancel_refresh();
+		} else if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+			runtime.spawn(async move { entry.write().await.cancel_refresh() });
+		}
+	}
+}
+
+// Withdraws the override granted by `force_refresh`, even if the refresh is cancelled.
+struct FullRotationOverride<'a>(&'a AtomicBool);
+impl Drop for FullRotationOverride<'_> {
+	fn drop(&mut self) {
+		self.0.store(false, Ordering::Release);
+	}
+}
+
+// Marks an explicit revalidation for as long as it runs, even if it is cancelled.
+struct ForcedRevalidation<'a>(&'a AtomicUsize);
+impl<'a> ForcedRevalidation<'a> {
+	fn begin(count: &'a AtomicUsize) -> Self {
+		count.fetch_add(1, Ordering::AcqRel);
+
+		Self(count)
+	}
+}
+impl Drop for ForcedRevalidation<'_> {
+	fn drop(&mut self) {
+		self.0.fetch_sub(1, Ordering::AcqRel);
+	}
+}
+
+#[derive(Clone, Debug)]
 enum RefreshOutcome {
 	Updated { jwks: Arc<JwkSet>, from_cache: bool },
 	Stale(Arc<JwkSet>),
 }
 
+#[derive(Debug)]
+enum LeaseDecision {
+	// Keys the lease holder persisted, already committed to the cache.
+	Adopted(Arc<JwkSet>),
+	// Fetch upstream, releasing the lease afterwards if one was taken.
+	Fetch(Option<String>),
+}
+
 #[derive(Clone, Copy, Debug)]
 enum RefreshTrigger {
 	Background,
@@ -701,17 +2748,6 @@ enum PreparedRequest {
 	Send(Box<Request<()>>),
 }
 
-fn random_jitter(max: Duration) -> Duration {
-	if max.is_zero() {
-		return Duration::ZERO;
-	}
-
-	let mut rng = rand::rng();
-	let jitter = rng.random_range(0.0..=max.as_secs_f64());
-
-	Duration::from_secs_f64(jitter)
-}
-
 fn extract_header(response: &Response<()>, name: &HeaderName) -> Option<String> {
 	response.headers().get(name).and_then(|value| value.to_str().ok()).map(|s| s.to_string())
 }
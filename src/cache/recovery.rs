@@ -0,0 +1,40 @@
+//! Background recovery probes for providers left without keys after failed fetches.
+//!
+//! When a fetch fails and nothing can be served, the entry stays empty until the next resolve
+//! tries again. With [`RecoveryPolicy`] configured, the cache manager keeps probing upstream on an
+//! exponential, capped, and jittered schedule instead, so low-traffic providers recover before a
+//! caller needs them.
+
+// self
+use crate::{_prelude::*, jitter, registry::RecoveryPolicy};
+
+/// Delay before recovery probe number `attempt`, counting from zero.
+///
+/// The delay doubles per attempt up to [`RecoveryPolicy::max_delay`] and is then jittered down to
+/// no less than half its value.
+pub fn probe_delay(policy: &RecoveryPolicy, attempt: u32, seed: u64) -> Duration {
+	let delay =
+		policy.initial_delay.saturating_mul(2_u32.saturating_pow(attempt)).min(policy.max_delay);
+
+	jitter::within(delay / 2, delay, jitter::mix(seed, u64::from(attempt)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn delays_grow_exponentially_up_to_the_cap() {
+		let policy = RecoveryPolicy {
+			initial_delay: Duration::from_secs(1),
+			max_delay: Duration::from_secs(10),
+		};
+
+		for (attempt, ceiling) in [(0, 1), (1, 2), (2, 4), (3, 8), (4, 10), (40, 10)] {
+			let ceiling = Duration::from_secs(ceiling);
+			let delay = probe_delay(&policy, attempt, 7);
+
+			assert!(delay >= ceiling / 2 && delay <= ceiling, "attempt {attempt}: {delay:?}");
+		}
+	}
+}
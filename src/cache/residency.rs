@@ -0,0 +1,160 @@
+//! Registry-wide bound on how many providers keep key sets in memory.
+//!
+//! Registries serving tens of thousands of tenants rarely need every key set resident at once.
+//! Providers are ordered by their last use, and registering use beyond the capacity hands back the
+//! least recently used ones so their payloads can be dropped. Registrations stay in place, and an
+//! evicted provider is rehydrated from persistence or upstream on its next resolve.
+
+// std
+use std::{
+	collections::{BTreeMap, HashMap},
+	fmt::{Debug, Formatter, Result as FmtResult},
+	sync::{
+		Mutex, MutexGuard,
+		atomic::{AtomicU64, Ordering},
+	},
+};
+
+/// Least-recently-used set of resident providers, keyed by ids it hands out.
+pub struct ResidentSet<T> {
+	capacity: usize,
+	next_id: AtomicU64,
+	lru: Mutex<Lru<T>>,
+}
+impl<T> ResidentSet<T>
+where
+	T: Clone,
+{
+	/// Create a set keeping at most `capacity` members, clamped to at least one.
+	pub fn new(capacity: usize) -> Self {
+		Self {
+			capacity: capacity.max(1),
+			next_id: AtomicU64::new(0),
+			lru: Mutex::new(Lru { tick: 0, members: HashMap::new(), order: BTreeMap::new() }),
+		}
+	}
+
+	/// Maximum number of resident members.
+	pub fn capacity(&self) -> usize {
+		self.capacity
+	}
+
+	/// Number of resident members.
+	pub fn len(&self) -> usize {
+		self.lock().members.len()
+	}
+
+	/// Whether no member is resident.
+	pub fn is_empty(&self) -> bool {
+		self.lock().members.is_empty()
+	}
+
+	/// Allocate an id for a new member.
+	pub fn next_id(&self) -> u64 {
+		self.next_id.fetch_add(1, Ordering::Relaxed)
+	}
+
+	/// Whether `id` is resident.
+	pub fn contains(&self, id: u64) -> bool {
+		self.lock().members.contains_key(&id)
+	}
+
+	/// Mark `id` as the most recently used member, returning the members pushed out of the set.
+	pub fn touch(&self, id: u64, member: &T) -> Vec<T> {
+		let mut lru = self.lock();
+
+		lru.tick += 1;
+
+		let tick = lru.tick;
+
+		if let Some((previous, _)) = lru.members.insert(id, (tick, member.clone())) {
+			lru.order.remove(&previous);
+		}
+
+		lru.order.insert(tick, id);
+
+		let mut evicted = Vec::new();
+
+		while lru.members.len() > self.capacity {
+			let Some((_, coldest)) = lru.order.pop_first() else {
+				break;
+			};
+
+			if let Some((_, member)) = lru.members.remove(&coldest) {
+				evicted.push(member);
+			}
+		}
+
+		evicted
+	}
+
+	/// Forget `id`, returning whether it was resident.
+	pub fn remove(&self, id: u64) -> bool {
+		let mut lru = self.lock();
+		let Some((tick, _)) = lru.members.remove(&id) else {
+			return false;
+		};
+
+		lru.order.remove(&tick);
+
+		true
+	}
+
+	/// Forget every member.
+	pub fn clear(&self) {
+		let mut lru = self.lock();
+
+		lru.members.clear();
+		lru.order.clear();
+	}
+
+	fn lock(&self) -> MutexGuard<'_, Lru<T>> {
+		self.lru.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+}
+// Members may refer back to the set, so only its size is printed.
+impl<T> Debug for ResidentSet<T>
+where
+	T: Clone,
+{
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("ResidentSet")
+			.field("capacity", &self.capacity)
+			.field("len", &self.len())
+			.finish()
+	}
+}
+
+struct Lru<T> {
+	tick: u64,
+	members: HashMap<u64, (u64, T)>,
+	// Last-use tick to member id, coldest first.
+	order: BTreeMap<u64, u64>,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn evicts_the_least_recently_used_members() {
+		let set = ResidentSet::new(2);
+		let (a, b, c) = (set.next_id(), set.next_id(), set.next_id());
+
+		assert!(set.touch(a, &"a").is_empty());
+		assert!(set.touch(b, &"b").is_empty());
+		assert!(set.touch(a, &"a").is_empty(), "touching a resident member keeps the size");
+		assert_eq!(set.touch(c, &"c"), ["b"]);
+		assert!(set.contains(a) && set.contains(c) && !set.contains(b));
+
+		assert!(set.remove(a));
+		assert!(!set.remove(a));
+		assert!(set.touch(b, &"b").is_empty());
+		assert_eq!(set.len(), 2);
+
+		set.clear();
+
+		assert!(set.is_empty());
+		assert_eq!(ResidentSet::<()>::new(0).capacity(), 1);
+	}
+}
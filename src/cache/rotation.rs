@@ -0,0 +1,121 @@
+//! Key rotation detection between consecutive JWKS payloads.
+
+// std
+use std::{
+	collections::BTreeSet,
+	fmt::{Debug, Formatter, Result as FmtResult},
+};
+// crates.io
+use jsonwebtoken::jwk::JwkSet;
+use serde::{Deserialize, Serialize};
+// self
+use crate::_prelude::*;
+
+/// Difference between the key ids of two consecutive JWKS payloads.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyRotation {
+	/// Tenant whose provider rotated keys.
+	pub tenant_id: String,
+	/// Provider that rotated keys.
+	pub provider_id: String,
+	/// Key ids present in the new payload but not the previous one, sorted.
+	pub added: Vec<String>,
+	/// Key ids present in the previous payload but not the new one, sorted.
+	pub removed: Vec<String>,
+}
+impl KeyRotation {
+	/// Compare the `kid` sets of two payloads, returning `None` when they match.
+	///
+	/// Keys without a `kid` are ignored.
+	pub fn between(
+		tenant_id: &str,
+		provider_id: &str,
+		previous: &JwkSet,
+		current: &JwkSet,
+	) -> Option<Self> {
+		let previous = kids(previous);
+		let current = kids(current);
+
+		if previous == current {
+			return None;
+		}
+
+		Some(Self {
+			tenant_id: tenant_id.to_owned(),
+			provider_id: provider_id.to_owned(),
+			added: current.difference(&previous).map(|kid| (*kid).to_owned()).collect(),
+			removed: previous.difference(&current).map(|kid| (*kid).to_owned()).collect(),
+		})
+	}
+}
+
+/// Callback invoked with every detected [`KeyRotation`].
+///
+/// Hooks run inline on the refresh task after the new payload is cached, so they should return
+/// quickly and hand slow work off to another task.
+#[derive(Clone)]
+pub struct KeyRotationHook(Arc<dyn Fn(&KeyRotation) + Send + Sync>);
+impl KeyRotationHook {
+	/// Wrap a callback.
+	pub fn new<F>(callback: F) -> Self
+	where
+		F: Fn(&KeyRotation) + Send + Sync + 'static,
+	{
+		Self(Arc::new(callback))
+	}
+
+	/// Invoke the callback.
+	pub fn notify(&self, rotation: &KeyRotation) {
+		(self.0)(rotation)
+	}
+}
+impl Debug for KeyRotationHook {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.write_str("KeyRotationHook(..)")
+	}
+}
+
+/// Whether `current` shares no `kid` with a non-empty `previous` set.
+pub fn replaces_every_kid(previous: &JwkSet, current: &JwkSet) -> bool {
+	let previous = kids(previous);
+
+	!previous.is_empty() && kids(current).is_disjoint(&previous)
+}
+
+fn kids(jwks: &JwkSet) -> BTreeSet<&str> {
+	jwks.keys.iter().filter_map(|jwk| jwk.common.key_id.as_deref()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn jwks(kids: &[&str]) -> JwkSet {
+		let keys = kids
+			.iter()
+			.map(|kid| serde_json::json!({ "kty": "oct", "kid": kid, "k": "c2VjcmV0" }))
+			.collect::<Vec<_>>();
+
+		serde_json::from_value(serde_json::json!({ "keys": keys })).expect("jwks")
+	}
+
+	#[test]
+	fn full_replacement_requires_no_shared_kid() {
+		assert!(replaces_every_kid(&jwks(&["a", "b"]), &jwks(&["c"])));
+		assert!(!replaces_every_kid(&jwks(&["a", "b"]), &jwks(&["b", "c"])));
+		assert!(!replaces_every_kid(&jwks(&[]), &jwks(&["c"])));
+	}
+
+	#[test]
+	fn rotation_reports_added_and_removed_kids() {
+		let rotation =
+			KeyRotation::between("tenant", "idp", &jwks(&["a", "b"]), &jwks(&["c", "b", "d"]))
+				.expect("rotation");
+
+		assert_eq!(rotation.added, vec!["c".to_string(), "d".to_string()]);
+		assert_eq!(rotation.removed, vec!["a".to_string()]);
+		assert!(
+			KeyRotation::between("tenant", "idp", &jwks(&["a", "b"]), &jwks(&["b", "a"])).is_none()
+		);
+	}
+}
@@ -0,0 +1,101 @@
+//! Registry-wide coordination of proactive refreshes.
+//!
+//! Registrations sharing a TTL would otherwise all become due in the same second. The scheduler
+//! pulls each refresh forward by a random share of the time left before it, spreading the wave
+//! across the TTL window, and admits a bounded number of background refreshes at once.
+
+// crates.io
+use tokio::sync::{Semaphore, SemaphorePermit};
+// self
+use crate::{_prelude::*, jitter, registry::RefreshSchedulePolicy};
+
+/// Smears proactive refreshes and caps how many run concurrently.
+#[derive(Debug)]
+pub struct RefreshScheduler {
+	policy: RefreshSchedulePolicy,
+	permits: Semaphore,
+}
+impl RefreshScheduler {
+	/// Create a scheduler enforcing `policy`.
+	pub fn new(policy: RefreshSchedulePolicy) -> Self {
+		let permits = Semaphore::new(policy.max_concurrent_refreshes.max(1));
+
+		Self { policy, permits }
+	}
+
+	/// Policy enforced by this scheduler.
+	pub fn policy(&self) -> &RefreshSchedulePolicy {
+		&self.policy
+	}
+
+	/// Pull `refresh_at` forward by up to `smear_ratio` of the time left until it.
+	pub fn smear(&self, now: Instant, refresh_at: Instant, seed: u64) -> Instant {
+		let window = refresh_at.saturating_duration_since(now).mul_f64(self.policy.smear_ratio);
+
+		refresh_at - jitter::up_to(window, seed)
+	}
+
+	/// Wait for a background refresh slot, returning the held permit and how long the caller
+	/// queued.
+	pub async fn acquire(&self) -> (SemaphorePermit<'_>, Duration) {
+		if let Ok(permit) = self.permits.try_acquire() {
+			return (permit, Duration::ZERO);
+		}
+
+		let started = Instant::now();
+		// The semaphore is never closed, so acquiring cannot fail.
+		let permit = self.permits.acquire().await.unwrap_or_else(|_| unreachable!());
+
+		(permit, started.elapsed())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// crates.io
+	use tokio::time;
+	// self
+	use super::*;
+
+	#[test]
+	fn smear_stays_between_now_and_the_refresh_point() {
+		let scheduler = RefreshScheduler::new(RefreshSchedulePolicy {
+			smear_ratio: 0.5,
+			max_concurrent_refreshes: 1,
+		});
+		let now = Instant::now();
+		let refresh_at = now + Duration::from_secs(300);
+
+		for seed in 0..64 {
+			let smeared = scheduler.smear(now, refresh_at, jitter::mix(7, seed));
+
+			assert!(smeared <= refresh_at);
+			assert!(smeared >= now + Duration::from_secs(150), "smeared past the window");
+		}
+
+		assert_eq!(scheduler.smear(now, now, 1), now, "due refreshes stay due");
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn caps_concurrent_refreshes() {
+		let scheduler = RefreshScheduler::new(RefreshSchedulePolicy {
+			smear_ratio: 0.,
+			max_concurrent_refreshes: 1,
+		});
+		let (held, wait) = scheduler.acquire().await;
+
+		assert_eq!(wait, Duration::ZERO);
+
+		let queued = scheduler.acquire();
+
+		tokio::pin!(queued);
+
+		assert!(time::timeout(Duration::from_secs(1), &mut queued).await.is_err());
+
+		drop(held);
+
+		let (_permit, wait) = queued.await;
+
+		assert!(wait >= Duration::from_secs(1));
+	}
+}
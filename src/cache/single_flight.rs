@@ -0,0 +1,116 @@
+//! Fair single-flight slot serialising upstream refresh work for one provider.
+//!
+//! Refreshes and invalidations queue for a single semaphore permit, which is granted in FIFO
+//! order, and the slot tracks how many callers are queued so the wait can be reported.
+
+// std
+use std::sync::atomic::{AtomicUsize, Ordering};
+// crates.io
+use tokio::sync::{Semaphore, SemaphorePermit};
+// self
+use crate::_prelude::*;
+
+/// FIFO single-flight slot with queue-depth tracking.
+#[derive(Debug)]
+pub struct SingleFlight {
+	permit: Semaphore,
+	waiters: AtomicUsize,
+}
+impl SingleFlight {
+	/// Wait for the slot, returning the held permit and how long the caller queued.
+	///
+	/// When the slot is taken, `on_queued` receives the queue depth including this caller before
+	/// it starts waiting.
+	pub async fn acquire(&self, on_queued: impl FnOnce(usize)) -> (SemaphorePermit<'_>, Duration) {
+		if let Ok(permit) = self.permit.try_acquire() {
+			return (permit, Duration::ZERO);
+		}
+
+		let started = Instant::now();
+		let queued = Queued::enter(&self.waiters);
+
+		on_queued(queued.depth);
+
+		// The semaphore is never closed, so acquiring cannot fail.
+		let permit = self.permit.acquire().await.unwrap_or_else(|_| unreachable!());
+
+		(permit, started.elapsed())
+	}
+
+	/// Number of callers currently queued for the slot.
+	pub fn waiters(&self) -> usize {
+		self.waiters.load(Ordering::Acquire)
+	}
+
+	/// Whether another caller currently holds the slot.
+	pub fn is_busy(&self) -> bool {
+		self.permit.available_permits() == 0
+	}
+}
+impl Default for SingleFlight {
+	fn default() -> Self {
+		Self { permit: Semaphore::new(1), waiters: AtomicUsize::new(0) }
+	}
+}
+
+// Counts a queued caller until it is granted the permit or its future is dropped.
+struct Queued<'a> {
+	waiters: &'a AtomicUsize,
+	depth: usize,
+}
+impl<'a> Queued<'a> {
+	fn enter(waiters: &'a AtomicUsize) -> Self {
+		let depth = waiters.fetch_add(1, Ordering::AcqRel) + 1;
+
+		Self { waiters, depth }
+	}
+}
+impl Drop for Queued<'_> {
+	fn drop(&mut self) {
+		self.waiters.fetch_sub(1, Ordering::AcqRel);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn waiters_are_counted_and_served_in_order() {
+		let slot = Arc::new(SingleFlight::default());
+		let (held, waited) = slot.acquire(|_| panic!("an idle slot must not queue")).await;
+
+		assert!(slot.is_busy());
+		assert_eq!(waited, Duration::ZERO);
+
+		let order = Arc::new(std::sync::Mutex::new(Vec::new()));
+		let mut tasks = Vec::new();
+
+		for id in 0..3 {
+			let waiter = slot.clone();
+			let order = order.clone();
+
+			tasks.push(tokio::spawn(async move {
+				let _permit = waiter.acquire(|depth| assert_eq!(depth, id + 1)).await;
+
+				order.lock().expect("order").push(id);
+			}));
+
+			while slot.waiters() <= id {
+				tokio::task::yield_now().await;
+			}
+		}
+
+		assert_eq!(slot.waiters(), 3);
+
+		drop(held);
+
+		for task in tasks {
+			task.await.expect("waiter");
+		}
+
+		assert_eq!(*order.lock().expect("order"), vec![0, 1, 2]);
+		assert_eq!(slot.waiters(), 0);
+		assert!(!slot.is_busy());
+	}
+}
@@ -1,18 +1,28 @@
 //! Cache state machine modelling JWKS lifecycle transitions.
 
 // crates.io
+use dashmap::DashMap;
 use http_cache_semantics::CachePolicy;
-use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{DecodingKey, jwk::Jwk};
 // self
-use crate::_prelude::*;
+use crate::{
+	_prelude::*,
+	cache::{activation::KeyActivations, storage::StoredJwks},
+	clock::{ClockAnchor, ClockDrift},
+	registry::{ClockJumpPolicy, ClockReconciliation, ProviderError},
+};
 
 /// Metadata captured for a cached JWKS payload.
 #[derive(Clone, Debug)]
 pub struct CachePayload {
-	/// JWKS document retained for the provider.
-	pub jwks: Arc<JwkSet>,
+	/// JWKS document retained for the provider, parsed or compressed.
+	pub jwks: StoredJwks,
 	/// HTTP cache policy derived from the last response.
 	pub policy: CachePolicy,
+	/// Activation times for keys that were published ahead of use.
+	pub activations: Arc<KeyActivations>,
+	/// Decoding keys converted from `jwks`, shared by every clone of this payload.
+	pub decoding_keys: Arc<DecodingKeyCache>,
 	/// Strong or weak validator supplied by the origin.
 	pub etag: Option<String>,
 	/// Last-Modified timestamp advertised by the origin.
@@ -28,6 +38,9 @@ pub struct CachePayload {
 	pub next_refresh_at: Instant,
 	/// Optional window permitting stale serving past expiry.
 	pub stale_deadline: Option<Instant>,
+	/// End of the upstream's `stale-while-revalidate` window, during which the expired payload is
+	/// served without waiting while a background refresh replaces it.
+	pub revalidate_deadline: Option<Instant>,
 	/// Exponential backoff duration before retrying a failed refresh.
 	///
 	/// This stores the most recent backoff duration; the cache manager combines
@@ -35,6 +48,10 @@ pub struct CachePayload {
 	pub retry_backoff: Option<Duration>,
 	/// Count of consecutive refresh errors.
 	pub error_count: u32,
+	/// Most recent refresh error, cleared by the next successful refresh.
+	pub last_error: Option<ProviderError>,
+	/// Clock readings taken when the monotonic deadlines were computed.
+	pub clock_anchor: ClockAnchor,
 }
 impl CachePayload {
 	/// Whether the payload has exceeded its freshness window.
@@ -47,16 +64,104 @@ impl CachePayload {
 		self.stale_deadline.map(|deadline| now <= deadline).unwrap_or(false)
 	}
 
+	/// Whether an expired payload may still be served while a background refresh runs.
+	pub fn can_revalidate_in_background(&self, now: Instant) -> bool {
+		self.revalidate_deadline.is_some_and(|deadline| now < deadline)
+	}
+
 	/// Update retry bookkeeping after a failed refresh.
-	pub fn bump_error(&mut self, backoff: Option<Duration>) {
+	pub fn bump_error(&mut self, backoff: Option<Duration>, error: ProviderError) {
 		self.error_count = self.error_count.saturating_add(1);
 		self.retry_backoff = backoff;
+		self.last_error = Some(error);
+	}
+
+	/// Activation time of `kid` when it lies after `now`.
+	pub fn pending_activation(&self, kid: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+		self.activations.get(kid).copied().filter(|activates_at| *activates_at > now)
+	}
+
+	/// Reconcile the monotonic deadlines after the clocks drifted apart by at least the policy
+	/// threshold, returning the drift that was handled.
+	///
+	/// The anchor is reset whenever a jump is handled, so each jump is reported once.
+	pub fn reconcile_clocks(
+		&mut self,
+		policy: &ClockJumpPolicy,
+		now: Instant,
+		wall: SystemTime,
+	) -> Option<ClockDrift> {
+		let drift = self.clock_anchor.drift(now, wall);
+
+		if drift.magnitude() < policy.threshold {
+			return None;
+		}
+
+		match policy.reconciliation {
+			ClockReconciliation::TrustMonotonic => (),
+			ClockReconciliation::TrustWallClock => {
+				let shift = |deadline: Instant| match drift {
+					// Deadlines that would precede the runtime's clock origin have long passed.
+					ClockDrift::Ahead(drift) =>
+						deadline.checked_sub(drift).unwrap_or(now.min(deadline)),
+					ClockDrift::Behind(drift) => deadline + drift,
+				};
+
+				self.expires_at = shift(self.expires_at);
+				self.next_refresh_at = shift(self.next_refresh_at);
+				self.stale_deadline = self.stale_deadline.map(shift);
+				self.revalidate_deadline = self.revalidate_deadline.map(shift);
+			},
+			ClockReconciliation::Revalidate => {
+				self.expires_at = self.expires_at.min(now);
+				self.next_refresh_at = self.next_refresh_at.min(now);
+				self.revalidate_deadline =
+					self.revalidate_deadline.map(|deadline| deadline.min(now));
+			},
+		}
+
+		self.clock_anchor = ClockAnchor { monotonic: now, wall };
+
+		Some(drift)
 	}
 
 	/// Reset failure bookkeeping after a successful refresh.
 	pub fn reset_failures(&mut self) {
 		self.error_count = 0;
 		self.retry_backoff = None;
+		self.last_error = None;
+	}
+}
+
+/// Per-`kid` cache of [`DecodingKey`]s converted from a single JWKS payload.
+///
+/// Every fetched payload starts with an empty cache, so refreshed keys are never decoded from a
+/// stale conversion.
+#[derive(Debug, Default)]
+pub struct DecodingKeyCache {
+	keys: DashMap<String, Arc<DecodingKey>>,
+}
+impl DecodingKeyCache {
+	/// Return the cached key for `kid`, converting `jwk` on first use.
+	pub fn get_or_convert(&self, kid: &str, jwk: &Jwk) -> Result<Arc<DecodingKey>> {
+		if let Some(key) = self.keys.get(kid) {
+			return Ok(key.clone());
+		}
+
+		let key = Arc::new(DecodingKey::from_jwk(jwk)?);
+
+		// Concurrent first uses may both convert; either result is equivalent.
+		Ok(self.keys.entry(kid.to_owned()).or_insert(key).clone())
+	}
+
+	/// Number of converted keys.
+	pub fn len(&self) -> usize {
+		self.keys.len()
+	}
+
+	/// Whether no key has been converted yet.
+	pub fn is_empty(&self) -> bool {
+		self.keys.is_empty()
 	}
 }
 
@@ -0,0 +1,211 @@
+//! In-memory representation of cached key sets.
+//!
+//! Key sets are kept parsed by default. With the `zstd` feature a registry can keep them as
+//! compressed JSON instead, parsing on demand through a small LRU of recently used sets shared by
+//! every provider, which trades CPU for memory when many providers publish large key sets.
+
+// std
+#[cfg(feature = "zstd")]
+use std::{
+	collections::VecDeque,
+	sync::{
+		Mutex, MutexGuard,
+		atomic::{AtomicU64, Ordering},
+	},
+};
+// crates.io
+use jsonwebtoken::jwk::JwkSet;
+// self
+use crate::_prelude::*;
+
+/// zstd level used for stored key sets; favours speed since every refresh compresses.
+#[cfg(feature = "zstd")]
+const COMPRESSION_LEVEL: i32 = 3;
+
+#[cfg(feature = "zstd")]
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Key set retained by a cached payload.
+#[derive(Clone, Debug)]
+pub enum StoredJwks {
+	/// Parsed key set.
+	Parsed(Arc<JwkSet>),
+	/// zstd-compressed JSON, parsed on demand.
+	#[cfg(feature = "zstd")]
+	Compressed(Arc<CompressedJwks>),
+}
+impl StoredJwks {
+	/// Store `jwks` compressed when `parsed` is supplied, otherwise as is.
+	///
+	/// Falls back to the parsed form if serialisation or compression fails.
+	#[cfg(feature = "zstd")]
+	pub fn new(jwks: Arc<JwkSet>, parsed: Option<&Arc<ParsedJwksCache>>) -> Self {
+		let Some(parsed) = parsed else {
+			return Self::Parsed(jwks);
+		};
+
+		match CompressedJwks::compress(&jwks, parsed.clone()) {
+			Ok(compressed) => {
+				parsed.insert(compressed.id, jwks);
+
+				Self::Compressed(Arc::new(compressed))
+			},
+			Err(err) => {
+				tracing::warn!(error = %err, "failed to compress JWKS; storing it parsed");
+
+				Self::Parsed(jwks)
+			},
+		}
+	}
+
+	/// Return the parsed key set, decompressing it when it is not cached.
+	pub fn load(&self) -> Result<Arc<JwkSet>> {
+		match self {
+			Self::Parsed(jwks) => Ok(jwks.clone()),
+			#[cfg(feature = "zstd")]
+			Self::Compressed(compressed) => compressed.load(),
+		}
+	}
+}
+impl From<Arc<JwkSet>> for StoredJwks {
+	fn from(jwks: Arc<JwkSet>) -> Self {
+		Self::Parsed(jwks)
+	}
+}
+
+/// zstd-compressed JSON encoding of a key set.
+#[cfg(feature = "zstd")]
+#[derive(Debug)]
+pub struct CompressedJwks {
+	id: u64,
+	bytes: Box<[u8]>,
+	parsed: Arc<ParsedJwksCache>,
+}
+#[cfg(feature = "zstd")]
+impl CompressedJwks {
+	fn compress(jwks: &JwkSet, parsed: Arc<ParsedJwksCache>) -> Result<Self> {
+		let json = serde_json::to_vec(jwks)?;
+		let bytes = zstd::bulk::compress(&json, COMPRESSION_LEVEL)?.into_boxed_slice();
+
+		Ok(Self { id: NEXT_ID.fetch_add(1, Ordering::Relaxed), bytes, parsed })
+	}
+
+	/// Size of the compressed encoding in bytes.
+	pub fn compressed_len(&self) -> usize {
+		self.bytes.len()
+	}
+
+	fn load(&self) -> Result<Arc<JwkSet>> {
+		if let Some(jwks) = self.parsed.get(self.id) {
+			return Ok(jwks);
+		}
+
+		let json = zstd::stream::decode_all(&*self.bytes)
+			.map_err(|err| Error::Cache(format!("Failed to decompress cached JWKS: {err}.")))?;
+		let jwks = Arc::new(serde_json::from_slice::<JwkSet>(&json)?);
+
+		self.parsed.insert(self.id, jwks.clone());
+
+		Ok(jwks)
+	}
+}
+
+/// Bounded LRU of parsed key sets shared by every compressed payload of a registry.
+#[cfg(feature = "zstd")]
+#[derive(Debug)]
+pub struct ParsedJwksCache {
+	capacity: usize,
+	// Most recently used first; linear scans are cheap at the small capacities this is sized for.
+	entries: Mutex<VecDeque<(u64, Arc<JwkSet>)>>,
+}
+#[cfg(feature = "zstd")]
+impl ParsedJwksCache {
+	/// Create a cache retaining at most `capacity` parsed sets; zero parses on every load.
+	pub fn new(capacity: usize) -> Self {
+		Self { capacity, entries: Mutex::new(VecDeque::with_capacity(capacity)) }
+	}
+
+	/// Number of parsed sets currently retained.
+	pub fn len(&self) -> usize {
+		self.lock().len()
+	}
+
+	/// Whether no parsed set is retained.
+	pub fn is_empty(&self) -> bool {
+		self.lock().is_empty()
+	}
+
+	fn get(&self, id: u64) -> Option<Arc<JwkSet>> {
+		let mut entries = self.lock();
+		let index = entries.iter().position(|(entry, _)| *entry == id)?;
+		let entry = entries.remove(index)?;
+		let jwks = entry.1.clone();
+
+		entries.push_front(entry);
+
+		Some(jwks)
+	}
+
+	fn insert(&self, id: u64, jwks: Arc<JwkSet>) {
+		if self.capacity == 0 {
+			return;
+		}
+
+		let mut entries = self.lock();
+
+		entries.retain(|(entry, _)| *entry != id);
+		entries.truncate(self.capacity - 1);
+		entries.push_front((id, jwks));
+	}
+
+	fn lock(&self) -> MutexGuard<'_, VecDeque<(u64, Arc<JwkSet>)>> {
+		self.entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+}
+
+#[cfg(all(test, feature = "zstd"))]
+mod tests {
+	// crates.io
+	use jsonwebtoken::jwk::Jwk;
+	// self
+	use super::*;
+
+	fn key_set(kid: &str) -> Arc<JwkSet> {
+		let jwk: Jwk = serde_json::from_value(serde_json::json!({
+			"kty": "oct",
+			"kid": kid,
+			"k": "c2VjcmV0LXNlY3JldC1zZWNyZXQtc2VjcmV0",
+		}))
+		.expect("jwk");
+
+		Arc::new(JwkSet { keys: vec![jwk; 64] })
+	}
+
+	#[test]
+	fn compressed_sets_round_trip_through_a_bounded_lru() {
+		let parsed = Arc::new(ParsedJwksCache::new(1));
+		let first = StoredJwks::new(key_set("first"), Some(&parsed));
+		let second = StoredJwks::new(key_set("second"), Some(&parsed));
+
+		let StoredJwks::Compressed(compressed) = &first else { panic!("expected compression") };
+
+		assert!(
+			compressed.compressed_len() < serde_json::to_vec(&*key_set("first")).unwrap().len()
+		);
+		assert_eq!(parsed.len(), 1, "inserting the second set evicts the first");
+
+		let loaded = first.load().expect("decompress");
+
+		assert_eq!(loaded.keys[0].common.key_id.as_deref(), Some("first"));
+		assert!(Arc::ptr_eq(&loaded, &first.load().expect("cached")));
+		assert_eq!(second.load().expect("decompress").keys.len(), 64);
+	}
+
+	#[test]
+	fn sets_stay_parsed_without_a_cache() {
+		let jwks = key_set("plain");
+
+		assert!(matches!(StoredJwks::new(jwks.clone(), None), StoredJwks::Parsed(_)));
+		assert!(Arc::ptr_eq(&StoredJwks::from(jwks.clone()).load().unwrap(), &jwks));
+	}
+}
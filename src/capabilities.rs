@@ -0,0 +1,81 @@
+//! Runtime view of the optional subsystems compiled into this build.
+
+// crates.io
+use serde::{Deserialize, Serialize};
+
+/// Optional subsystems enabled through Cargo features.
+///
+/// Embedding frameworks can branch on these flags instead of mirroring the crate's `cfg` logic.
+/// Flags are added along with new features, so the struct cannot be built outside the crate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Capabilities {
+	/// `RedisSnapshotStore` and `RegistryBuilder::with_redis_client` are available (`redis`).
+	pub redis: bool,
+	/// Cache metrics are emitted through the `metrics` facade (`metrics`).
+	pub metrics: bool,
+	/// The bundled Prometheus exporter can be installed (`prometheus`).
+	pub prometheus: bool,
+	/// Gzip-encoded upstream responses are accepted and `Registry::write_statuses_json_gzip` is
+	/// available (`gzip`).
+	pub gzip: bool,
+	/// Brotli-encoded upstream responses are accepted (`brotli`).
+	pub brotli: bool,
+	/// The axum middleware module is available (`jwks-cache-axum`).
+	pub axum: bool,
+	/// The `admin` module's HTTP router is available (`admin-api`).
+	pub admin_api: bool,
+	/// The `grpc` module's tonic `CacheControl` service is available (`grpc`).
+	pub grpc: bool,
+	/// Errors convert into RFC 7807 problem details (`problem-details`).
+	pub problem_details: bool,
+	/// Randomised refresh jitter is available (`rand`).
+	pub rand: bool,
+	/// `Registry::install_sighup_reload` is available (`unix`, on Unix targets only).
+	pub unix: bool,
+	/// Registration files ending in `.toml` can be loaded (`toml`).
+	pub toml: bool,
+	/// Registration files ending in `.yaml` or `.yml` can be loaded (`yaml`).
+	pub yaml: bool,
+	/// `RegistryBuilder::compress_jwks` is available (`zstd`).
+	pub zstd: bool,
+	/// The `test_util` module's in-process identity provider is available (`test-util`).
+	pub test_util: bool,
+	/// The `jwks-cache` command-line binary is enabled (`cli`).
+	pub cli: bool,
+}
+
+/// Report which optional subsystems this build of the crate includes.
+pub const fn capabilities() -> Capabilities {
+	Capabilities {
+		redis: cfg!(feature = "redis"),
+		metrics: cfg!(feature = "metrics"),
+		prometheus: cfg!(feature = "prometheus"),
+		gzip: cfg!(feature = "gzip"),
+		brotli: cfg!(feature = "brotli"),
+		axum: cfg!(feature = "jwks-cache-axum"),
+		admin_api: cfg!(feature = "admin-api"),
+		grpc: cfg!(feature = "grpc"),
+		problem_details: cfg!(feature = "problem-details"),
+		rand: cfg!(feature = "rand"),
+		unix: cfg!(all(unix, feature = "unix")),
+		toml: cfg!(feature = "toml"),
+		yaml: cfg!(feature = "yaml"),
+		zstd: cfg!(feature = "zstd"),
+		test_util: cfg!(feature = "test-util"),
+		cli: cfg!(feature = "cli"),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn prometheus_implies_metrics() {
+		let capabilities = capabilities();
+
+		assert!(!capabilities.prometheus || capabilities.metrics);
+		assert_eq!(capabilities.redis, cfg!(feature = "redis"));
+	}
+}
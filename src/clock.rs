@@ -0,0 +1,122 @@
+//! Time source shared by freshness evaluation, scheduling, and status reporting.
+//!
+//! The cache schedules work on [`tokio::time::Instant`], but HTTP cache semantics and status
+//! timestamps need wall-clock time. Reading wall-clock time through this module offsets
+//! [`SystemTime::now`] by however far tokio's clock has been paused or advanced, so tests using
+//! `tokio::time::pause` and `tokio::time::advance` move freshness decisions without real sleeps.
+//! Outside a paused runtime the offset is zero and readings match the system clock.
+
+// std
+use std::time::Instant as StdInstant;
+// self
+use crate::_prelude::*;
+
+/// Monotonic time according to the tokio clock.
+pub fn now() -> Instant {
+	Instant::now()
+}
+
+/// Wall-clock time aligned with the tokio clock.
+pub fn system_now() -> SystemTime {
+	let virtual_now = Instant::now().into_std();
+	let real_now = StdInstant::now();
+	let wall = SystemTime::now();
+
+	match virtual_now.checked_duration_since(real_now) {
+		Some(ahead) => wall + ahead,
+		None => wall - real_now.saturating_duration_since(virtual_now),
+	}
+}
+
+/// UTC timestamp aligned with the tokio clock.
+pub fn utc_now() -> DateTime<Utc> {
+	DateTime::<Utc>::from(system_now())
+}
+
+/// Monotonic and wall-clock readings taken at the same moment.
+///
+/// Comparing how far each clock has moved since the anchor reveals clock jumps: monotonic time
+/// stops while the host is suspended, and wall-clock time can be stepped in either direction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClockAnchor {
+	/// Monotonic reading.
+	pub monotonic: Instant,
+	/// Wall-clock reading.
+	pub wall: SystemTime,
+}
+impl ClockAnchor {
+	/// Anchor both clocks at the current time.
+	pub fn now() -> Self {
+		Self { monotonic: now(), wall: system_now() }
+	}
+
+	/// How far the wall clock has moved relative to the monotonic clock since the anchor.
+	pub fn drift(&self, monotonic: Instant, wall: SystemTime) -> ClockDrift {
+		let monotonic_elapsed = monotonic.saturating_duration_since(self.monotonic);
+
+		match wall.duration_since(self.wall) {
+			Ok(wall_elapsed) if wall_elapsed >= monotonic_elapsed =>
+				ClockDrift::Ahead(wall_elapsed - monotonic_elapsed),
+			Ok(wall_elapsed) => ClockDrift::Behind(monotonic_elapsed - wall_elapsed),
+			Err(err) => ClockDrift::Behind(monotonic_elapsed.saturating_add(err.duration())),
+		}
+	}
+}
+
+/// Signed difference between wall-clock and monotonic time elapsed since a [`ClockAnchor`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockDrift {
+	/// The wall clock moved further, as after a suspend or a forward step.
+	Ahead(Duration),
+	/// The wall clock moved less, as after a backward step.
+	Behind(Duration),
+}
+impl ClockDrift {
+	/// Size of the drift regardless of direction.
+	pub fn magnitude(&self) -> Duration {
+		match self {
+			Self::Ahead(drift) | Self::Behind(drift) => *drift,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test(start_paused = true)]
+	async fn wall_clock_follows_paused_tokio_time() {
+		let before = system_now();
+
+		tokio::time::advance(Duration::from_secs(3_600)).await;
+
+		let elapsed = system_now().duration_since(before).expect("clock moved forward");
+
+		// Real time keeps moving between the clock reads, so allow a small sampling error.
+		assert!(elapsed > Duration::from_secs(3_599), "{elapsed:?}");
+		assert!(elapsed < Duration::from_secs(3_601), "{elapsed:?}");
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn drift_compares_elapsed_wall_and_monotonic_time() {
+		let anchor = ClockAnchor::now();
+		let later = anchor.monotonic + Duration::from_secs(60);
+
+		assert_eq!(
+			anchor.drift(later, anchor.wall + Duration::from_secs(60)),
+			ClockDrift::Ahead(Duration::ZERO)
+		);
+		assert_eq!(
+			anchor.drift(later, anchor.wall + Duration::from_secs(3_660)),
+			ClockDrift::Ahead(Duration::from_secs(3_600))
+		);
+		assert_eq!(
+			anchor.drift(later, anchor.wall + Duration::from_secs(20)),
+			ClockDrift::Behind(Duration::from_secs(40))
+		);
+		assert_eq!(
+			anchor.drift(later, anchor.wall - Duration::from_secs(30)).magnitude(),
+			Duration::from_secs(90)
+		);
+	}
+}
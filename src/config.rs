@@ -0,0 +1,592 @@
+//! Typed configuration values validated at parse time.
+//!
+//! These newtypes accept either raw integers or human-readable strings during deserialisation so
+//! invalid values are rejected while the configuration is parsed instead of later in
+//! [`IdentityProviderRegistration::validate`](crate::IdentityProviderRegistration::validate).
+//! The [`duration`] and [`optional_duration`] adapters give plain [`Duration`] fields the same
+//! treatment, and [`interpolate_env`] expands `${VAR}` references in configuration strings.
+
+// std
+use std::{
+	env,
+	fmt::{Display, Formatter, Result as FmtResult},
+	str::FromStr,
+};
+// crates.io
+use serde::{
+	Deserialize, Deserializer, Serialize, Serializer,
+	de::{self, MapAccess, Visitor},
+};
+// self
+use crate::_prelude::*;
+
+const BYTE_UNITS: [(&str, u64); 9] = [
+	("b", 1),
+	("kb", 1_000),
+	("kib", 1_024),
+	("mb", 1_000_000),
+	("mib", 1_048_576),
+	("gb", 1_000_000_000),
+	("gib", 1_073_741_824),
+	("k", 1_000),
+	("m", 1_000_000),
+];
+
+/// Time-to-live expressed in whole seconds.
+///
+/// Deserialises from an integer number of seconds (`300`) or a `humantime` string (`"5m"`).
+/// Zero and sub-second values are rejected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TtlSeconds(Duration);
+impl TtlSeconds {
+	/// Construct a TTL from whole seconds without validation.
+	pub const fn from_secs(secs: u64) -> Self {
+		Self(Duration::from_secs(secs))
+	}
+
+	/// Construct a TTL from a [`Duration`], rejecting zero and sub-second precision.
+	pub fn new(value: Duration) -> Result<Self> {
+		if value.is_zero() {
+			return Err(Error::Validation {
+				field: "ttl",
+				reason: "Must be greater than zero.".into(),
+			});
+		}
+		if value.subsec_nanos() != 0 {
+			return Err(Error::Validation {
+				field: "ttl",
+				reason: format!("Must be a whole number of seconds, got {value:?}."),
+			});
+		}
+
+		Ok(Self(value))
+	}
+
+	/// Parse a TTL from an integer or `humantime` string.
+	pub fn parse(value: &str) -> Result<Self> {
+		let trimmed = value.trim();
+
+		if let Ok(secs) = trimmed.parse::<u64>() {
+			return Self::new(Duration::from_secs(secs));
+		}
+
+		let duration = humantime::parse_duration(trimmed).map_err(|err| Error::Validation {
+			field: "ttl",
+			reason: format!("Invalid duration '{trimmed}': {err}."),
+		})?;
+
+		Self::new(duration)
+	}
+
+	/// TTL as a [`Duration`].
+	pub const fn as_duration(self) -> Duration {
+		self.0
+	}
+
+	/// TTL in whole seconds.
+	pub const fn as_secs(self) -> u64 {
+		self.0.as_secs()
+	}
+}
+impl Display for TtlSeconds {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		write!(f, "{}", humantime::format_duration(self.0))
+	}
+}
+impl From<TtlSeconds> for Duration {
+	fn from(value: TtlSeconds) -> Self {
+		value.0
+	}
+}
+impl FromStr for TtlSeconds {
+	type Err = Error;
+
+	fn from_str(value: &str) -> Result<Self> {
+		Self::parse(value)
+	}
+}
+impl TryFrom<Duration> for TtlSeconds {
+	type Error = Error;
+
+	fn try_from(value: Duration) -> Result<Self> {
+		Self::new(value)
+	}
+}
+impl Serialize for TtlSeconds {
+	fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serializer.serialize_u64(self.as_secs())
+	}
+}
+impl<'de> Deserialize<'de> for TtlSeconds {
+	fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		deserializer.deserialize_any(TtlSecondsVisitor)
+	}
+}
+
+/// Size in bytes.
+///
+/// Deserialises from an integer number of bytes (`1048576`) or a string with an SI or IEC suffix
+/// (`"1MB"`, `"1MiB"`, `"512 KiB"`). Zero is rejected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ByteSize(u64);
+impl ByteSize {
+	/// Construct a size from raw bytes without validation.
+	pub const fn from_bytes(bytes: u64) -> Self {
+		Self(bytes)
+	}
+
+	/// Construct a size from raw bytes, rejecting zero.
+	pub fn new(bytes: u64) -> Result<Self> {
+		if bytes == 0 {
+			return Err(Error::Validation {
+				field: "byte_size",
+				reason: "Must be greater than zero.".into(),
+			});
+		}
+
+		Ok(Self(bytes))
+	}
+
+	/// Parse a size from an integer or suffixed string.
+	pub fn parse(value: &str) -> Result<Self> {
+		let trimmed = value.trim();
+		let split = trimmed.find(|c: char| !c.is_ascii_digit()).unwrap_or(trimmed.len());
+		let (digits, unit) = trimmed.split_at(split);
+		let invalid = |reason: &str| Error::Validation {
+			field: "byte_size",
+			reason: format!("Invalid size '{trimmed}': {reason}."),
+		};
+		let amount = digits.parse::<u64>().map_err(|_| invalid("expected a leading integer"))?;
+		let unit = unit.trim().to_ascii_lowercase();
+		let multiplier = if unit.is_empty() {
+			1
+		} else {
+			BYTE_UNITS
+				.iter()
+				.find_map(|(name, multiplier)| (*name == unit).then_some(*multiplier))
+				.ok_or_else(|| invalid("unknown unit"))?
+		};
+		let bytes = amount.checked_mul(multiplier).ok_or_else(|| invalid("value overflows u64"))?;
+
+		Self::new(bytes)
+	}
+
+	/// Size in bytes.
+	pub const fn as_u64(self) -> u64 {
+		self.0
+	}
+}
+impl Display for ByteSize {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		write!(f, "{} bytes", self.0)
+	}
+}
+impl From<ByteSize> for u64 {
+	fn from(value: ByteSize) -> Self {
+		value.0
+	}
+}
+impl FromStr for ByteSize {
+	type Err = Error;
+
+	fn from_str(value: &str) -> Result<Self> {
+		Self::parse(value)
+	}
+}
+impl Serialize for ByteSize {
+	fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serializer.serialize_u64(self.0)
+	}
+}
+impl<'de> Deserialize<'de> for ByteSize {
+	fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		deserializer.deserialize_any(ByteSizeVisitor)
+	}
+}
+
+/// Serde adapter for [`Duration`] fields, used as `#[serde(with =
+/// "jwks_cache::config::duration")]`.
+///
+/// Deserialises from a `humantime` string (`"90s"`, `"1h 30m"`), an integer number of seconds, or
+/// the `{ secs, nanos }` map of serde's default representation. Serialises as a `humantime` string.
+pub mod duration {
+	// self
+	use super::*;
+
+	/// Serialise `value` as a `humantime` string.
+	pub fn serialize<S>(value: &Duration, serializer: S) -> std::result::Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		HumanDuration(*value).serialize(serializer)
+	}
+
+	/// Deserialise a duration from any supported representation.
+	pub fn deserialize<'de, D>(deserializer: D) -> std::result::Result<Duration, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		Ok(HumanDuration::deserialize(deserializer)?.0)
+	}
+}
+
+/// Serde adapter like [`duration`] for `Option<Duration>` fields, with `null` for `None`.
+pub mod optional_duration {
+	// self
+	use super::*;
+
+	/// Serialise `value` as a `humantime` string, or `null` when unset.
+	pub fn serialize<S>(
+		value: &Option<Duration>,
+		serializer: S,
+	) -> std::result::Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		value.map(HumanDuration).serialize(serializer)
+	}
+
+	/// Deserialise an optional duration from any representation [`duration`] supports.
+	pub fn deserialize<'de, D>(deserializer: D) -> std::result::Result<Option<Duration>, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		Ok(Option::<HumanDuration>::deserialize(deserializer)?.map(|value| value.0))
+	}
+}
+
+/// Expand `${VAR}` references in `value` from the process environment.
+///
+/// `${VAR:-fallback}` substitutes `fallback` when `VAR` is unset or empty, and `$${` produces a
+/// literal `${`. Any other `$` is kept as is. Referencing an unset variable without a fallback is
+/// a validation error, so a missing secret fails loudly instead of becoming an empty string.
+pub fn interpolate_env(value: &str) -> Result<String> {
+	interpolate_with(value, |name| env::var(name).ok())
+}
+
+fn interpolate_with<F>(value: &str, lookup: F) -> Result<String>
+where
+	F: Fn(&str) -> Option<String>,
+{
+	let mut output = String::with_capacity(value.len());
+	let mut rest = value;
+
+	while let Some(start) = rest.find('$') {
+		output.push_str(&rest[..start]);
+		rest = &rest[start..];
+
+		if let Some(escaped) = rest.strip_prefix("$${") {
+			output.push_str("${");
+			rest = escaped;
+
+			continue;
+		}
+
+		let Some(reference) = rest.strip_prefix("${") else {
+			output.push('$');
+			rest = &rest[1..];
+
+			continue;
+		};
+		let end = reference.find('}').ok_or_else(|| Error::Validation {
+			field: "env",
+			reason: format!("Unterminated variable reference in '{value}'."),
+		})?;
+		let (name, fallback) = match reference[..end].split_once(":-") {
+			Some((name, fallback)) => (name, Some(fallback)),
+			None => (&reference[..end], None),
+		};
+		let resolved = match (lookup(name).filter(|value| !value.is_empty()), fallback) {
+			(Some(resolved), _) => resolved,
+			(None, Some(fallback)) => fallback.to_owned(),
+			(None, None) =>
+				return Err(Error::Validation {
+					field: "env",
+					reason: format!("Environment variable `{name}` is not set."),
+				}),
+		};
+
+		output.push_str(&resolved);
+		rest = &reference[end + 1..];
+	}
+
+	output.push_str(rest);
+
+	Ok(output)
+}
+
+// Wire representation behind the `duration` adapters.
+struct HumanDuration(Duration);
+impl Serialize for HumanDuration {
+	fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serializer.collect_str(&humantime::format_duration(self.0))
+	}
+}
+impl<'de> Deserialize<'de> for HumanDuration {
+	fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		deserializer.deserialize_any(HumanDurationVisitor).map(Self)
+	}
+}
+
+struct HumanDurationVisitor;
+impl<'de> Visitor<'de> for HumanDurationVisitor {
+	type Value = Duration;
+
+	fn expecting(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.write_str("a number of seconds or a duration string such as \"90s\"")
+	}
+
+	fn visit_u64<E>(self, value: u64) -> std::result::Result<Self::Value, E>
+	where
+		E: de::Error,
+	{
+		Ok(Duration::from_secs(value))
+	}
+
+	fn visit_i64<E>(self, value: i64) -> std::result::Result<Self::Value, E>
+	where
+		E: de::Error,
+	{
+		let value = u64::try_from(value)
+			.map_err(|_| E::invalid_value(de::Unexpected::Signed(value), &self))?;
+
+		self.visit_u64(value)
+	}
+
+	fn visit_str<E>(self, value: &str) -> std::result::Result<Self::Value, E>
+	where
+		E: de::Error,
+	{
+		let trimmed = value.trim();
+
+		if let Ok(secs) = trimmed.parse::<u64>() {
+			return Ok(Duration::from_secs(secs));
+		}
+
+		humantime::parse_duration(trimmed)
+			.map_err(|err| E::custom(format!("invalid duration '{trimmed}': {err}")))
+	}
+
+	fn visit_map<A>(self, mut map: A) -> std::result::Result<Self::Value, A::Error>
+	where
+		A: MapAccess<'de>,
+	{
+		let (mut secs, mut nanos) = (0_u64, 0_u32);
+
+		while let Some(key) = map.next_key::<String>()? {
+			match key.as_str() {
+				"secs" => secs = map.next_value()?,
+				"nanos" => nanos = map.next_value()?,
+				_ => return Err(de::Error::unknown_field(&key, &["secs", "nanos"])),
+			}
+		}
+
+		Ok(Duration::new(secs, nanos))
+	}
+}
+
+struct TtlSecondsVisitor;
+impl Visitor<'_> for TtlSecondsVisitor {
+	type Value = TtlSeconds;
+
+	fn expecting(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.write_str("a positive number of seconds or a duration string such as \"5m\"")
+	}
+
+	fn visit_u64<E>(self, value: u64) -> std::result::Result<Self::Value, E>
+	where
+		E: de::Error,
+	{
+		TtlSeconds::new(Duration::from_secs(value)).map_err(E::custom)
+	}
+
+	fn visit_i64<E>(self, value: i64) -> std::result::Result<Self::Value, E>
+	where
+		E: de::Error,
+	{
+		let value = u64::try_from(value)
+			.map_err(|_| E::invalid_value(de::Unexpected::Signed(value), &self))?;
+
+		self.visit_u64(value)
+	}
+
+	fn visit_str<E>(self, value: &str) -> std::result::Result<Self::Value, E>
+	where
+		E: de::Error,
+	{
+		TtlSeconds::parse(value).map_err(E::custom)
+	}
+}
+
+struct ByteSizeVisitor;
+impl Visitor<'_> for ByteSizeVisitor {
+	type Value = ByteSize;
+
+	fn expecting(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.write_str("a positive number of bytes or a size string such as \"1MiB\"")
+	}
+
+	fn visit_u64<E>(self, value: u64) -> std::result::Result<Self::Value, E>
+	where
+		E: de::Error,
+	{
+		ByteSize::new(value).map_err(E::custom)
+	}
+
+	fn visit_i64<E>(self, value: i64) -> std::result::Result<Self::Value, E>
+	where
+		E: de::Error,
+	{
+		let value = u64::try_from(value)
+			.map_err(|_| E::invalid_value(de::Unexpected::Signed(value), &self))?;
+
+		self.visit_u64(value)
+	}
+
+	fn visit_str<E>(self, value: &str) -> std::result::Result<Self::Value, E>
+	where
+		E: de::Error,
+	{
+		ByteSize::parse(value).map_err(E::custom)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn durations_accept_humantime_integers_and_legacy_maps() {
+		#[derive(Debug, PartialEq, Serialize, Deserialize)]
+		struct Config {
+			#[serde(with = "duration")]
+			timeout: Duration,
+			#[serde(default, with = "optional_duration")]
+			grace: Option<Duration>,
+		}
+
+		for raw in [
+			r#"{ "timeout": "1m 30s" }"#,
+			r#"{ "timeout": 90, "grace": null }"#,
+			r#"{ "timeout": { "secs": 90, "nanos": 0 } }"#,
+		] {
+			let config: Config = serde_json::from_str(raw).expect(raw);
+
+			assert_eq!(config, Config { timeout: Duration::from_secs(90), grace: None }, "{raw}");
+		}
+
+		let config =
+			Config { timeout: Duration::from_millis(250), grace: Some(Duration::from_secs(300)) };
+		let json = serde_json::to_string(&config).expect("serialize");
+
+		assert_eq!(json, r#"{"timeout":"250ms","grace":"5m"}"#);
+		assert_eq!(serde_json::from_str::<Config>(&json).expect("round trip"), config);
+		assert!(serde_json::from_str::<Config>(r#"{ "timeout": "soon" }"#).is_err());
+	}
+
+	#[test]
+	fn registrations_read_humantime_durations() {
+		let registration: crate::IdentityProviderRegistration =
+			serde_json::from_value(serde_json::json!({
+				"tenant_id": "tenant-a",
+				"provider_id": "idp",
+				"jwks_url": "https://idp.example.com/jwks",
+				"max_ttl": "48h",
+				"max_refresh_early": "24h",
+				"negative_cache_ttl": 30,
+				"retry_policy": {
+					"max_retries": 2,
+					"attempt_timeout": "250ms",
+					"initial_backoff": "100ms",
+					"max_backoff": "2s",
+					"deadline": "5s",
+				},
+			}))
+			.expect("registration");
+
+		assert_eq!(registration.max_refresh_early, Some(Duration::from_secs(24 * 60 * 60)));
+		assert_eq!(registration.negative_cache_ttl, Duration::from_secs(30));
+		assert_eq!(registration.retry_policy.attempt_timeout, Duration::from_millis(250));
+		registration.validate().expect("valid registration");
+
+		let json = serde_json::to_value(&registration).expect("serialize");
+
+		assert_eq!(json["retry_policy"]["deadline"], "5s");
+		assert_eq!(json["max_refresh_early"], "1day");
+	}
+
+	#[test]
+	fn interpolation_expands_defaults_and_escapes() {
+		let lookup = |name: &str| (name == "IDP_HOST").then(|| "idp.example.com".to_owned());
+
+		assert_eq!(
+			interpolate_with("https://${IDP_HOST}/jwks?k=$1", lookup).expect("interpolated"),
+			"https://idp.example.com/jwks?k=$1"
+		);
+		assert_eq!(
+			interpolate_with("${PORT:-8443} $${HOME}", lookup).expect("fallback"),
+			"8443 ${HOME}"
+		);
+		assert!(matches!(
+			interpolate_with("${API_KEY}", lookup),
+			Err(Error::Validation { field: "env", reason }) if reason.contains("API_KEY")
+		));
+		assert!(interpolate_with("${IDP_HOST", lookup).is_err());
+	}
+
+	#[test]
+	fn ttl_accepts_integers_and_humantime_strings() {
+		let from_int: TtlSeconds = serde_json::from_str("90").expect("integer ttl");
+		let from_str: TtlSeconds = serde_json::from_str("\"1h 30m\"").expect("string ttl");
+
+		assert_eq!(from_int.as_duration(), Duration::from_secs(90));
+		assert_eq!(from_str.as_duration(), Duration::from_secs(5_400));
+		assert_eq!(serde_json::to_string(&from_str).expect("serialize"), "5400");
+	}
+
+	#[test]
+	fn ttl_rejects_zero_negative_and_subsecond_values() {
+		for raw in ["0", "-5", "\"250ms\"", "\"soon\""] {
+			assert!(serde_json::from_str::<TtlSeconds>(raw).is_err(), "{raw} should be rejected");
+		}
+	}
+
+	#[test]
+	fn byte_size_accepts_si_and_iec_suffixes() {
+		for (raw, expected) in [
+			("1024", 1_024),
+			("\"1MiB\"", 1_048_576),
+			("\"512 KB\"", 512_000),
+			("\"2gib\"", 2_147_483_648),
+		] {
+			let size: ByteSize = serde_json::from_str(raw).expect("valid size");
+
+			assert_eq!(size.as_u64(), expected, "{raw}");
+		}
+	}
+
+	#[test]
+	fn byte_size_rejects_zero_unknown_units_and_overflow() {
+		for raw in ["0", "\"0KiB\"", "\"12 parsecs\"", "\"MiB\"", "\"99999999999999GiB\""] {
+			assert!(serde_json::from_str::<ByteSize>(raw).is_err(), "{raw} should be rejected");
+		}
+	}
+}
@@ -0,0 +1,95 @@
+//! Cache lifecycle events broadcast to subscribers.
+//!
+//! [`Registry::subscribe`](crate::Registry::subscribe) hands out a `tokio::sync::broadcast`
+//! receiver, so alerting and audit logging can react to refreshes, stale serving, and
+//! registration changes without polling statuses. Events are dropped when nobody is subscribed,
+//! and slow subscribers observe `RecvError::Lagged` instead of blocking the cache.
+
+// crates.io
+use serde::{Deserialize, Serialize};
+// self
+use crate::{_prelude::*, clock};
+
+/// Default number of events buffered per subscriber before older ones are dropped.
+pub const DEFAULT_EVENT_CAPACITY: usize = 256;
+
+/// What happened to a provider's cache.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CacheEventKind {
+	/// An upstream fetch started.
+	RefreshStarted,
+	/// An upstream fetch updated or revalidated the cached keys.
+	RefreshSucceeded,
+	/// An upstream fetch failed after exhausting its retries.
+	RefreshFailed {
+		/// Error that ended the last attempt, as given by [`Error::redacted`].
+		error: String,
+		/// Stable [`Error::code`] of that error.
+		code: String,
+	},
+	/// Cached keys were served past their freshness window.
+	StaleServed,
+	/// Cached keys were discarded via invalidation.
+	Invalidated,
+	/// Cached keys were dropped to keep the registry within its resident provider limit.
+	Evicted,
+	/// A refreshed discovery document moved the provider's `jwks_uri`, and fetches now follow it.
+	JwksUriChanged {
+		/// JWKS URL used before the change.
+		previous: String,
+		/// JWKS URL advertised by the discovery document.
+		current: String,
+	},
+	/// A refreshed discovery document advertised a `jwks_uri` that failed HTTPS or allowlist
+	/// checks; fetches keep using the previous URL.
+	JwksUriRejected {
+		/// JWKS URL advertised by the discovery document.
+		advertised: String,
+		/// Validation error for the advertised URL.
+		error: String,
+	},
+	/// A fetch from the primary JWKS URL failed and a mirror served the keys instead.
+	MirrorUsed {
+		/// Mirror URL that answered, with credentials and query redacted.
+		mirror: String,
+		/// Error returned by the primary URL.
+		error: String,
+	},
+	/// A provider was registered or re-registered.
+	ProviderRegistered,
+	/// A provider was unregistered.
+	///
+	/// Emitted after its refreshes were cancelled and its snapshot deleted; no further events
+	/// follow for the removed registration.
+	ProviderUnregistered,
+}
+
+/// Lifecycle event for a single tenant/provider pair.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheEvent {
+	/// Tenant the event belongs to.
+	pub tenant_id: String,
+	/// Provider the event belongs to.
+	pub provider_id: String,
+	/// UTC timestamp when the event was emitted.
+	pub occurred_at: DateTime<Utc>,
+	/// Event payload.
+	#[serde(flatten)]
+	pub kind: CacheEventKind,
+}
+impl CacheEvent {
+	/// Create an event stamped with the current time.
+	pub fn new(
+		tenant_id: impl Into<String>,
+		provider_id: impl Into<String>,
+		kind: CacheEventKind,
+	) -> Self {
+		Self {
+			tenant_id: tenant_id.into(),
+			provider_id: provider_id.into(),
+			occurred_at: clock::utc_now(),
+			kind,
+		}
+	}
+}
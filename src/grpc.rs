@@ -0,0 +1,250 @@
+//! Tonic implementation of the `jwks_cache.v1.CacheControl` gRPC service.
+//!
+//! [`CacheControlService`] serves the contract in `proto/jwks_cache/v1/cache_control.proto` on top
+//! of a [`Registry`], so control planes written in other languages can register, resolve, and
+//! inspect providers. Messages and the generated client live in [`proto`].
+//!
+//! The service performs no authentication. Wrap it with
+//! [`CacheControlServer::with_interceptor`](proto::cache_control_server::CacheControlServer::with_interceptor)
+//! or a tower layer before exposing it beyond a trusted network.
+
+// crates.io
+use tonic::{Code, Request, Response, Status, metadata::MetadataValue};
+// self
+use crate::{
+	_prelude::*,
+	registry::{
+		IdentityProviderRegistration, ProviderError, ProviderState, ProviderStatus, Registry,
+		StatusFilter,
+	},
+};
+
+/// Messages, client, and server generated from `cache_control.proto`.
+#[allow(clippy::all, missing_docs)]
+pub mod proto {
+	tonic::include_proto!("jwks_cache.v1");
+}
+use proto::cache_control_server::{CacheControl, CacheControlServer};
+
+/// Page size used by `ListStatuses` when the request sets no `limit`.
+pub const DEFAULT_PAGE_LIMIT: usize = 100;
+/// Largest page `ListStatuses` returns, regardless of the requested `limit`.
+pub const MAX_PAGE_LIMIT: usize = 1_000;
+/// Metadata key carrying [`Error::code`] on failed calls.
+pub const ERROR_CODE_METADATA: &str = "jwks-cache-error-code";
+
+/// `CacheControl` service backed by a [`Registry`].
+#[derive(Clone, Debug)]
+pub struct CacheControlService {
+	registry: Registry,
+}
+impl CacheControlService {
+	/// Serve `registry`.
+	pub fn new(registry: Registry) -> Self {
+		Self { registry }
+	}
+
+	/// Wrap the service in the generated server, ready for `tonic::transport::Server`.
+	pub fn into_server(self) -> CacheControlServer<Self> {
+		CacheControlServer::new(self)
+	}
+
+	async fn provider_status(
+		&self,
+		tenant_id: &str,
+		provider_id: &str,
+	) -> Result<proto::ProviderStatus> {
+		Ok(self.registry.provider_status(tenant_id, provider_id).await?.into())
+	}
+}
+
+#[tonic::async_trait]
+impl CacheControl for CacheControlService {
+	async fn register(
+		&self,
+		request: Request<proto::RegisterRequest>,
+	) -> std::result::Result<Response<proto::ProviderStatus>, Status> {
+		let registration: IdentityProviderRegistration =
+			serde_json::from_str(&request.into_inner().registration_json).map_err(|err| {
+				Status::invalid_argument(format!("Invalid registration_json: {err}."))
+			})?;
+		let registration = self.registry.register(registration).await.map_err(status_from)?;
+		let status = self
+			.provider_status(&registration.tenant_id, &registration.provider_id)
+			.await
+			.map_err(status_from)?;
+
+		Ok(Response::new(status))
+	}
+
+	async fn unregister(
+		&self,
+		request: Request<proto::ProviderRef>,
+	) -> std::result::Result<Response<proto::UnregisterResponse>, Status> {
+		let provider = request.into_inner();
+		let removed = self
+			.registry
+			.unregister(&provider.tenant_id, &provider.provider_id)
+			.await
+			.map_err(status_from)?;
+
+		Ok(Response::new(proto::UnregisterResponse { removed }))
+	}
+
+	async fn resolve(
+		&self,
+		request: Request<proto::ResolveRequest>,
+	) -> std::result::Result<Response<proto::ResolveResponse>, Status> {
+		let request = request.into_inner();
+		let provider = provider_ref(request.provider)?;
+		let jwks = self
+			.registry
+			.resolve(&provider.tenant_id, &provider.provider_id, request.kid.as_deref())
+			.await
+			.map_err(status_from)?;
+		let jwks_json = serde_json::to_string(&*jwks).map_err(|err| status_from(err.into()))?;
+
+		Ok(Response::new(proto::ResolveResponse { jwks_json }))
+	}
+
+	async fn refresh(
+		&self,
+		request: Request<proto::RefreshRequest>,
+	) -> std::result::Result<Response<proto::ProviderStatus>, Status> {
+		let request = request.into_inner();
+		let provider = provider_ref(request.provider)?;
+		let (tenant_id, provider_id) = (&provider.tenant_id, &provider.provider_id);
+
+		if request.force {
+			self.registry.force_refresh(tenant_id, provider_id).await
+		} else {
+			self.registry.refresh(tenant_id, provider_id).await
+		}
+		.map_err(status_from)?;
+
+		Ok(Response::new(self.provider_status(tenant_id, provider_id).await.map_err(status_from)?))
+	}
+
+	async fn status(
+		&self,
+		request: Request<proto::ProviderRef>,
+	) -> std::result::Result<Response<proto::ProviderStatus>, Status> {
+		let provider = request.into_inner();
+		let status = self
+			.provider_status(&provider.tenant_id, &provider.provider_id)
+			.await
+			.map_err(status_from)?;
+
+		Ok(Response::new(status))
+	}
+
+	async fn list_statuses(
+		&self,
+		request: Request<proto::ListStatusesRequest>,
+	) -> std::result::Result<Response<proto::ListStatusesResponse>, Status> {
+		let request = request.into_inner();
+		let state = match proto::ProviderState::try_from(request.state) {
+			Ok(proto::ProviderState::Unspecified) => None,
+			Ok(proto::ProviderState::Empty) => Some(ProviderState::Empty),
+			Ok(proto::ProviderState::Loading) => Some(ProviderState::Loading),
+			Ok(proto::ProviderState::Ready) => Some(ProviderState::Ready),
+			Ok(proto::ProviderState::Refreshing) => Some(ProviderState::Refreshing),
+			Err(_) =>
+				return Err(Status::invalid_argument(format!(
+					"Unknown provider state {}.",
+					request.state
+				))),
+		};
+		let filter = StatusFilter { tenant_id: request.tenant_id, state, failing: request.failing };
+		let limit = match request.limit {
+			0 => DEFAULT_PAGE_LIMIT,
+			limit => (limit as usize).min(MAX_PAGE_LIMIT),
+		};
+		let offset = usize::try_from(request.offset).unwrap_or(usize::MAX);
+		let page = self.registry.statuses_page(&filter, offset, limit).await;
+
+		Ok(Response::new(proto::ListStatusesResponse {
+			statuses: page.statuses.into_iter().map(Into::into).collect(),
+			total: page.total as u64,
+			next_offset: page.next_offset.map(|offset| offset as u64),
+		}))
+	}
+}
+
+impl From<ProviderStatus> for proto::ProviderStatus {
+	fn from(status: ProviderStatus) -> Self {
+		Self {
+			tenant_id: status.tenant_id,
+			provider_id: status.provider_id,
+			state: proto::ProviderState::from(status.state).into(),
+			last_refresh: status.last_refresh.map(timestamp),
+			next_refresh: status.next_refresh.map(timestamp),
+			expires_at: status.expires_at.map(timestamp),
+			error_count: status.error_count,
+			last_error: status.last_error.map(Into::into),
+			age_seconds: status.age_seconds,
+			time_to_ready_ms: status.time_to_ready_ms,
+		}
+	}
+}
+impl From<ProviderError> for proto::ProviderError {
+	fn from(error: ProviderError) -> Self {
+		Self {
+			message: error.message,
+			code: error.code,
+			occurred_at: Some(timestamp(error.occurred_at)),
+			http_status: error.http_status.map(u32::from),
+		}
+	}
+}
+impl From<ProviderState> for proto::ProviderState {
+	fn from(state: ProviderState) -> Self {
+		match state {
+			ProviderState::Empty => Self::Empty,
+			ProviderState::Loading => Self::Loading,
+			ProviderState::Ready => Self::Ready,
+			ProviderState::Refreshing => Self::Refreshing,
+		}
+	}
+}
+
+fn timestamp(at: DateTime<Utc>) -> prost_types::Timestamp {
+	prost_types::Timestamp { seconds: at.timestamp(), nanos: at.timestamp_subsec_nanos() as i32 }
+}
+
+fn provider_ref(
+	provider: Option<proto::ProviderRef>,
+) -> std::result::Result<proto::ProviderRef, Status> {
+	provider.ok_or_else(|| Status::invalid_argument("Missing provider."))
+}
+
+// Map a crate error onto the closest gRPC status, carrying its stable code in metadata.
+fn status_from(err: Error) -> Status {
+	let mut status = Status::new(code_for(&err), err.to_string());
+
+	status.metadata_mut().insert(ERROR_CODE_METADATA, MetadataValue::from_static(err.code()));
+
+	status
+}
+
+fn code_for(err: &Error) -> Code {
+	match err.root() {
+		Error::NotRegistered { .. } | Error::KeyNotFound { .. } | Error::KeyNotActive { .. } =>
+			Code::NotFound,
+		Error::Validation { .. } | Error::Url(_) => Code::InvalidArgument,
+		Error::InvalidToken(_) => Code::Unauthenticated,
+		Error::Security(_) => Code::PermissionDenied,
+		Error::Reqwest(err) if err.is_timeout() => Code::DeadlineExceeded,
+		Error::ResolveTimeout { .. } => Code::DeadlineExceeded,
+		Error::Reqwest(_)
+		| Error::HttpStatus { .. }
+		| Error::KeySetReplaced { .. }
+		| Error::RedirectPolicy { .. }
+		| Error::Serde(_)
+		| Error::Jsonwebtoken(_)
+		| Error::InvalidJwks(_)
+		| Error::Cache(_)
+		| Error::CircuitOpen { .. } => Code::Unavailable,
+		_ => Code::Internal,
+	}
+}
@@ -1,5 +1,11 @@
 //! HTTP helpers for JWKS retrieval and cache semantics.
 
+pub mod auth;
+pub mod capture;
 pub mod client;
+pub mod connection;
+pub mod discovery;
+pub mod rate_limit;
+pub mod resolve;
 pub mod retry;
 pub mod semantics;
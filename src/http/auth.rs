@@ -0,0 +1,278 @@
+//! Authorization for JWKS endpoints protected by OAuth.
+//!
+//! An [`AuthProvider`] supplies the `Authorization` header attached to each JWKS fetch of a
+//! registration. [`StaticToken`] sends a fixed credential, and [`ClientCredentials`] obtains
+//! bearer tokens through the OAuth 2.0 client-credentials grant, reusing each token until shortly
+//! before it expires.
+
+// std
+use std::{
+	fmt::{Debug, Formatter, Result as FmtResult},
+	sync::Mutex as StdMutex,
+};
+// crates.io
+use async_trait::async_trait;
+use http::{HeaderValue, header::ACCEPT};
+use reqwest::Client;
+use serde::Deserialize;
+use tokio::sync::Mutex;
+use url::Url;
+// self
+use crate::{
+	_prelude::*,
+	config::ByteSize,
+	http::{capture::REDACTED, client::read_body_limited},
+	security,
+};
+
+/// Default margin before expiry at which [`ClientCredentials`] requests a new token.
+pub const DEFAULT_TOKEN_REFRESH_EARLY: Duration = Duration::from_secs(30);
+/// Largest token endpoint response [`ClientCredentials`] reads.
+pub const MAX_TOKEN_RESPONSE_BYTES: ByteSize = ByteSize::from_bytes(64 * 1024);
+
+/// Supplies the `Authorization` header for a provider's JWKS requests.
+#[async_trait]
+pub trait AuthProvider
+where
+	Self: Debug + Send + Sync,
+{
+	/// Return the `Authorization` header value for the next JWKS request.
+	///
+	/// `client` is the HTTP client serving the provider's fetches, for providers that request
+	/// tokens over HTTP.
+	async fn authorization(&self, client: &Client) -> Result<HeaderValue>;
+
+	/// Discard cached credentials after the JWKS endpoint rejected them with `401`.
+	fn invalidate(&self) {}
+}
+
+/// Fixed `Authorization` header value.
+#[derive(Clone, Debug)]
+pub struct StaticToken {
+	value: HeaderValue,
+}
+impl StaticToken {
+	/// Send `Bearer {token}`.
+	pub fn bearer(token: impl AsRef<str>) -> Result<Self> {
+		Self::new(format!("Bearer {}", token.as_ref()))
+	}
+
+	/// Send `value` verbatim, including its scheme.
+	pub fn new(value: impl AsRef<str>) -> Result<Self> {
+		let mut value = HeaderValue::from_str(value.as_ref()).map_err(|_| Error::Validation {
+			field: "auth",
+			reason: "Token is not a valid header value.".into(),
+		})?;
+
+		value.set_sensitive(true);
+
+		Ok(Self { value })
+	}
+}
+#[async_trait]
+impl AuthProvider for StaticToken {
+	async fn authorization(&self, _client: &Client) -> Result<HeaderValue> {
+		Ok(self.value.clone())
+	}
+}
+
+/// Bearer tokens obtained through the OAuth 2.0 client-credentials grant.
+///
+/// The client authenticates to `token_url` with HTTP Basic authentication. Tokens are reused
+/// until [`DEFAULT_TOKEN_REFRESH_EARLY`] (or the configured margin) before `expires_in`, or until
+/// the JWKS endpoint rejects them; tokens without `expires_in` are reused until rejected.
+/// Concurrent fetches share one token request, which counts against the fetch's
+/// `attempt_timeout` and reads at most [`MAX_TOKEN_RESPONSE_BYTES`].
+pub struct ClientCredentials {
+	token_url: Url,
+	client_id: String,
+	client_secret: String,
+	scope: Option<String>,
+	refresh_early: Duration,
+	require_https: bool,
+	token: StdMutex<Option<CachedToken>>,
+	requesting: Mutex<()>,
+}
+impl ClientCredentials {
+	/// Request tokens from `token_url` as `client_id`.
+	pub fn new(
+		token_url: impl AsRef<str>,
+		client_id: impl Into<String>,
+		client_secret: impl Into<String>,
+	) -> Result<Self> {
+		Ok(Self {
+			token_url: Url::parse(token_url.as_ref())?,
+			client_id: client_id.into(),
+			client_secret: client_secret.into(),
+			scope: None,
+			refresh_early: DEFAULT_TOKEN_REFRESH_EARLY,
+			require_https: true,
+			token: StdMutex::new(None),
+			requesting: Mutex::new(()),
+		})
+	}
+
+	/// Request tokens for `scope`.
+	pub fn with_scope(mut self, scope: impl Into<String>) -> Self {
+		self.scope = Some(scope.into());
+
+		self
+	}
+
+	/// Request a new token `margin` before the current one expires.
+	pub fn with_refresh_early(mut self, margin: Duration) -> Self {
+		self.refresh_early = margin;
+
+		self
+	}
+
+	/// Allow a plain-HTTP token endpoint, for local testing only.
+	pub fn with_require_https(mut self, require_https: bool) -> Self {
+		self.require_https = require_https;
+
+		self
+	}
+
+	fn cached(&self, now: Instant) -> Option<HeaderValue> {
+		let token = self.token.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+		token
+			.as_ref()
+			.filter(|token| token.valid_until.is_none_or(|deadline| now < deadline))
+			.map(|token| token.value.clone())
+	}
+
+	async fn request_token(&self, client: &Client) -> Result<CachedToken> {
+		if self.require_https {
+			security::enforce_https(&self.token_url)?;
+		}
+
+		let mut form = vec![("grant_type", "client_credentials")];
+
+		if let Some(scope) = &self.scope {
+			form.push(("scope", scope));
+		}
+
+		let requested_at = Instant::now();
+		let response = client
+			.post(self.token_url.clone())
+			.basic_auth(&self.client_id, Some(&self.client_secret))
+			.header(ACCEPT, "application/json")
+			.form(&form)
+			.send()
+			.await?;
+		let status = response.status();
+
+		if !status.is_success() {
+			let body = read_body_limited(response, MAX_TOKEN_RESPONSE_BYTES, "Token response")
+				.await
+				.ok()
+				.map(|body| String::from_utf8_lossy(&body).into_owned());
+
+			return Err(Error::HttpStatus {
+				status,
+				url: self.token_url.clone(),
+				body,
+				retry_after_secs: None,
+			});
+		}
+
+		let body = read_body_limited(response, MAX_TOKEN_RESPONSE_BYTES, "Token response").await?;
+		let token = serde_json::from_slice::<TokenResponse>(&body)?;
+		let mut value =
+			HeaderValue::from_str(&format!("Bearer {}", token.access_token)).map_err(|_| {
+				Error::Validation {
+					field: "auth",
+					reason: "Access token is not a valid header value.".into(),
+				}
+			})?;
+
+		value.set_sensitive(true);
+
+		tracing::debug!(
+			token_url = %self.token_url,
+			expires_in = ?token.expires_in,
+			"access token issued"
+		);
+
+		Ok(CachedToken {
+			value,
+			valid_until: token.expires_in.map(|expires_in| {
+				requested_at + Duration::from_secs(expires_in).saturating_sub(self.refresh_early)
+			}),
+		})
+	}
+}
+impl Debug for ClientCredentials {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("ClientCredentials")
+			.field("token_url", &self.token_url.as_str())
+			.field("client_id", &self.client_id)
+			.field("client_secret", &REDACTED)
+			.field("scope", &self.scope)
+			.field("refresh_early", &self.refresh_early)
+			.finish_non_exhaustive()
+	}
+}
+#[async_trait]
+impl AuthProvider for ClientCredentials {
+	async fn authorization(&self, client: &Client) -> Result<HeaderValue> {
+		if let Some(value) = self.cached(Instant::now()) {
+			return Ok(value);
+		}
+
+		let _requesting = self.requesting.lock().await;
+
+		// Another fetch may have obtained a token while this one waited.
+		if let Some(value) = self.cached(Instant::now()) {
+			return Ok(value);
+		}
+
+		let token = self.request_token(client).await?;
+		let value = token.value.clone();
+
+		*self.token.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(token);
+
+		Ok(value)
+	}
+
+	fn invalidate(&self) {
+		*self.token.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+	}
+}
+
+struct CachedToken {
+	value: HeaderValue,
+	// `None` when the token endpoint did not advertise a lifetime.
+	valid_until: Option<Instant>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+	access_token: String,
+	#[serde(default)]
+	expires_in: Option<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn credentials_never_appear_in_debug_output() {
+		let token = StaticToken::bearer("s3cr3t-token").expect("valid token");
+		let credentials = ClientCredentials::new("https://idp.example/token", "client", "s3cr3t")
+			.expect("valid token url");
+
+		assert_eq!(
+			token.authorization(&Client::new()).await.expect("static token"),
+			"Bearer s3cr3t-token"
+		);
+		assert!(!format!("{token:?}").contains("s3cr3t"));
+		assert!(!format!("{credentials:?}").contains("s3cr3t"));
+		assert!(matches!(
+			StaticToken::bearer("line\nbreak"),
+			Err(Error::Validation { field: "auth", .. })
+		));
+	}
+}
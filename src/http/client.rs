@@ -1,16 +1,44 @@
 //! HTTP client integration for JWKS retrieval.
 
 // std
-use std::marker::PhantomData;
+use std::{fs::File, io::Read, marker::PhantomData, path::Path};
 // crates.io
 use http::{
-	HeaderMap, Request, Response, StatusCode,
-	header::{CACHE_CONTROL, ETAG, LAST_MODIFIED},
+	HeaderMap, HeaderName, Method, Request, Response, StatusCode,
+	header::{
+		AGE, AUTHORIZATION, CACHE_CONTROL, DATE, ETAG, EXPIRES, LAST_MODIFIED, LOCATION, PRAGMA,
+		RETRY_AFTER, VARY,
+	},
 };
 use jsonwebtoken::jwk::JwkSet;
-use reqwest::Client;
+use reqwest::{Client, ClientBuilder, NoProxy, Proxy, redirect::Policy};
+use rustls::pki_types::CertificateDer;
+use serde::Deserialize;
+use url::Url;
 // self
-use crate::{_prelude::*, registry::IdentityProviderRegistration, security};
+use crate::{
+	_prelude::*,
+	clock,
+	config::ByteSize,
+	http::{
+		capture::{CapturedExchange, ExchangeLog, REDACTED, RedirectHop},
+		connection::ConnectionTracker,
+		resolve::SharedResolver,
+	},
+	registry::{
+		HttpClientConfig, IdentityProviderRegistration, JwksSource, ProxySettings, StaticJwks,
+	},
+	security::{self, ClientIdentity, HostAllowlist, PublicResolver, SpkiFingerprint},
+};
+
+/// `RateLimit-Reset` from the IETF rate limit headers draft, in seconds until the quota resets.
+pub const RATELIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+
+/// Response headers kept with cached payloads when a registration sets no `retained_headers`.
+///
+/// These are the headers HTTP cache semantics consult for freshness and revalidation.
+pub const DEFAULT_RETAINED_HEADERS: [HeaderName; 8] =
+	[CACHE_CONTROL, ETAG, LAST_MODIFIED, DATE, AGE, VARY, EXPIRES, PRAGMA];
 
 /// HTTP exchange metadata captured for cache semantics evaluation.
 #[derive(Clone, Debug)]
@@ -48,51 +76,454 @@ pub struct HttpFetch {
 	pub exchange: HttpExchange,
 	/// Parsed JWKS payload when the origin returned content.
 	pub jwks: Option<Arc<JwkSet>>,
+	/// Raw JSON document behind `jwks`, including fields `JwkSet` does not model.
+	pub document: Option<serde_json::Value>,
 	/// Entity tag validator advertised by the origin.
 	pub etag: Option<String>,
 	/// Last-Modified timestamp advertised by the origin.
 	pub last_modified: Option<DateTime<Utc>>,
+	/// Whether the response reused a pooled connection, when the transport reports it.
+	pub connection_reused: Option<bool>,
+}
+
+/// Build the default reqwest client used for JWKS and discovery retrieval.
+///
+/// The client does not follow redirects itself; fetches follow them hop by hop so every target is
+/// checked against the registration's `max_redirects`, HTTPS, and allowlist policy.
+pub fn default_client() -> Result<Client> {
+	Ok(client_builder().build()?)
+}
+
+/// Build a client like [`default_client`] that routes requests according to `proxy`.
+pub fn proxied_client(proxy: &ProxySettings) -> Result<Client> {
+	Ok(proxied_builder(proxy)?.build()?)
+}
+
+/// Use `client` when supplied, otherwise build one from `proxy`, otherwise the default client.
+///
+/// Built clients also trust `trusted_roots`, enforce `pins` during the TLS handshake, and present
+/// `identity` to servers requiring mutual TLS. Built clients without a proxy resolve hosts through
+/// `resolver` when supplied; with `block_private_networks`, its answers (or the system resolver's)
+/// pass through [`PublicResolver`]. `config` tunes pooling and protocols of built clients.
+/// `resolver` and `config` are ignored for a supplied `client`; the other settings cannot be added
+/// to an existing client, so supplying them together is rejected.
+#[allow(clippy::too_many_arguments)]
+pub fn build_client(
+	client: Option<&Client>,
+	proxy: Option<&ProxySettings>,
+	trusted_roots: &[CertificateDer<'static>],
+	pins: &[SpkiFingerprint],
+	identity: Option<&ClientIdentity>,
+	block_private_networks: bool,
+	resolver: Option<&SharedResolver>,
+	config: &HttpClientConfig,
+) -> Result<Client> {
+	if let Some(client) = client {
+		if block_private_networks {
+			return Err(Error::Validation {
+				field: "block_private_networks",
+				reason: "Cannot be enforced on an injected HTTP client.".into(),
+			});
+		}
+		if !pins.is_empty() {
+			return Err(Error::Validation {
+				field: "pinned_spki",
+				reason: "Cannot be enforced on an injected HTTP client.".into(),
+			});
+		}
+		if !trusted_roots.is_empty() {
+			return Err(Error::Validation {
+				field: "trusted_roots",
+				reason: "Cannot be applied to an injected HTTP client.".into(),
+			});
+		}
+		if identity.is_some() {
+			return Err(Error::Validation {
+				field: "client_identity",
+				reason: "Cannot be applied to an injected HTTP client.".into(),
+			});
+		}
+
+		return Ok(client.clone());
+	}
+
+	let mut builder = match proxy {
+		Some(proxy) => proxied_builder(proxy)?,
+		None => client_builder(),
+	}
+	.pool_idle_timeout(config.pool_idle_timeout)
+	.pool_max_idle_per_host(config.pool_max_idle_per_host.unwrap_or(usize::MAX))
+	.tcp_keepalive(config.tcp_keepalive);
+
+	if config.http2_prior_knowledge {
+		builder = builder.http2_prior_knowledge();
+	}
+
+	if !trusted_roots.is_empty() || !pins.is_empty() || identity.is_some() {
+		builder =
+			builder.use_preconfigured_tls(security::tls_config(trusted_roots, pins, identity)?);
+	}
+	// Behind a proxy, target hosts are resolved by the proxy rather than by this client.
+	if proxy.is_none() {
+		if block_private_networks {
+			builder = builder.dns_resolver(Arc::new(PublicResolver::new(resolver.cloned())));
+		} else if let Some(resolver) = resolver {
+			builder = builder.dns_resolver(Arc::new(resolver.clone()));
+		}
+	}
+
+	Ok(builder.build()?)
+}
+
+fn client_builder() -> ClientBuilder {
+	Client::builder()
+		.redirect(Policy::none())
+		.user_agent(format!("jwks-cache/{}", env!("CARGO_PKG_VERSION")))
+		.connect_timeout(Duration::from_secs(5))
+}
+
+fn proxied_builder(proxy: &ProxySettings) -> Result<ClientBuilder> {
+	proxy.validate()?;
+
+	let no_proxy = NoProxy::from_string(&proxy.no_proxy.join(","));
+	// Drop the environment proxies so the explicit settings are the only egress configuration.
+	let mut builder = client_builder().no_proxy();
+
+	if let Some(url) = &proxy.http_proxy {
+		builder = builder.proxy(Proxy::http(url.as_str())?.no_proxy(no_proxy.clone()));
+	}
+	if let Some(url) = &proxy.https_proxy {
+		builder = builder.proxy(Proxy::https(url.as_str())?.no_proxy(no_proxy));
+	}
+
+	Ok(builder)
 }
 
 /// Execute an HTTP request to retrieve JWKS for the given registration.
+///
+/// Every attempt that reaches the network, successful or not, is appended to `capture`, and every
+/// response is reported to `connections`. Redirect hops must also pass the registry `allowlist`.
 pub async fn fetch_jwks(
 	client: &Client,
 	registration: &IdentityProviderRegistration,
+	allowlist: &HostAllowlist,
 	request: &Request<()>,
 	attempt_timeout: Duration,
+	capture: &ExchangeLog,
+	connections: &ConnectionTracker,
 ) -> Result<HttpFetch> {
 	if registration.require_https {
 		security::enforce_https(&registration.jwks_url)?;
 	}
 
-	let method = request.method().clone();
-	let mut builder = client.request(method, registration.jwks_url.clone());
+	let mut captured = CapturedExchange::begin(&registration.jwks_url, request);
+
+	// Configured headers typically carry gateway credentials.
+	for (name, value) in &mut captured.request_headers {
+		if registration.extra_headers.keys().any(|extra| extra.eq_ignore_ascii_case(name)) {
+			*value = REDACTED.into();
+		}
+	}
+
+	let start = Instant::now();
+	let result = send_jwks_request(
+		client,
+		registration,
+		allowlist,
+		request,
+		attempt_timeout,
+		connections,
+		&mut captured,
+	)
+	.await;
+
+	captured.elapsed = start.elapsed();
+
+	if let Err(err) = &result {
+		captured.error = Some(err.redacted());
+		captured.error_code = Some(err.code().into());
+	}
+
+	capture.record(captured).await;
+
+	result
+}
+
+/// Build a reqwest redirect policy enforcing the registration's redirect rules.
+///
+/// Each hop must stay within `max_redirects`, use HTTPS when `require_https` is set, and target a
+/// host allowed by both `allowed_domains` and the registry's `allowlist`. Clients built by the
+/// registry follow redirects themselves through [`send_following_redirects`]; this policy lets
+/// injected clients apply the same rules. Injected clients cannot drop `extra_headers` on the way,
+/// so with any configured, a hop to another origin is rejected instead.
+pub fn redirect_policy(
+	registration: &IdentityProviderRegistration,
+	allowlist: &HostAllowlist,
+) -> Policy {
+	let registration = registration.clone();
+	let allowlist = allowlist.clone();
+
+	Policy::custom(move |attempt| {
+		// `previous` holds the original URL plus every redirect already followed.
+		let hop = u8::try_from(attempt.previous().len()).unwrap_or(u8::MAX);
+		let crosses_origin = attempt
+			.previous()
+			.last()
+			.is_some_and(|previous| previous.origin() != attempt.url().origin());
+
+		if crosses_origin && !registration.extra_headers.is_empty() {
+			return attempt.error("Target is another origin, which must not see extra_headers.");
+		}
+
+		match redirect_rejection(&registration, &allowlist, hop, attempt.url()) {
+			Some(reason) => attempt.error(reason),
+			None => attempt.follow(),
+		}
+	})
+}
+
+/// Send a request, following redirects while each hop satisfies the registration's policy.
+///
+/// Every redirect is appended to `redirects` before it is checked, so rejected hops still show up
+/// in diagnostics. Clients that follow redirects themselves hide their hops from `redirects`, but
+/// the URL they end up at is still checked against the policy.
+#[allow(clippy::too_many_arguments)]
+pub async fn send_following_redirects(
+	client: &Client,
+	registration: &IdentityProviderRegistration,
+	allowlist: &HostAllowlist,
+	method: &Method,
+	url: &Url,
+	headers: &HeaderMap,
+	attempt_timeout: Duration,
+	redirects: &mut Vec<RedirectHop>,
+) -> Result<reqwest::Response> {
+	let mut url = url.clone();
+	let mut headers = headers.clone();
+
+	loop {
+		let response = client
+			.request(method.clone(), url.clone())
+			.headers(headers.clone())
+			.timeout(attempt_timeout)
+			.send()
+			.await?;
+		let status = response.status();
+
+		if response.url() != &url {
+			let hop = u8::try_from(redirects.len() + 1).unwrap_or(u8::MAX);
+
+			if let Some(reason) = redirect_rejection(registration, allowlist, hop, response.url()) {
+				return Err(Error::RedirectPolicy { hop, url: response.url().clone(), reason });
+			}
+		}
+		if !status.is_redirection() || status == StatusCode::NOT_MODIFIED {
+			return Ok(response);
+		}
+
+		let Some(location) = response.headers().get(LOCATION) else {
+			return Ok(response);
+		};
+		// Bounded by `max_redirects`, which is itself a `u8`.
+		let hop = u8::try_from(redirects.len() + 1).unwrap_or(u8::MAX);
+		let target =
+			location.to_str().ok().and_then(|location| url.join(location).ok()).ok_or_else(
+				|| Error::RedirectPolicy {
+					hop,
+					url: url.clone(),
+					reason: "Location header is not a valid URL.".into(),
+				},
+			)?;
+
+		redirects.push(RedirectHop::new(status, &target));
+
+		if let Some(reason) = redirect_rejection(registration, allowlist, hop, &target) {
+			return Err(Error::RedirectPolicy { hop, url: target, reason });
+		}
+
+		tracing::debug!(hop, status = %status, "following redirect");
+
+		// Like browsers and reqwest, keep credentials from leaking to another origin. Configured
+		// headers typically carry gateway credentials, so they follow the same rule.
+		if target.origin() != url.origin() {
+			headers.remove(AUTHORIZATION);
+
+			for name in registration.extra_headers.keys() {
+				headers.remove(name.as_str());
+			}
+		}
+
+		url = target;
+	}
+}
+
+fn redirect_rejection(
+	registration: &IdentityProviderRegistration,
+	allowlist: &HostAllowlist,
+	hop: u8,
+	target: &Url,
+) -> Option<String> {
+	if hop > registration.max_redirects {
+		Some(format!("Exceeds max_redirects ({}).", registration.max_redirects))
+	} else if registration.require_https && target.scheme() != "https" {
+		Some("Target must use HTTPS.".into())
+	} else if !target
+		.host_str()
+		.is_some_and(|host| security::host_is_allowed(host, &registration.allowed_domains))
+	{
+		Some("Target host is not within the allowed_domains allowlist.".into())
+	} else if !target.host_str().is_some_and(|host| allowlist.allows(host)) {
+		Some("Target host is not in the registry allowlist.".into())
+	} else if registration.block_private_networks && security::enforce_public_host(target).is_err()
+	{
+		Some("Target is a non-public address.".into())
+	} else {
+		None
+	}
+}
+
+/// Read a response body, failing as soon as it grows past `limit` bytes.
+///
+/// The limit applies to the decoded body and is checked chunk by chunk, so a compressed response
+/// cannot inflate past it in memory. `subject` names the body in the error.
+pub async fn read_body_limited(
+	mut response: reqwest::Response,
+	limit: ByteSize,
+	subject: &str,
+) -> Result<Vec<u8>> {
+	let exceeded = || Error::Validation {
+		field: "max_response_bytes",
+		reason: format!("{subject} exceeds the configured guard of {limit}."),
+	};
+
+	if response.content_length().is_some_and(|length| length > limit.as_u64()) {
+		return Err(exceeded());
+	}
+
+	let mut body = Vec::new();
+
+	while let Some(chunk) = response.chunk().await? {
+		if (body.len() + chunk.len()) as u64 > limit.as_u64() {
+			return Err(exceeded());
+		}
+
+		body.extend_from_slice(&chunk);
+	}
+
+	Ok(body)
+}
+
+// Blocking counterpart of `read_body_limited` for static key set files.
+fn read_file_limited(path: &Path, limit: ByteSize) -> Result<Vec<u8>> {
+	let mut body = Vec::new();
+
+	File::open(path)?.take(limit.as_u64() + 1).read_to_end(&mut body)?;
+
+	if body.len() as u64 > limit.as_u64() {
+		return Err(Error::Validation {
+			field: "max_response_bytes",
+			reason: format!("Static key set exceeds the configured guard of {limit}."),
+		});
+	}
+
+	Ok(body)
+}
 
-	for (name, value) in request.headers().iter() {
-		builder = builder.header(name, value);
+// Parse `document` and apply the registration's key set guards.
+fn checked_jwks(
+	registration: &IdentityProviderRegistration,
+	document: &serde_json::Value,
+) -> Result<JwkSet> {
+	let jwks = JwkSet::deserialize(document)?;
+
+	if jwks.keys.len() < registration.min_keys {
+		return Err(Error::InvalidJwks(format!(
+			"Key set has {count} keys, below the configured minimum of {min}.",
+			count = jwks.keys.len(),
+			min = registration.min_keys
+		)));
 	}
 
-	builder = builder.timeout(attempt_timeout);
+	registration.jwks_validation.check(&jwks)?;
+
+	Ok(jwks)
+}
+
+/// Extract cache-control header as string for diagnostics.
+pub fn cache_control_header(headers: &HeaderMap) -> Option<String> {
+	headers.get(CACHE_CONTROL).and_then(|value| value.to_str().ok()).map(|s| s.to_string())
+}
+
+fn retained_headers(registration: &IdentityProviderRegistration, headers: &HeaderMap) -> HeaderMap {
+	let retain = |name: &HeaderName| match &registration.retained_headers {
+		Some(names) => names.iter().any(|retained| name.as_str().eq_ignore_ascii_case(retained)),
+		None => DEFAULT_RETAINED_HEADERS.contains(name),
+	};
+
+	headers
+		.iter()
+		.filter(|(name, _)| retain(name))
+		.map(|(name, value)| (name.clone(), value.clone()))
+		.collect()
+}
+
+async fn send_jwks_request(
+	client: &Client,
+	registration: &IdentityProviderRegistration,
+	allowlist: &HostAllowlist,
+	request: &Request<()>,
+	attempt_timeout: Duration,
+	connections: &ConnectionTracker,
+	captured: &mut CapturedExchange,
+) -> Result<HttpFetch> {
+	// Credentials are only added to the outgoing headers: cache semantics treat authorized
+	// requests as uncacheable, so `request` stays as it is for freshness evaluation.
+	let mut headers = request.headers().clone();
+
+	if let Some(auth) = &registration.auth {
+		// A hung token endpoint must not outlive the attempt, or hold up every other fetch.
+		let authorization =
+			tokio::time::timeout(attempt_timeout, auth.authorization(client)).await.map_err(
+				|_| Error::Cache(format!("Authorization timed out after {attempt_timeout:?}.")),
+			)??;
+
+		headers.insert(AUTHORIZATION, authorization);
+		captured.request_headers.push((AUTHORIZATION.as_str().into(), REDACTED.into()));
+	}
 
 	let start = Instant::now();
-	let response = builder.send().await?;
+	let response = send_following_redirects(
+		client,
+		registration,
+		allowlist,
+		request.method(),
+		&registration.jwks_url,
+		&headers,
+		attempt_timeout,
+		&mut captured.redirects,
+	)
+	.await?;
 	let elapsed = start.elapsed();
 	let status = response.status();
-	let headers = response.headers().clone();
-	let mut response_builder = Response::builder().status(status);
 
-	if let Some(existing) = response_builder.headers_mut() {
-		existing.extend(headers.iter().map(|(name, value)| (name.clone(), value.clone())));
+	if status == StatusCode::UNAUTHORIZED
+		&& let Some(auth) = &registration.auth
+	{
+		auth.invalidate();
 	}
+	let headers = response.headers().clone();
+	let connection_reused = connections.observe(&response);
 
-	let response_template = response_builder.body(()).map_err(Error::from)?;
-	let etag = response_template
-		.headers()
-		.get(ETAG)
-		.and_then(|value| value.to_str().ok())
-		.map(|s| s.to_string());
-	let last_modified = response_template
-		.headers()
+	captured.set_response(status, &headers);
+	captured.connection_reused = connection_reused;
+
+	let mut response_template = Response::new(());
+
+	*response_template.status_mut() = status;
+	*response_template.headers_mut() = retained_headers(registration, &headers);
+
+	let etag = headers.get(ETAG).and_then(|value| value.to_str().ok()).map(|s| s.to_string());
+	let last_modified = headers
 		.get(LAST_MODIFIED)
 		.and_then(|value| value.to_str().ok())
 		.and_then(|raw| httpdate::parse_http_date(raw).ok())
@@ -101,28 +532,33 @@ pub async fn fetch_jwks(
 	if status == StatusCode::NOT_MODIFIED {
 		let exchange = HttpExchange::new(request.clone(), response_template, elapsed);
 
-		return Ok(HttpFetch { exchange, jwks: None, etag, last_modified });
+		return Ok(HttpFetch {
+			exchange,
+			jwks: None,
+			document: None,
+			etag,
+			last_modified,
+			connection_reused,
+		});
 	}
 	if !status.is_success() {
+		let retry_after_secs = current_retry_hint(&headers);
 		let body = response.text().await.ok();
 
-		return Err(Error::HttpStatus { status, url: registration.jwks_url.clone(), body });
+		return Err(Error::HttpStatus {
+			status,
+			url: registration.jwks_url.clone(),
+			body,
+			retry_after_secs,
+		});
 	}
 
-	let bytes = response.bytes().await?;
+	let bytes = read_body_limited(response, registration.max_response_bytes, "Response").await?;
 
-	if bytes.len() as u64 > registration.max_response_bytes {
-		return Err(Error::Validation {
-			field: "max_response_bytes",
-			reason: format!(
-				"Response size {size} bytes exceeds the configured guard of {limit} bytes.",
-				size = bytes.len(),
-				limit = registration.max_response_bytes
-			),
-		});
-	}
+	captured.body_bytes = Some(bytes.len() as u64);
 
-	let jwks: JwkSet = serde_json::from_slice(&bytes)?;
+	let document: serde_json::Value = serde_json::from_slice(&bytes)?;
+	let jwks = checked_jwks(registration, &document)?;
 	let exchange = HttpExchange::new(request.clone(), response_template, elapsed);
 
 	tracing::debug!(
@@ -133,10 +569,207 @@ pub async fn fetch_jwks(
 		"jwks fetch complete"
 	);
 
-	Ok(HttpFetch { exchange, jwks: Some(Arc::new(jwks)), etag, last_modified })
+	Ok(HttpFetch {
+		exchange,
+		jwks: Some(Arc::new(jwks)),
+		document: Some(document),
+		etag,
+		last_modified,
+		connection_reused,
+	})
 }
 
-/// Extract cache-control header as string for diagnostics.
-pub fn cache_control_header(headers: &HeaderMap) -> Option<String> {
-	headers.get(CACHE_CONTROL).and_then(|value| value.to_str().ok()).map(|s| s.to_string())
+/// Load the key set of a [`JwksSource::Static`] registration as if it had been fetched.
+///
+/// The result carries `request` and a header-less `200 OK` response, so cache semantics clamp its
+/// TTL to `min_ttl`. Files larger than `max_response_bytes` are rejected, and every key set must
+/// pass the registration's `min_keys` and `jwks_validation` checks.
+pub async fn load_static_jwks(
+	registration: &IdentityProviderRegistration,
+	request: &Request<()>,
+) -> Result<HttpFetch> {
+	let JwksSource::Static(source) = &registration.source else {
+		return Err(Error::Cache("Registration has no static key set.".into()));
+	};
+	let start = Instant::now();
+	let document = match source {
+		StaticJwks::File(path) => {
+			let path = path.clone();
+			let limit = registration.max_response_bytes;
+			let bytes = tokio::task::spawn_blocking(move || read_file_limited(&path, limit))
+				.await
+				.map_err(|err| Error::Cache(format!("Static key set read failed: {err}.")))??;
+
+			serde_json::from_slice(&bytes)?
+		},
+		StaticJwks::Inline(jwks) => serde_json::to_value(jwks)?,
+	};
+	let jwks = checked_jwks(registration, &document)?;
+	let exchange = HttpExchange::new(request.clone(), Response::new(()), start.elapsed());
+
+	tracing::debug!(
+		tenant = %registration.tenant_id,
+		provider = %registration.provider_id,
+		keys = jwks.keys.len(),
+		"static jwks loaded"
+	);
+
+	Ok(HttpFetch {
+		exchange,
+		jwks: Some(Arc::new(jwks)),
+		document: Some(document),
+		etag: None,
+		last_modified: None,
+		connection_reused: None,
+	})
+}
+
+/// Seconds requested by `Retry-After` (seconds or an HTTP date) or `RateLimit-Reset` (seconds),
+/// whichever is longer.
+///
+/// Hints saturate at [`u16::MAX`] seconds, about 18 hours, far beyond any retry deadline.
+pub fn retry_hint(headers: &HeaderMap, now: SystemTime) -> Option<u16> {
+	let header = |name: &HeaderName| headers.get(name).and_then(|value| value.to_str().ok());
+	let retry_after = header(&RETRY_AFTER).and_then(|raw| match raw.trim().parse::<u64>() {
+		Ok(secs) => Some(secs),
+		Err(_) => Some(httpdate::parse_http_date(raw).ok()?.duration_since(now).ok()?.as_secs()),
+	});
+	let reset = header(&RATELIMIT_RESET).and_then(|raw| raw.trim().parse::<u64>().ok());
+
+	retry_after.max(reset).map(|secs| u16::try_from(secs).unwrap_or(u16::MAX))
+}
+
+/// [`retry_hint`] measured against [`clock::system_now`].
+///
+/// HTTP-date hints then agree with freshness decisions when tests pause or advance tokio time.
+pub fn current_retry_hint(headers: &HeaderMap) -> Option<u16> {
+	retry_hint(headers, clock::system_now())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::registry::AllowlistMode;
+
+	#[test]
+	fn only_retained_headers_are_kept() {
+		let mut headers = HeaderMap::new();
+
+		headers.insert(CACHE_CONTROL, "max-age=300".parse().expect("valid header"));
+		headers.insert(ETAG, "\"v1\"".parse().expect("valid header"));
+		headers.insert("x-debug-trace", "a".repeat(4_096).parse().expect("valid header"));
+
+		let registration =
+			IdentityProviderRegistration::new("tenant-a", "provider", "https://idp.example/jwks")
+				.expect("registration");
+		let kept = retained_headers(&registration, &headers);
+
+		assert_eq!(kept.len(), 2);
+		assert!(kept.contains_key(CACHE_CONTROL) && kept.contains_key(ETAG));
+
+		let registration = registration.with_retained_headers(["Cache-Control", "X-Debug-Trace"]);
+		let kept = retained_headers(&registration, &headers);
+
+		assert_eq!(kept.len(), 2);
+		assert!(kept.contains_key(CACHE_CONTROL) && kept.contains_key("x-debug-trace"));
+
+		let err = registration.with_retained_headers(["bad header"]).validate();
+
+		assert!(matches!(err, Err(Error::Validation { field: "retained_headers", .. })));
+	}
+
+	#[test]
+	fn redirects_to_private_addresses_are_rejected_when_blocked() {
+		let registration =
+			IdentityProviderRegistration::new("tenant-a", "provider", "https://idp.example/jwks")
+				.expect("registration");
+		let metadata = Url::parse("https://169.254.169.254/latest/meta-data").expect("url");
+		let allowlist = HostAllowlist::default();
+
+		assert_eq!(redirect_rejection(&registration, &allowlist, 1, &metadata), None);
+
+		let registration = registration.with_block_private_networks(true);
+
+		assert!(redirect_rejection(&registration, &allowlist, 1, &metadata).is_some());
+		assert_eq!(
+			redirect_rejection(
+				&registration,
+				&allowlist,
+				1,
+				&Url::parse("https://keys.example/jwks").unwrap()
+			),
+			None
+		);
+	}
+
+	#[test]
+	fn redirects_must_pass_the_registry_allowlist() {
+		let registration =
+			IdentityProviderRegistration::new("tenant-a", "provider", "https://idp.example/jwks")
+				.expect("registration");
+		let target = Url::parse("https://keys.example/jwks").expect("url");
+		let listed = HostAllowlist::new(AllowlistMode::List, vec!["keys.example".into()]);
+
+		assert_eq!(redirect_rejection(&registration, &listed, 1, &target), None);
+
+		for allowlist in [
+			HostAllowlist::new(AllowlistMode::List, vec!["idp.example".into()]),
+			HostAllowlist::new(AllowlistMode::List, Vec::new()),
+			HostAllowlist::new(AllowlistMode::DenyAll, vec!["keys.example".into()]),
+		] {
+			assert!(redirect_rejection(&registration, &allowlist, 1, &target).is_some());
+		}
+	}
+
+	#[test]
+	fn retry_hints_take_the_longest_server_delay() {
+		let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+		let mut headers = HeaderMap::new();
+
+		assert_eq!(retry_hint(&headers, now), None);
+
+		headers.insert(
+			RETRY_AFTER,
+			httpdate::fmt_http_date(now + Duration::from_secs(30)).parse().unwrap(),
+		);
+
+		assert_eq!(retry_hint(&headers, now), Some(30));
+
+		headers.insert(RATELIMIT_RESET, "45".parse().unwrap());
+
+		assert_eq!(retry_hint(&headers, now), Some(45));
+
+		headers.insert(RETRY_AFTER, "120".parse().unwrap());
+
+		assert_eq!(retry_hint(&headers, now), Some(120));
+
+		headers.insert(RATELIMIT_RESET, "100000".parse().unwrap());
+
+		assert_eq!(retry_hint(&headers, now), Some(u16::MAX));
+
+		headers.insert(
+			RETRY_AFTER,
+			httpdate::fmt_http_date(now - Duration::from_secs(5)).parse().unwrap(),
+		);
+		headers.remove(RATELIMIT_RESET);
+
+		assert_eq!(retry_hint(&headers, now), None, "past dates impose no delay");
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn http_date_retry_hints_follow_paused_time() {
+		tokio::time::advance(Duration::from_secs(3_600)).await;
+
+		let mut headers = HeaderMap::new();
+
+		headers.insert(
+			RETRY_AFTER,
+			httpdate::fmt_http_date(clock::system_now() + Duration::from_secs(30)).parse().unwrap(),
+		);
+
+		// HTTP dates have whole-second precision, so the hint may round down by one second.
+		let hint = current_retry_hint(&headers);
+
+		assert!(matches!(hint, Some(29..=30)), "hint ignored the advanced clock: {hint:?}");
+	}
 }
@@ -0,0 +1,53 @@
+//! Upstream connection reuse tracking.
+//!
+//! reqwest does not report whether a response travelled over a pooled connection, so reuse is
+//! inferred from the transport's socket addresses: a response carried on the same local and remote
+//! address pair as the previous one reused that connection.
+
+// std
+use std::{net::SocketAddr, sync::Mutex};
+// crates.io
+use hyper_util::client::legacy::connect::HttpInfo;
+use reqwest::Response;
+// self
+use crate::_prelude::*;
+
+/// Per-provider record of the most recent upstream connection.
+#[derive(Debug, Default)]
+pub struct ConnectionTracker {
+	inner: Mutex<TrackerInner>,
+}
+impl ConnectionTracker {
+	/// Create a tracker that has not observed any connection yet.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Record the connection behind `response`, returning whether it was reused.
+	///
+	/// Returns `None` when the transport does not expose its socket addresses.
+	pub fn observe(&self, response: &Response) -> Option<bool> {
+		let info = response.extensions().get::<HttpInfo>()?;
+		let addrs = (info.local_addr(), info.remote_addr());
+		let mut inner = self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+		let reused = inner.last_addrs == Some(addrs);
+
+		inner.last_addrs = Some(addrs);
+		inner.last_used = Some(Instant::now());
+
+		Some(reused)
+	}
+
+	/// Time since the last observed response, or `None` before the first one.
+	pub fn idle_for(&self, now: Instant) -> Option<Duration> {
+		let inner = self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+		inner.last_used.map(|last_used| now.saturating_duration_since(last_used))
+	}
+}
+
+#[derive(Debug, Default)]
+struct TrackerInner {
+	last_addrs: Option<(SocketAddr, SocketAddr)>,
+	last_used: Option<Instant>,
+}
@@ -0,0 +1,192 @@
+//! OpenID Connect discovery document retrieval.
+
+// crates.io
+use http::{HeaderMap, HeaderValue, Method, Request, Response, StatusCode, header::ACCEPT};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use url::Url;
+// self
+use crate::{
+	_prelude::*,
+	clock,
+	http::{client, semantics},
+	registry::{DiscoveryRegistration, IdentityProviderRegistration},
+	security::{self, HostAllowlist},
+};
+
+/// Path appended to an issuer URL to locate its discovery document.
+pub const WELL_KNOWN_PATH: &str = ".well-known/openid-configuration";
+
+/// Subset of OpenID Provider metadata relevant to JWKS caching.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OpenIdConfiguration {
+	/// Issuer identifier advertised by the provider.
+	pub issuer: Url,
+	/// Location of the provider's JWKS document.
+	pub jwks_uri: Url,
+	/// Remaining metadata fields, retained verbatim.
+	#[serde(flatten)]
+	pub additional: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Discovery document cached alongside its own freshness window.
+#[derive(Clone, Debug)]
+pub struct CachedDiscovery {
+	/// Parsed provider metadata.
+	pub metadata: Arc<OpenIdConfiguration>,
+	/// UTC timestamp when the document was fetched.
+	pub fetched_at: DateTime<Utc>,
+	/// Monotonic deadline after which the document must be fetched again.
+	pub expires_at: Instant,
+}
+impl CachedDiscovery {
+	/// Whether the discovery document has exceeded its freshness window.
+	pub fn is_expired(&self, now: Instant) -> bool {
+		now >= self.expires_at
+	}
+}
+
+/// Build the discovery document URL for an issuer.
+pub fn discovery_url(issuer: &Url) -> Result<Url> {
+	let mut base = issuer.clone();
+
+	if !base.path().ends_with('/') {
+		let path = format!("{}/", base.path());
+
+		base.set_path(&path);
+	}
+
+	Ok(base.join(WELL_KNOWN_PATH)?)
+}
+
+/// Fetch and validate the discovery document for a registration.
+pub async fn fetch_discovery(
+	client: &Client,
+	registration: &IdentityProviderRegistration,
+	allowlist: &HostAllowlist,
+	discovery: &DiscoveryRegistration,
+) -> Result<CachedDiscovery> {
+	let url = discovery_url(&discovery.issuer)?;
+
+	if registration.require_https {
+		security::enforce_https(&url)?;
+	}
+
+	let start = Instant::now();
+	let mut headers = HeaderMap::new();
+
+	headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
+
+	let response = client::send_following_redirects(
+		client,
+		registration,
+		allowlist,
+		&Method::GET,
+		&url,
+		&headers,
+		registration.retry_policy.attempt_timeout,
+		&mut Vec::new(),
+	)
+	.await?;
+	let status = response.status();
+
+	if !status.is_success() {
+		let retry_after_secs = client::current_retry_hint(response.headers());
+		let body = response.text().await.ok();
+
+		return Err(Error::HttpStatus { status, url, body, retry_after_secs });
+	}
+
+	let headers = response.headers().clone();
+	let bytes =
+		client::read_body_limited(response, registration.max_response_bytes, "Discovery document")
+			.await?;
+
+	let metadata: OpenIdConfiguration = serde_json::from_slice(&bytes)?;
+
+	if !issuers_match(&metadata.issuer, &discovery.issuer) {
+		return Err(Error::Validation {
+			field: "discovery.issuer",
+			reason: format!(
+				"Discovery document issuer {} does not match the configured issuer {}.",
+				metadata.issuer, discovery.issuer
+			),
+		});
+	}
+
+	let ttl = discovery_ttl(&url, status, headers, discovery)?;
+
+	tracing::debug!(
+		tenant = %registration.tenant_id,
+		provider = %registration.provider_id,
+		jwks_uri = %metadata.jwks_uri,
+		elapsed = ?start.elapsed(),
+		?ttl,
+		"discovery fetch complete"
+	);
+
+	Ok(CachedDiscovery {
+		metadata: Arc::new(metadata),
+		fetched_at: clock::utc_now(),
+		expires_at: Instant::now() + ttl,
+	})
+}
+
+fn discovery_ttl(
+	url: &Url,
+	status: StatusCode,
+	headers: http::HeaderMap,
+	discovery: &DiscoveryRegistration,
+) -> Result<Duration> {
+	let request = Request::builder().method(Method::GET).uri(url.as_str()).body(())?;
+	let mut response = Response::builder().status(status).body(())?;
+
+	*response.headers_mut() = headers;
+
+	let policy = semantics::cache_policy(&request, &response);
+	let ttl = if policy.is_storable() {
+		policy.time_to_live(clock::system_now())
+	} else {
+		Duration::ZERO
+	};
+
+	Ok(ttl.max(discovery.min_ttl.as_duration()).min(discovery.max_ttl.as_duration()))
+}
+
+fn issuers_match(advertised: &Url, configured: &Url) -> bool {
+	advertised.as_str().trim_end_matches('/') == configured.as_str().trim_end_matches('/')
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn discovery_url_preserves_issuer_path() {
+		let root = Url::parse("https://accounts.example.com").expect("url");
+		let realm = Url::parse("https://sso.example.com/realms/acme").expect("url");
+		let slashed = Url::parse("https://sso.example.com/realms/acme/").expect("url");
+
+		assert_eq!(
+			discovery_url(&root).expect("discovery url").as_str(),
+			"https://accounts.example.com/.well-known/openid-configuration"
+		);
+
+		for issuer in [realm, slashed] {
+			assert_eq!(
+				discovery_url(&issuer).expect("discovery url").as_str(),
+				"https://sso.example.com/realms/acme/.well-known/openid-configuration"
+			);
+		}
+	}
+
+	#[test]
+	fn issuer_comparison_ignores_trailing_slash() {
+		let advertised = Url::parse("https://accounts.example.com/").expect("url");
+		let configured = Url::parse("https://accounts.example.com").expect("url");
+		let other = Url::parse("https://evil.example.com").expect("url");
+
+		assert!(issuers_match(&advertised, &configured));
+		assert!(!issuers_match(&other, &configured));
+	}
+}
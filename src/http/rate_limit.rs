@@ -0,0 +1,101 @@
+//! Token-bucket rate limiting of upstream requests, shared by every provider of a registry.
+//!
+//! Buckets are keyed by host, so many registrations pointing at one identity provider draw from
+//! the same budget. Callers reserve a token up front and wait out any deficit, which admits them
+//! in arrival order without polling.
+
+// crates.io
+use dashmap::DashMap;
+use tokio::time;
+use url::Url;
+// self
+use crate::{_prelude::*, registry::RateLimitPolicy};
+
+/// Per-host token buckets enforcing a [`RateLimitPolicy`].
+#[derive(Debug)]
+pub struct HostRateLimiter {
+	policy: RateLimitPolicy,
+	buckets: DashMap<String, Bucket>,
+}
+impl HostRateLimiter {
+	/// Create a limiter with a full bucket for every host.
+	pub fn new(policy: RateLimitPolicy) -> Self {
+		Self { policy, buckets: DashMap::new() }
+	}
+
+	/// Policy enforced by this limiter.
+	pub fn policy(&self) -> &RateLimitPolicy {
+		&self.policy
+	}
+
+	/// Wait until a request to `url`'s host is admitted, returning how long the caller waited.
+	///
+	/// URLs without a host are admitted immediately.
+	pub async fn acquire(&self, url: &Url) -> Duration {
+		let Some(host) = url.host_str() else {
+			return Duration::ZERO;
+		};
+		let wait = self.reserve(host, Instant::now());
+
+		if !wait.is_zero() {
+			time::sleep(wait).await;
+		}
+
+		wait
+	}
+
+	// Take a token for `host`, returning how long until the reservation is covered.
+	fn reserve(&self, host: &str, now: Instant) -> Duration {
+		let rate = self.policy.requests_per_second;
+		let capacity = f64::from(self.policy.burst.max(1));
+		let mut bucket = self
+			.buckets
+			.entry(host.to_ascii_lowercase())
+			.or_insert(Bucket { tokens: capacity, updated_at: now });
+		let refill = now.saturating_duration_since(bucket.updated_at).as_secs_f64() * rate;
+
+		bucket.tokens = (bucket.tokens + refill).min(capacity) - 1.;
+		bucket.updated_at = now;
+
+		if bucket.tokens >= 0. {
+			Duration::ZERO
+		} else {
+			Duration::from_secs_f64(-bucket.tokens / rate)
+		}
+	}
+}
+
+#[derive(Debug)]
+struct Bucket {
+	// Negative while callers are waiting on reservations.
+	tokens: f64,
+	updated_at: Instant,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test(start_paused = true)]
+	async fn hosts_share_a_bucket_that_refills_at_the_policy_rate() {
+		let limiter = HostRateLimiter::new(RateLimitPolicy { requests_per_second: 2., burst: 2 });
+		let login = Url::parse("https://login.example.com/tenant-a/keys").unwrap();
+		let other_tenant = Url::parse("https://LOGIN.example.com/tenant-b/keys").unwrap();
+		let elsewhere = Url::parse("https://keys.example.net/jwks.json").unwrap();
+
+		assert_eq!(limiter.acquire(&login).await, Duration::ZERO);
+		assert_eq!(limiter.acquire(&other_tenant).await, Duration::ZERO);
+		assert_eq!(limiter.acquire(&elsewhere).await, Duration::ZERO, "hosts are independent");
+
+		let started = Instant::now();
+
+		assert_eq!(limiter.acquire(&login).await, Duration::from_millis(500));
+		assert_eq!(limiter.acquire(&other_tenant).await, Duration::from_millis(500));
+		assert_eq!(started.elapsed(), Duration::from_secs(1));
+
+		time::advance(Duration::from_secs(10)).await;
+
+		assert_eq!(limiter.acquire(&login).await, Duration::ZERO, "idle time refills the burst");
+		assert_eq!(limiter.acquire(&login).await, Duration::ZERO);
+	}
+}
@@ -0,0 +1,161 @@
+//! DNS resolution hooks for upstream fetches.
+//!
+//! [`RegistryBuilder::dns_resolver`](crate::RegistryBuilder::dns_resolver) installs any reqwest
+//! [`Resolve`] implementation on the clients a registry builds. [`HostResolver`] covers the common
+//! cases: pinning identity provider hosts to known addresses and choosing the address family
+//! tried first when connecting.
+
+// std
+use std::{
+	collections::HashMap,
+	fmt::{Debug, Formatter, Result as FmtResult},
+	net::{IpAddr, SocketAddr},
+};
+// crates.io
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use tokio::net::lookup_host;
+// self
+use crate::_prelude::*;
+
+/// Address family ordering applied to resolved addresses.
+///
+/// Connections try addresses in order, falling back to the next family after a short delay
+/// ("happy eyeballs"), so preferring a family decides which one is attempted first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IpPreference {
+	/// Keep the order returned by the resolver.
+	#[default]
+	Any,
+	/// Try IPv4 addresses first.
+	PreferIpv4,
+	/// Try IPv6 addresses first.
+	PreferIpv6,
+	/// Use only IPv4 addresses.
+	Ipv4Only,
+	/// Use only IPv6 addresses.
+	Ipv6Only,
+}
+impl IpPreference {
+	/// Filter and order `addrs` according to this preference.
+	pub fn apply(self, mut addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+		match self {
+			Self::Any => {},
+			Self::PreferIpv4 => addrs.sort_by_key(|addr| !addr.is_ipv4()),
+			Self::PreferIpv6 => addrs.sort_by_key(|addr| !addr.is_ipv6()),
+			Self::Ipv4Only => addrs.retain(SocketAddr::is_ipv4),
+			Self::Ipv6Only => addrs.retain(SocketAddr::is_ipv6),
+		}
+
+		addrs
+	}
+}
+
+/// Resolver with per-host address pins and an address family preference.
+///
+/// Hosts without pins go through the system resolver.
+#[derive(Clone, Debug, Default)]
+pub struct HostResolver {
+	pinned: HashMap<String, Vec<IpAddr>>,
+	preference: IpPreference,
+}
+impl HostResolver {
+	/// Create a resolver that defers to the system resolver.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Resolve `host` to `addrs` instead of querying DNS.
+	pub fn pin<I>(mut self, host: impl AsRef<str>, addrs: I) -> Self
+	where
+		I: IntoIterator<Item = IpAddr>,
+	{
+		self.pinned.insert(host.as_ref().to_ascii_lowercase(), addrs.into_iter().collect());
+
+		self
+	}
+
+	/// Order or restrict resolved addresses by family.
+	pub fn prefer(mut self, preference: IpPreference) -> Self {
+		self.preference = preference;
+
+		self
+	}
+}
+impl Resolve for HostResolver {
+	fn resolve(&self, name: Name) -> Resolving {
+		let host = name.as_str().to_ascii_lowercase();
+		let pinned = self.pinned.get(&host).cloned();
+		let preference = self.preference;
+
+		Box::pin(async move {
+			// The connector replaces port 0 with the port of the request URL.
+			let addrs = match pinned {
+				Some(ips) => ips.into_iter().map(|ip| SocketAddr::new(ip, 0)).collect(),
+				None => lookup_host((host.as_str(), 0)).await?.collect(),
+			};
+			let addrs = preference.apply(addrs);
+
+			if addrs.is_empty() {
+				return Err(format!("{host} has no addresses matching {preference:?}").into());
+			}
+
+			Ok(Box::new(addrs.into_iter()) as Addrs)
+		})
+	}
+}
+
+/// Type-erased resolver shared by every client a registry builds.
+#[derive(Clone)]
+pub struct SharedResolver(Arc<dyn Resolve>);
+impl SharedResolver {
+	/// Share `resolver` between clients.
+	pub fn new<R>(resolver: R) -> Self
+	where
+		R: Resolve + 'static,
+	{
+		Self(Arc::new(resolver))
+	}
+}
+impl Debug for SharedResolver {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.write_str("SharedResolver")
+	}
+}
+impl Resolve for SharedResolver {
+	fn resolve(&self, name: Name) -> Resolving {
+		self.0.resolve(name)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// std
+	use std::str::FromStr;
+	// self
+	use super::*;
+
+	#[tokio::test]
+	async fn pinned_hosts_skip_dns_and_follow_the_preference() {
+		let v4: IpAddr = "203.0.113.10".parse().unwrap();
+		let v6: IpAddr = "2001:db8::10".parse().unwrap();
+		let resolver = HostResolver::new().pin("Login.Example.com", [v4, v6]);
+		let resolve = |resolver: HostResolver| async move {
+			resolver
+				.resolve(Name::from_str("login.example.com").unwrap())
+				.await
+				.map(|addrs| addrs.map(|addr| addr.ip()).collect::<Vec<_>>())
+		};
+
+		assert_eq!(resolve(resolver.clone()).await.unwrap(), [v4, v6]);
+		assert_eq!(
+			resolve(resolver.clone().prefer(IpPreference::PreferIpv6)).await.unwrap(),
+			[v6, v4]
+		);
+		assert_eq!(resolve(resolver.clone().prefer(IpPreference::Ipv4Only)).await.unwrap(), [v4]);
+		assert!(
+			resolve(resolver.pin("login.example.com", [v4]).prefer(IpPreference::Ipv6Only))
+				.await
+				.is_err()
+		);
+	}
+}
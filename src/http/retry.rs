@@ -23,13 +23,23 @@ pub struct RetryExecutor<'a> {
 	policy: &'a RetryPolicy,
 	deadline: Instant,
 	retries_used: u32,
+	jitter_seed: u64,
 }
 impl<'a> RetryExecutor<'a> {
 	/// Create a new executor respecting the supplied retry policy.
 	pub fn new(policy: &'a RetryPolicy) -> Self {
 		let deadline = Instant::now() + policy.deadline;
 
-		Self { policy, deadline, retries_used: 0 }
+		Self { policy, deadline, retries_used: 0, jitter_seed: 0 }
+	}
+
+	/// Seed deterministic jitter, typically with a hash of the tenant/provider pair.
+	///
+	/// The seed only influences backoff when the `rand` feature is disabled.
+	pub fn with_jitter_seed(mut self, seed: u64) -> Self {
+		self.jitter_seed = seed;
+
+		self
 	}
 
 	/// Budget the next attempt, returning either the permitted timeout or exhaustion.
@@ -66,18 +76,38 @@ impl<'a> RetryExecutor<'a> {
 
 	/// Advance retry state and compute the backoff delay for the next attempt.
 	pub fn next_backoff(&mut self) -> Option<Duration> {
+		self.next_backoff_with_hint(None)
+	}
+
+	/// Like [`Self::next_backoff`], but wait at least `hint`, the delay the server asked for.
+	///
+	/// Returns `None` when the hint reaches past the retry deadline, since an earlier attempt
+	/// would only be rejected again.
+	pub fn next_backoff_with_hint(&mut self, hint: Option<Duration>) -> Option<Duration> {
 		if !self.can_retry() {
 			tracing::debug!(attempt = self.retries_used, "retry budget exhausted");
 
 			return None;
 		}
 
+		let remaining = self.remaining_budget();
+
+		if let Some(hint) = hint
+			&& hint >= remaining
+		{
+			tracing::debug!(?hint, ?remaining, "server retry hint exceeds the retry deadline");
+
+			return None;
+		}
+
 		let attempt = self.retries_used;
 
 		self.retries_used = self.retries_used.saturating_add(1);
 
-		let mut delay = self.policy.compute_backoff(attempt);
-		let remaining = self.remaining_budget();
+		let mut delay = self
+			.policy
+			.compute_backoff_with_seed(attempt, self.jitter_seed)
+			.max(hint.unwrap_or_default());
 
 		if !remaining.is_zero() {
 			delay = delay.min(remaining);
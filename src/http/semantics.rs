@@ -1,10 +1,15 @@
 //! HTTP cache semantics integration helpers.
 
 // crates.io
-use http::{Method, Request, Response, Uri};
-use http_cache_semantics::{AfterResponse, CachePolicy};
+use http::{
+	HeaderMap, HeaderName, HeaderValue, Method, Request, Response, StatusCode, Uri,
+	header::{ACCEPT, CACHE_CONTROL},
+};
+use http_cache_semantics::{AfterResponse, CacheOptions, CachePolicy, RequestLike, ResponseLike};
 // self
-use crate::{_prelude::*, http::client::HttpExchange, registry::IdentityProviderRegistration};
+use crate::{
+	_prelude::*, clock, http::client::HttpExchange, registry::IdentityProviderRegistration,
+};
 
 /// Freshness evaluation derived from HTTP headers and registry policy.
 #[derive(Clone, Debug)]
@@ -12,9 +17,27 @@ pub struct Freshness {
 	/// Effective time-to-live allowed for the JWKS payload.
 	/// Clamped TTL in seconds, derived from HTTP Cache-Control and registry bounds.
 	pub ttl: Duration,
+	/// Upstream `stale-while-revalidate` window, before registration bounds are applied.
+	pub stale_while_revalidate: Option<Duration>,
+	/// Upstream `stale-if-error` window, before registration bounds are applied.
+	pub stale_if_error: Option<Duration>,
 	/// HTTP cache policy describing future request handling.
 	pub policy: CachePolicy,
 }
+impl Freshness {
+	/// Freshness without upstream stale directives, as for synthesised responses.
+	pub fn new(ttl: Duration, policy: CachePolicy) -> Self {
+		Self { ttl, stale_while_revalidate: None, stale_if_error: None, policy }
+	}
+
+	// Record the stale directives of the response `headers`.
+	fn with_stale_directives(mut self, headers: &HeaderMap) -> Self {
+		self.stale_while_revalidate = directive_seconds(headers, "stale-while-revalidate");
+		self.stale_if_error = directive_seconds(headers, "stale-if-error");
+
+		self
+	}
+}
 
 /// Result of applying conditional revalidation.
 #[derive(Debug)]
@@ -30,13 +53,29 @@ pub struct Revalidation {
 /// Build a baseline HTTP request for the provider JWKS endpoint.
 pub fn base_request(registration: &IdentityProviderRegistration) -> Result<Request<()>> {
 	let uri = parse_uri(registration)?;
-
-	Request::builder()
+	let mut request = Request::builder()
 		.method(Method::GET)
 		.uri(uri)
-		.header("accept", "application/json")
-		.body(())
-		.map_err(Error::from)
+		.header(ACCEPT, "application/json")
+		.body(())?;
+
+	for (name, value) in &registration.extra_headers {
+		let name = HeaderName::from_bytes(name.as_bytes()).map_err(http::Error::from)?;
+		let value = HeaderValue::from_str(value).map_err(http::Error::from)?;
+
+		request.headers_mut().insert(name, value);
+	}
+
+	Ok(request)
+}
+
+/// Build a cache policy for an exchange, timestamped with [`clock::system_now`].
+pub fn cache_policy<Req, Res>(request: &Req, response: &Res) -> CachePolicy
+where
+	Req: RequestLike,
+	Res: ResponseLike,
+{
+	CachePolicy::new_options(request, response, clock::system_now(), CacheOptions::default())
 }
 
 /// Evaluate HTTP cache semantics to determine TTL for the fetched JWKS document.
@@ -44,21 +83,34 @@ pub fn evaluate_freshness(
 	registration: &IdentityProviderRegistration,
 	exchange: &HttpExchange,
 ) -> Result<Freshness> {
-	let policy = CachePolicy::new(&exchange.request, &exchange.response);
-	let storable = policy.is_storable();
-	let ttl = if storable {
-		clamp_ttl(
-			policy.time_to_live(SystemTime::now()),
-			registration.min_ttl,
-			registration.max_ttl,
-		)
-	} else {
-		registration.min_ttl
-	};
+	let policy = cache_policy(&exchange.request, &exchange.response);
+	let ttl = effective_ttl(registration, &policy, clock::system_now());
+
+	tracing::debug!(ttl=?ttl, storable = policy.is_storable(), "evaluated freshness");
+
+	Ok(Freshness::new(ttl, policy).with_stale_directives(exchange.response.headers()))
+}
 
-	tracing::debug!(ttl=?ttl, storable, "evaluated freshness");
+/// Preview the TTL applied if the provider answered a fetch with `status` and `headers`.
+///
+/// Uses the same cache semantics and `min_ttl`/`max_ttl` clamping as live fetches, so tooling can
+/// explain caching behaviour without contacting the provider.
+pub fn preview_ttl(
+	registration: &IdentityProviderRegistration,
+	status: StatusCode,
+	headers: &HeaderMap,
+) -> Result<Duration> {
+	let request = base_request(registration)?;
+	let mut response = Response::new(());
+
+	*response.status_mut() = status;
+	*response.headers_mut() = headers.clone();
 
-	Ok(Freshness { ttl, policy })
+	// Evaluate at the response time so the preview is exact rather than a few microseconds short.
+	let now = clock::system_now();
+	let policy = CachePolicy::new_options(&request, &response, now, CacheOptions::default());
+
+	Ok(effective_ttl(registration, &policy, now))
 }
 
 /// Evaluate cache semantics for a conditional revalidation attempt.
@@ -68,25 +120,65 @@ pub fn evaluate_revalidation(
 	request: &Request<()>,
 	response: &Response<()>,
 ) -> Result<Revalidation> {
-	let now = SystemTime::now();
+	let now = clock::system_now();
 	let outcome = policy.after_response(request, response, now);
 	let (policy, parts, modified) = match outcome {
 		AfterResponse::NotModified(policy, parts) => (policy, parts, false),
 		AfterResponse::Modified(policy, parts) => (policy, parts, true),
 	};
 	let response = Response::from_parts(parts, ());
-	let ttl = clamp_ttl(policy.time_to_live(now), registration.min_ttl, registration.max_ttl);
+	let ttl = clamp_ttl(
+		policy.time_to_live(now),
+		registration.min_ttl.as_duration(),
+		registration.max_ttl.as_duration(),
+	);
+
+	let freshness = Freshness::new(ttl, policy).with_stale_directives(response.headers());
 
-	Ok(Revalidation { freshness: Freshness { ttl, policy }, response, modified })
+	Ok(Revalidation { freshness, response, modified })
 }
 
 fn parse_uri(registration: &IdentityProviderRegistration) -> Result<Uri> {
+	// Static key sets are never requested, and `file:` URLs have no HTTP form.
+	if registration.is_static() {
+		return Ok(Uri::default());
+	}
+
 	registration.jwks_url.as_str().parse::<Uri>().map_err(|err| Error::Validation {
 		field: "jwks_url",
 		reason: format!("Failed to convert URL to http::Uri: {err}."),
 	})
 }
 
+fn effective_ttl(
+	registration: &IdentityProviderRegistration,
+	policy: &CachePolicy,
+	now: SystemTime,
+) -> Duration {
+	if policy.is_storable() {
+		clamp_ttl(
+			policy.time_to_live(now),
+			registration.min_ttl.as_duration(),
+			registration.max_ttl.as_duration(),
+		)
+	} else {
+		registration.min_ttl.as_duration()
+	}
+}
+
+// Seconds carried by the first `name=<seconds>` Cache-Control directive, if any.
+fn directive_seconds(headers: &HeaderMap, name: &str) -> Option<Duration> {
+	headers
+		.get_all(CACHE_CONTROL)
+		.iter()
+		.filter_map(|value| value.to_str().ok())
+		.flat_map(|value| value.split(','))
+		.filter_map(|directive| directive.split_once('='))
+		.find(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+		.and_then(|(_, value)| value.trim().trim_matches('"').parse().ok())
+		.map(Duration::from_secs)
+}
+
 fn clamp_ttl(ttl: Duration, min: Duration, max: Duration) -> Duration {
 	if ttl < min {
 		min
@@ -100,13 +192,11 @@ fn clamp_ttl(ttl: Duration, min: Duration, max: Duration) -> Duration {
 #[cfg(test)]
 mod tests {
 	// crates.io
-	use http::{
-		StatusCode,
-		header::{CACHE_CONTROL, ETAG},
-	};
+	use http::header::{AGE, CACHE_CONTROL, ETAG};
 	use http_cache_semantics::BeforeRequest;
 	// self
 	use super::*;
+	use crate::config::TtlSeconds;
 
 	fn make_registration() -> IdentityProviderRegistration {
 		IdentityProviderRegistration::new(
@@ -121,8 +211,8 @@ mod tests {
 	fn clamps_ttl_to_registration_bounds() {
 		let mut registration = make_registration();
 
-		registration.min_ttl = Duration::from_secs(30);
-		registration.max_ttl = Duration::from_secs(60);
+		registration.min_ttl = TtlSeconds::from_secs(30);
+		registration.max_ttl = TtlSeconds::from_secs(60);
 
 		let request = base_request(&registration).expect("request");
 		let response = Response::builder()
@@ -136,13 +226,78 @@ mod tests {
 		assert_eq!(freshness.ttl, Duration::from_secs(30));
 	}
 
+	#[test]
+	fn records_upstream_stale_directives() {
+		let registration = make_registration();
+		let freshness = |cache_control: &[&str]| {
+			let mut response = Response::builder().status(StatusCode::OK);
+
+			for value in cache_control {
+				response = response.header(CACHE_CONTROL, *value);
+			}
+
+			let exchange = HttpExchange::new(
+				base_request(&registration).expect("request"),
+				response.body(()).expect("response"),
+				Duration::from_millis(5),
+			);
+
+			evaluate_freshness(&registration, &exchange).expect("freshness")
+		};
+
+		let both = freshness(&["max-age=300, Stale-While-Revalidate=60", "stale-if-error=\"600\""]);
+
+		assert_eq!(both.stale_while_revalidate, Some(Duration::from_secs(60)));
+		assert_eq!(both.stale_if_error, Some(Duration::from_secs(600)));
+
+		let none = freshness(&["max-age=300, stale-if-error=soon"]);
+
+		assert_eq!(none.stale_while_revalidate, None);
+		assert_eq!(none.stale_if_error, None);
+	}
+
+	#[test]
+	fn preview_ttl_matches_fetch_clamping() {
+		let mut registration = make_registration();
+
+		registration.min_ttl = TtlSeconds::from_secs(30);
+		registration.max_ttl = TtlSeconds::from_secs(600);
+
+		let preview = |cache_control: &str| {
+			let mut headers = HeaderMap::new();
+
+			headers.insert(CACHE_CONTROL, cache_control.parse().expect("header value"));
+
+			preview_ttl(&registration, StatusCode::OK, &headers).expect("preview")
+		};
+
+		assert_eq!(preview("max-age=120"), Duration::from_secs(120));
+		assert_eq!(preview("max-age=5"), Duration::from_secs(30));
+		assert_eq!(preview("max-age=86400"), Duration::from_secs(600));
+		assert_eq!(preview("no-store"), Duration::from_secs(30));
+	}
+
+	#[test]
+	fn ttl_discounts_the_upstream_age() {
+		let registration = make_registration();
+		let mut headers = HeaderMap::new();
+
+		headers.insert(CACHE_CONTROL, "max-age=600".parse().expect("header value"));
+		headers.insert(AGE, "500".parse().expect("header value"));
+
+		assert_eq!(
+			preview_ttl(&registration, StatusCode::OK, &headers).expect("preview"),
+			Duration::from_secs(100)
+		);
+	}
+
 	#[test]
 	fn adds_etag_to_conditional_revalidation_headers() {
 		let mut registration = make_registration();
 
 		registration.require_https = false;
-		registration.min_ttl = Duration::from_secs(1);
-		registration.max_ttl = Duration::from_secs(10);
+		registration.min_ttl = TtlSeconds::from_secs(1);
+		registration.max_ttl = TtlSeconds::from_secs(10);
 
 		let request = base_request(&registration).expect("request");
 		let response = Response::builder()
@@ -155,7 +310,7 @@ mod tests {
 		let freshness = evaluate_freshness(&registration, &exchange).expect("freshness");
 		let request = base_request(&registration).expect("request");
 		let decision =
-			freshness.policy.before_request(&request, SystemTime::now() + Duration::from_secs(5));
+			freshness.policy.before_request(&request, clock::system_now() + Duration::from_secs(5));
 
 		match decision {
 			BeforeRequest::Stale { request, .. } => {
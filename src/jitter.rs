@@ -0,0 +1,130 @@
+//! Jitter sampling shared by retry backoff and refresh scheduling.
+//!
+//! With the `rand` feature enabled, samples come from a thread-local RNG. Without it, samples are
+//! derived from a caller-supplied seed (typically a hash of the tenant/provider pair), which keeps
+//! schedules decorrelated across providers while remaining reproducible. The module also mints
+//! tokens that tell replicas apart.
+
+// std
+#[cfg(feature = "rand")] use std::cell::RefCell;
+use std::{
+	process,
+	sync::atomic::{AtomicU64, Ordering},
+};
+// crates.io
+#[cfg(feature = "rand")] use rand::{Rng, SeedableRng, rngs::SmallRng};
+// self
+use crate::{_prelude::*, clock};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+#[cfg(feature = "rand")]
+thread_local! {
+	static SMALL_RNG: RefCell<SmallRng> = RefCell::new(SmallRng::from_rng(&mut rand::rng()));
+}
+
+/// Derive a stable jitter seed for a tenant/provider pair.
+pub fn seed_for(tenant: &str, provider: &str) -> u64 {
+	let mut hash = FNV_OFFSET_BASIS;
+
+	for byte in tenant.bytes().chain([0]).chain(provider.bytes()) {
+		hash ^= u64::from(byte);
+		hash = hash.wrapping_mul(FNV_PRIME);
+	}
+
+	hash
+}
+
+/// Combine a base seed with a discriminator such as an attempt number.
+pub fn mix(seed: u64, discriminator: u64) -> u64 {
+	splitmix64(seed ^ splitmix64(discriminator))
+}
+
+/// Sample a duration within `[min, max]`.
+pub fn within(min: Duration, max: Duration, seed: u64) -> Duration {
+	if max <= min {
+		return max;
+	}
+
+	let span = (max.as_nanos() - min.as_nanos()).min(u64::MAX as u128) as u64;
+
+	min + Duration::from_nanos(sample(span, seed))
+}
+
+/// Sample a duration within `[0, max]`.
+pub fn up_to(max: Duration, seed: u64) -> Duration {
+	within(Duration::ZERO, max, seed)
+}
+
+/// Mint a token unique across processes and replicas, which may share a process id inside
+/// containers.
+pub fn unique_token() -> String {
+	static ISSUED: AtomicU64 = AtomicU64::new(0);
+
+	format!(
+		"{}-{}-{}",
+		process::id(),
+		clock::utc_now().timestamp_nanos_opt().unwrap_or_default(),
+		ISSUED.fetch_add(1, Ordering::Relaxed)
+	)
+}
+
+#[cfg(feature = "rand")]
+fn sample(upper: u64, _seed: u64) -> u64 {
+	SMALL_RNG.with(|cell| cell.borrow_mut().random_range(0..=upper))
+}
+
+#[cfg(not(feature = "rand"))]
+fn sample(upper: u64, seed: u64) -> u64 {
+	let value = splitmix64(seed);
+
+	match upper.checked_add(1) {
+		Some(modulus) => value % modulus,
+		None => value,
+	}
+}
+
+fn splitmix64(value: u64) -> u64 {
+	let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+	z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+	z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn samples_stay_within_bounds() {
+		let min = Duration::from_millis(200);
+		let max = Duration::from_millis(250);
+
+		for attempt in 0..64 {
+			let value = within(min, max, mix(seed_for("tenant", "provider"), attempt));
+
+			assert!(value >= min && value <= max, "{value:?} outside bounds");
+		}
+
+		assert_eq!(within(max, min, 7), min);
+		assert_eq!(up_to(Duration::ZERO, 7), Duration::ZERO);
+	}
+
+	#[test]
+	fn seeds_differ_across_providers() {
+		assert_ne!(seed_for("tenant", "provider-a"), seed_for("tenant", "provider-b"));
+		assert_ne!(seed_for("tenant-a", "b"), seed_for("tenant", "-ab"));
+	}
+
+	#[cfg(not(feature = "rand"))]
+	#[test]
+	fn deterministic_samples_are_reproducible() {
+		let seed = seed_for("tenant", "provider");
+		let max = Duration::from_secs(5);
+
+		assert_eq!(up_to(max, seed), up_to(max, seed));
+	}
+}
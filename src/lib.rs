@@ -3,12 +3,24 @@
 
 #![deny(clippy::all, missing_docs, unused_crate_dependencies)]
 
+#[cfg(feature = "admin-api")] pub mod admin;
 pub mod cache;
+pub mod capabilities;
+pub mod clock;
+pub mod config;
+pub mod events;
+#[cfg(feature = "grpc")] pub mod grpc;
 pub mod http;
 #[cfg(feature = "metrics")] pub mod metrics;
+#[cfg(feature = "jwks-cache-axum")] pub mod middleware;
+pub mod persistence;
+#[cfg(feature = "problem-details")] pub mod problem;
 pub mod security;
+pub mod tasks;
+#[cfg(feature = "test-util")] pub mod test_util;
 
 mod error;
+mod jitter;
 mod registry;
 mod _prelude {
 	pub use std::{
@@ -22,18 +34,55 @@ mod _prelude {
 	pub use crate::{Error, Result};
 }
 #[cfg(feature = "prometheus")] pub use crate::metrics::install_default_exporter;
+#[cfg(feature = "redis")]
+pub use crate::persistence::{RedisInvalidationBus, RedisSnapshotStore};
+#[cfg(feature = "problem-details")] pub use crate::problem::ProblemDetails;
 #[cfg(feature = "metrics")] pub use crate::registry::StatusMetric;
 pub use crate::{
-	error::{Error, Result},
+	cache::{
+		activation::{KeyActivation, NotBeforeField},
+		breaker::CircuitState,
+		history::StatusSample,
+		manager::ResolveOptions,
+		rotation::KeyRotation,
+	},
+	capabilities::{Capabilities, capabilities},
+	config::{ByteSize, TtlSeconds},
+	error::{Error, ErrorKind, Result},
+	events::{CacheEvent, CacheEventKind},
+	http::{
+		auth::{AuthProvider, ClientCredentials, StaticToken},
+		capture::CapturedExchange,
+		resolve::{HostResolver, IpPreference},
+	},
+	persistence::{
+		Invalidation, InvalidationBus, InvalidationTarget, MemoryInvalidationBus,
+		MemoryRefreshLock, MemorySnapshotStore, RefreshLock, SnapshotKey, SnapshotStore,
+	},
 	registry::{
-		IdentityProviderRegistration, JitterStrategy, PersistentSnapshot, ProviderState,
-		ProviderStatus, Registry, RegistryBuilder, RetryPolicy,
+		AZURE_AD_HOST, AZURE_AD_MIN_TTL, AggregateStatus, AllowlistMode, COGNITO_MIN_TTL,
+		CircuitBreakerPolicy, ClockJumpPolicy, ClockReconciliation, DiscoveryRegistration,
+		FailingProvider, HealthReport, HealthStatus, HttpClientConfig,
+		IdentityProviderRegistration, JitterStrategy, JwksCache, JwksCacheOptions, JwksSource,
+		JwksValidationPolicy, KidNamespace, PersistentSnapshot, ProviderError, ProviderHealth,
+		ProviderState, ProviderStatus, ProxySettings, RateLimitPolicy, RecoveryPolicy,
+		RefreshSchedulePolicy, RegistrationFile, Registry, RegistryBuilder, RegistryConfigFile,
+		RegistrySettings, ReloadReport, RestorePolicy, RetryPolicy, SNAPSHOT_VERSION,
+		STANDALONE_ID, STATIC_INLINE_URL, ShutdownOptions, ShutdownReport, StateCounts, StaticJwks,
+		StatusFilter, StatusFormat, StatusPage, SupportBundle, WarmUpFailure, WarmUpReport,
 	},
 };
 
+// Only the `jwks-cache` binary uses it.
+#[cfg(feature = "cli")] use clap as _;
+
 #[cfg(test)]
 mod _test {
+	use axum as _;
+	use criterion as _;
 	use metrics_util as _;
+	use tokio_rustls as _;
+	use tower as _;
 	use tracing_subscriber as _;
 	use wiremock as _;
 }
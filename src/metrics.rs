@@ -7,9 +7,10 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use metrics::Label;
 #[cfg(feature = "prometheus")]
 use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 // self
-use crate::_prelude::*;
+use crate::{_prelude::*, clock::ClockDrift};
 
 type LabelSet = SmallVec<[Label; 4]>;
 
@@ -20,6 +21,21 @@ const METRIC_MISSES_TOTAL: &str = "jwks_cache_misses_total";
 const METRIC_REFRESH_TOTAL: &str = "jwks_cache_refresh_total";
 const METRIC_REFRESH_DURATION: &str = "jwks_cache_refresh_duration_seconds";
 const METRIC_REFRESH_ERRORS: &str = "jwks_cache_refresh_errors_total";
+const METRIC_REFRESH_ABORTED: &str = "jwks_cache_refresh_aborted_total";
+const METRIC_UPSTREAM_CONNECTIONS: &str = "jwks_cache_upstream_connections_total";
+const METRIC_REFRESH_LEAD_ADAPTED: &str = "jwks_cache_refresh_lead_adapted_total";
+const METRIC_REFRESH_LEAD: &str = "jwks_cache_refresh_lead_seconds";
+const METRIC_UPSTREAM_AGE_EXCEEDED: &str = "jwks_cache_upstream_age_exceeded_total";
+const METRIC_UPSTREAM_AGE: &str = "jwks_cache_upstream_age_seconds";
+const METRIC_SINGLE_FLIGHT_WAIT: &str = "jwks_cache_single_flight_wait_seconds";
+const METRIC_SINGLE_FLIGHT_WAITERS: &str = "jwks_cache_single_flight_waiters";
+const METRIC_TIME_TO_READY: &str = "jwks_cache_time_to_ready_seconds";
+const METRIC_CLOCK_JUMPS: &str = "jwks_cache_clock_jumps_total";
+const METRIC_SECONDS_UNTIL_EXPIRY: &str = "jwks_cache_seconds_until_expiry";
+const METRIC_PAYLOAD_AGE: &str = "jwks_cache_payload_age_seconds";
+const METRIC_RATE_LIMIT_WAIT: &str = "jwks_cache_rate_limit_wait_seconds";
+const METRIC_REFRESH_QUEUE_WAIT: &str = "jwks_cache_refresh_queue_wait_seconds";
+const METRIC_RESOLVE_DURATION: &str = "jwks_cache_resolve_duration_seconds";
 
 /// Shared Prometheus handle installed by [`install_default_exporter`].
 #[cfg(feature = "prometheus")]
@@ -33,6 +49,9 @@ pub struct ProviderMetrics {
 	stale_serves: AtomicU64,
 	refresh_successes: AtomicU64,
 	refresh_errors: AtomicU64,
+	refresh_aborts: AtomicU64,
+	connections_reused: AtomicU64,
+	connections_opened: AtomicU64,
 	last_refresh_micros: AtomicU64,
 }
 impl ProviderMetrics {
@@ -66,6 +85,20 @@ impl ProviderMetrics {
 		self.refresh_errors.fetch_add(1, Ordering::Relaxed);
 	}
 
+	/// Record background refreshes aborted before completion.
+	pub fn record_refresh_aborted(&self, count: u64) {
+		self.refresh_aborts.fetch_add(count, Ordering::Relaxed);
+	}
+
+	/// Record whether an upstream response reused a pooled connection.
+	pub fn record_connection(&self, reused: bool) {
+		if reused {
+			self.connections_reused.fetch_add(1, Ordering::Relaxed);
+		} else {
+			self.connections_opened.fetch_add(1, Ordering::Relaxed);
+		}
+	}
+
 	/// Take a point-in-time snapshot for status reporting.
 	pub fn snapshot(&self) -> ProviderMetricsSnapshot {
 		ProviderMetricsSnapshot {
@@ -74,6 +107,9 @@ impl ProviderMetrics {
 			stale_serves: self.stale_serves.load(Ordering::Relaxed),
 			refresh_successes: self.refresh_successes.load(Ordering::Relaxed),
 			refresh_errors: self.refresh_errors.load(Ordering::Relaxed),
+			refresh_aborts: self.refresh_aborts.load(Ordering::Relaxed),
+			connections_reused: self.connections_reused.load(Ordering::Relaxed),
+			connections_opened: self.connections_opened.load(Ordering::Relaxed),
 			last_refresh_micros: match self.last_refresh_micros.load(Ordering::Relaxed) {
 				0 => None,
 				value => Some(value),
@@ -83,7 +119,7 @@ impl ProviderMetrics {
 }
 
 /// Read-only snapshot of per-provider telemetry counters.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ProviderMetricsSnapshot {
 	/// Total number of cache lookups observed.
 	pub total_requests: u64,
@@ -95,6 +131,12 @@ pub struct ProviderMetricsSnapshot {
 	pub refresh_successes: u64,
 	/// Count of refresh attempts that resulted in errors.
 	pub refresh_errors: u64,
+	/// Count of background refreshes aborted by unregistration or registry shutdown.
+	pub refresh_aborts: u64,
+	/// Count of upstream responses carried on a reused pooled connection.
+	pub connections_reused: u64,
+	/// Count of upstream responses that needed a new connection.
+	pub connections_opened: u64,
 	/// Microsecond latency of the most recent refresh.
 	pub last_refresh_micros: Option<u64>,
 }
@@ -118,6 +160,27 @@ impl ProviderMetricsSnapshot {
 	}
 }
 
+/// Where a resolve obtained the keys it returned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResolveSource {
+	/// Served from a fresh cached payload.
+	Memory,
+	/// Waited for an upstream refresh, whether run by this caller or a concurrent one.
+	Refresh,
+	/// Served from a stale payload while upstream was failing or slow.
+	Stale,
+}
+impl ResolveSource {
+	/// Label value reported for this source.
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::Memory => "memory",
+			Self::Refresh => "refresh",
+			Self::Stale => "stale",
+		}
+	}
+}
+
 /// Install the default Prometheus recorder backed by `metrics`.
 ///
 /// Multiple invocations are safe; subsequent calls become no-ops once the recorder is installed.
@@ -176,6 +239,106 @@ pub fn record_refresh_error(tenant: &str, provider: &str) {
 	metrics::counter!(METRIC_REFRESH_ERRORS, base_labels(tenant, provider).iter()).increment(1);
 }
 
+/// Record background refreshes aborted before completion.
+pub fn record_refresh_aborted(tenant: &str, provider: &str, count: u64) {
+	metrics::counter!(METRIC_REFRESH_ABORTED, base_labels(tenant, provider).iter())
+		.increment(count);
+}
+
+/// Record whether an upstream response reused a pooled connection.
+pub fn record_upstream_connection(tenant: &str, provider: &str, reused: bool) {
+	let mut labels = base_labels(tenant, provider);
+
+	labels.push(Label::new("reused", if reused { "true" } else { "false" }));
+
+	metrics::counter!(METRIC_UPSTREAM_CONNECTIONS, labels.iter()).increment(1);
+}
+
+/// Record a refresh scheduled with a lead widened to cover slow upstream fetches.
+pub fn record_refresh_lead_adapted(tenant: &str, provider: &str, lead: Duration) {
+	let labels = base_labels(tenant, provider);
+
+	metrics::counter!(METRIC_REFRESH_LEAD_ADAPTED, labels.iter()).increment(1);
+	metrics::gauge!(METRIC_REFRESH_LEAD, labels.iter()).set(lead.as_secs_f64());
+}
+
+/// Record a fetched payload whose upstream `Age` exceeded the registration's warning threshold.
+pub fn record_upstream_age_exceeded(tenant: &str, provider: &str, age: Duration) {
+	let labels = base_labels(tenant, provider);
+
+	metrics::counter!(METRIC_UPSTREAM_AGE_EXCEEDED, labels.iter()).increment(1);
+	metrics::gauge!(METRIC_UPSTREAM_AGE, labels.iter()).set(age.as_secs_f64());
+}
+
+/// Record the number of callers queued for a provider's single-flight slot.
+pub fn record_single_flight_waiters(tenant: &str, provider: &str, waiters: usize) {
+	metrics::gauge!(METRIC_SINGLE_FLIGHT_WAITERS, base_labels(tenant, provider).iter())
+		.set(waiters as f64);
+}
+
+/// Record how long a caller queued for a provider's single-flight slot.
+pub fn record_single_flight_wait(tenant: &str, provider: &str, wait: Duration) {
+	metrics::histogram!(METRIC_SINGLE_FLIGHT_WAIT, base_labels(tenant, provider).iter())
+		.record(wait.as_secs_f64());
+}
+
+/// Record how long a provider took from registration to its first usable keys.
+pub fn record_time_to_ready(tenant: &str, provider: &str, elapsed: Duration) {
+	metrics::histogram!(METRIC_TIME_TO_READY, base_labels(tenant, provider).iter())
+		.record(elapsed.as_secs_f64());
+}
+
+/// Record a jump between the monotonic and wall clocks detected while resolving.
+pub fn record_clock_jump(tenant: &str, provider: &str, drift: ClockDrift) {
+	let mut labels = base_labels(tenant, provider);
+
+	labels.push(Label::new(
+		"direction",
+		match drift {
+			ClockDrift::Ahead(_) => "ahead",
+			ClockDrift::Behind(_) => "behind",
+		},
+	));
+
+	metrics::counter!(METRIC_CLOCK_JUMPS, labels.iter()).increment(1);
+}
+
+/// Record how long a provider's payload stays fresh and how old it is.
+///
+/// `until_expiry` turns negative once the payload has expired.
+pub fn record_payload_freshness(tenant: &str, provider: &str, until_expiry: f64, age: Duration) {
+	let labels = base_labels(tenant, provider);
+
+	metrics::gauge!(METRIC_SECONDS_UNTIL_EXPIRY, labels.iter()).set(until_expiry);
+	metrics::gauge!(METRIC_PAYLOAD_AGE, labels.iter()).set(age.as_secs_f64());
+}
+
+/// Record how long an upstream request waited on the per-host rate limit.
+pub fn record_rate_limit_wait(tenant: &str, provider: &str, wait: Duration) {
+	metrics::histogram!(METRIC_RATE_LIMIT_WAIT, base_labels(tenant, provider).iter())
+		.record(wait.as_secs_f64());
+}
+
+/// Record how long a background refresh queued behind the registry-wide concurrency cap.
+pub fn record_refresh_queue_wait(tenant: &str, provider: &str, wait: Duration) {
+	metrics::histogram!(METRIC_REFRESH_QUEUE_WAIT, base_labels(tenant, provider).iter())
+		.record(wait.as_secs_f64());
+}
+
+/// Record the end-to-end latency of a resolve, labelled by where its keys came from.
+pub fn record_resolve_duration(
+	tenant: &str,
+	provider: &str,
+	source: ResolveSource,
+	duration: Duration,
+) {
+	let mut labels = base_labels(tenant, provider);
+
+	labels.push(Label::new("source", source.as_str()));
+
+	metrics::histogram!(METRIC_RESOLVE_DURATION, labels.iter()).record(duration.as_secs_f64());
+}
+
 fn base_labels(tenant: &str, provider: &str) -> LabelSet {
 	let mut labels = LabelSet::with_capacity(2);
 
@@ -315,4 +478,152 @@ mod tests {
 
 		assert!((duration - 0.020).abs() < 1e-6, "expected ~20ms histogram, got {duration}");
 	}
+
+	#[test]
+	fn labels_upstream_connections_by_reuse() {
+		let snapshot = capture_metrics(|| {
+			record_upstream_connection("tenant-c", "provider-3", false);
+			record_upstream_connection("tenant-c", "provider-3", true);
+			record_upstream_connection("tenant-c", "provider-3", true);
+		});
+		let opened = [("tenant", "tenant-c"), ("provider", "provider-3"), ("reused", "false")];
+		let reused = [("tenant", "tenant-c"), ("provider", "provider-3"), ("reused", "true")];
+
+		assert_eq!(counter_value(&snapshot, "jwks_cache_upstream_connections_total", &opened), 1);
+		assert_eq!(counter_value(&snapshot, "jwks_cache_upstream_connections_total", &reused), 2);
+	}
+
+	#[test]
+	fn counts_aged_upstream_payloads() {
+		let snapshot = capture_metrics(|| {
+			record_upstream_age_exceeded("tenant-d", "provider-4", Duration::from_secs(500));
+		});
+		let labels = [("tenant", "tenant-d"), ("provider", "provider-4")];
+
+		assert_eq!(counter_value(&snapshot, "jwks_cache_upstream_age_exceeded_total", &labels), 1);
+	}
+
+	#[test]
+	fn records_single_flight_waits() {
+		let snapshot = capture_metrics(|| {
+			record_single_flight_waiters("tenant-e", "provider-5", 2);
+			record_single_flight_wait("tenant-e", "provider-5", Duration::from_millis(250));
+		});
+		let labels = [("tenant", "tenant-e"), ("provider", "provider-5")];
+		let wait =
+			last_histogram_value(&snapshot, "jwks_cache_single_flight_wait_seconds", &labels)
+				.expect("single-flight wait recorded");
+
+		assert!((wait - 0.25).abs() < 1e-6, "expected ~250ms histogram, got {wait}");
+		assert!(snapshot.iter().any(|(key, value)| {
+			key.kind() == MetricKind::Gauge
+				&& Borrow::<str>::borrow(key.key().name()) == "jwks_cache_single_flight_waiters"
+				&& labels_match(key, &labels)
+				&& matches!(value, DebugValue::Gauge(waiters) if waiters.into_inner() == 2.0)
+		}));
+	}
+
+	#[test]
+	fn records_time_to_ready() {
+		let snapshot = capture_metrics(|| {
+			record_time_to_ready("tenant-f", "provider-6", Duration::from_millis(1_500));
+		});
+		let labels = [("tenant", "tenant-f"), ("provider", "provider-6")];
+		let elapsed = last_histogram_value(&snapshot, "jwks_cache_time_to_ready_seconds", &labels)
+			.expect("time to ready recorded");
+
+		assert!((elapsed - 1.5).abs() < 1e-6, "expected ~1.5s histogram, got {elapsed}");
+	}
+
+	#[test]
+	fn records_payload_freshness_gauges() {
+		let snapshot = capture_metrics(|| {
+			record_payload_freshness("tenant-h", "provider-8", -12.5, Duration::from_secs(312));
+		});
+		let labels = [("tenant", "tenant-h"), ("provider", "provider-8")];
+		let gauge = |name: &str| {
+			snapshot.iter().find_map(|(key, value)| match value {
+				DebugValue::Gauge(value)
+					if Borrow::<str>::borrow(key.key().name()) == name
+						&& labels_match(key, &labels) =>
+					Some(value.into_inner()),
+				_ => None,
+			})
+		};
+
+		assert_eq!(gauge("jwks_cache_seconds_until_expiry"), Some(-12.5));
+		assert_eq!(gauge("jwks_cache_payload_age_seconds"), Some(312.0));
+	}
+
+	#[test]
+	fn records_rate_limit_waits() {
+		let snapshot = capture_metrics(|| {
+			record_rate_limit_wait("tenant-i", "provider-9", Duration::from_millis(750));
+		});
+		let labels = [("tenant", "tenant-i"), ("provider", "provider-9")];
+		let wait = last_histogram_value(&snapshot, "jwks_cache_rate_limit_wait_seconds", &labels)
+			.expect("rate limit wait recorded");
+
+		assert!((wait - 0.75).abs() < 1e-6, "expected ~750ms histogram, got {wait}");
+	}
+
+	#[test]
+	fn records_refresh_queue_waits() {
+		let snapshot = capture_metrics(|| {
+			record_refresh_queue_wait("tenant-j", "provider-10", Duration::from_secs(2));
+		});
+		let labels = [("tenant", "tenant-j"), ("provider", "provider-10")];
+		let wait =
+			last_histogram_value(&snapshot, "jwks_cache_refresh_queue_wait_seconds", &labels)
+				.expect("refresh queue wait recorded");
+
+		assert!((wait - 2.0).abs() < 1e-6, "expected ~2s histogram, got {wait}");
+	}
+
+	#[test]
+	fn records_resolve_durations_by_source() {
+		let snapshot = capture_metrics(|| {
+			record_resolve_duration(
+				"tenant-k",
+				"provider-11",
+				ResolveSource::Memory,
+				Duration::from_micros(20),
+			);
+			record_resolve_duration(
+				"tenant-k",
+				"provider-11",
+				ResolveSource::Refresh,
+				Duration::from_millis(300),
+			);
+		});
+		let memory = [("tenant", "tenant-k"), ("provider", "provider-11"), ("source", "memory")];
+		let refresh = [("tenant", "tenant-k"), ("provider", "provider-11"), ("source", "refresh")];
+		let stale = [("tenant", "tenant-k"), ("provider", "provider-11"), ("source", "stale")];
+		let name = "jwks_cache_resolve_duration_seconds";
+
+		assert!(last_histogram_value(&snapshot, name, &memory).expect("memory resolve") < 0.001);
+		assert!(
+			(last_histogram_value(&snapshot, name, &refresh).expect("refresh resolve") - 0.3).abs()
+				< 1e-6
+		);
+		assert!(last_histogram_value(&snapshot, name, &stale).is_none());
+	}
+
+	#[test]
+	fn counts_clock_jumps_by_direction() {
+		let snapshot = capture_metrics(|| {
+			record_clock_jump("tenant-g", "provider-7", ClockDrift::Ahead(Duration::from_secs(90)));
+			record_clock_jump("tenant-g", "provider-7", ClockDrift::Ahead(Duration::from_secs(60)));
+			record_clock_jump(
+				"tenant-g",
+				"provider-7",
+				ClockDrift::Behind(Duration::from_secs(70)),
+			);
+		});
+		let ahead = [("tenant", "tenant-g"), ("provider", "provider-7"), ("direction", "ahead")];
+		let behind = [("tenant", "tenant-g"), ("provider", "provider-7"), ("direction", "behind")];
+
+		assert_eq!(counter_value(&snapshot, "jwks_cache_clock_jumps_total", &ahead), 2);
+		assert_eq!(counter_value(&snapshot, "jwks_cache_clock_jumps_total", &behind), 1);
+	}
 }
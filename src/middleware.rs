@@ -0,0 +1,283 @@
+//! Axum extractor and tower layer for bearer-token verification.
+//!
+//! [`JwtAuthLayer`] pulls the bearer token from `Authorization`, selects the tenant/provider via a
+//! [`ProviderSelector`], resolves the signing key from the [`Registry`], and verifies the JWT. The
+//! verified token is stored in request extensions, where [`ValidatedClaims`] deserialises it.
+
+// std
+use std::{
+	future::Future,
+	pin::Pin,
+	task::{Context, Poll},
+};
+// crates.io
+use axum::{
+	extract::{FromRequestParts, Request},
+	response::{IntoResponse, Response},
+};
+use http::{HeaderValue, StatusCode, header, request::Parts};
+use jsonwebtoken::{Header, Validation};
+use serde::de::DeserializeOwned;
+use tower_layer::Layer;
+use tower_service::Service;
+// self
+use crate::{_prelude::*, Registry};
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+/// Chooses the tenant/provider pair whose keys verify a request.
+pub trait ProviderSelector
+where
+	Self: Clone + Send + Sync + 'static,
+{
+	/// Return `(tenant_id, provider_id)` for the request, or `None` to reject it.
+	fn select(&self, parts: &Parts) -> Option<(String, String)>;
+}
+impl<F> ProviderSelector for F
+where
+	F: Fn(&Parts) -> Option<(String, String)> + Clone + Send + Sync + 'static,
+{
+	fn select(&self, parts: &Parts) -> Option<(String, String)> {
+		self(parts)
+	}
+}
+
+/// Reasons a request failed authentication.
+#[derive(Debug)]
+pub enum AuthRejection {
+	/// No bearer token was supplied.
+	MissingToken,
+	/// The selector could not determine a tenant/provider pair.
+	ProviderNotSelected,
+	/// The token failed verification; the reason is logged but not sent to the client.
+	InvalidToken(String),
+	/// Signing keys could not be resolved; the reason is logged but not sent to the client.
+	KeysUnavailable(String),
+	/// [`ValidatedClaims`] was used on a route without [`JwtAuthLayer`].
+	MissingLayer,
+	/// Verified claims could not be deserialised; the reason is logged but not sent to the client.
+	InvalidClaims(String),
+}
+impl IntoResponse for AuthRejection {
+	fn into_response(self) -> Response {
+		// Rejection details name upstream URLs and tenants, so they are logged, never returned.
+		let (status, challenge, message) = match self {
+			Self::MissingToken => (StatusCode::UNAUTHORIZED, Some("Bearer"), "Missing bearer token."),
+			Self::ProviderNotSelected => (
+				StatusCode::UNAUTHORIZED,
+				Some("Bearer error=\"invalid_request\""),
+				"Unable to determine the identity provider for this request.",
+			),
+			Self::InvalidToken(reason) => {
+				tracing::debug!(reason, "rejected bearer token");
+
+				(StatusCode::UNAUTHORIZED, Some("Bearer error=\"invalid_token\""), "Invalid token.")
+			},
+			Self::KeysUnavailable(reason) => {
+				tracing::warn!(reason, "signing keys unavailable for bearer token verification");
+
+				(StatusCode::SERVICE_UNAVAILABLE, None, "Signing keys are temporarily unavailable.")
+			},
+			Self::MissingLayer => (
+				StatusCode::INTERNAL_SERVER_ERROR,
+				None,
+				"JwtAuthLayer is not installed for this route.",
+			),
+			Self::InvalidClaims(reason) => {
+				tracing::debug!(reason, "rejected token claims");
+
+				(StatusCode::UNAUTHORIZED, Some("Bearer error=\"invalid_token\""), "Invalid token.")
+			},
+		};
+		let mut response = (status, message).into_response();
+
+		if let Some(challenge) = challenge {
+			response
+				.headers_mut()
+				.insert(header::WWW_AUTHENTICATE, HeaderValue::from_static(challenge));
+		}
+
+		response
+	}
+}
+
+/// Selects a fixed tenant/provider pair for every request.
+#[derive(Clone, Debug)]
+pub struct StaticProvider {
+	tenant_id: Arc<str>,
+	provider_id: Arc<str>,
+}
+impl StaticProvider {
+	/// Always verify against `tenant_id`/`provider_id`.
+	pub fn new(tenant_id: impl Into<String>, provider_id: impl Into<String>) -> Self {
+		Self { tenant_id: Arc::from(tenant_id.into()), provider_id: Arc::from(provider_id.into()) }
+	}
+}
+impl ProviderSelector for StaticProvider {
+	fn select(&self, _: &Parts) -> Option<(String, String)> {
+		Some((self.tenant_id.to_string(), self.provider_id.to_string()))
+	}
+}
+
+/// Token verified by [`JwtAuthLayer`], stored in request extensions.
+#[derive(Clone, Debug)]
+pub struct VerifiedToken {
+	/// Tenant whose keys verified the token.
+	pub tenant_id: String,
+	/// Provider whose keys verified the token.
+	pub provider_id: String,
+	/// Decoded JOSE header.
+	pub header: Header,
+	/// Verified claims as raw JSON.
+	pub claims: serde_json::Value,
+}
+
+/// Extractor yielding claims verified by [`JwtAuthLayer`].
+#[derive(Clone, Debug)]
+pub struct ValidatedClaims<T>(pub T);
+impl<S, T> FromRequestParts<S> for ValidatedClaims<T>
+where
+	S: Send + Sync,
+	T: DeserializeOwned,
+{
+	type Rejection = AuthRejection;
+
+	async fn from_request_parts(
+		parts: &mut Parts,
+		_: &S,
+	) -> std::result::Result<Self, Self::Rejection> {
+		let token = parts.extensions.get::<VerifiedToken>().ok_or(AuthRejection::MissingLayer)?;
+		let claims = serde_json::from_value(token.claims.clone())
+			.map_err(|err| AuthRejection::InvalidClaims(format!("Unexpected claims: {err}.")))?;
+
+		Ok(Self(claims))
+	}
+}
+
+/// Tower layer that verifies bearer tokens against keys cached in a [`Registry`].
+#[derive(Clone, Debug)]
+pub struct JwtAuthLayer<P> {
+	auth: Arc<Authenticator<P>>,
+}
+impl<P> JwtAuthLayer<P>
+where
+	P: ProviderSelector,
+{
+	/// Verify tokens with keys from `registry`, using `validation` for claim checks.
+	///
+	/// The token's `alg` must be listed in `validation.algorithms`.
+	pub fn new(registry: Registry, selector: P, validation: Validation) -> Self {
+		Self { auth: Arc::new(Authenticator { registry, selector, validation }) }
+	}
+}
+impl<S, P> Layer<S> for JwtAuthLayer<P> {
+	type Service = JwtAuth<S, P>;
+
+	fn layer(&self, inner: S) -> Self::Service {
+		JwtAuth { inner, auth: self.auth.clone() }
+	}
+}
+
+/// Service produced by [`JwtAuthLayer`].
+#[derive(Clone, Debug)]
+pub struct JwtAuth<S, P> {
+	inner: S,
+	auth: Arc<Authenticator<P>>,
+}
+impl<S, P> Service<Request> for JwtAuth<S, P>
+where
+	S: Service<Request, Response = Response> + Clone + Send + 'static,
+	S::Future: Send,
+	P: ProviderSelector,
+{
+	type Error = S::Error;
+	type Future = BoxFuture<std::result::Result<Response, S::Error>>;
+	type Response = Response;
+
+	fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), Self::Error>> {
+		self.inner.poll_ready(cx)
+	}
+
+	fn call(&mut self, request: Request) -> Self::Future {
+		// Take the service that was driven to readiness and leave a fresh clone in its place.
+		let clone = self.inner.clone();
+		let mut inner = std::mem::replace(&mut self.inner, clone);
+		let auth = self.auth.clone();
+
+		Box::pin(async move {
+			let (mut parts, body) = request.into_parts();
+
+			match auth.authenticate(&parts).await {
+				Ok(token) => {
+					parts.extensions.insert(token);
+
+					inner.call(Request::from_parts(parts, body)).await
+				},
+				Err(rejection) => Ok(rejection.into_response()),
+			}
+		})
+	}
+}
+
+#[derive(Debug)]
+struct Authenticator<P> {
+	registry: Registry,
+	selector: P,
+	validation: Validation,
+}
+impl<P> Authenticator<P>
+where
+	P: ProviderSelector,
+{
+	async fn authenticate(
+		&self,
+		parts: &Parts,
+	) -> std::result::Result<VerifiedToken, AuthRejection> {
+		let token = bearer_token(parts).ok_or(AuthRejection::MissingToken)?;
+		let (tenant_id, provider_id) =
+			self.selector.select(parts).ok_or(AuthRejection::ProviderNotSelected)?;
+		let data = self
+			.registry
+			.verify_token::<serde_json::Value>(&tenant_id, &provider_id, token, &self.validation)
+			.await
+			.map_err(|err| match err {
+				Error::InvalidToken(_)
+				| Error::KeyNotFound { .. }
+				| Error::KeyNotActive { .. }
+				| Error::NotRegistered { .. } => AuthRejection::InvalidToken(err.to_string()),
+				err => AuthRejection::KeysUnavailable(err.to_string()),
+			})?;
+
+		Ok(VerifiedToken { tenant_id, provider_id, header: data.header, claims: data.claims })
+	}
+}
+
+fn bearer_token(parts: &Parts) -> Option<&str> {
+	let value = parts.headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+	let (scheme, token) = value.split_once(' ')?;
+
+	(scheme.eq_ignore_ascii_case("bearer") && !token.trim().is_empty()).then(|| token.trim())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn bearer_token_requires_bearer_scheme() {
+		let parts = |value: &str| {
+			let (parts, _) = http::Request::builder()
+				.header(header::AUTHORIZATION, value)
+				.body(())
+				.expect("request")
+				.into_parts();
+
+			parts
+		};
+
+		assert_eq!(bearer_token(&parts("Bearer abc.def.ghi")), Some("abc.def.ghi"));
+		assert_eq!(bearer_token(&parts("bearer  abc")), Some("abc"));
+		assert_eq!(bearer_token(&parts("Basic dXNlcjpwYXNz")), None);
+		assert_eq!(bearer_token(&parts("Bearer ")), None);
+	}
+}
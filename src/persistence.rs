@@ -0,0 +1,586 @@
+//! Pluggable snapshot storage used for warm starts.
+//!
+//! Implement [`SnapshotStore`] to back snapshots with any durable store and pass it to
+//! [`RegistryBuilder::with_snapshot_store`](crate::RegistryBuilder::with_snapshot_store).
+//! Replicas sharing a store can also share a [`RefreshLock`], so only the replica holding a
+//! provider's lease fetches upstream while the others adopt the snapshot it persists, and an
+//! [`InvalidationBus`], so invalidating keys on one replica discards them on every replica.
+
+// std
+use std::{
+	collections::HashMap,
+	fmt::Debug,
+	sync::{
+		Mutex as StdMutex, MutexGuard,
+		atomic::{AtomicU64, Ordering},
+	},
+};
+// crates.io
+use async_trait::async_trait;
+#[cfg(feature = "redis")] use futures_util::StreamExt;
+#[cfg(feature = "redis")]
+use redis::{
+	AsyncCommands, RedisError,
+	aio::{MultiplexedConnection, PubSub},
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{RwLock, broadcast};
+#[cfg(feature = "redis")] use tokio::{task::AbortHandle, time};
+// self
+use crate::{_prelude::*, registry::PersistentSnapshot};
+#[cfg(feature = "redis")] use crate::{clock, jitter};
+
+/// Durable storage for [`PersistentSnapshot`]s keyed by tenant/provider pair.
+#[async_trait]
+pub trait SnapshotStore
+where
+	Self: Debug + Send + Sync,
+{
+	/// Store the supplied snapshots, replacing existing entries for the same providers.
+	async fn persist(&self, snapshots: &[PersistentSnapshot]) -> Result<()>;
+
+	/// Load the snapshot for a tenant/provider pair, if present.
+	async fn load(&self, tenant_id: &str, provider_id: &str) -> Result<Option<PersistentSnapshot>>;
+
+	/// Delete the snapshot for a tenant/provider pair, returning whether one existed.
+	async fn delete(&self, tenant_id: &str, provider_id: &str) -> Result<bool>;
+
+	/// List the tenant/provider pairs that currently have a snapshot.
+	async fn list(&self) -> Result<Vec<SnapshotKey>>;
+}
+
+/// Default time a [`RefreshLock`] lease stays valid unless released.
+pub const DEFAULT_LEASE_DURATION: Duration = Duration::from_secs(30);
+
+const INVALIDATION_CAPACITY: usize = 64;
+#[cfg(feature = "redis")]
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+
+/// Lease electing the one replica that refreshes a tenant/provider pair.
+///
+/// Leases expire on their own after [`Self::lease_duration`], so a replica that dies while
+/// holding one only delays the others until then.
+#[async_trait]
+pub trait RefreshLock
+where
+	Self: Debug + Send + Sync,
+{
+	/// Take the lease for a tenant/provider pair, returning the holder token when granted and
+	/// `None` while another holder's lease is live.
+	async fn try_acquire(&self, tenant_id: &str, provider_id: &str) -> Result<Option<String>>;
+
+	/// Release the lease taken with `token`, unless it already expired and passed to another
+	/// holder.
+	async fn release(&self, tenant_id: &str, provider_id: &str, token: &str) -> Result<()>;
+
+	/// How long a granted lease lasts.
+	fn lease_duration(&self) -> Duration;
+}
+
+/// Channel propagating invalidations between replicas.
+#[async_trait]
+pub trait InvalidationBus
+where
+	Self: Debug + Send + Sync,
+{
+	/// Deliver `invalidation` to every subscriber, including those of the publishing replica.
+	async fn publish(&self, invalidation: &Invalidation) -> Result<()>;
+
+	/// Receive invalidations published from now on.
+	fn subscribe(&self) -> broadcast::Receiver<Invalidation>;
+}
+
+/// Invalidation announced on an [`InvalidationBus`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Invalidation {
+	/// Identifies the publishing registry, which already applied the invalidation.
+	pub origin: String,
+	/// Keys to discard.
+	pub target: InvalidationTarget,
+}
+
+/// Keys discarded by an [`Invalidation`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "scope", rename_all = "snake_case")]
+pub enum InvalidationTarget {
+	/// One tenant/provider pair.
+	Provider {
+		/// Tenant identifier of the invalidated provider.
+		tenant_id: String,
+		/// Provider identifier within the tenant scope.
+		provider_id: String,
+	},
+	/// Every provider registered with the receiving registry.
+	All,
+}
+
+/// Tenant/provider pair identifying a stored snapshot.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SnapshotKey {
+	/// Tenant identifier associated with the snapshot.
+	pub tenant_id: String,
+	/// Provider identifier within the tenant scope.
+	pub provider_id: String,
+}
+impl SnapshotKey {
+	/// Construct a key from tenant and provider identifiers.
+	pub fn new(tenant_id: impl Into<String>, provider_id: impl Into<String>) -> Self {
+		Self { tenant_id: tenant_id.into(), provider_id: provider_id.into() }
+	}
+}
+
+/// In-process snapshot store, useful for tests and single-process deployments.
+#[derive(Debug, Default)]
+pub struct MemorySnapshotStore {
+	snapshots: RwLock<HashMap<SnapshotKey, PersistentSnapshot>>,
+}
+impl MemorySnapshotStore {
+	/// Create an empty store.
+	pub fn new() -> Self {
+		Self::default()
+	}
+}
+#[async_trait]
+impl SnapshotStore for MemorySnapshotStore {
+	async fn persist(&self, snapshots: &[PersistentSnapshot]) -> Result<()> {
+		let mut stored = self.snapshots.write().await;
+
+		for snapshot in snapshots {
+			stored.insert(
+				SnapshotKey::new(&snapshot.tenant_id, &snapshot.provider_id),
+				snapshot.clone(),
+			);
+		}
+
+		Ok(())
+	}
+
+	async fn load(&self, tenant_id: &str, provider_id: &str) -> Result<Option<PersistentSnapshot>> {
+		Ok(self.snapshots.read().await.get(&SnapshotKey::new(tenant_id, provider_id)).cloned())
+	}
+
+	async fn delete(&self, tenant_id: &str, provider_id: &str) -> Result<bool> {
+		Ok(self.snapshots.write().await.remove(&SnapshotKey::new(tenant_id, provider_id)).is_some())
+	}
+
+	async fn list(&self) -> Result<Vec<SnapshotKey>> {
+		let mut keys: Vec<SnapshotKey> = self.snapshots.read().await.keys().cloned().collect();
+
+		keys.sort();
+
+		Ok(keys)
+	}
+}
+
+/// In-process refresh lock, useful for tests and for registries sharing one process.
+#[derive(Debug)]
+pub struct MemoryRefreshLock {
+	leases: StdMutex<HashMap<SnapshotKey, Lease>>,
+	lease_duration: Duration,
+	issued: AtomicU64,
+}
+impl MemoryRefreshLock {
+	/// Create a lock granting leases of [`DEFAULT_LEASE_DURATION`].
+	pub fn new() -> Self {
+		Self {
+			leases: StdMutex::new(HashMap::new()),
+			lease_duration: DEFAULT_LEASE_DURATION,
+			issued: AtomicU64::new(0),
+		}
+	}
+
+	/// Grant leases lasting `duration`.
+	pub fn with_lease_duration(mut self, duration: Duration) -> Self {
+		self.lease_duration = duration;
+
+		self
+	}
+
+	fn lock_leases(&self) -> MutexGuard<'_, HashMap<SnapshotKey, Lease>> {
+		self.leases.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+}
+impl Default for MemoryRefreshLock {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+#[async_trait]
+impl RefreshLock for MemoryRefreshLock {
+	async fn try_acquire(&self, tenant_id: &str, provider_id: &str) -> Result<Option<String>> {
+		let now = Instant::now();
+		let mut leases = self.lock_leases();
+		let key = SnapshotKey::new(tenant_id, provider_id);
+
+		if leases.get(&key).is_some_and(|lease| lease.expires_at > now) {
+			return Ok(None);
+		}
+
+		let token = self.issued.fetch_add(1, Ordering::Relaxed).to_string();
+
+		leases.insert(key, Lease { token: token.clone(), expires_at: now + self.lease_duration });
+
+		Ok(Some(token))
+	}
+
+	async fn release(&self, tenant_id: &str, provider_id: &str, token: &str) -> Result<()> {
+		let mut leases = self.lock_leases();
+		let key = SnapshotKey::new(tenant_id, provider_id);
+
+		if leases.get(&key).is_some_and(|lease| lease.token == token) {
+			leases.remove(&key);
+		}
+
+		Ok(())
+	}
+
+	fn lease_duration(&self) -> Duration {
+		self.lease_duration
+	}
+}
+
+#[derive(Debug)]
+struct Lease {
+	token: String,
+	expires_at: Instant,
+}
+
+/// In-process invalidation bus, useful for tests and for registries sharing one process.
+#[derive(Debug)]
+pub struct MemoryInvalidationBus {
+	sender: broadcast::Sender<Invalidation>,
+}
+impl MemoryInvalidationBus {
+	/// Create a bus without subscribers.
+	pub fn new() -> Self {
+		Self { sender: broadcast::channel(INVALIDATION_CAPACITY).0 }
+	}
+}
+impl Default for MemoryInvalidationBus {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+#[async_trait]
+impl InvalidationBus for MemoryInvalidationBus {
+	async fn publish(&self, invalidation: &Invalidation) -> Result<()> {
+		// Send only fails without subscribers, which is not an error.
+		let _ = self.sender.send(invalidation.clone());
+
+		Ok(())
+	}
+
+	fn subscribe(&self) -> broadcast::Receiver<Invalidation> {
+		self.sender.subscribe()
+	}
+}
+
+/// Redis-backed snapshot store keyed as `<namespace>:<tenant>:<provider>`.
+///
+/// Clones share one multiplexed connection, opened on first use and reopened after it breaks.
+/// The store doubles as a [`RefreshLock`], keeping leases under `<namespace>-lock:`.
+#[cfg(feature = "redis")]
+#[derive(Clone, Debug)]
+pub struct RedisSnapshotStore {
+	connection: SharedConnection,
+	namespace: Arc<str>,
+	lease_duration: Duration,
+}
+#[cfg(feature = "redis")]
+impl RedisSnapshotStore {
+	/// Create a store using the default `jwks-cache` namespace.
+	pub fn new(client: redis::Client) -> Self {
+		Self {
+			connection: SharedConnection::new(client),
+			namespace: Arc::from("jwks-cache"),
+			lease_duration: DEFAULT_LEASE_DURATION,
+		}
+	}
+
+	/// Override the key namespace.
+	pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+		self.namespace = Arc::from(namespace.into());
+
+		self
+	}
+
+	/// Grant refresh leases lasting `duration`.
+	pub fn with_lease_duration(mut self, duration: Duration) -> Self {
+		self.lease_duration = duration;
+
+		self
+	}
+
+	fn key(&self, tenant: &str, provider: &str) -> String {
+		format!("{}:{tenant}:{provider}", self.namespace)
+	}
+
+	// Kept outside `<namespace>:*` so `list` never reads a lease as a snapshot.
+	fn lease_key(&self, tenant: &str, provider: &str) -> String {
+		format!("{}-lock:{tenant}:{provider}", self.namespace)
+	}
+}
+#[cfg(feature = "redis")]
+#[async_trait]
+impl SnapshotStore for RedisSnapshotStore {
+	async fn persist(&self, snapshots: &[PersistentSnapshot]) -> Result<()> {
+		if snapshots.is_empty() {
+			return Ok(());
+		}
+
+		let mut pipeline = redis::pipe();
+
+		for snapshot in snapshots {
+			let key = self.key(&snapshot.tenant_id, &snapshot.provider_id);
+			let payload = serde_json::to_string(snapshot)?;
+			let ttl = (snapshot.expires_at - clock::utc_now())
+				.to_std()
+				.unwrap_or_else(|_| Duration::from_secs(1));
+			let ttl_secs = ttl.as_secs().max(1);
+
+			pipeline.set_ex(key, payload, ttl_secs).ignore();
+		}
+
+		let mut conn = self.connection.get().await?;
+
+		pipeline.exec_async(&mut conn).await.map_err(|err| self.connection.discard_broken(err))
+	}
+
+	async fn load(&self, tenant_id: &str, provider_id: &str) -> Result<Option<PersistentSnapshot>> {
+		let mut conn = self.connection.get().await?;
+		let key = self.key(tenant_id, provider_id);
+		let value: Option<String> =
+			conn.get(key).await.map_err(|err| self.connection.discard_broken(err))?;
+
+		if let Some(json) = value {
+			let snapshot = PersistentSnapshot::from_json(&json)?;
+
+			Ok(Some(snapshot))
+		} else {
+			Ok(None)
+		}
+	}
+
+	async fn delete(&self, tenant_id: &str, provider_id: &str) -> Result<bool> {
+		let mut conn = self.connection.get().await?;
+		let removed: u64 = conn
+			.del(self.key(tenant_id, provider_id))
+			.await
+			.map_err(|err| self.connection.discard_broken(err))?;
+
+		Ok(removed > 0)
+	}
+
+	async fn list(&self) -> Result<Vec<SnapshotKey>> {
+		let mut conn = self.connection.get().await?;
+		let pattern = format!("{}:*", self.namespace);
+		let mut redis_keys = Vec::new();
+
+		{
+			let mut iter = conn
+				.scan_match::<_, String>(pattern)
+				.await
+				.map_err(|err| self.connection.discard_broken(err))?;
+
+			while let Some(key) = iter.next_item().await {
+				redis_keys.push(key);
+			}
+		}
+
+		// Identifiers may contain `:`, so read them from the payload instead of the key.
+		let mut keys = Vec::with_capacity(redis_keys.len());
+
+		for redis_key in redis_keys {
+			let value: Option<String> =
+				conn.get(redis_key).await.map_err(|err| self.connection.discard_broken(err))?;
+
+			if let Some(json) = value {
+				let snapshot = PersistentSnapshot::from_json(&json)?;
+
+				keys.push(SnapshotKey::new(snapshot.tenant_id, snapshot.provider_id));
+			}
+		}
+
+		keys.sort();
+
+		Ok(keys)
+	}
+}
+#[cfg(feature = "redis")]
+#[async_trait]
+impl RefreshLock for RedisSnapshotStore {
+	async fn try_acquire(&self, tenant_id: &str, provider_id: &str) -> Result<Option<String>> {
+		let token = jitter::unique_token();
+		let mut conn = self.connection.get().await?;
+		let granted: Option<String> = redis::cmd("SET")
+			.arg(self.lease_key(tenant_id, provider_id))
+			.arg(&token)
+			.arg("NX")
+			.arg("PX")
+			.arg(self.lease_duration.as_millis().max(1) as u64)
+			.query_async(&mut conn)
+			.await
+			.map_err(|err| self.connection.discard_broken(err))?;
+
+		Ok(granted.map(|_| token))
+	}
+
+	async fn release(&self, tenant_id: &str, provider_id: &str, token: &str) -> Result<()> {
+		// Compare and delete atomically so an expired lease taken over by another replica stays.
+		const RELEASE: &str = "if redis.call('get', KEYS[1]) == ARGV[1] then \
+			return redis.call('del', KEYS[1]) else return 0 end";
+
+		let mut conn = self.connection.get().await?;
+
+		redis::cmd("EVAL")
+			.arg(RELEASE)
+			.arg(1)
+			.arg(self.lease_key(tenant_id, provider_id))
+			.arg(token)
+			.exec_async(&mut conn)
+			.await
+			.map_err(|err| self.connection.discard_broken(err))
+	}
+
+	fn lease_duration(&self) -> Duration {
+		self.lease_duration
+	}
+}
+
+/// Invalidation bus over a Redis pub/sub channel.
+///
+/// A background task holds the subscription until the bus is dropped and resubscribes after the
+/// connection drops. Invalidations published while it is disconnected are not delivered.
+#[cfg(feature = "redis")]
+#[derive(Debug)]
+pub struct RedisInvalidationBus {
+	connection: SharedConnection,
+	channel: String,
+	sender: broadcast::Sender<Invalidation>,
+	listener: AbortHandle,
+}
+#[cfg(feature = "redis")]
+impl RedisInvalidationBus {
+	/// Subscribe to `channel`, failing when Redis is unreachable.
+	pub async fn connect(client: redis::Client, channel: impl Into<String>) -> Result<Self> {
+		let channel = channel.into();
+		let pubsub = subscribe_channel(&client, &channel).await?;
+		let sender = broadcast::channel(INVALIDATION_CAPACITY).0;
+		let listener =
+			tokio::spawn(listen(client.clone(), channel.clone(), pubsub, sender.clone()))
+				.abort_handle();
+
+		Ok(Self { connection: SharedConnection::new(client), channel, sender, listener })
+	}
+}
+#[cfg(feature = "redis")]
+impl Drop for RedisInvalidationBus {
+	fn drop(&mut self) {
+		self.listener.abort();
+	}
+}
+#[cfg(feature = "redis")]
+#[async_trait]
+impl InvalidationBus for RedisInvalidationBus {
+	async fn publish(&self, invalidation: &Invalidation) -> Result<()> {
+		let payload = serde_json::to_string(invalidation)?;
+		let mut conn = self.connection.get().await?;
+
+		conn.publish::<_, _, ()>(&self.channel, payload)
+			.await
+			.map_err(|err| self.connection.discard_broken(err))
+	}
+
+	fn subscribe(&self) -> broadcast::Receiver<Invalidation> {
+		self.sender.subscribe()
+	}
+}
+
+// Multiplexed connection shared by clones, opened on first use and reopened after it breaks.
+#[cfg(feature = "redis")]
+#[derive(Clone, Debug)]
+struct SharedConnection {
+	client: redis::Client,
+	connection: Arc<StdMutex<Option<MultiplexedConnection>>>,
+}
+#[cfg(feature = "redis")]
+impl SharedConnection {
+	fn new(client: redis::Client) -> Self {
+		Self { client, connection: Arc::new(StdMutex::new(None)) }
+	}
+
+	async fn get(&self) -> Result<MultiplexedConnection> {
+		let cached = self.lock_connection().clone();
+
+		if let Some(connection) = cached {
+			return Ok(connection);
+		}
+
+		let connection = self.client.get_multiplexed_async_connection().await?;
+
+		// Keep whichever connection a concurrent caller stored first.
+		Ok(self.lock_connection().get_or_insert(connection).clone())
+	}
+
+	fn lock_connection(&self) -> MutexGuard<'_, Option<MultiplexedConnection>> {
+		self.connection.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+
+	// Forget a broken shared connection so the next call reconnects.
+	fn discard_broken(&self, err: RedisError) -> Error {
+		if err.is_connection_dropped() || err.is_unrecoverable_error() {
+			*self.lock_connection() = None;
+		}
+
+		err.into()
+	}
+}
+
+#[cfg(feature = "redis")]
+async fn subscribe_channel(client: &redis::Client, channel: &str) -> Result<PubSub> {
+	let mut pubsub = client.get_async_pubsub().await?;
+
+	pubsub.subscribe(channel).await?;
+
+	Ok(pubsub)
+}
+
+// Forward invalidations from `pubsub` to `sender`, resubscribing whenever the connection drops.
+#[cfg(feature = "redis")]
+async fn listen(
+	client: redis::Client,
+	channel: String,
+	mut pubsub: PubSub,
+	sender: broadcast::Sender<Invalidation>,
+) {
+	loop {
+		let mut messages = pubsub.into_on_message();
+
+		while let Some(message) = messages.next().await {
+			let invalidation = message
+				.get_payload::<String>()
+				.map_err(Error::from)
+				.and_then(|payload| Ok(serde_json::from_str::<Invalidation>(&payload)?));
+
+			match invalidation {
+				// Send only fails without subscribers, which is not an error.
+				Ok(invalidation) => {
+					let _ = sender.send(invalidation);
+				},
+				Err(err) =>
+					tracing::warn!(%channel, error = %err, "ignoring malformed invalidation"),
+			}
+		}
+
+		tracing::warn!(%channel, "invalidation subscription lost; resubscribing");
+
+		pubsub = loop {
+			time::sleep(RESUBSCRIBE_DELAY).await;
+
+			match subscribe_channel(&client, &channel).await {
+				Ok(pubsub) => break pubsub,
+				Err(err) => tracing::warn!(%channel, error = %err, "failed to resubscribe"),
+			}
+		};
+	}
+}
@@ -0,0 +1,124 @@
+//! RFC 7807 problem-details conversion for crate errors.
+
+// crates.io
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+// self
+use crate::_prelude::*;
+
+/// Media type for serialised problem-details bodies.
+pub const CONTENT_TYPE: &str = "application/problem+json";
+
+/// RFC 7807 problem-details body describing a crate [`Error`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProblemDetails {
+	/// URI reference identifying the problem type.
+	#[serde(rename = "type")]
+	pub type_uri: String,
+	/// Short, human-readable summary of the problem type.
+	pub title: String,
+	/// HTTP status code suggested for the response.
+	pub status: u16,
+	/// Human-readable explanation specific to this occurrence.
+	pub detail: String,
+	/// URI reference identifying the specific occurrence, when known.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub instance: Option<String>,
+	/// Stable machine-readable error code, matching [`Error::code`].
+	pub code: String,
+}
+impl ProblemDetails {
+	/// Attach an occurrence URI such as the request path.
+	pub fn with_instance(mut self, instance: impl Into<String>) -> Self {
+		self.instance = Some(instance.into());
+
+		self
+	}
+
+	/// Suggested HTTP status as a [`StatusCode`].
+	pub fn status_code(&self) -> StatusCode {
+		StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+	}
+}
+impl From<&Error> for ProblemDetails {
+	fn from(error: &Error) -> Self {
+		let (status, title) = classify(error);
+		let code = error.code();
+
+		Self {
+			type_uri: format!("urn:jwks-cache:error:{}", code.to_ascii_lowercase()),
+			title: title.into(),
+			status: status.as_u16(),
+			detail: error.to_string(),
+			instance: None,
+			code: code.into(),
+		}
+	}
+}
+impl From<Error> for ProblemDetails {
+	fn from(error: Error) -> Self {
+		Self::from(&error)
+	}
+}
+
+fn classify(error: &Error) -> (StatusCode, &'static str) {
+	match error {
+		Error::NotRegistered { .. } => (StatusCode::NOT_FOUND, "Provider not registered"),
+		Error::KeyNotFound { .. } => (StatusCode::NOT_FOUND, "Signing key not found"),
+		Error::KeyNotActive { .. } => (StatusCode::NOT_FOUND, "Signing key not yet active"),
+		Error::InvalidToken(_) => (StatusCode::UNAUTHORIZED, "Invalid token"),
+		Error::Validation { .. } => (StatusCode::BAD_REQUEST, "Invalid configuration"),
+		Error::Url(_) => (StatusCode::BAD_REQUEST, "Invalid URL"),
+		Error::Security(_) => (StatusCode::FORBIDDEN, "Security policy violation"),
+		Error::Reqwest(err) if err.is_timeout() =>
+			(StatusCode::GATEWAY_TIMEOUT, "Upstream request timed out"),
+		Error::Reqwest(_) => (StatusCode::BAD_GATEWAY, "Upstream unavailable"),
+		Error::HttpStatus { .. } => (StatusCode::BAD_GATEWAY, "Upstream returned an error status"),
+		Error::KeySetReplaced { .. } =>
+			(StatusCode::BAD_GATEWAY, "Upstream replaced every signing key"),
+		Error::RedirectPolicy { .. } =>
+			(StatusCode::BAD_GATEWAY, "Upstream redirect rejected by policy"),
+		Error::Serde(_) | Error::Jsonwebtoken(_) | Error::InvalidJwks(_) =>
+			(StatusCode::BAD_GATEWAY, "Upstream returned an invalid payload"),
+		Error::Provider { source, .. } => classify(source),
+		Error::Shared(err) => classify(err),
+		Error::Cache(_) => (StatusCode::SERVICE_UNAVAILABLE, "JWKS unavailable"),
+		Error::CircuitOpen { .. } => (StatusCode::SERVICE_UNAVAILABLE, "Upstream circuit open"),
+		Error::ResolveTimeout { .. } =>
+			(StatusCode::SERVICE_UNAVAILABLE, "Timed out waiting for signing keys"),
+		#[cfg(feature = "redis")]
+		Error::Redis(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Persistence failure"),
+		#[cfg(feature = "toml")]
+		Error::Toml(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Invalid configuration"),
+		#[cfg(feature = "yaml")]
+		Error::Yaml(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Invalid configuration"),
+		Error::Io(_) | Error::SystemTime(_) | Error::Http(_) | Error::Metrics(_) =>
+			(StatusCode::INTERNAL_SERVER_ERROR, "Internal error"),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn not_registered_maps_to_not_found() {
+		let error = Error::NotRegistered { tenant: "tenant".into(), provider: "idp".into() };
+		let problem = ProblemDetails::from(&error).with_instance("/tenants/tenant/idp");
+		let body = serde_json::to_value(&problem).expect("serialize");
+
+		assert_eq!(problem.status_code(), StatusCode::NOT_FOUND);
+		assert_eq!(body["type"], "urn:jwks-cache:error:jwks_cache_not_registered");
+		assert_eq!(body["code"], "JWKS_CACHE_NOT_REGISTERED");
+		assert_eq!(body["instance"], "/tenants/tenant/idp");
+	}
+
+	#[test]
+	fn instance_is_omitted_when_absent() {
+		let problem = ProblemDetails::from(Error::Security("blocked".into()));
+		let body = serde_json::to_value(&problem).expect("serialize");
+
+		assert_eq!(body["status"], 403);
+		assert!(body.get("instance").is_none());
+	}
+}
@@ -2,30 +2,81 @@
 //!
 //! The registry owns tenant registrations, cache metadata, and optional persistence wiring.
 
+mod export;
+mod presets;
+mod reload;
+mod standalone;
+pub use presets::{AZURE_AD_HOST, AZURE_AD_MIN_TTL, COGNITO_MIN_TTL};
+pub use reload::{RegistrationFile, RegistryConfigFile, RegistrySettings, ReloadReport};
+pub use standalone::{JwksCache, JwksCacheOptions, STANDALONE_ID};
+pub(crate) use export::{ChunkEncoding, StatusDocument};
+
 // std
-use std::{cell::RefCell, collections::HashMap, mem};
+#[cfg(feature = "metrics")] use std::collections::BTreeMap;
+use std::{
+	collections::{HashMap, HashSet},
+	future::Future,
+	mem,
+	path::{self, PathBuf},
+	pin,
+	sync::OnceLock,
+};
 // crates.io
-use jsonwebtoken::jwk::JwkSet;
-use rand::{Rng, SeedableRng, rngs::SmallRng};
-#[cfg(feature = "redis")] use redis::AsyncCommands;
-use serde::{Deserialize, Serialize};
-use tokio::sync::RwLock;
+use base64::prelude::*;
+use dashmap::DashMap;
+use futures_util::{Stream, StreamExt, stream};
+use http::{HeaderName, HeaderValue, StatusCode};
+use jsonwebtoken::{
+	DecodingKey, TokenData, Validation,
+	jwk::{AlgorithmParameters, Jwk, JwkSet},
+};
+use reqwest::{Client, dns::Resolve};
+use rustls::pki_types::CertificateDer;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tokio::{
+	io::{AsyncWrite, AsyncWriteExt},
+	sync::{
+		Mutex,
+		broadcast::{self, error::RecvError},
+		watch,
+	},
+	task::{AbortHandle, JoinSet},
+};
 use url::Url;
 // self
-#[cfg(feature = "metrics")] use crate::metrics::{ProviderMetrics, ProviderMetricsSnapshot};
+#[cfg(feature = "zstd")] use crate::cache::storage::ParsedJwksCache;
+#[cfg(feature = "metrics")]
+use crate::metrics::{self, ProviderMetrics, ProviderMetricsSnapshot};
+#[cfg(feature = "redis")] use crate::persistence::RedisSnapshotStore;
 use crate::{
 	_prelude::*,
 	cache::{
-		manager::{CacheManager, CacheSnapshot},
+		activation::{KeyActivation, KeyActivations},
+		breaker::CircuitState,
+		history::{DEFAULT_STATUS_HISTORY_CAPACITY, StatusSample},
+		manager::{CacheManager, CacheSnapshot, ResolveOptions},
+		residency::ResidentSet,
+		rotation::{KeyRotation, KeyRotationHook},
+		scheduler::RefreshScheduler,
 		state::CacheState,
 	},
-	security::{self, SpkiFingerprint},
+	clock,
+	config::{ByteSize, TtlSeconds},
+	events::{CacheEvent, CacheEventKind, DEFAULT_EVENT_CAPACITY},
+	http::{
+		auth::AuthProvider,
+		capture::{self, CapturedExchange, DEFAULT_EXCHANGE_CAPTURE_CAPACITY},
+		client as http_client,
+		discovery::{self, OpenIdConfiguration},
+		rate_limit::HostRateLimiter,
+		resolve::SharedResolver,
+	},
+	jitter,
+	persistence::{Invalidation, InvalidationBus, InvalidationTarget, RefreshLock, SnapshotStore},
+	security::{self, ClientIdentity, HostAllowlist, SpkiFingerprint},
+	tasks::TaskSet,
 };
 
-thread_local! {
-	static SMALL_RNG: RefCell<SmallRng> = RefCell::new(SmallRng::from_rng(&mut rand::rng()));
-}
-
 /// Default refresh lead time before TTL expiry.
 pub const DEFAULT_REFRESH_EARLY: Duration = Duration::from_secs(30);
 /// Default stale-while-error window.
@@ -36,10 +87,163 @@ pub const MIN_TTL_FLOOR: Duration = Duration::from_secs(30);
 pub const DEFAULT_MAX_TTL: Duration = Duration::from_secs(60 * 60 * 24);
 /// Default size guard (1 MiB).
 pub const DEFAULT_MAX_RESPONSE_BYTES: u64 = 1_048_576;
+/// Default window during which an unknown `kid` is not revalidated again.
+pub const DEFAULT_MISSING_KID_TTL: Duration = Duration::from_secs(30);
 /// Default prefetch jitter.
 pub const DEFAULT_PREFETCH_JITTER: Duration = Duration::from_secs(5);
+/// Default drift between the monotonic and wall clocks treated as a clock jump.
+pub const DEFAULT_CLOCK_JUMP_THRESHOLD: Duration = Duration::from_secs(60);
+/// Number of providers listed in [`AggregateStatus::top_failing`].
+pub const AGGREGATE_TOP_FAILING: usize = 5;
 /// Maximum redirect depth.
 pub const MAX_REDIRECTS: u8 = 10;
+/// Default minimum TTL applied to OpenID discovery documents.
+pub const DEFAULT_DISCOVERY_MIN_TTL: Duration = Duration::from_secs(60 * 5);
+/// Default maximum TTL applied to OpenID discovery documents.
+pub const DEFAULT_DISCOVERY_MAX_TTL: Duration = Duration::from_secs(60 * 60 * 24);
+/// Default time [`Registry::shutdown_gracefully`] waits for in-flight refreshes.
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+/// Default number of providers fetched at once by [`Registry::warm_up`].
+pub const DEFAULT_WARM_UP_CONCURRENCY: usize = 16;
+/// Default time a pooled connection may stay idle before it is closed.
+pub const DEFAULT_POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+/// Default idle time before TCP keepalive probes start.
+pub const DEFAULT_TCP_KEEPALIVE: Duration = Duration::from_secs(15);
+/// `jwks_url` given to inline [`StaticJwks`] registrations built by
+/// [`IdentityProviderRegistration::from_static`].
+pub const STATIC_INLINE_URL: &str = "static:inline";
+/// Format version written to new [`PersistentSnapshot`]s.
+///
+/// Snapshots persisted before the format was versioned read as version `0`.
+pub const SNAPSHOT_VERSION: u32 = 1;
+/// Request headers managed by the cache or transport, which `extra_headers` may not set.
+pub const FORBIDDEN_EXTRA_HEADERS: [&str; 12] = [
+	"host",
+	"connection",
+	"keep-alive",
+	"proxy-connection",
+	"transfer-encoding",
+	"te",
+	"trailer",
+	"upgrade",
+	"content-length",
+	"accept-encoding",
+	"if-none-match",
+	"if-modified-since",
+];
+
+/// How the registry-wide domain allowlist gates provider hosts.
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AllowlistMode {
+	/// Accept any host; the registry allowlist is neither enforced nor inherited.
+	AllowAll,
+	/// Reject every host regardless of the configured allowlist.
+	DenyAll,
+	/// Accept only hosts within the registry allowlist; an empty list rejects every host.
+	List,
+}
+
+/// When [`Registry::register`] restores a provider's persisted snapshot.
+#[derive(Clone, Debug, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestorePolicy {
+	/// Restore before `register` returns, so the first resolve already sees the snapshot.
+	///
+	/// A failed restore is logged rather than returned, since the provider is already live.
+	#[default]
+	Inline,
+	/// Restore in a background task; [`Registry::wait_ready`] waits for it to finish.
+	///
+	/// Keeps bulk registration fast when the store is slow. Resolves racing the restore fetch
+	/// upstream, and the snapshot never replaces keys such a fetch already cached.
+	Background,
+	/// Never restore during registration; [`Registry::restore_from_persistence`] still can.
+	Skip,
+}
+
+/// Options for [`Registry::shutdown_gracefully`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShutdownOptions {
+	/// How long to wait for in-flight refreshes before aborting them.
+	pub drain_timeout: Duration,
+	/// Persist every snapshot after draining when a snapshot store is configured.
+	pub persist: bool,
+}
+impl ShutdownOptions {
+	/// Wait at most `timeout` for in-flight refreshes.
+	pub fn with_drain_timeout(mut self, timeout: Duration) -> Self {
+		self.drain_timeout = timeout;
+
+		self
+	}
+
+	/// Enable or disable persisting snapshots after draining.
+	pub fn with_persist(mut self, persist: bool) -> Self {
+		self.persist = persist;
+
+		self
+	}
+}
+impl Default for ShutdownOptions {
+	fn default() -> Self {
+		Self { drain_timeout: DEFAULT_DRAIN_TIMEOUT, persist: true }
+	}
+}
+
+/// Outcome of [`Registry::shutdown_gracefully`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShutdownReport {
+	/// Background refreshes that finished within the drain timeout.
+	pub drained: usize,
+	/// Background tasks aborted: keepalive and recovery probes, and refreshes past the timeout.
+	pub aborted: usize,
+	/// Whether snapshots were written to the configured store.
+	pub persisted: bool,
+}
+
+/// Outcome of [`Registry::warm_up`].
+///
+/// Providers are listed in `(tenant_id, provider_id)` order.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WarmUpReport {
+	/// Providers holding usable keys, as `(tenant_id, provider_id)` pairs.
+	pub ready: Vec<(String, String)>,
+	/// Providers whose keys could not be fetched.
+	pub failed: Vec<WarmUpFailure>,
+}
+impl WarmUpReport {
+	/// Whether every provider holds usable keys.
+	pub fn all_ready(&self) -> bool {
+		self.failed.is_empty()
+	}
+}
+
+/// Provider reported in [`WarmUpReport::failed`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WarmUpFailure {
+	/// Tenant identifier that owns the provider.
+	pub tenant_id: String,
+	/// Provider identifier unique within the tenant.
+	pub provider_id: String,
+	/// Error returned by the fetch.
+	pub error: String,
+}
+
+/// JSON layout produced by [`Registry::write_statuses_json`] and [`ProviderStatus::to_json`].
+#[derive(Clone, Debug, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusFormat {
+	/// Statuses as serialised by serde, in a bare array with PascalCase `state` values.
+	#[default]
+	Legacy,
+	/// Versioned envelope `{"format_version": 1, "statuses": [...]}` with snake_case `state`
+	/// values.
+	///
+	/// Field names, enum spellings, and label ordering stay fixed for this version; incompatible
+	/// changes ship as a new variant.
+	V1,
+}
 
 /// Supported jitter strategies for retry policies.
 #[derive(Clone, Debug, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
@@ -55,31 +259,66 @@ pub enum JitterStrategy {
 }
 
 /// Public representation of provider lifecycle state.
+///
+/// Serialises as PascalCase for compatibility; [`StatusFormat::V1`] uses [`Self::as_str`]
+/// instead. Both spellings deserialise.
 #[derive(Clone, Debug, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub enum ProviderState {
 	/// No JWKS payload has been cached yet.
+	#[serde(alias = "empty")]
 	Empty,
 	/// Initial fetch operation is currently running.
+	#[serde(alias = "loading")]
 	Loading,
 	/// Fresh JWKS payload is available for requests.
+	#[serde(alias = "ready")]
 	Ready,
 	/// Cache is serving while a refresh is in progress.
+	#[serde(alias = "refreshing")]
 	Refreshing,
 }
+impl ProviderState {
+	/// Stable snake_case name of the state.
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::Empty => "empty",
+			Self::Loading => "loading",
+			Self::Ready => "ready",
+			Self::Refreshing => "refreshing",
+		}
+	}
+}
+impl From<&CacheState> for ProviderState {
+	fn from(state: &CacheState) -> Self {
+		match state {
+			CacheState::Empty => Self::Empty,
+			CacheState::Loading => Self::Loading,
+			CacheState::Ready(_) => Self::Ready,
+			CacheState::Refreshing(_) => Self::Refreshing,
+		}
+	}
+}
 
 /// Retry configuration for HTTP fetch operations.
+///
+/// Duration fields serialise as `humantime` strings such as `"250ms"` or `"24h"`, and also accept
+/// integer seconds or serde's `{ secs, nanos }` form; see [`crate::config::duration`].
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RetryPolicy {
 	/// Maximum number of retry attempts to perform after the initial request.
 	pub max_retries: u32,
 	/// Timeout applied to each individual HTTP attempt.
+	#[serde(with = "crate::config::duration")]
 	pub attempt_timeout: Duration,
 	/// Initial delay before retrying after a failure.
+	#[serde(with = "crate::config::duration")]
 	pub initial_backoff: Duration,
 	/// Upper bound applied to exponential backoff growth.
+	#[serde(with = "crate::config::duration")]
 	pub max_backoff: Duration,
 	/// Overall deadline that bounds the entire retry sequence.
+	#[serde(with = "crate::config::duration")]
 	pub deadline: Duration,
 	/// Strategy used to randomize the computed backoff.
 	#[serde(default)]
@@ -120,29 +359,36 @@ impl RetryPolicy {
 		self.default_backoff(attempt)
 	}
 
-	/// Default exponential backoff with jitter following the AWS architecture guidance.
-	pub fn default_backoff(&self, attempt: u32) -> Duration {
+	/// Compute backoff for a retry attempt, using `seed` for deterministic jitter.
+	///
+	/// The seed only influences the result when the `rand` feature is disabled.
+	pub fn compute_backoff_with_seed(&self, attempt: u32, seed: u64) -> Duration {
 		let exponent = attempt.min(32);
 		let base = self.initial_backoff.mul_f64(2f64.powi(exponent as i32));
 		let bounded = base.min(self.max_backoff).max(self.initial_backoff);
 
-		self.apply_jitter(bounded, attempt)
+		self.apply_jitter(bounded, attempt, jitter::mix(seed, u64::from(attempt)))
 	}
 
-	fn apply_jitter(&self, bounded: Duration, attempt: u32) -> Duration {
+	/// Default exponential backoff with jitter following the AWS architecture guidance.
+	pub fn default_backoff(&self, attempt: u32) -> Duration {
+		self.compute_backoff_with_seed(attempt, 0)
+	}
+
+	fn apply_jitter(&self, bounded: Duration, attempt: u32, seed: u64) -> Duration {
 		match self.jitter {
 			JitterStrategy::None => bounded,
 			JitterStrategy::Full => {
 				let lower = bounded.mul_f64(0.8).max(self.initial_backoff);
 				let upper = bounded.min(self.max_backoff);
 
-				random_within(lower, upper)
+				jitter::within(lower, upper, seed)
 			},
 			JitterStrategy::Decorrelated => {
 				let prev = if attempt == 0 { self.initial_backoff } else { bounded };
 				let ceiling = self.max_backoff.min(prev.mul_f64(3.0));
 
-				random_within(self.initial_backoff, ceiling.max(self.initial_backoff))
+				jitter::within(self.initial_backoff, ceiling.max(self.initial_backoff), seed)
 			},
 		}
 	}
@@ -160,238 +406,142 @@ impl Default for RetryPolicy {
 	}
 }
 
-/// Registration describing how to fetch and maintain JWKS for a provider.
+/// Circuit breaker configuration for upstream JWKS fetches.
+///
+/// Failures are counted per refresh, after the [`RetryPolicy`] has been exhausted.
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct IdentityProviderRegistration {
-	/// Tenant identifier used for metrics, caching, and persistence scope.
-	pub tenant_id: String,
-	/// Provider identifier unique within the tenant.
-	pub provider_id: String,
-	/// URL of the JWKS endpoint to fetch signing keys from.
-	pub jwks_url: Url,
-	/// Whether HTTPS is required for JWKS retrieval.
-	#[serde(default = "default_true")]
-	pub require_https: bool,
-	/// Optional allowlist of domains permitted for redirects.
-	#[serde(default, deserialize_with = "crate::security::deserialize_allowed_domains")]
-	pub allowed_domains: Vec<String>,
-	/// Lead time before expiry to trigger proactive refresh.
-	#[serde(default = "default_refresh_early")]
-	pub refresh_early: Duration,
-	/// Duration to continue serving stale data when refresh fails.
-	#[serde(default = "default_stale_while_error")]
-	pub stale_while_error: Duration,
-	/// Minimum TTL applied to upstream responses.
-	#[serde(default = "default_min_ttl")]
-	pub min_ttl: Duration,
-	/// Maximum TTL applied to upstream responses.
-	#[serde(default = "default_max_ttl")]
-	pub max_ttl: Duration,
-	/// Maximum size allowed for JWKS payloads in bytes.
-	#[serde(default = "default_max_response_bytes")]
-	pub max_response_bytes: u64,
-	/// TTL applied when persisting negative cache outcomes.
-	#[serde(default)]
-	pub negative_cache_ttl: Duration,
-	/// Maximum number of redirects to follow during fetch.
-	#[serde(default = "default_max_redirects")]
-	pub max_redirects: u8,
-	/// Optional SPKI fingerprints used for TLS pinning.
-	#[serde(default)]
-	pub pinned_spki: Vec<SpkiFingerprint>,
-	/// Random jitter applied when scheduling proactive refreshes.
-	#[serde(default = "default_prefetch_jitter")]
-	pub prefetch_jitter: Duration,
-	/// Retry policy configuration for JWKS fetch attempts.
-	#[serde(default)]
-	pub retry_policy: RetryPolicy,
+pub struct CircuitBreakerPolicy {
+	/// Consecutive failed refreshes that open the breaker.
+	pub failure_threshold: u32,
+	/// How long the breaker stays open before admitting trial fetches.
+	#[serde(with = "crate::config::duration")]
+	pub open_duration: Duration,
+	/// Successful trial fetches required to close a half-open breaker.
+	pub half_open_probes: u32,
 }
-impl IdentityProviderRegistration {
-	/// Construct a new registration with default cache settings.
-	pub fn new(
-		tenant_id: impl Into<String>,
-		provider_id: impl Into<String>,
-		jwks_url: impl AsRef<str>,
-	) -> Result<Self> {
-		let jwks_url = Url::parse(jwks_url.as_ref())?;
-
-		Ok(Self {
-			tenant_id: tenant_id.into(),
-			provider_id: provider_id.into(),
-			jwks_url,
-			require_https: true,
-			allowed_domains: Vec::new(),
-			refresh_early: DEFAULT_REFRESH_EARLY,
-			stale_while_error: DEFAULT_STALE_WHILE_ERROR,
-			min_ttl: MIN_TTL_FLOOR,
-			max_ttl: DEFAULT_MAX_TTL,
-			max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
-			negative_cache_ttl: Duration::ZERO,
-			max_redirects: 3,
-			pinned_spki: Vec::new(),
-			prefetch_jitter: DEFAULT_PREFETCH_JITTER,
-			retry_policy: RetryPolicy::default(),
-		})
-	}
-
-	/// Canonicalise the domain allowlist in-place.
-	pub fn normalize_allowed_domains(&mut self) {
-		let domains = mem::take(&mut self.allowed_domains);
-
-		self.allowed_domains = security::normalize_allowlist(domains);
-	}
-
-	/// Set HTTPS requirement to the desired value.
-	pub fn with_require_https(mut self, require_https: bool) -> Self {
-		self.require_https = require_https;
-
-		self
-	}
-
-	/// Validate the registration against the documented constraints.
+impl CircuitBreakerPolicy {
+	/// Validate invariants for circuit breaker configuration.
 	pub fn validate(&self) -> Result<()> {
-		validate_tenant_id(&self.tenant_id)?;
-		validate_provider_id(&self.provider_id)?;
-
-		if self.require_https {
-			security::enforce_https(&self.jwks_url)?;
-		}
-
-		if let Some(host) = self.jwks_url.host_str() {
-			if !security::host_is_allowed(host, &self.allowed_domains) {
-				return Err(Error::Validation {
-					field: "jwks_url",
-					reason: "Host is not within the allowed_domains allowlist.".into(),
-				});
-			}
-		} else {
+		if self.failure_threshold == 0 {
 			return Err(Error::Validation {
-				field: "jwks_url",
-				reason: "Must include a host component.".into(),
+				field: "circuit_breaker.failure_threshold",
+				reason: "Must be greater than zero.".into(),
 			});
 		}
-
-		if self.refresh_early < Duration::from_secs(1) {
+		if self.open_duration < Duration::from_secs(1) {
 			return Err(Error::Validation {
-				field: "refresh_early",
+				field: "circuit_breaker.open_duration",
 				reason: "Must be at least 1 second.".into(),
 			});
 		}
-		if self.min_ttl < MIN_TTL_FLOOR {
-			return Err(Error::Validation {
-				field: "min_ttl",
-				reason: format!("Must be at least {:?}.", MIN_TTL_FLOOR),
-			});
-		}
-		if self.max_ttl < self.min_ttl {
-			return Err(Error::Validation {
-				field: "max_ttl",
-				reason: "Must be greater than or equal to min_ttl.".into(),
-			});
-		}
-		if self.refresh_early >= self.max_ttl {
-			return Err(Error::Validation {
-				field: "refresh_early",
-				reason: "Must be less than max_ttl.".into(),
-			});
-		}
-		if self.max_response_bytes == 0 {
+		if self.half_open_probes == 0 {
 			return Err(Error::Validation {
-				field: "max_response_bytes",
+				field: "circuit_breaker.half_open_probes",
 				reason: "Must be greater than zero.".into(),
 			});
 		}
-		if self.max_redirects > MAX_REDIRECTS {
+
+		Ok(())
+	}
+}
+impl Default for CircuitBreakerPolicy {
+	fn default() -> Self {
+		Self { failure_threshold: 5, open_duration: Duration::from_secs(30), half_open_probes: 1 }
+	}
+}
+
+/// Background recovery probing for providers left without keys after failed fetches.
+///
+/// Probes run only while the cache entry is empty, at most one at a time per provider.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecoveryPolicy {
+	/// Delay before the first probe.
+	#[serde(with = "crate::config::duration")]
+	pub initial_delay: Duration,
+	/// Upper bound applied to exponential delay growth.
+	#[serde(with = "crate::config::duration")]
+	pub max_delay: Duration,
+}
+impl RecoveryPolicy {
+	/// Validate invariants for recovery probe configuration.
+	pub fn validate(&self) -> Result<()> {
+		if self.initial_delay.is_zero() {
 			return Err(Error::Validation {
-				field: "max_redirects",
-				reason: format!("Must be less than or equal to {}.", MAX_REDIRECTS),
+				field: "recovery_probe.initial_delay",
+				reason: "Must be greater than zero.".into(),
 			});
 		}
-		if !self.negative_cache_ttl.is_zero() && self.negative_cache_ttl < Duration::from_secs(1) {
+		if self.max_delay < self.initial_delay {
 			return Err(Error::Validation {
-				field: "negative_cache_ttl",
-				reason: "Must be zero or at least one second.".into(),
+				field: "recovery_probe.max_delay",
+				reason: "Must be greater than or equal to initial_delay.".into(),
 			});
 		}
 
-		self.retry_policy.validate()?;
-
-		for domain in &self.allowed_domains {
-			if let Some(canonical) = security::canonicalize_dns_name(domain) {
-				if canonical != *domain {
-					return Err(Error::Validation {
-						field: "allowed_domains",
-						reason: "Entries must be canonical hostnames (lowercase, no trailing dot)."
-							.into(),
-					});
-				}
-			} else {
-				return Err(Error::Validation {
-					field: "allowed_domains",
-					reason: "Entries must be non-empty hostnames.".into(),
-				});
-			}
-		}
-
 		Ok(())
 	}
 }
+impl Default for RecoveryPolicy {
+	fn default() -> Self {
+		Self { initial_delay: Duration::from_secs(5), max_delay: Duration::from_secs(300) }
+	}
+}
 
-/// Snapshot of cache payload persisted to external storage.
+/// Registry-wide token bucket applied to upstream requests per host.
+///
+/// Shields identity providers shared by many registrations from request bursts, such as a mass
+/// cold start or a wave of refreshes scheduled together.
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct PersistentSnapshot {
-	/// Tenant identifier associated with the snapshot.
-	pub tenant_id: String,
-	/// Provider identifier within the tenant scope.
-	pub provider_id: String,
-	/// Serialized JWKS payload captured from the cache.
-	pub jwks_json: String,
-	/// Entity tag returned by the JWKS endpoint, if present.
-	pub etag: Option<String>,
-	/// Last-Modified timestamp advertised by the JWKS endpoint.
-	#[serde(default)]
-	pub last_modified: Option<DateTime<Utc>>,
-	/// UTC timestamp when the cached payload expires.
-	pub expires_at: DateTime<Utc>,
-	/// UTC timestamp when the snapshot was persisted.
-	pub persisted_at: DateTime<Utc>,
+pub struct RateLimitPolicy {
+	/// Requests admitted per second to each host once the burst is spent.
+	pub requests_per_second: f64,
+	/// Requests admitted back to back to an idle host.
+	pub burst: u32,
 }
-impl PersistentSnapshot {
-	/// Validate snapshot metadata aligns with registration expectations.
-	pub fn validate(&self, registration: &IdentityProviderRegistration) -> Result<()> {
-		if self.jwks_json.len() as u64 > registration.max_response_bytes {
+impl RateLimitPolicy {
+	/// Validate invariants for rate limiting.
+	pub fn validate(&self) -> Result<()> {
+		if !self.requests_per_second.is_finite() || self.requests_per_second <= 0. {
 			return Err(Error::Validation {
-				field: "jwks_json",
-				reason: format!(
-					"Snapshot exceeds max_response_bytes ({} bytes).",
-					registration.max_response_bytes
-				),
+				field: "rate_limit.requests_per_second",
+				reason: "Must be a positive number.".into(),
 			});
 		}
-
-		if self.tenant_id != registration.tenant_id {
+		if self.burst == 0 {
 			return Err(Error::Validation {
-				field: "tenant_id",
-				reason: "Snapshot tenant does not match registration.".into(),
+				field: "rate_limit.burst",
+				reason: "Must be greater than zero.".into(),
 			});
 		}
-		if self.provider_id != registration.provider_id {
+
+		Ok(())
+	}
+}
+
+/// Registry-wide scheduling of proactive refreshes.
+///
+/// Spreads refreshes of registrations sharing a TTL across the TTL window, on top of each
+/// registration's `prefetch_jitter`, and bounds how many background refreshes run at once.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RefreshSchedulePolicy {
+	/// Share of the time until a refresh is due, in `[0, 1]`, by which it may be pulled forward.
+	pub smear_ratio: f64,
+	/// Background refreshes allowed in flight across the registry.
+	pub max_concurrent_refreshes: usize,
+}
+impl RefreshSchedulePolicy {
+	/// Validate invariants for refresh scheduling.
+	pub fn validate(&self) -> Result<()> {
+		if !(0. ..=1.).contains(&self.smear_ratio) {
 			return Err(Error::Validation {
-				field: "provider_id",
-				reason: "Snapshot provider does not match registration.".into(),
+				field: "refresh_schedule.smear_ratio",
+				reason: "Must be between 0 and 1.".into(),
 			});
 		}
-
-		if let Some(etag) = &self.etag
-			&& !etag.is_ascii()
-		{
-			return Err(Error::Validation { field: "etag", reason: "ETag must be ASCII.".into() });
-		}
-
-		if self.expires_at < self.persisted_at {
+		if self.max_concurrent_refreshes == 0 {
 			return Err(Error::Validation {
-				field: "expires_at",
-				reason: "Cannot be earlier than persisted_at.".into(),
+				field: "refresh_schedule.max_concurrent_refreshes",
+				reason: "Must be greater than zero.".into(),
 			});
 		}
 
@@ -399,315 +549,2759 @@ impl PersistentSnapshot {
 	}
 }
 
-/// Internal key mapping tenants and providers.
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub struct TenantProviderKey {
-	pub tenant_id: String,
-	pub provider_id: String,
+/// Detection of clock jumps between refreshes, such as a suspended laptop or VM resuming.
+///
+/// Cached deadlines run on the monotonic clock, which stops while the host sleeps, whereas HTTP
+/// freshness is defined in wall-clock time. On each resolve the drift between the two clocks since
+/// the payload's deadlines were computed is compared against `threshold`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ClockJumpPolicy {
+	/// Drift between the clocks treated as a jump.
+	#[serde(default = "default_clock_jump_threshold", with = "crate::config::duration")]
+	pub threshold: Duration,
+	/// How cached deadlines are reconciled once a jump is detected.
+	#[serde(default)]
+	pub reconciliation: ClockReconciliation,
 }
-impl TenantProviderKey {
-	pub fn new(tenant_id: impl Into<String>, provider_id: impl Into<String>) -> Self {
-		Self { tenant_id: tenant_id.into(), provider_id: provider_id.into() }
+impl ClockJumpPolicy {
+	/// Validate invariants for clock jump detection.
+	pub fn validate(&self) -> Result<()> {
+		if self.threshold < Duration::from_secs(1) {
+			return Err(Error::Validation {
+				field: "clock_jump.threshold",
+				reason: "Must be at least 1 second.".into(),
+			});
+		}
+
+		Ok(())
+	}
+}
+impl Default for ClockJumpPolicy {
+	fn default() -> Self {
+		Self {
+			threshold: DEFAULT_CLOCK_JUMP_THRESHOLD,
+			reconciliation: ClockReconciliation::default(),
+		}
 	}
 }
 
-/// Builder for [`Registry`] enabling multi-tenant configuration.
-#[derive(Debug, Default)]
-pub struct RegistryBuilder {
-	config: RegistryConfig,
+/// Reconciliation applied to a cached payload when the clocks disagree about its age.
+#[derive(Clone, Debug, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClockReconciliation {
+	/// Keep the monotonic deadlines; the jump is only logged and counted.
+	#[default]
+	TrustMonotonic,
+	/// Shift the deadlines by the drift so they follow the wall clock.
+	TrustWallClock,
+	/// Expire the payload so the next resolve revalidates it upstream.
+	Revalidate,
 }
-impl RegistryBuilder {
-	/// Create a builder with default configuration.
-	pub fn new() -> Self {
-		Self::default()
-	}
 
-	/// Enforce HTTPS for registrations (enabled by default).
-	pub fn require_https(mut self, require_https: bool) -> Self {
-		self.config.require_https = require_https;
+/// Checks applied to every fetched JWKS before it replaces the cached payload.
+///
+/// A rejected set fails the fetch like any other upstream error, so the previous payload stays in
+/// place. RSA keys whose `n` or `e` is not valid base64url, whose modulus is empty, or whose
+/// exponent is even or below 3 are always rejected.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JwksValidationPolicy {
+	/// Reject sets in which two keys share a `kid`.
+	#[serde(default = "default_true")]
+	pub reject_duplicate_kids: bool,
+	/// Accepted `kty` values (`RSA`, `EC`, `OKP`, `oct`); empty accepts every type.
+	#[serde(default)]
+	pub allowed_key_types: Vec<String>,
+	/// Reject keys without a `use` parameter.
+	#[serde(default)]
+	pub require_use: bool,
+	/// Reject keys without an `alg` parameter.
+	#[serde(default)]
+	pub require_alg: bool,
+}
+impl JwksValidationPolicy {
+	/// Validate invariants for the policy itself.
+	pub fn validate(&self) -> Result<()> {
+		if let Some(kty) = self
+			.allowed_key_types
+			.iter()
+			.find(|kty| !matches!(kty.as_str(), "RSA" | "EC" | "OKP" | "oct"))
+		{
+			return Err(Error::Validation {
+				field: "jwks_validation.allowed_key_types",
+				reason: format!("Unsupported key type '{kty}'."),
+			});
+		}
 
-		self
+		Ok(())
 	}
 
-	/// Override the default refresh-early offset applied to registrations.
-	pub fn default_refresh_early(mut self, value: Duration) -> Self {
-		self.config.default_refresh_early = value;
+	/// Check a fetched JWKS against the policy.
+	pub fn check(&self, jwks: &JwkSet) -> Result<()> {
+		let mut kids = HashSet::new();
 
-		self
-	}
+		for (index, jwk) in jwks.keys.iter().enumerate() {
+			let label = match jwk.common.key_id.as_deref() {
+				Some(kid) => format!("Key '{kid}'"),
+				None => format!("Key #{index}"),
+			};
+			let kty = match &jwk.algorithm {
+				AlgorithmParameters::RSA(_) => "RSA",
+				AlgorithmParameters::EllipticCurve(_) => "EC",
+				AlgorithmParameters::OctetKeyPair(_) => "OKP",
+				AlgorithmParameters::OctetKey(_) => "oct",
+			};
 
-	/// Override the default stale-while-error window applied to registrations.
-	pub fn default_stale_while_error(mut self, value: Duration) -> Self {
-		self.config.default_stale_while_error = value;
+			if let Some(kid) = jwk.common.key_id.as_deref()
+				&& self.reject_duplicate_kids
+				&& !kids.insert(kid)
+			{
+				return Err(Error::InvalidJwks(format!("{label} appears more than once.")));
+			}
+			if !self.allowed_key_types.is_empty()
+				&& !self.allowed_key_types.iter().any(|allowed| allowed == kty)
+			{
+				return Err(Error::InvalidJwks(format!(
+					"{label} has key type '{kty}', which the policy does not allow."
+				)));
+			}
+			if self.require_use && jwk.common.public_key_use.is_none() {
+				return Err(Error::InvalidJwks(format!("{label} is missing `use`.")));
+			}
+			if self.require_alg && jwk.common.key_algorithm.is_none() {
+				return Err(Error::InvalidJwks(format!("{label} is missing `alg`.")));
+			}
+			if let AlgorithmParameters::RSA(rsa) = &jwk.algorithm
+				&& let Err(reason) = check_rsa_parameters(&rsa.n, &rsa.e)
+			{
+				return Err(Error::InvalidJwks(format!("{label} {reason}")));
+			}
+		}
 
-		self
+		Ok(())
 	}
+}
+impl Default for JwksValidationPolicy {
+	fn default() -> Self {
+		Self {
+			reject_duplicate_kids: true,
+			allowed_key_types: Vec::new(),
+			require_use: false,
+			require_alg: false,
+		}
+	}
+}
 
-	/// Add an entry to the global domain allowlist.
-	pub fn add_allowed_domain(mut self, domain: impl Into<String>) -> Self {
-		let raw = domain.into();
-
-		if let Some(domain) = security::canonicalize_dns_name(&raw)
-			&& !self.config.allowed_domains.contains(&domain)
+/// Egress proxy configuration for upstream fetches.
+///
+/// Explicit settings replace the `HTTP_PROXY`/`HTTPS_PROXY`/`NO_PROXY` environment variables, so
+/// an empty value routes every request directly.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProxySettings {
+	/// Proxy used for `http://` endpoints.
+	#[serde(default)]
+	pub http_proxy: Option<Url>,
+	/// Proxy used for `https://` endpoints.
+	#[serde(default)]
+	pub https_proxy: Option<Url>,
+	/// Hosts, domains, IP addresses, or CIDR ranges reached without the proxy, in `NO_PROXY`
+	/// syntax.
+	#[serde(default)]
+	pub no_proxy: Vec<String>,
+}
+impl ProxySettings {
+	/// Validate invariants for proxy configuration.
+	pub fn validate(&self) -> Result<()> {
+		for (field, proxy) in
+			[("proxy.http_proxy", &self.http_proxy), ("proxy.https_proxy", &self.https_proxy)]
 		{
-			self.config.allowed_domains.push(domain);
+			if let Some(url) = proxy
+				&& !matches!(url.scheme(), "http" | "https")
+			{
+				return Err(Error::Validation {
+					field,
+					reason: "Proxy URL scheme must be http or https.".into(),
+				});
+			}
 		}
 
-		self
+		Ok(())
 	}
+}
 
-	/// Replace the global domain allowlist.
-	pub fn allowed_domains<I, S>(mut self, domains: I) -> Self
-	where
-		I: IntoIterator<Item = S>,
-		S: Into<String>,
-	{
-		self.config.allowed_domains.clear();
+/// Connection pool and protocol tuning for HTTP clients built by the registry.
+///
+/// Defaults match reqwest's. Bursts of refreshes against one identity provider may queue behind a
+/// few pooled connections; capping idle connections, recycling them sooner, or speaking HTTP/2
+/// from the first byte changes how those requests are spread.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpClientConfig {
+	/// Close pooled connections idle for this long; `None` keeps them open indefinitely.
+	#[serde(default, with = "crate::config::optional_duration")]
+	pub pool_idle_timeout: Option<Duration>,
+	/// Idle connections kept per host; `None` imposes no limit.
+	pub pool_max_idle_per_host: Option<usize>,
+	/// Speak HTTP/2 without negotiation, including over plain HTTP.
+	///
+	/// Only for upstreams known to support HTTP/2; HTTP/1-only servers fail every request.
+	pub http2_prior_knowledge: bool,
+	/// Idle time before TCP keepalive probes start; `None` disables keepalive.
+	#[serde(default, with = "crate::config::optional_duration")]
+	pub tcp_keepalive: Option<Duration>,
+}
+impl HttpClientConfig {
+	/// Validate invariants for client tuning.
+	pub fn validate(&self) -> Result<()> {
+		for (field, duration) in [
+			("http_client.pool_idle_timeout", self.pool_idle_timeout),
+			("http_client.tcp_keepalive", self.tcp_keepalive),
+		] {
+			if duration.is_some_and(|duration| duration.is_zero()) {
+				return Err(Error::Validation {
+					field,
+					reason: "Must be greater than zero; use None to disable.".into(),
+				});
+			}
+		}
 
-		for domain in domains {
-			self = self.add_allowed_domain(domain);
+		Ok(())
+	}
+}
+impl Default for HttpClientConfig {
+	fn default() -> Self {
+		Self {
+			pool_idle_timeout: Some(DEFAULT_POOL_IDLE_TIMEOUT),
+			pool_max_idle_per_host: None,
+			http2_prior_knowledge: false,
+			tcp_keepalive: Some(DEFAULT_TCP_KEEPALIVE),
 		}
+	}
+}
 
-		self
+/// Rewrite applied to a provider's `kid`s wherever callers see or look them up.
+///
+/// Lookups must use the namespaced form, so a token carrying another provider's raw `kid` cannot
+/// select a key from this provider.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KidNamespace {
+	/// Text prepended to every upstream `kid`.
+	#[serde(default)]
+	pub prefix: String,
+	/// Text appended to every upstream `kid`.
+	#[serde(default)]
+	pub suffix: String,
+}
+impl KidNamespace {
+	/// Construct a namespace from a prefix and a suffix, either of which may be empty.
+	pub fn new(prefix: impl Into<String>, suffix: impl Into<String>) -> Self {
+		Self { prefix: prefix.into(), suffix: suffix.into() }
 	}
 
-	#[cfg(feature = "redis")]
-	/// Configure Redis-backed persistence for snapshots.
-	pub fn with_redis_client(mut self, client: redis::Client) -> Self {
-		self.config.persistence = Some(RedisPersistence::new(client));
+	/// Namespaced form of an upstream `kid`.
+	pub fn apply(&self, kid: &str) -> String {
+		format!("{}{kid}{}", self.prefix, self.suffix)
+	}
 
-		self
+	/// Upstream `kid` behind a namespaced one, or `None` when `kid` lies outside the namespace.
+	pub fn strip<'a>(&self, kid: &'a str) -> Option<&'a str> {
+		kid.strip_prefix(self.prefix.as_str())?.strip_suffix(self.suffix.as_str())
 	}
 
-	#[cfg(feature = "redis")]
-	/// Adjust the Redis key namespace (defaults to `jwks-cache`).
-	pub fn redis_namespace(mut self, namespace: impl Into<String>) -> Self {
-		if let Some(persistence) = self.config.persistence.as_mut() {
-			persistence.namespace = Arc::from(namespace.into());
-		} else {
-			panic!("Redis client must be configured before setting namespace.");
+	/// Copy of `jwks` with every `kid` namespaced.
+	pub fn apply_to_set(&self, jwks: &JwkSet) -> JwkSet {
+		let mut jwks = jwks.clone();
+
+		for jwk in &mut jwks.keys {
+			if let Some(kid) = jwk.common.key_id.as_mut() {
+				*kid = self.apply(kid);
+			}
 		}
 
-		self
+		jwks
 	}
 
-	/// Finalise the configuration and construct a [`Registry`].
-	pub fn build(self) -> Registry {
-		let mut config = self.config;
+	/// Validate invariants for the namespace.
+	pub fn validate(&self) -> Result<()> {
+		if self.prefix.is_empty() && self.suffix.is_empty() {
+			return Err(Error::Validation {
+				field: "kid_namespace",
+				reason: "Prefix or suffix must be non-empty.".into(),
+			});
+		}
 
-		config.allowed_domains = security::normalize_allowlist(config.allowed_domains);
+		Ok(())
+	}
+}
 
-		Registry {
-			inner: Arc::new(RwLock::new(RegistryState { providers: HashMap::new() })),
-			config: Arc::new(config),
+/// OpenID Connect discovery settings for a registration.
+///
+/// The discovery document is cached with its own TTL, independent of the JWKS payload.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DiscoveryRegistration {
+	/// Issuer URL whose `/.well-known/openid-configuration` advertises the JWKS location.
+	pub issuer: Url,
+	/// Minimum TTL applied to the discovery document.
+	#[serde(default = "default_discovery_min_ttl")]
+	pub min_ttl: TtlSeconds,
+	/// Maximum TTL applied to the discovery document.
+	#[serde(default = "default_discovery_max_ttl")]
+	pub max_ttl: TtlSeconds,
+}
+impl DiscoveryRegistration {
+	/// Create discovery settings for an issuer with default TTL bounds.
+	pub fn new(issuer: Url) -> Self {
+		Self { issuer, min_ttl: default_discovery_min_ttl(), max_ttl: default_discovery_max_ttl() }
+	}
+
+	/// Validate discovery settings against the owning registration.
+	pub fn validate(&self, registration: &IdentityProviderRegistration) -> Result<()> {
+		if registration.is_static() {
+			return Err(Error::Validation {
+				field: "source",
+				reason: "Static key sets cannot be combined with discovery.".into(),
+			});
+		}
+		if registration.require_https {
+			security::enforce_https(&self.issuer)?;
+		}
+		if registration.block_private_networks {
+			security::enforce_public_host(&self.issuer)?;
+		}
+
+		match self.issuer.host_str() {
+			Some(host) if security::host_is_allowed(host, &registration.allowed_domains) => {},
+			Some(_) => {
+				return Err(Error::Validation {
+					field: "discovery.issuer",
+					reason: "Host is not within the allowed_domains allowlist.".into(),
+				});
+			},
+			None => {
+				return Err(Error::Validation {
+					field: "discovery.issuer",
+					reason: "Must include a host component.".into(),
+				});
+			},
+		}
+
+		if self.max_ttl < self.min_ttl {
+			return Err(Error::Validation {
+				field: "discovery.max_ttl",
+				reason: "Must be greater than or equal to discovery.min_ttl.".into(),
+			});
 		}
+
+		Ok(())
 	}
 }
 
-/// Registry state container.
-#[derive(Clone, Debug)]
-pub struct Registry {
-	inner: Arc<RwLock<RegistryState>>,
-	config: Arc<RegistryConfig>,
+/// Where a registration obtains its key set.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JwksSource {
+	/// Fetch [`IdentityProviderRegistration::jwks_url`] over HTTP.
+	#[default]
+	Http,
+	/// Serve a key set available without network access.
+	Static(StaticJwks),
 }
-impl Registry {
-	/// Create a new registry instance with defaults.
-	pub fn new() -> Self {
-		Self::builder().build()
-	}
 
-	/// Create a [`RegistryBuilder`] for advanced configuration.
-	pub fn builder() -> RegistryBuilder {
-		RegistryBuilder::new()
+/// Key set served by a [`JwksSource::Static`] registration.
+///
+/// Static key sets go through the same `min_keys` and `jwks_validation` checks as fetched ones.
+/// They carry no cache headers, so they are reloaded every `min_ttl`; a file edited in place is
+/// picked up then, or immediately through [`Registry::refresh`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StaticJwks {
+	/// JWKS document read from a local file, limited to `max_response_bytes`.
+	File(PathBuf),
+	/// Key set embedded in the registration.
+	Inline(JwkSet),
+}
+impl StaticJwks {
+	/// Parse an inline key set from a JWKS JSON document.
+	pub fn inline_json(json: &str) -> Result<Self> {
+		Ok(Self::Inline(serde_json::from_str(json)?))
 	}
 
-	/// Register or update a provider configuration.
-	pub async fn register(&self, mut registration: IdentityProviderRegistration) -> Result<()> {
-		if self.config.require_https {
-			if !registration.require_https {
-				return Err(Error::Security(
-					"Registry requires HTTPS for all provider registrations.".into(),
-				));
-			}
-		} else {
-			registration.require_https = false;
+	// URL reported as `jwks_url` for registrations built by
+	// `IdentityProviderRegistration::from_static`.
+	fn url(&self) -> Result<Url> {
+		match self {
+			Self::File(path) =>
+				Url::from_file_path(path::absolute(path)?).map_err(|_| Error::Validation {
+					field: "source",
+					reason: "File path cannot be expressed as a URL.".into(),
+				}),
+			Self::Inline(_) => Ok(Url::parse(STATIC_INLINE_URL)?),
 		}
+	}
+}
+
+/// Registration describing how to fetch and maintain JWKS for a provider.
+///
+/// Duration fields serialise as `humantime` strings such as `"250ms"` or `"24h"`, and also accept
+/// integer seconds or serde's `{ secs, nanos }` form; see [`crate::config::duration`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IdentityProviderRegistration {
+	/// Tenant identifier used for metrics, caching, and persistence scope.
+	pub tenant_id: String,
+	/// Provider identifier unique within the tenant.
+	pub provider_id: String,
+	/// URL of the JWKS endpoint to fetch signing keys from.
+	///
+	/// When [`Self::discovery`] is set, this is replaced with the advertised `jwks_uri` during
+	/// registration. With a [`JwksSource::Static`] source, it only identifies the key set in
+	/// statuses.
+	pub jwks_url: Url,
+	/// Mirrors serving the same key set, tried in order when a fetch from `jwks_url` fails.
+	///
+	/// Each mirror is held to the same HTTPS, network, and allowlist checks as `jwks_url`. A
+	/// failing fetch attempt tries every mirror before the retry policy backs off, and stale keys
+	/// are only served once all of them keep failing.
+	#[serde(default)]
+	pub mirror_urls: Vec<Url>,
+	/// Where the key set comes from; HTTP unless set to a static key set.
+	#[serde(default)]
+	pub source: JwksSource,
+	/// Optional OpenID Connect discovery settings used to locate the JWKS endpoint.
+	#[serde(default)]
+	pub discovery: Option<DiscoveryRegistration>,
+	/// Whether HTTPS is required for JWKS retrieval.
+	#[serde(default = "default_true")]
+	pub require_https: bool,
+	/// Whether upstream hosts must be publicly routable.
+	///
+	/// Rejects IP literals in private, loopback, link-local, and similar ranges in the JWKS URL,
+	/// discovery issuer, and redirect targets, and drops such addresses when resolving hostnames.
+	/// Requires a client built by the registry; behind a proxy, only IP literals are checked.
+	#[serde(default)]
+	pub block_private_networks: bool,
+	/// Optional allowlist of domains permitted for redirects.
+	#[serde(default, deserialize_with = "crate::security::deserialize_allowed_domains")]
+	pub allowed_domains: Vec<String>,
+	/// Lead time before expiry to trigger proactive refresh.
+	///
+	/// `None` inherits the registry default (see [`RegistryBuilder::default_refresh_early`]).
+	#[serde(default, with = "crate::config::optional_duration")]
+	pub refresh_early: Option<Duration>,
+	/// Duration to continue serving stale data when refresh fails.
+	///
+	/// `None` inherits the registry default (see [`RegistryBuilder::default_stale_while_error`]).
+	#[serde(default, with = "crate::config::optional_duration")]
+	pub stale_while_error: Option<Duration>,
+	/// Upper bound for widening `refresh_early` when upstream fetches become slow.
+	///
+	/// When set, the refresh lead grows to twice the p95 latency of recent fetches, up to this
+	/// cap, so a proactive refresh can finish before the keys expire. `None` keeps the lead
+	/// fixed.
+	#[serde(default, with = "crate::config::optional_duration")]
+	pub max_refresh_early: Option<Duration>,
+	/// Minimum TTL applied to upstream responses.
+	#[serde(default = "default_min_ttl")]
+	pub min_ttl: TtlSeconds,
+	/// Maximum TTL applied to upstream responses.
+	#[serde(default = "default_max_ttl")]
+	pub max_ttl: TtlSeconds,
+	/// Maximum size allowed for JWKS payloads in bytes.
+	#[serde(default = "default_max_response_bytes")]
+	pub max_response_bytes: ByteSize,
+	/// Reject refreshes whose key set shares no `kid` with the cached one.
+	///
+	/// Guards against a hijacked endpoint swapping the entire key set. Expected full rotations are
+	/// accepted through [`Registry::force_refresh`].
+	#[serde(default)]
+	pub reject_full_rotation: bool,
+	/// Upstream age above which a fetched payload is reported as an old CDN copy.
+	///
+	/// The age includes the `Age` header added by caches in front of the provider. `None`
+	/// disables the warning.
+	#[serde(default, with = "crate::config::optional_duration")]
+	pub upstream_age_warning: Option<Duration>,
+	/// Fewest keys a fetched JWKS may contain before it is rejected; `0` disables the guard.
+	///
+	/// A rejected set fails the refresh, so the previous payload keeps serving.
+	#[serde(default = "default_min_keys")]
+	pub min_keys: usize,
+	/// Response headers kept in memory alongside the cached payload.
+	///
+	/// `None` keeps [`DEFAULT_RETAINED_HEADERS`](crate::http::client::DEFAULT_RETAINED_HEADERS);
+	/// a list replaces them. Captured exchanges still record every (redacted) header.
+	#[serde(default)]
+	pub retained_headers: Option<Vec<String>>,
+	/// Headers added to every JWKS request, such as API keys or routing hints for private
+	/// gateways.
+	///
+	/// Names in [`FORBIDDEN_EXTRA_HEADERS`] are rejected, and captured exchanges redact every
+	/// configured value. Like `Authorization`, they are dropped once a redirect leaves the
+	/// origin of `jwks_url`, so only same-origin hops receive them.
+	#[serde(default)]
+	pub extra_headers: HashMap<String, String>,
+	/// TTL applied when persisting negative cache outcomes.
+	#[serde(default, with = "crate::config::duration")]
+	pub negative_cache_ttl: Duration,
+	/// Window during which a `kid` missing after forced revalidation is answered from cache.
+	///
+	/// Zero disables the negative cache, so every unknown `kid` revalidates upstream.
+	#[serde(default = "default_missing_kid_ttl", with = "crate::config::duration")]
+	pub missing_kid_ttl: Duration,
+	/// Maximum number of redirects to follow during fetch.
+	#[serde(default = "default_max_redirects")]
+	pub max_redirects: u8,
+	/// Optional SPKI fingerprints enforced during the TLS handshake.
+	///
+	/// Applies to discovery and JWKS fetches alike, and requires a client built by the registry.
+	#[serde(default)]
+	pub pinned_spki: Vec<SpkiFingerprint>,
+	/// PEM-encoded CA certificates trusted for this provider in addition to the web PKI roots.
+	///
+	/// Lets providers signed by a private CA be fetched without disabling TLS verification.
+	/// Like [`pinned_spki`](Self::pinned_spki), requires a client built by the registry.
+	#[serde(default)]
+	pub trusted_roots: Vec<String>,
+	/// Client certificate and key presented to providers that require mutual TLS.
+	///
+	/// Like [`pinned_spki`](Self::pinned_spki), requires a client built by the registry.
+	#[serde(default)]
+	pub client_identity: Option<ClientIdentity>,
+	/// Random jitter applied when scheduling proactive refreshes.
+	#[serde(default = "default_prefetch_jitter", with = "crate::config::duration")]
+	pub prefetch_jitter: Duration,
+	/// Retry policy configuration for JWKS fetch attempts.
+	#[serde(default)]
+	pub retry_policy: RetryPolicy,
+	/// Circuit breaker applied to upstream fetches; `None` disables it.
+	#[serde(default)]
+	pub circuit_breaker: Option<CircuitBreakerPolicy>,
+	/// Background probing after a failed fetch leaves the provider without keys; `None` waits for
+	/// the next resolve instead.
+	#[serde(default)]
+	pub recovery_probe: Option<RecoveryPolicy>,
+	/// Idle period after which a `HEAD` probe keeps the pooled upstream connection warm.
+	///
+	/// Meant for providers with long TTLs, so the eventual refresh does not pay for a new TLS
+	/// handshake. Must stay below the connection pool's idle timeout (90 seconds by default) to
+	/// keep the connection alive. `None` disables probing.
+	#[serde(default, with = "crate::config::optional_duration")]
+	pub keepalive_interval: Option<Duration>,
+	/// Detection and reconciliation of jumps between the monotonic and wall clocks.
+	#[serde(default)]
+	pub clock_jump: ClockJumpPolicy,
+	/// Checks every fetched JWKS must pass before it replaces the cached payload.
+	#[serde(default)]
+	pub jwks_validation: JwksValidationPolicy,
+	/// Namespace applied to this provider's `kid`s in resolution and verification helpers.
+	#[serde(default)]
+	pub kid_namespace: Option<KidNamespace>,
+	/// Proxy settings for this provider, overriding the registry client and proxy.
+	#[serde(default)]
+	pub proxy: Option<ProxySettings>,
+	/// HTTP client used for this provider, overriding the registry client.
+	///
+	/// Not serialised; registrations loaded from configuration files use the registry client.
+	/// Takes precedence over [`proxy`](Self::proxy).
+	#[serde(skip)]
+	pub http_client: Option<Client>,
+	/// Credentials attached as the `Authorization` header of every JWKS request.
+	///
+	/// Not serialised; registrations loaded from configuration files fetch without credentials.
+	#[serde(skip)]
+	pub auth: Option<Arc<dyn AuthProvider>>,
+}
+impl IdentityProviderRegistration {
+	/// Construct a new registration with default cache settings.
+	pub fn new(
+		tenant_id: impl Into<String>,
+		provider_id: impl Into<String>,
+		jwks_url: impl AsRef<str>,
+	) -> Result<Self> {
+		let jwks_url = Url::parse(jwks_url.as_ref())?;
+
+		Ok(Self {
+			tenant_id: tenant_id.into(),
+			provider_id: provider_id.into(),
+			jwks_url,
+			mirror_urls: Vec::new(),
+			source: JwksSource::Http,
+			discovery: None,
+			require_https: true,
+			block_private_networks: false,
+			allowed_domains: Vec::new(),
+			refresh_early: None,
+			stale_while_error: None,
+			max_refresh_early: None,
+			min_ttl: default_min_ttl(),
+			max_ttl: default_max_ttl(),
+			max_response_bytes: default_max_response_bytes(),
+			reject_full_rotation: false,
+			upstream_age_warning: None,
+			min_keys: default_min_keys(),
+			retained_headers: None,
+			extra_headers: HashMap::new(),
+			negative_cache_ttl: Duration::ZERO,
+			missing_kid_ttl: DEFAULT_MISSING_KID_TTL,
+			max_redirects: 3,
+			pinned_spki: Vec::new(),
+			trusted_roots: Vec::new(),
+			client_identity: None,
+			prefetch_jitter: DEFAULT_PREFETCH_JITTER,
+			retry_policy: RetryPolicy::default(),
+			circuit_breaker: None,
+			recovery_probe: None,
+			keepalive_interval: None,
+			clock_jump: ClockJumpPolicy::default(),
+			jwks_validation: JwksValidationPolicy::default(),
+			kid_namespace: None,
+			proxy: None,
+			http_client: None,
+			auth: None,
+		})
+	}
+
+	/// Construct a registration that discovers its JWKS endpoint from an OpenID issuer.
+	///
+	/// `jwks_url` initially points at the discovery document and is replaced with the advertised
+	/// `jwks_uri` when the registration is added to a [`Registry`].
+	pub fn from_issuer(
+		tenant_id: impl Into<String>,
+		provider_id: impl Into<String>,
+		issuer: impl AsRef<str>,
+	) -> Result<Self> {
+		let issuer = Url::parse(issuer.as_ref())?;
+		let mut registration =
+			Self::new(tenant_id, provider_id, discovery::discovery_url(&issuer)?.as_str())?;
+
+		registration.discovery = Some(DiscoveryRegistration::new(issuer));
+
+		Ok(registration)
+	}
+
+	/// Construct a registration that serves `jwks` without contacting any upstream.
+	///
+	/// `jwks_url` is set to the file's `file://` URL, or to [`STATIC_INLINE_URL`] for an inline set.
+	pub fn from_static(
+		tenant_id: impl Into<String>,
+		provider_id: impl Into<String>,
+		jwks: StaticJwks,
+	) -> Result<Self> {
+		let mut registration = Self::new(tenant_id, provider_id, jwks.url()?.as_str())?;
+
+		registration.source = JwksSource::Static(jwks);
+
+		Ok(registration)
+	}
+
+	/// Whether the key set is served from a [`JwksSource::Static`] source.
+	pub fn is_static(&self) -> bool {
+		matches!(self.source, JwksSource::Static(_))
+	}
+
+	/// Canonicalise the domain allowlist in-place.
+	pub fn normalize_allowed_domains(&mut self) {
+		let domains = mem::take(&mut self.allowed_domains);
+
+		self.allowed_domains = security::normalize_allowlist(domains);
+	}
+
+	/// Append a mirror tried after `jwks_url` and any earlier mirrors.
+	pub fn with_mirror_url(mut self, url: impl AsRef<str>) -> Result<Self> {
+		self.mirror_urls.push(Url::parse(url.as_ref())?);
+
+		Ok(self)
+	}
+
+	/// Set HTTPS requirement to the desired value.
+	pub fn with_require_https(mut self, require_https: bool) -> Self {
+		self.require_https = require_https;
+
+		self
+	}
+
+	/// Reject upstream hosts that are or resolve to non-public addresses.
+	pub fn with_block_private_networks(mut self, block: bool) -> Self {
+		self.block_private_networks = block;
+
+		self
+	}
+
+	/// Pin the refresh-early offset, bypassing the registry default.
+	pub fn with_refresh_early(mut self, value: Duration) -> Self {
+		self.refresh_early = Some(value);
+
+		self
+	}
+
+	/// Let the refresh lead grow up to `cap` when upstream fetches become slow.
+	pub fn with_max_refresh_early(mut self, cap: Duration) -> Self {
+		self.max_refresh_early = Some(cap);
+
+		self
+	}
+
+	/// Pin the stale-while-error window, bypassing the registry default.
+	pub fn with_stale_while_error(mut self, value: Duration) -> Self {
+		self.stale_while_error = Some(value);
+
+		self
+	}
+
+	/// Guard upstream fetches with a circuit breaker.
+	pub fn with_circuit_breaker(mut self, policy: CircuitBreakerPolicy) -> Self {
+		self.circuit_breaker = Some(policy);
+
+		self
+	}
+
+	/// Keep probing upstream in the background while failed fetches leave the provider empty.
+	pub fn with_recovery_probe(mut self, policy: RecoveryPolicy) -> Self {
+		self.recovery_probe = Some(policy);
+
+		self
+	}
+
+	/// Reject refreshes that replace every `kid` at once.
+	pub fn with_reject_full_rotation(mut self, reject: bool) -> Self {
+		self.reject_full_rotation = reject;
+
+		self
+	}
+
+	/// Warn when a fetched payload is already older than `threshold` upstream.
+	pub fn with_upstream_age_warning(mut self, threshold: Duration) -> Self {
+		self.upstream_age_warning = Some(threshold);
+
+		self
+	}
+
+	/// Reject fetched key sets with fewer than `min_keys` keys.
+	pub fn with_min_keys(mut self, min_keys: usize) -> Self {
+		self.min_keys = min_keys;
+
+		self
+	}
+
+	/// Keep only `headers` from upstream responses instead of the cache-relevant defaults.
+	pub fn with_retained_headers<I, S>(mut self, headers: I) -> Self
+	where
+		I: IntoIterator<Item = S>,
+		S: Into<String>,
+	{
+		self.retained_headers = Some(headers.into_iter().map(Into::into).collect());
+
+		self
+	}
+
+	/// Send `name: value` with every JWKS request.
+	pub fn with_extra_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+		self.extra_headers.insert(name.into(), value.into());
+
+		self
+	}
+
+	/// Probe the upstream connection after `interval` without traffic to keep it pooled.
+	pub fn with_keepalive(mut self, interval: Duration) -> Self {
+		self.keepalive_interval = Some(interval);
+
+		self
+	}
+
+	/// Replace how clock jumps are detected and reconciled.
+	pub fn with_clock_jump(mut self, policy: ClockJumpPolicy) -> Self {
+		self.clock_jump = policy;
+
+		self
+	}
+
+	/// Trust the CA certificates in `pem` for this provider in addition to the web PKI roots.
+	pub fn with_trusted_root(mut self, pem: impl Into<String>) -> Self {
+		self.trusted_roots.push(pem.into());
+
+		self
+	}
+
+	/// Present `identity` when the provider requests a client certificate.
+	pub fn with_client_identity(mut self, identity: ClientIdentity) -> Self {
+		self.client_identity = Some(identity);
+
+		self
+	}
+
+	/// Replace the checks applied to fetched key sets.
+	pub fn with_jwks_validation(mut self, policy: JwksValidationPolicy) -> Self {
+		self.jwks_validation = policy;
+
+		self
+	}
+
+	/// Expose this provider's `kid`s through `namespace`.
+	pub fn with_kid_namespace(mut self, namespace: KidNamespace) -> Self {
+		self.kid_namespace = Some(namespace);
+
+		self
+	}
+
+	/// Fetch this provider's keys through `proxy` instead of the registry client or proxy.
+	pub fn with_proxy(mut self, proxy: ProxySettings) -> Self {
+		self.proxy = Some(proxy);
+
+		self
+	}
+
+	/// Fetch this provider's keys with `client` instead of the registry client.
+	pub fn with_http_client(mut self, client: Client) -> Self {
+		self.http_client = Some(client);
+
+		self
+	}
+
+	/// Authorize JWKS requests with credentials from `provider`.
+	pub fn with_auth<A>(mut self, provider: A) -> Self
+	where
+		A: AuthProvider + 'static,
+	{
+		self.auth = Some(Arc::new(provider));
+
+		self
+	}
+
+	/// Refresh-early offset in force, falling back to [`DEFAULT_REFRESH_EARLY`] when unset.
+	pub fn effective_refresh_early(&self) -> Duration {
+		self.refresh_early.unwrap_or(DEFAULT_REFRESH_EARLY)
+	}
+
+	/// Stale-while-error window in force, falling back to [`DEFAULT_STALE_WHILE_ERROR`] when
+	/// unset.
+	pub fn effective_stale_while_error(&self) -> Duration {
+		self.stale_while_error.unwrap_or(DEFAULT_STALE_WHILE_ERROR)
+	}
+
+	/// Decode [`trusted_roots`](Self::trusted_roots) into DER certificates.
+	pub fn trusted_root_certificates(&self) -> Result<Vec<CertificateDer<'static>>> {
+		let mut certificates = Vec::new();
+
+		for pem in &self.trusted_roots {
+			certificates.extend(security::parse_pem_certificates(pem)?);
+		}
+
+		Ok(certificates)
+	}
+
+	// HTTPS, public-host, and allowlist checks shared by `jwks_url` and its mirrors.
+	fn validate_upstream_url(&self, url: &Url, field: &'static str) -> Result<()> {
+		if self.require_https {
+			security::enforce_https(url)?;
+		}
+		if self.block_private_networks {
+			security::enforce_public_host(url)?;
+		}
+
+		match url.host_str() {
+			Some(host) if security::host_is_allowed(host, &self.allowed_domains) => Ok(()),
+			Some(_) => Err(Error::Validation {
+				field,
+				reason: "Host is not within the allowed_domains allowlist.".into(),
+			}),
+			None =>
+				Err(Error::Validation { field, reason: "Must include a host component.".into() }),
+		}
+	}
+
+	/// Validate the registration against the documented constraints.
+	pub fn validate(&self) -> Result<()> {
+		validate_tenant_id(&self.tenant_id)?;
+		validate_provider_id(&self.provider_id)?;
+
+		// Static key sets are never fetched, so the URL policies do not apply.
+		if self.is_static() {
+			if !self.mirror_urls.is_empty() {
+				return Err(Error::Validation {
+					field: "mirror_urls",
+					reason: "Static key sets cannot have mirrors.".into(),
+				});
+			}
+		} else {
+			self.validate_upstream_url(&self.jwks_url, "jwks_url")?;
+		}
+
+		for mirror in &self.mirror_urls {
+			self.validate_upstream_url(mirror, "mirror_urls")?;
+		}
+
+		if self.effective_refresh_early() < Duration::from_secs(1) {
+			return Err(Error::Validation {
+				field: "refresh_early",
+				reason: "Must be at least 1 second.".into(),
+			});
+		}
+		if self.min_ttl.as_duration() < MIN_TTL_FLOOR {
+			return Err(Error::Validation {
+				field: "min_ttl",
+				reason: format!("Must be at least {:?}.", MIN_TTL_FLOOR),
+			});
+		}
+		if self.max_ttl < self.min_ttl {
+			return Err(Error::Validation {
+				field: "max_ttl",
+				reason: "Must be greater than or equal to min_ttl.".into(),
+			});
+		}
+		if self.effective_refresh_early() >= self.max_ttl.as_duration() {
+			return Err(Error::Validation {
+				field: "refresh_early",
+				reason: "Must be less than max_ttl.".into(),
+			});
+		}
+		if let Some(cap) = self.max_refresh_early
+			&& (cap < self.effective_refresh_early() || cap >= self.max_ttl.as_duration())
+		{
+			return Err(Error::Validation {
+				field: "max_refresh_early",
+				reason: "Must be at least refresh_early and less than max_ttl.".into(),
+			});
+		}
+		if self.max_response_bytes.as_u64() == 0 {
+			return Err(Error::Validation {
+				field: "max_response_bytes",
+				reason: "Must be greater than zero.".into(),
+			});
+		}
+		if let Some(name) = self
+			.retained_headers
+			.iter()
+			.flatten()
+			.find(|name| HeaderName::from_bytes(name.as_bytes()).is_err())
+		{
+			return Err(Error::Validation {
+				field: "retained_headers",
+				reason: format!("`{name}` is not a valid header name."),
+			});
+		}
+		for (name, value) in &self.extra_headers {
+			let reason = if HeaderName::from_bytes(name.as_bytes()).is_err() {
+				format!("`{name}` is not a valid header name.")
+			} else if FORBIDDEN_EXTRA_HEADERS
+				.iter()
+				.any(|forbidden| forbidden.eq_ignore_ascii_case(name))
+			{
+				format!("`{name}` is managed by the cache and cannot be set.")
+			} else if HeaderValue::from_str(value).is_err() {
+				format!("The value of `{name}` is not a valid header value.")
+			} else {
+				continue;
+			};
+
+			return Err(Error::Validation { field: "extra_headers", reason });
+		}
+		if self.max_redirects > MAX_REDIRECTS {
+			return Err(Error::Validation {
+				field: "max_redirects",
+				reason: format!("Must be less than or equal to {}.", MAX_REDIRECTS),
+			});
+		}
+		if !self.negative_cache_ttl.is_zero() && self.negative_cache_ttl < Duration::from_secs(1) {
+			return Err(Error::Validation {
+				field: "negative_cache_ttl",
+				reason: "Must be zero or at least one second.".into(),
+			});
+		}
+
+		if !self.missing_kid_ttl.is_zero() && self.missing_kid_ttl < Duration::from_secs(1) {
+			return Err(Error::Validation {
+				field: "missing_kid_ttl",
+				reason: "Must be zero or at least one second.".into(),
+			});
+		}
+
+		if let Some(interval) = self.keepalive_interval
+			&& interval < Duration::from_secs(1)
+		{
+			return Err(Error::Validation {
+				field: "keepalive_interval",
+				reason: "Must be at least one second.".into(),
+			});
+		}
+
+		self.retry_policy.validate()?;
+
+		if let Some(breaker) = &self.circuit_breaker {
+			breaker.validate()?;
+		}
+		if let Some(recovery) = &self.recovery_probe {
+			recovery.validate()?;
+		}
+
+		self.clock_jump.validate()?;
+
+		self.jwks_validation.validate()?;
+
+		if let Some(namespace) = &self.kid_namespace {
+			namespace.validate()?;
+		}
+		if let Some(proxy) = &self.proxy {
+			proxy.validate()?;
+		}
+		if !self.pinned_spki.is_empty() && self.http_client.is_some() {
+			return Err(Error::Validation {
+				field: "pinned_spki",
+				reason: "Cannot be enforced on an injected HTTP client.".into(),
+			});
+		}
+		if !self.trusted_roots.is_empty() && self.http_client.is_some() {
+			return Err(Error::Validation {
+				field: "trusted_roots",
+				reason: "Cannot be applied to an injected HTTP client.".into(),
+			});
+		}
+
+		self.trusted_root_certificates()?;
+
+		if let Some(identity) = &self.client_identity {
+			if self.http_client.is_some() {
+				return Err(Error::Validation {
+					field: "client_identity",
+					reason: "Cannot be applied to an injected HTTP client.".into(),
+				});
+			}
+
+			identity.validate()?;
+		}
+
+		if let Some(discovery) = &self.discovery {
+			discovery.validate(self)?;
+		}
+
+		for domain in &self.allowed_domains {
+			if let Some(canonical) = security::canonicalize_dns_name(domain) {
+				if canonical != *domain {
+					return Err(Error::Validation {
+						field: "allowed_domains",
+						reason: "Entries must be canonical hostnames (lowercase, no trailing dot)."
+							.into(),
+					});
+				}
+			} else {
+				return Err(Error::Validation {
+					field: "allowed_domains",
+					reason: "Entries must be non-empty hostnames.".into(),
+				});
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// Snapshot of cache payload persisted to external storage.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PersistentSnapshot {
+	/// Format version the snapshot was written with, see [`SNAPSHOT_VERSION`].
+	#[serde(default)]
+	pub version: u32,
+	/// Tenant identifier associated with the snapshot.
+	pub tenant_id: String,
+	/// Provider identifier within the tenant scope.
+	pub provider_id: String,
+	/// Serialized JWKS payload captured from the cache.
+	pub jwks_json: String,
+	/// Entity tag returned by the JWKS endpoint, if present.
+	pub etag: Option<String>,
+	/// Last-Modified timestamp advertised by the JWKS endpoint.
+	#[serde(default)]
+	pub last_modified: Option<DateTime<Utc>>,
+	/// UTC timestamp when the cached payload expires.
+	pub expires_at: DateTime<Utc>,
+	/// UTC timestamp when the snapshot was persisted.
+	pub persisted_at: DateTime<Utc>,
+	/// Age of the payload in seconds when persisted, including its upstream `Age`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub age_seconds: Option<u64>,
+	/// Activation times of keys published ahead of use, keyed by `kid`.
+	#[serde(default, skip_serializing_if = "KeyActivations::is_empty")]
+	pub key_activations: KeyActivations,
+}
+impl PersistentSnapshot {
+	/// Decode a snapshot written by any release, migrating older formats to [`SNAPSHOT_VERSION`].
+	///
+	/// Fields this release does not know are ignored, so snapshots written by newer releases
+	/// still load as long as the fields read here remain. Those that do not are rejected with
+	/// [`Error::Validation`] on `version` rather than a bare decoding error.
+	pub fn from_json(json: &str) -> Result<Self> {
+		let mut value = serde_json::from_str::<serde_json::Value>(json)?;
+		let version = value.get("version").and_then(serde_json::Value::as_u64).unwrap_or(0);
+
+		if version < u64::from(SNAPSHOT_VERSION) {
+			Self::migrate(&mut value)?;
+		}
+
+		serde_json::from_value(value).map_err(|err| {
+			if version > u64::from(SNAPSHOT_VERSION) {
+				Error::Validation {
+					field: "version",
+					reason: format!(
+						"Snapshot format {version} is newer than supported format {SNAPSHOT_VERSION}: {err}."
+					),
+				}
+			} else {
+				err.into()
+			}
+		})
+	}
+
+	// Upgrade an older snapshot to the current format. Unversioned snapshots only lack fields
+	// that default on deserialization, so they just gain a version; later formats add their
+	// steps here.
+	fn migrate(value: &mut serde_json::Value) -> Result<()> {
+		let Some(fields) = value.as_object_mut() else {
+			return Err(Error::Validation {
+				field: "version",
+				reason: "Snapshot must be a JSON object.".into(),
+			});
+		};
+
+		fields.insert("version".into(), SNAPSHOT_VERSION.into());
+
+		Ok(())
+	}
+
+	/// Validate snapshot metadata aligns with registration expectations.
+	pub fn validate(&self, registration: &IdentityProviderRegistration) -> Result<()> {
+		if self.jwks_json.len() as u64 > registration.max_response_bytes.as_u64() {
+			return Err(Error::Validation {
+				field: "jwks_json",
+				reason: format!(
+					"Snapshot exceeds max_response_bytes ({}).",
+					registration.max_response_bytes
+				),
+			});
+		}
+
+		if self.tenant_id != registration.tenant_id {
+			return Err(Error::Validation {
+				field: "tenant_id",
+				reason: "Snapshot tenant does not match registration.".into(),
+			});
+		}
+		if self.provider_id != registration.provider_id {
+			return Err(Error::Validation {
+				field: "provider_id",
+				reason: "Snapshot provider does not match registration.".into(),
+			});
+		}
+
+		if let Some(etag) = &self.etag
+			&& !etag.is_ascii()
+		{
+			return Err(Error::Validation { field: "etag", reason: "ETag must be ASCII.".into() });
+		}
+
+		if self.expires_at < self.persisted_at {
+			return Err(Error::Validation {
+				field: "expires_at",
+				reason: "Cannot be earlier than persisted_at.".into(),
+			});
+		}
+
+		Ok(())
+	}
+}
+
+/// Internal key mapping tenants and providers.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TenantProviderKey {
+	pub tenant_id: String,
+	pub provider_id: String,
+}
+impl TenantProviderKey {
+	pub fn new(tenant_id: impl Into<String>, provider_id: impl Into<String>) -> Self {
+		Self { tenant_id: tenant_id.into(), provider_id: provider_id.into() }
+	}
+}
+
+/// Builder for [`Registry`] enabling multi-tenant configuration.
+#[derive(Debug, Default)]
+pub struct RegistryBuilder {
+	config: RegistryConfig,
+	#[cfg(feature = "redis")]
+	redis: Option<RedisSnapshotStore>,
+	#[cfg(feature = "redis")]
+	redis_refresh_lock: bool,
+}
+impl RegistryBuilder {
+	/// Create a builder with default configuration.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Enforce HTTPS for registrations (enabled by default).
+	pub fn require_https(mut self, require_https: bool) -> Self {
+		self.config.require_https = require_https;
+
+		self
+	}
+
+	/// Require every registration to target publicly routable hosts (disabled by default).
+	///
+	/// Meant for control planes fetching tenant-supplied URLs, where private addresses are an SSRF
+	/// vector. See [`IdentityProviderRegistration::block_private_networks`].
+	pub fn block_private_networks(mut self, block: bool) -> Self {
+		self.config.block_private_networks = block;
+
+		self
+	}
+
+	/// Override the default refresh-early offset applied to registrations.
+	pub fn default_refresh_early(mut self, value: Duration) -> Self {
+		self.config.default_refresh_early = value;
+
+		self
+	}
+
+	/// Override the default stale-while-error window applied to registrations.
+	pub fn default_stale_while_error(mut self, value: Duration) -> Self {
+		self.config.default_stale_while_error = value;
+
+		self
+	}
+
+	/// Number of status samples retained per provider for [`Registry::status_history`].
+	///
+	/// Defaults to [`DEFAULT_STATUS_HISTORY_CAPACITY`]; zero disables history recording.
+	pub fn status_history_capacity(mut self, capacity: usize) -> Self {
+		self.config.status_history_capacity = capacity;
+
+		self
+	}
+
+	/// Number of upstream fetches captured per provider for [`Registry::recent_exchanges`].
+	///
+	/// Defaults to [`DEFAULT_EXCHANGE_CAPTURE_CAPACITY`]; zero disables capture.
+	pub fn exchange_capture_capacity(mut self, capacity: usize) -> Self {
+		self.config.exchange_capture_capacity = capacity;
+
+		self
+	}
+
+	/// Bound how long resolves wait on upstream fetches before serving stale keys or failing.
+	///
+	/// Unset by default, so resolves wait for the fetch's own retry policy. Applies to
+	/// [`Registry::resolve`], [`Registry::resolve_key`], [`Registry::decoding_key`], and
+	/// [`Registry::verify_token`]; see [`CacheManager::resolve_with_timeout`].
+	pub fn resolve_timeout(mut self, timeout: Duration) -> Self {
+		self.config.resolve_timeout = Some(timeout);
+
+		self
+	}
+
+	/// Number of events buffered per [`Registry::subscribe`] receiver.
+	///
+	/// Defaults to [`DEFAULT_EVENT_CAPACITY`]; receivers that fall further behind observe
+	/// `RecvError::Lagged` and skip the oldest events. Values below one are clamped to one.
+	pub fn event_capacity(mut self, capacity: usize) -> Self {
+		self.config.event_capacity = capacity;
+
+		self
+	}
+
+	/// Keep key sets in memory for at most `limit` providers, dropping the least recently used.
+	///
+	/// Evicted providers stay registered and report [`ProviderState::Empty`] until their next
+	/// resolve, which restores the keys from the snapshot store when they are still fresh there
+	/// and fetches them upstream otherwise. With a snapshot store, payloads are persisted before
+	/// they are dropped. Values below one are clamped to one. Applies to providers registered
+	/// afterwards.
+	pub fn max_resident_providers(mut self, limit: usize) -> Self {
+		self.config.max_resident_providers = Some(limit);
+
+		self
+	}
+
+	/// Keep cached key sets zstd-compressed, retaining up to `parsed_capacity` parsed sets across
+	/// all providers.
+	///
+	/// Sets outside that LRU are decompressed and parsed on each lookup, trading CPU for memory in
+	/// registries holding many large key sets. Applies to providers registered afterwards.
+	#[cfg(feature = "zstd")]
+	pub fn compress_jwks(mut self, parsed_capacity: usize) -> Self {
+		self.config.parsed_jwks = Some(Arc::new(ParsedJwksCache::new(parsed_capacity)));
+
+		self
+	}
+
+	/// Rate-limit JWKS and discovery fetches per upstream host across every provider.
+	///
+	/// Fetches beyond the budget wait for a token instead of failing. The policy is validated when
+	/// providers are registered. Applies to providers registered afterwards.
+	pub fn outbound_rate_limit(mut self, policy: RateLimitPolicy) -> Self {
+		self.config.rate_limiter = Some(Arc::new(HostRateLimiter::new(policy)));
+
+		self
+	}
+
+	/// Smear proactive refreshes across the TTL window and cap concurrent background refreshes.
+	///
+	/// Refreshes over the cap wait for a slot; foreground fetches for callers without usable keys
+	/// are never queued. The policy is validated when providers are registered. Applies to
+	/// providers registered afterwards.
+	pub fn refresh_schedule(mut self, policy: RefreshSchedulePolicy) -> Self {
+		self.config.scheduler = Some(Arc::new(RefreshScheduler::new(policy)));
+
+		self
+	}
+
+	/// Invoke `callback` whenever a refresh changes a provider's set of key ids.
+	///
+	/// The callback receives the added and removed kids and runs inline on the refresh task, so it
+	/// should return quickly. The initial fetch of a provider does not count as a rotation. Calling
+	/// this more than once registers additional callbacks.
+	pub fn on_keys_rotated<F>(mut self, callback: F) -> Self
+	where
+		F: Fn(&KeyRotation) + Send + Sync + 'static,
+	{
+		self.config.rotation_hooks.push(KeyRotationHook::new(callback));
+
+		self
+	}
+
+	/// Defer keys published ahead of use until `hook` reports them active.
+	///
+	/// Deferred keys stay cached, so `resolve_key` starts returning them as soon as their
+	/// activation time passes without another upstream fetch.
+	pub fn key_activation<A>(mut self, hook: A) -> Self
+	where
+		A: KeyActivation + 'static,
+	{
+		self.config.key_activation = Some(Arc::new(hook));
+
+		self
+	}
+
+	/// Choose how the global domain allowlist is enforced.
+	///
+	/// When unset, an empty allowlist behaves as [`AllowlistMode::AllowAll`] and a non-empty one as
+	/// [`AllowlistMode::List`].
+	pub fn allowlist_mode(mut self, mode: AllowlistMode) -> Self {
+		self.config.allowlist_mode = Some(mode);
+
+		self
+	}
+
+	/// Deny any host outside the global allowlist, including when the allowlist is empty.
+	pub fn strict_allowlist(self) -> Self {
+		self.allowlist_mode(AllowlistMode::List)
+	}
+
+	/// Add an entry to the global domain allowlist.
+	pub fn add_allowed_domain(mut self, domain: impl Into<String>) -> Self {
+		let raw = domain.into();
+
+		if let Some(domain) = security::canonicalize_dns_name(&raw)
+			&& !self.config.allowed_domains.contains(&domain)
+		{
+			self.config.allowed_domains.push(domain);
+		}
+
+		self
+	}
+
+	/// Replace the global domain allowlist.
+	pub fn allowed_domains<I, S>(mut self, domains: I) -> Self
+	where
+		I: IntoIterator<Item = S>,
+		S: Into<String>,
+	{
+		self.config.allowed_domains.clear();
+
+		for domain in domains {
+			self = self.add_allowed_domain(domain);
+		}
+
+		self
+	}
+
+	/// Share `client` across every provider that does not bring its own.
+	///
+	/// Use this to apply proxy settings, custom root certificates, or a shared connection pool.
+	/// Without it, each registration builds its own client with
+	/// [`default_client`](crate::http::client::default_client).
+	pub fn with_http_client(mut self, client: Client) -> Self {
+		self.config.http_client = Some(client);
+
+		self
+	}
+
+	/// Route fetches through `proxy` for every provider without its own client or proxy.
+	///
+	/// Ignored when [`with_http_client`](Self::with_http_client) supplies a shared client.
+	pub fn with_proxy(mut self, proxy: ProxySettings) -> Self {
+		self.config.proxy = Some(proxy);
+
+		self
+	}
+
+	/// Tune connection pooling and HTTP/2 for every client the registry builds.
+	///
+	/// Ignored for shared or per-registration HTTP clients, which carry their own settings.
+	pub fn http_client_config(mut self, config: HttpClientConfig) -> Self {
+		self.config.client_config = config;
+
+		self
+	}
+
+	/// Resolve upstream hosts through `resolver` in every client the registry builds.
+	///
+	/// Use [`HostResolver`](crate::http::resolve::HostResolver) to pin hosts to known addresses or
+	/// prefer an address family, or any [`Resolve`] implementation to query an internal resolver.
+	/// Registrations blocking private networks still drop non-public answers. Ignored for shared
+	/// or per-registration HTTP clients and for fetches routed through a proxy, which resolves
+	/// target hosts itself.
+	pub fn dns_resolver<R>(mut self, resolver: R) -> Self
+	where
+		R: Resolve + 'static,
+	{
+		self.config.resolver = Some(SharedResolver::new(resolver));
+
+		self
+	}
+
+	/// Choose the JSON layout of [`Registry::write_statuses_json`]; defaults to
+	/// [`StatusFormat::Legacy`].
+	pub fn status_format(mut self, format: StatusFormat) -> Self {
+		self.config.status_format = format;
+
+		self
+	}
+
+	/// Choose when registration restores persisted snapshots; defaults to
+	/// [`RestorePolicy::Inline`].
+	pub fn restore_policy(mut self, policy: RestorePolicy) -> Self {
+		self.config.restore_policy = policy;
+
+		self
+	}
+
+	/// Persist each provider's snapshot as soon as a refresh succeeds (disabled by default).
+	///
+	/// Without it, snapshots are only written by [`Registry::persist_all`] and graceful shutdown,
+	/// so a crash loses keys fetched since the last run. Writes happen in the background and
+	/// failures are logged. Has no effect without a snapshot store.
+	pub fn write_through(mut self, enabled: bool) -> Self {
+		self.config.write_through = enabled;
+
+		self
+	}
+
+	/// Coordinate refreshes across replicas sharing the snapshot store through `lock`.
+	///
+	/// Only the replica holding a provider's lease fetches upstream and persists the result; the
+	/// others adopt that snapshot instead of fetching. A replica falls back to fetching itself
+	/// when the lock is unreachable or the holder's lease runs out first, so a crashed holder
+	/// only delays refreshes by one lease. Requires a snapshot store.
+	pub fn with_refresh_lock(mut self, lock: Arc<dyn RefreshLock>) -> Self {
+		self.config.refresh_lock = Some(lock);
+
+		self
+	}
+
+	/// Propagate [`Registry::invalidate`] and [`Registry::invalidate_all`] to every registry on
+	/// `bus`.
+	///
+	/// Each registry applies invalidations published by the others to the providers it has
+	/// registered, so an emergency key rotation clears the whole fleet. Registries start
+	/// listening with their first registration.
+	pub fn with_invalidation_bus(mut self, bus: Arc<dyn InvalidationBus>) -> Self {
+		self.config.invalidation_bus = Some(bus);
+
+		self
+	}
+
+	/// Configure a custom snapshot store for persistence and warm starts.
+	pub fn with_snapshot_store(mut self, store: Arc<dyn SnapshotStore>) -> Self {
+		self.config.snapshot_store = Some(store);
+		#[cfg(feature = "redis")]
+		{
+			self.redis = None;
+		}
+
+		self
+	}
+
+	#[cfg(feature = "redis")]
+	/// Configure Redis-backed persistence for snapshots.
+	pub fn with_redis_client(mut self, client: redis::Client) -> Self {
+		self.config.snapshot_store = None;
+		self.redis = Some(RedisSnapshotStore::new(client));
+
+		self
+	}
+
+	#[cfg(feature = "redis")]
+	/// Adjust the Redis key namespace (defaults to `jwks-cache`).
+	pub fn redis_namespace(mut self, namespace: impl Into<String>) -> Self {
+		if let Some(redis) = self.redis.take() {
+			self.redis = Some(redis.with_namespace(namespace));
+		} else {
+			panic!("Redis client must be configured before setting namespace.");
+		}
+
+		self
+	}
+
+	#[cfg(feature = "redis")]
+	/// Coordinate refreshes through leases lasting `lease_duration` in the configured Redis
+	/// store; see [`Self::with_refresh_lock`].
+	pub fn redis_refresh_lock(mut self, lease_duration: Duration) -> Self {
+		if let Some(redis) = self.redis.take() {
+			self.redis = Some(redis.with_lease_duration(lease_duration));
+			self.redis_refresh_lock = true;
+		} else {
+			panic!("Redis client must be configured before enabling the refresh lock.");
+		}
+
+		self
+	}
+
+	/// Finalise the configuration and construct a [`Registry`].
+	pub fn build(self) -> Registry {
+		let mut config = self.config;
+
+		#[cfg(feature = "redis")]
+		if let Some(redis) = self.redis {
+			if self.redis_refresh_lock {
+				config.refresh_lock = Some(Arc::new(redis.clone()));
+			}
+
+			config.snapshot_store = Some(Arc::new(redis));
+		}
+
+		config.allowed_domains = security::normalize_allowlist(config.allowed_domains);
+
+		let (events, _) = broadcast::channel(config.event_capacity.max(1));
+
+		Registry {
+			inner: Arc::new(RegistryState {
+				providers: DashMap::new(),
+				config_managed: Mutex::new(HashSet::new()),
+				events,
+				persistence: Arc::new(Mutex::new(())),
+				origin: jitter::unique_token(),
+				invalidation_listener: OnceLock::new(),
+				resident: config
+					.max_resident_providers
+					.map(|limit| Arc::new(ResidentSet::new(limit))),
+				created_at: Instant::now(),
+			}),
+			config: Arc::new(config),
+		}
+	}
+
+	/// Build a registry, run `scope` with it, and stop its background tasks once `scope` returns.
+	///
+	/// Refreshes still in flight when `scope` completes are aborted and awaited, even if the
+	/// scope leaked a clone of the registry.
+	pub async fn run<F, Fut, T>(self, scope: F) -> T
+	where
+		F: FnOnce(Registry) -> Fut,
+		Fut: Future<Output = T>,
+	{
+		let registry = self.build();
+		let output = scope(registry.clone()).await;
+
+		registry.shutdown().await;
+
+		output
+	}
+}
+
+/// Registry state container.
+#[derive(Clone, Debug)]
+pub struct Registry {
+	inner: Arc<RegistryState>,
+	config: Arc<RegistryConfig>,
+}
+impl Registry {
+	/// Create a new registry instance with defaults.
+	pub fn new() -> Self {
+		Self::builder().build()
+	}
+
+	/// Create a [`RegistryBuilder`] for advanced configuration.
+	pub fn builder() -> RegistryBuilder {
+		RegistryBuilder::new()
+	}
+
+	/// Run `scope` with a default registry; see [`RegistryBuilder::run`].
+	pub async fn run<F, Fut, T>(scope: F) -> T
+	where
+		F: FnOnce(Registry) -> Fut,
+		Fut: Future<Output = T>,
+	{
+		Self::builder().run(scope).await
+	}
+
+	/// Abort every background refresh owned by this registry and wait for them to stop.
+	///
+	/// Background tasks are also aborted when the last clone of the registry is dropped; this
+	/// method lets callers wait for that explicitly. Later refreshes are spawned as usual.
+	pub async fn shutdown(&self) {
+		for handle in self.handles() {
+			handle.shutdown().await;
+		}
+	}
+
+	/// Stop background work for a process exit, keeping warm state.
+	///
+	/// Keepalive and recovery probes are aborted, and no further background refreshes are
+	/// scheduled. Refreshes already running get until [`ShutdownOptions::drain_timeout`] to finish
+	/// and are aborted afterwards. Snapshots are then persisted when
+	/// [`ShutdownOptions::persist`] is set and a store is configured, so the next process can warm
+	/// start from keys fetched during the drain. Resolves keep working and fetch in the
+	/// foreground.
+	pub async fn shutdown_gracefully(&self, options: &ShutdownOptions) -> Result<ShutdownReport> {
+		let deadline = Instant::now() + options.drain_timeout;
+		let mut report = ShutdownReport::default();
+
+		for handle in self.handles() {
+			let (drained, aborted) = handle.drain(deadline).await;
+
+			report.drained += drained;
+			report.aborted += aborted;
+		}
+
+		if options.persist && self.config.snapshot_store.is_some() {
+			self.persist_all().await?;
+
+			report.persisted = true;
+		}
+
+		tracing::info!(
+			drained = report.drained,
+			aborted = report.aborted,
+			persisted = report.persisted,
+			"registry shut down"
+		);
+
+		Ok(report)
+	}
+
+	/// Number of background refreshes currently owned by this registry.
+	pub fn background_tasks(&self) -> usize {
+		self.inner.providers.iter().map(|entry| entry.value().tasks.len()).sum()
+	}
+
+	/// Register or update a provider configuration.
+	///
+	/// Returns the effective registration after registry defaults, allowlist inheritance, and
+	/// normalisation have been applied.
+	pub async fn register(
+		&self,
+		registration: IdentityProviderRegistration,
+	) -> Result<Arc<IdentityProviderRegistration>> {
+		let prepared = self.prepare(registration).await?;
+
+		Ok(self.install(prepared).await)
+	}
+
+	/// Apply a changed registration to a registered provider without discarding its cache.
+	///
+	/// Settings such as refresh timing, TTL clamps, retry policy, and allowlists take effect
+	/// immediately, while the cached keys, status history, and metrics carry over; the cached
+	/// payload keeps the expiry computed when it was fetched. When the effective `jwks_url`
+	/// changes, the provider is rebuilt exactly as by [`Self::register`].
+	///
+	/// Returns [`Error::NotRegistered`] when the provider is not registered.
+	pub async fn update(
+		&self,
+		registration: IdentityProviderRegistration,
+	) -> Result<Arc<IdentityProviderRegistration>> {
+		let previous = self.handle(&registration.tenant_id, &registration.provider_id)?;
+		let mut prepared = self.prepare(registration).await?;
+
+		if prepared.manager.registration().jwks_url
+			== previous.manager.effective_registration().jwks_url
+			&& prepared.manager.registration().source == previous.manager.registration().source
+		{
+			prepared.manager = prepared.manager.with_state_of(&previous.manager);
+			prepared.preserved = true;
+		}
+
+		Ok(self.install(prepared).await)
+	}
+
+	// Everything `register` does that can fail before the registry changes: defaulting,
+	// validation, client construction, and discovery.
+	async fn prepare(
+		&self,
+		mut registration: IdentityProviderRegistration,
+	) -> Result<PreparedProvider> {
+		let source = Arc::new(registration.clone());
+
+		if self.config.require_https {
+			if !registration.require_https {
+				return Err(Error::Security(
+					"Registry requires HTTPS for all provider registrations.".into(),
+				));
+			}
+		} else {
+			registration.require_https = false;
+		}
+
+		if self.config.block_private_networks {
+			registration.block_private_networks = true;
+		}
+
+		registration.normalize_allowed_domains();
+
+		registration.refresh_early.get_or_insert(self.config.default_refresh_early);
+		registration.stale_while_error.get_or_insert(self.config.default_stale_while_error);
+
+		if registration.allowed_domains.is_empty()
+			&& !self.config.allowed_domains.is_empty()
+			&& self.config.allowlist_mode() == AllowlistMode::List
+		{
+			registration.allowed_domains = self.config.allowed_domains.clone();
+		}
+
+		let trusted_roots = registration.trusted_root_certificates()?;
+		let custom_client = !trusted_roots.is_empty()
+			|| !registration.pinned_spki.is_empty()
+			|| registration.client_identity.is_some()
+			|| registration.block_private_networks;
+		let (client, proxy) = match (&registration.http_client, &registration.proxy) {
+			// Custom TLS and resolver settings need a client built for them, so skip the shared
+			// client.
+			(None, None) => (
+				self.config.http_client.as_ref().filter(|_| !custom_client),
+				self.config.proxy.as_ref(),
+			),
+			(client, proxy) => (client.as_ref(), proxy.as_ref()),
+		};
+		let client = http_client::build_client(
+			client,
+			proxy,
+			&trusted_roots,
+			&registration.pinned_spki,
+			registration.client_identity.as_ref(),
+			registration.block_private_networks,
+			self.config.resolver.as_ref(),
+			&self.config.client_config,
+		)?;
+		self.config.client_config.validate()?;
+
+		if self.config.refresh_lock.is_some() && self.config.snapshot_store.is_none() {
+			return Err(Error::Validation {
+				field: "refresh_lock",
+				reason: "Requires a snapshot store to share refreshed keys.".into(),
+			});
+		}
+
+		if let Some(limiter) = &self.config.rate_limiter {
+			limiter.policy().validate()?;
+		}
+		if let Some(scheduler) = &self.config.scheduler {
+			scheduler.policy().validate()?;
+		}
+
+		let discovered = match registration.discovery.clone() {
+			Some(settings) => {
+				self.ensure_host_allowed(&settings.issuer)?;
+				settings.validate(&registration)?;
+
+				if let Some(limiter) = &self.config.rate_limiter {
+					limiter.acquire(&settings.issuer).await;
+				}
+
+				let document = discovery::fetch_discovery(
+					&client,
+					&registration,
+					&self.config.host_allowlist(),
+					&settings,
+				)
+				.await?;
+
+				registration.jwks_url = document.metadata.jwks_uri.clone();
+
+				Some(document)
+			},
+			None => None,
+		};
+
+		self.ensure_host_allowed(&registration.jwks_url)?;
+
+		for mirror in &registration.mirror_urls {
+			self.ensure_host_allowed(mirror)?;
+		}
+
+		registration.validate()?;
+
+		let key = TenantProviderKey::new(&registration.tenant_id, &registration.provider_id);
+		let tasks = TaskSet::new();
+		let manager = CacheManager::with_client(registration.clone(), client)
+			.with_history_capacity(self.config.status_history_capacity)
+			.with_exchange_capacity(self.config.exchange_capture_capacity)
+			.with_resolve_timeout(self.config.resolve_timeout)
+			.with_rotation_hooks(self.config.rotation_hooks.clone())
+			.with_key_activation(self.config.key_activation.clone())
+			.with_event_sender(self.inner.events.clone())
+			.with_rate_limiter(self.config.rate_limiter.clone())
+			.with_host_allowlist(self.config.host_allowlist())
+			.with_refresh_scheduler(self.config.scheduler.clone())
+			.with_resident_set(self.inner.resident.clone())
+			.with_task_handle(tasks.handle());
+		let manager = match &self.config.snapshot_store {
+			Some(store) => manager
+				.with_snapshot_store(store.clone(), self.inner.persistence.clone())
+				.with_write_through(self.config.write_through)
+				.with_refresh_lock(self.config.refresh_lock.clone()),
+			None => manager,
+		};
+		#[cfg(feature = "zstd")]
+		let manager = manager.with_compressed_storage(self.config.parsed_jwks.clone());
+
+		if let Some(document) = discovered {
+			manager.store_discovery(document).await;
+		}
+
+		Ok(PreparedProvider { source, key, manager, tasks, preserved: false })
+	}
+
+	// Make a prepared provider visible. Nothing here can fail, so a reload installing several
+	// providers never stops partway; snapshot restore failures are logged instead.
+	async fn install(&self, prepared: PreparedProvider) -> Arc<IdentityProviderRegistration> {
+		let PreparedProvider { source, key, manager, tasks, preserved } = prepared;
+
+		self.listen_for_invalidations();
+
+		#[cfg(feature = "metrics")]
+		let metrics = manager.metrics();
+		let registration = manager.registration();
+		let (restored_sender, restored) = watch::channel(false);
+		let handle = Arc::new(ProviderHandle {
+			source,
+			registration: registration.clone(),
+			manager,
+			tasks,
+			restored,
+			#[cfg(feature = "metrics")]
+			metrics,
+		});
+
+		// The replaced manager's refreshes would only update a cache nobody reads any more.
+		if let Some(previous) = self.inner.providers.insert(key.clone(), handle.clone()) {
+			previous.manager.retire().await;
+			previous.abort_refreshes();
+		}
+
+		match (&self.config.snapshot_store, self.config.restore_policy) {
+			_ if preserved => {
+				restored_sender.send_replace(true);
+			},
+			(Some(store), RestorePolicy::Inline) => {
+				let restore = async {
+					if let Some(snapshot) = store.load(&key.tenant_id, &key.provider_id).await? {
+						handle.manager.restore_snapshot(snapshot).await?;
+					}
+
+					Ok::<_, Error>(())
+				};
+
+				// The provider is already live, so a failed restore only costs a cold first fetch.
+				if let Err(err) = restore.await {
+					tracing::warn!(
+						tenant = %key.tenant_id,
+						provider = %key.provider_id,
+						error = %err,
+						"snapshot restore failed"
+					);
+				}
+
+				restored_sender.send_replace(true);
+			},
+			(Some(store), RestorePolicy::Background) => {
+				let store = store.clone();
+				let manager = handle.manager.clone();
+				let key = key.clone();
+
+				handle.tasks.handle().spawn(async move {
+					let restore = async {
+						if let Some(snapshot) = store.load(&key.tenant_id, &key.provider_id).await?
+						{
+							manager.restore_snapshot_if_empty(snapshot).await?;
+						}
+
+						Ok::<_, Error>(())
+					};
+
+					if let Err(err) = restore.await {
+						tracing::warn!(
+							tenant = %key.tenant_id,
+							provider = %key.provider_id,
+							error = %err,
+							"background snapshot restore failed"
+						);
+					}
+
+					restored_sender.send_replace(true);
+				});
+			},
+			_ => {
+				restored_sender.send_replace(true);
+			},
+		}
+
+		handle.manager.start_keepalive();
+		self.emit(&key.tenant_id, &key.provider_id, CacheEventKind::ProviderRegistered);
+
+		registration
+	}
+
+	/// Return the registration currently in force for a tenant/provider pair.
+	///
+	/// The result reflects registry defaulting and normalisation applied by [`Self::register`],
+	/// and any `jwks_url` a refreshed discovery document has since moved the provider to.
+	pub async fn effective_registration(
+		&self,
+		tenant_id: &str,
+		provider_id: &str,
+	) -> Result<Arc<IdentityProviderRegistration>> {
+		Ok(self.handle(tenant_id, provider_id)?.manager.effective_registration())
+	}
+
+	/// Effective registrations, optionally limited to one tenant, ordered by tenant and provider.
+	///
+	/// Each entry is what [`Self::effective_registration`] would return for that provider.
+	pub fn list(&self, tenant_id: Option<&str>) -> Vec<Arc<IdentityProviderRegistration>> {
+		let mut registrations = self
+			.handles()
+			.into_iter()
+			.filter(|handle| tenant_id.is_none_or(|tenant| handle.registration.tenant_id == tenant))
+			.map(|handle| handle.manager.effective_registration())
+			.collect::<Vec<_>>();
+
+		registrations
+			.sort_by(|a, b| (&a.tenant_id, &a.provider_id).cmp(&(&b.tenant_id, &b.provider_id)));
+
+		registrations
+	}
+
+	/// Tenants with at least one registered provider, sorted and deduplicated.
+	pub fn tenants(&self) -> Vec<String> {
+		let mut tenants = self
+			.inner
+			.providers
+			.iter()
+			.map(|entry| entry.key().tenant_id.clone())
+			.collect::<Vec<_>>();
+
+		tenants.sort_unstable();
+		tenants.dedup();
+
+		tenants
+	}
+
+	/// Return the cached OpenID discovery document for a provider registered via discovery.
+	pub async fn discovery_document(
+		&self,
+		tenant_id: &str,
+		provider_id: &str,
+	) -> Result<Option<Arc<OpenIdConfiguration>>> {
+		let handle = self.handle(tenant_id, provider_id)?;
+
+		Ok(handle.manager.discovery().await.map(|document| document.metadata))
+	}
+
+	/// Resolve JWKS for a tenant/provider pair.
+	pub async fn resolve(
+		&self,
+		tenant_id: &str,
+		provider_id: &str,
+		kid: Option<&str>,
+	) -> Result<Arc<JwkSet>> {
+		self.handle(tenant_id, provider_id)?.manager.resolve(kid).await
+	}
+
+	/// Resolve JWKS for a tenant/provider pair with per-call [`ResolveOptions`].
+	pub async fn resolve_with(
+		&self,
+		tenant_id: &str,
+		provider_id: &str,
+		kid: Option<&str>,
+		options: &ResolveOptions,
+	) -> Result<Arc<JwkSet>> {
+		self.handle(tenant_id, provider_id)?.manager.resolve_with(kid, options).await
+	}
+
+	/// Resolve a single signing key by `kid`.
+	///
+	/// Unknown kids trigger one forced revalidation, after which repeated misses are answered from
+	/// a negative cache for [`IdentityProviderRegistration::missing_kid_ttl`].
+	pub async fn resolve_key(
+		&self,
+		tenant_id: &str,
+		provider_id: &str,
+		kid: &str,
+	) -> Result<Arc<Jwk>> {
+		self.handle(tenant_id, provider_id)?.manager.resolve_key(kid).await
+	}
+
+	/// Resolve the [`DecodingKey`] for `kid`, converted once per fetched JWKS payload.
+	pub async fn decoding_key(
+		&self,
+		tenant_id: &str,
+		provider_id: &str,
+		kid: &str,
+	) -> Result<Arc<DecodingKey>> {
+		self.handle(tenant_id, provider_id)?.manager.decoding_key(kid).await
+	}
+
+	/// Verify a JWT against the provider's signing keys.
+	///
+	/// The `kid` from the token header is resolved via [`Registry::decoding_key`], so an unknown
+	/// kid triggers one forced refresh before the token is rejected. The token's `alg` must be
+	/// listed in `validation.algorithms`.
+	pub async fn verify_token<T>(
+		&self,
+		tenant_id: &str,
+		provider_id: &str,
+		token: &str,
+		validation: &Validation,
+	) -> Result<TokenData<T>>
+	where
+		T: DeserializeOwned,
+	{
+		let header = jsonwebtoken::decode_header(token)
+			.map_err(|err| Error::InvalidToken(format!("Malformed header: {err}.")))?;
+		let kid = header
+			.kid
+			.as_deref()
+			.ok_or_else(|| Error::InvalidToken("Header is missing `kid`.".into()))?;
+		let key = self.decoding_key(tenant_id, provider_id, kid).await?;
+
+		jsonwebtoken::decode(token, &key, validation)
+			.map_err(|err| Error::InvalidToken(format!("{err}.")))
+	}
+
+	/// Merge the signing keys of every provider registered for `tenant_id` into one JWKS.
+	///
+	/// Providers are resolved concurrently and merged in `provider_id` order; a tenant without
+	/// providers yields an empty set. A provider that fails to resolve is logged and left out, so
+	/// one outage does not take down the union; only when every provider fails is the first error,
+	/// in `provider_id` order, returned. With `prefix_kids`, every `kid` is rewritten to
+	/// `{provider_id}:{kid}` so keys from different providers cannot collide; otherwise a `kid`
+	/// published by more than one provider fails with [`Error::Validation`].
+	pub async fn aggregate_jwks(&self, tenant_id: &str, prefix_kids: bool) -> Result<JwkSet> {
+		let mut resolves = JoinSet::new();
+
+		for handle in self.handles() {
+			if handle.registration.tenant_id == tenant_id {
+				resolves.spawn(async move {
+					let outcome = handle.manager.resolve(None).await;
+
+					(handle, outcome)
+				});
+			}
+		}
+
+		let mut outcomes = Vec::with_capacity(resolves.len());
+
+		while let Some(joined) = resolves.join_next().await {
+			// Resolves are never aborted, so a join error can only carry a panic.
+			outcomes.push(joined.unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic())));
+		}
+
+		outcomes
+			.sort_by(|(a, _), (b, _)| a.registration.provider_id.cmp(&b.registration.provider_id));
+
+		let mut keys = Vec::new();
+		let mut seen = HashMap::new();
+		let mut resolved = false;
+		let mut first_error = None;
+
+		for (handle, outcome) in outcomes {
+			let provider_id = &handle.registration.provider_id;
+			let jwks = match outcome {
+				Ok(jwks) => jwks,
+				Err(err) => {
+					tracing::warn!(
+						tenant = %tenant_id,
+						provider = %provider_id,
+						error = %err.redacted(),
+						"leaving provider out of the aggregated jwks"
+					);
+
+					first_error.get_or_insert(err);
+
+					continue;
+				},
+			};
+
+			resolved = true;
+
+			for jwk in &jwks.keys {
+				let mut jwk = jwk.clone();
+
+				if let Some(kid) = jwk.common.key_id.as_mut() {
+					if prefix_kids {
+						*kid = format!("{provider_id}:{kid}");
+					}
+					if let Some(other) = seen.insert(kid.clone(), provider_id.clone())
+						&& &other != provider_id
+					{
+						return Err(Error::Validation {
+							field: "kid",
+							reason: format!(
+								"Key id '{kid}' is published by providers '{other}' and '{provider_id}'."
+							),
+						});
+					}
+				}
+
+				keys.push(jwk);
+			}
+		}
+
+		match first_error {
+			Some(err) if !resolved => Err(err),
+			_ => Ok(JwkSet { keys }),
+		}
+	}
+
+	/// Trigger a manual refresh for a registered provider.
+	pub async fn refresh(&self, tenant_id: &str, provider_id: &str) -> Result<()> {
+		self.handle(tenant_id, provider_id)?.manager.trigger_refresh().await
+	}
+
+	/// Refresh a provider now and wait for the result, accepting a key set that replaces every
+	/// `kid`.
+	///
+	/// This is the override for [`IdentityProviderRegistration::reject_full_rotation`] once a full
+	/// rotation has been confirmed as expected.
+	pub async fn force_refresh(&self, tenant_id: &str, provider_id: &str) -> Result<()> {
+		self.handle(tenant_id, provider_id)?.manager.force_refresh().await
+	}
+
+	/// Discard cached keys for a provider, forcing a fresh fetch on the next resolve.
+	///
+	/// With `purge_snapshot`, the persisted snapshot is deleted too so a restart cannot restore the
+	/// discarded keys.
+	pub async fn invalidate(
+		&self,
+		tenant_id: &str,
+		provider_id: &str,
+		purge_snapshot: bool,
+	) -> Result<()> {
+		self.handle(tenant_id, provider_id)?.manager.invalidate().await;
+
+		if purge_snapshot && let Some(store) = &self.config.snapshot_store {
+			store.delete(tenant_id, provider_id).await?;
+		}
+
+		self.announce(InvalidationTarget::Provider {
+			tenant_id: tenant_id.to_owned(),
+			provider_id: provider_id.to_owned(),
+		})
+		.await
+	}
+
+	/// Discard cached keys for every provider, returning how many were invalidated.
+	///
+	/// All in-memory entries are cleared before any snapshot is deleted. Snapshot deletion is
+	/// attempted for every provider and the first failure is returned.
+	pub async fn invalidate_all(&self, purge_snapshot: bool) -> Result<usize> {
+		let handles = self.handles();
+
+		for handle in &handles {
+			handle.manager.invalidate().await;
+		}
+
+		if purge_snapshot && let Some(store) = &self.config.snapshot_store {
+			let mut first_error = None;
+
+			for handle in &handles {
+				let registration = &handle.registration;
+
+				if let Err(err) =
+					store.delete(&registration.tenant_id, &registration.provider_id).await
+				{
+					tracing::warn!(
+						tenant = %registration.tenant_id,
+						provider = %registration.provider_id,
+						error = %err,
+						"failed to delete persisted snapshot"
+					);
+
+					first_error.get_or_insert(err);
+				}
+			}
+
+			if let Some(err) = first_error {
+				return Err(err);
+			}
+		}
+
+		self.announce(InvalidationTarget::All).await?;
+
+		Ok(handles.len())
+	}
+
+	/// Remove a provider registration if present, deleting its persisted snapshot.
+	///
+	/// Background refreshes are aborted, and refreshes still running on behalf of other callers
+	/// finish without updating the cache, persisting, or emitting events. Once this returns, no
+	/// snapshot for the provider remains in the store, and
+	/// [`CacheEventKind::ProviderUnregistered`] is the last event emitted for the registration.
+	///
+	/// The snapshot is deleted from the store itself, not from a per-registry view. When replicas
+	/// share a store, such as one Redis instance, unregistering on one replica (including a
+	/// config reload that drops the provider) removes the snapshot the others warm-start from,
+	/// until one of them persists it again.
+	pub async fn unregister(&self, tenant_id: &str, provider_id: &str) -> Result<bool> {
+		let key = TenantProviderKey::new(tenant_id, provider_id);
+		let Some((_, handle)) = self.inner.providers.remove(&key) else {
+			return Ok(false);
+		};
+
+		handle.manager.retire().await;
+		handle.abort_refreshes();
+
+		// Wait for any `persist_all` that captured this provider before deleting its snapshot.
+		let _persistence = self.inner.persistence.lock().await;
+
+		if let Some(store) = &self.config.snapshot_store
+			&& let Err(err) = store.delete(tenant_id, provider_id).await
+		{
+			tracing::warn!(
+				tenant = tenant_id,
+				provider = provider_id,
+				error = %err,
+				"failed to delete persisted snapshot"
+			);
+		}
+
+		self.emit(tenant_id, provider_id, CacheEventKind::ProviderUnregistered);
+
+		Ok(true)
+	}
+
+	/// Subscribe to lifecycle events for every provider in this registry.
+	///
+	/// Only events emitted after subscribing are delivered. The buffer is bounded by
+	/// [`RegistryBuilder::event_capacity`].
+	pub fn subscribe(&self) -> broadcast::Receiver<CacheEvent> {
+		self.inner.events.subscribe()
+	}
+
+	/// Format used by [`Self::write_statuses_json`]; see [`RegistryBuilder::status_format`].
+	pub fn status_format(&self) -> StatusFormat {
+		self.config.status_format
+	}
+
+	/// Fetch status information for a specific provider.
+	pub async fn provider_status(
+		&self,
+		tenant_id: &str,
+		provider_id: &str,
+	) -> Result<ProviderStatus> {
+		Ok(self.handle(tenant_id, provider_id)?.status().await)
+	}
+
+	/// Status samples recorded after each upstream refresh, oldest first.
+	///
+	/// The history is bounded by [`RegistryBuilder::status_history_capacity`], which makes it
+	/// suitable for drawing short hit-rate and error trends without an external time-series store.
+	pub async fn status_history(
+		&self,
+		tenant_id: &str,
+		provider_id: &str,
+	) -> Result<Vec<StatusSample>> {
+		Ok(self.handle(tenant_id, provider_id)?.manager.status_history().await)
+	}
+
+	/// Redacted request/response headers and timing of the provider's most recent fetches.
+	///
+	/// Failed attempts are included with their error, so this answers what the identity provider
+	/// actually returned without enabling debug logging. Bounded by
+	/// [`RegistryBuilder::exchange_capture_capacity`].
+	pub async fn recent_exchanges(
+		&self,
+		tenant_id: &str,
+		provider_id: &str,
+	) -> Result<Vec<CapturedExchange>> {
+		Ok(self.handle(tenant_id, provider_id)?.manager.recent_exchanges().await)
+	}
+
+	/// Collect a [`SupportBundle`] for one provider.
+	///
+	/// Serialise it, for example with `serde_json::to_string_pretty`, to get a single JSON
+	/// document for a support ticket.
+	pub async fn support_bundle(
+		&self,
+		tenant_id: &str,
+		provider_id: &str,
+	) -> Result<SupportBundle> {
+		let handle = self.handle(tenant_id, provider_id)?;
+
+		Ok(SupportBundle {
+			crate_version: env!("CARGO_PKG_VERSION").to_owned(),
+			generated_at: clock::utc_now(),
+			registration: redact_registration(&handle.manager.effective_registration()),
+			status: handle.status().await,
+			#[cfg(feature = "metrics")]
+			metrics: handle.metrics.snapshot(),
+			history: handle.manager.status_history().await,
+			exchanges: handle.manager.recent_exchanges().await,
+		})
+	}
+
+	/// Fetch status for every registered provider.
+	pub async fn all_statuses(&self) -> Vec<ProviderStatus> {
+		let handles = self.handles();
+		let mut statuses = Vec::with_capacity(handles.len());
+
+		for handle in handles {
+			statuses.push(handle.status().await);
+		}
+
+		statuses
+	}
+
+	/// Fetch the statuses matching `filter`, skipping the first `offset` and returning at most
+	/// `limit`.
+	///
+	/// Providers are ordered by tenant, then provider, so consecutive pages do not overlap while
+	/// the registrations are unchanged.
+	pub async fn statuses_page(
+		&self,
+		filter: &StatusFilter,
+		offset: usize,
+		limit: usize,
+	) -> StatusPage {
+		let mut handles = self.handles();
+
+		handles.retain(|handle| {
+			filter.tenant_id.as_ref().is_none_or(|tenant| *tenant == handle.registration.tenant_id)
+		});
+		handles.sort_by(|a, b| {
+			a.registration
+				.tenant_id
+				.cmp(&b.registration.tenant_id)
+				.then_with(|| a.registration.provider_id.cmp(&b.registration.provider_id))
+		});
+
+		let mut page = StatusPage { statuses: Vec::new(), total: 0, next_offset: None };
+
+		for handle in handles {
+			let status = handle.status().await;
+
+			if !filter.matches(&status) {
+				continue;
+			}
+			if (offset..offset.saturating_add(limit)).contains(&page.total) {
+				page.statuses.push(status);
+			}
+
+			page.total += 1;
+		}
+
+		let end = offset.saturating_add(page.statuses.len());
+
+		if end < page.total && !page.statuses.is_empty() {
+			page.next_offset = Some(end);
+		}
+
+		page
+	}
+
+	/// Summarise state counts, errors, and staleness across every provider.
+	///
+	/// Only cache state is inspected (no metrics snapshots), so this is cheap enough to call from
+	/// readiness probes.
+	pub async fn aggregate_status(&self) -> AggregateStatus {
+		let mut aggregate = AggregateStatus::default();
+		let mut failing = Vec::new();
+		let mut all_ready_at = Some(self.inner.created_at);
+
+		for handle in self.handles() {
+			let snapshot = handle.manager.snapshot().await;
+
+			aggregate.total_providers += 1;
+			all_ready_at = all_ready_at.zip(handle.manager.first_ready_at()).map(|(a, b)| a.max(b));
+
+			let payload = match &snapshot.state {
+				CacheState::Empty => {
+					aggregate.by_state.empty += 1;
+
+					continue;
+				},
+				CacheState::Loading => {
+					aggregate.by_state.loading += 1;
+
+					continue;
+				},
+				CacheState::Ready(payload) => {
+					aggregate.by_state.ready += 1;
+
+					payload
+				},
+				CacheState::Refreshing(payload) => {
+					aggregate.by_state.refreshing += 1;
+
+					payload
+				},
+			};
+
+			aggregate.total_error_count += u64::from(payload.error_count);
+			aggregate.worst_staleness = aggregate
+				.worst_staleness
+				.max(snapshot.captured_at.saturating_duration_since(payload.expires_at));
+
+			if payload.error_count > 0 {
+				failing.push(FailingProvider {
+					tenant_id: handle.registration.tenant_id.clone(),
+					provider_id: handle.registration.provider_id.clone(),
+					error_count: payload.error_count,
+				});
+			}
+		}
+
+		failing.sort_by(|a, b| {
+			b.error_count
+				.cmp(&a.error_count)
+				.then_with(|| a.tenant_id.cmp(&b.tenant_id))
+				.then_with(|| a.provider_id.cmp(&b.provider_id))
+		});
+		failing.truncate(AGGREGATE_TOP_FAILING);
+
+		aggregate.top_failing = failing;
+
+		if aggregate.total_providers > 0 {
+			aggregate.time_to_all_ready = all_ready_at
+				.map(|ready_at| ready_at.saturating_duration_since(self.inner.created_at));
+		}
+
+		aggregate
+	}
+
+	/// Classify every provider's health and roll it up into a single verdict.
+	///
+	/// Like [`Self::aggregate_status`], only cache state is inspected. See [`HealthStatus`] for
+	/// how providers are classified; the registry is unhealthy only when every provider is, so
+	/// one failing tenant does not take down a shared service. An empty registry is healthy.
+	pub async fn health(&self) -> HealthReport {
+		let mut report = HealthReport::default();
+		let mut providers = Vec::new();
+
+		for handle in self.handles() {
+			let snapshot = handle.manager.snapshot().await;
+			let circuit_open =
+				snapshot.circuit.is_some_and(|circuit| circuit.state != CircuitState::Closed);
+			let (status, reason, error_count) = match snapshot.state.payload() {
+				None => (HealthStatus::Unhealthy, "no usable keys are cached", 0),
+				Some(payload) => {
+					let expired = payload.is_expired(snapshot.captured_at);
+					let (status, reason) = if expired && payload.error_count > 0 {
+						if payload.can_serve_stale(snapshot.captured_at) {
+							(HealthStatus::Degraded, "serving stale keys while refreshes fail")
+						} else {
+							(HealthStatus::Unhealthy, "keys expired and refreshes are failing")
+						}
+					} else if circuit_open {
+						(HealthStatus::Degraded, "circuit breaker is not closed")
+					} else if payload.error_count > 0 {
+						(HealthStatus::Degraded, "refreshes are failing")
+					} else {
+						(HealthStatus::Healthy, "")
+					};
+
+					(status, reason, payload.error_count)
+				},
+			};
+
+			match status {
+				HealthStatus::Healthy => {
+					report.healthy += 1;
+
+					continue;
+				},
+				HealthStatus::Degraded => report.degraded += 1,
+				HealthStatus::Unhealthy => report.unhealthy += 1,
+			}
+
+			providers.push(ProviderHealth {
+				tenant_id: handle.registration.tenant_id.clone(),
+				provider_id: handle.registration.provider_id.clone(),
+				status,
+				reason: reason.into(),
+				error_count,
+			});
+		}
+
+		providers.sort_by(|a, b| {
+			b.status
+				.cmp(&a.status)
+				.then_with(|| b.error_count.cmp(&a.error_count))
+				.then_with(|| a.tenant_id.cmp(&b.tenant_id))
+				.then_with(|| a.provider_id.cmp(&b.provider_id))
+		});
+		providers.truncate(AGGREGATE_TOP_FAILING);
+
+		report.worst = providers;
+		report.status = if report.degraded + report.unhealthy == 0 {
+			HealthStatus::Healthy
+		} else if report.healthy + report.degraded == 0 {
+			HealthStatus::Unhealthy
+		} else {
+			HealthStatus::Degraded
+		};
+
+		report
+	}
+
+	/// Stream the status of every provider, computing each one only when it is polled.
+	pub fn status_stream(&self) -> impl Stream<Item = ProviderStatus> + Send + 'static {
+		stream::iter(self.handles()).then(|handle| async move { handle.status().await })
+	}
+
+	/// Serialise every provider status into `writer` in the configured [`StatusFormat`].
+	///
+	/// Statuses are written one at a time through [`Self::status_stream`], so large registries
+	/// never buffer the full document and the runtime is never blocked on I/O.
+	pub async fn write_statuses_json<W>(&self, writer: W) -> Result<()>
+	where
+		W: AsyncWrite + Unpin,
+	{
+		self.write_statuses(writer, ChunkEncoding::Identity).await?;
+
+		Ok(())
+	}
+
+	/// Serialise every provider status as gzip-compressed JSON, returning the inner writer.
+	#[cfg(feature = "gzip")]
+	pub async fn write_statuses_json_gzip<W>(&self, writer: W) -> Result<W>
+	where
+		W: AsyncWrite + Unpin,
+	{
+		self.write_statuses(writer, ChunkEncoding::gzip()).await
+	}
+
+	async fn write_statuses<W>(&self, mut writer: W, encoding: ChunkEncoding) -> Result<W>
+	where
+		W: AsyncWrite + Unpin,
+	{
+		let format = self.config.status_format;
+		let (prefix, suffix): (&[u8], &[u8]) = match format {
+			StatusFormat::Legacy => (b"[", b"]"),
+			StatusFormat::V1 => (br#"{"format_version":1,"statuses":["#, b"]}"),
+		};
+		let mut document = StatusDocument::new(format, encoding);
+		let mut statuses = pin::pin!(self.status_stream());
 
-		registration.normalize_allowed_domains();
+		writer.write_all(&document.open(prefix)?).await?;
 
-		if registration.refresh_early == DEFAULT_REFRESH_EARLY {
-			registration.refresh_early = self.config.default_refresh_early;
-		}
-		if registration.stale_while_error == DEFAULT_STALE_WHILE_ERROR {
-			registration.stale_while_error = self.config.default_stale_while_error;
-		}
-		if registration.allowed_domains.is_empty() && !self.config.allowed_domains.is_empty() {
-			registration.allowed_domains = self.config.allowed_domains.clone();
+		while let Some(status) = statuses.next().await {
+			writer.write_all(&document.push(&status)?).await?;
 		}
 
-		if let Some(host) = registration.jwks_url.host_str()
-			&& !security::host_is_allowed(host, &self.config.allowed_domains)
-		{
-			return Err(Error::Security(format!(
-				"Host '{host}' is not in the registry allowlist."
-			)));
-		}
+		writer.write_all(&document.close(suffix)?).await?;
+		writer.flush().await?;
 
-		let key = TenantProviderKey::new(&registration.tenant_id, &registration.provider_id);
-		let manager = CacheManager::new(registration.clone())?;
-		#[cfg(feature = "metrics")]
-		let metrics = manager.metrics();
-		let handle = Arc::new(ProviderHandle {
-			registration: Arc::new(registration),
-			manager,
-			#[cfg(feature = "metrics")]
-			metrics,
-		});
+		Ok(writer)
+	}
 
-		{
-			let mut state = self.inner.write().await;
+	/// Persist snapshots for every provider when persistence is configured.
+	pub async fn persist_all(&self) -> Result<()> {
+		if let Some(store) = &self.config.snapshot_store {
+			let _persistence = self.inner.persistence.lock().await;
+			let handles = self.handles();
+			let mut snapshots = Vec::new();
 
-			state.providers.insert(key.clone(), handle.clone());
-		}
+			for handle in handles {
+				if let Some(snapshot) = handle.manager.persistent_snapshot().await? {
+					snapshots.push(snapshot);
+				}
+			}
 
-		#[cfg(feature = "redis")]
-		if let Some(persistence) = &self.config.persistence
-			&& let Some(snapshot) = persistence.load(&key.tenant_id, &key.provider_id).await?
-		{
-			handle.manager.restore_snapshot(snapshot).await?;
+			store.persist(&snapshots).await?;
 		}
 
 		Ok(())
 	}
 
-	/// Resolve JWKS for a tenant/provider pair.
-	pub async fn resolve(
-		&self,
-		tenant_id: &str,
-		provider_id: &str,
-		kid: Option<&str>,
-	) -> Result<Arc<JwkSet>> {
-		let key = TenantProviderKey::new(tenant_id, provider_id);
-		let handle = {
-			let state = self.inner.read().await;
-
-			state.providers.get(&key).cloned()
-		};
-		let handle = handle.ok_or_else(|| Error::NotRegistered {
-			tenant: tenant_id.to_string(),
-			provider: provider_id.to_string(),
-		})?;
+	/// Wait until every registered provider has finished restoring its persisted snapshot.
+	///
+	/// Only [`RestorePolicy::Background`] restores can still be running once
+	/// [`Self::register`] returns. Await this before serving traffic so resolves do not fetch keys
+	/// a snapshot would have provided.
+	pub async fn wait_ready(&self) {
+		for handle in self.handles() {
+			let mut restored = handle.restored.clone();
 
-		handle.manager.resolve(kid).await
+			// A closed channel means the restore was aborted, which is just as final.
+			let _ = restored.wait_for(|restored| *restored).await;
+		}
 	}
 
-	/// Trigger a manual refresh for a registered provider.
-	pub async fn refresh(&self, tenant_id: &str, provider_id: &str) -> Result<()> {
-		let key = TenantProviderKey::new(tenant_id, provider_id);
-		let handle = {
-			let state = self.inner.read().await;
-			state.providers.get(&key).cloned()
-		};
-		let handle = handle.ok_or_else(|| Error::NotRegistered {
-			tenant: tenant_id.to_string(),
-			provider: provider_id.to_string(),
-		})?;
-
-		handle.manager.trigger_refresh().await
+	/// Fetch keys for every registered provider, returning once each is ready or has failed.
+	///
+	/// Runs at most [`DEFAULT_WARM_UP_CONCURRENCY`] fetches at once; see
+	/// [`Self::warm_up_with_concurrency`].
+	pub async fn warm_up(&self) -> WarmUpReport {
+		self.warm_up_with_concurrency(DEFAULT_WARM_UP_CONCURRENCY).await
 	}
 
-	/// Remove a provider registration if present.
-	pub async fn unregister(&self, tenant_id: &str, provider_id: &str) -> Result<bool> {
-		let key = TenantProviderKey::new(tenant_id, provider_id);
-		let mut state = self.inner.write().await;
-
-		Ok(state.providers.remove(&key).is_some())
-	}
+	/// Fetch keys for every registered provider, at most `concurrency` at once.
+	///
+	/// Background snapshot restores are awaited first, and providers already holding keys are not
+	/// refetched. Await this before reporting readiness so the first requests do not pay for
+	/// cold fetches. A `concurrency` of zero is treated as one.
+	pub async fn warm_up_with_concurrency(&self, concurrency: usize) -> WarmUpReport {
+		self.wait_ready().await;
 
-	/// Fetch status information for a specific provider.
-	pub async fn provider_status(
-		&self,
-		tenant_id: &str,
-		provider_id: &str,
-	) -> Result<ProviderStatus> {
-		let key = TenantProviderKey::new(tenant_id, provider_id);
-		let handle = {
-			let state = self.inner.read().await;
+		let mut pending = self.handles().into_iter();
+		let mut fetches = JoinSet::new();
+		let mut report = WarmUpReport::default();
 
-			state.providers.get(&key).cloned()
-		};
-		let handle = handle.ok_or_else(|| Error::NotRegistered {
-			tenant: tenant_id.to_string(),
-			provider: provider_id.to_string(),
-		})?;
+		loop {
+			while fetches.len() < concurrency.max(1)
+				&& let Some(handle) = pending.next()
+			{
+				fetches.spawn(async move {
+					let outcome = handle.manager.resolve(None).await;
 
-		Ok(handle.status().await)
-	}
+					(handle, outcome)
+				});
+			}
 
-	/// Fetch status for every registered provider.
-	pub async fn all_statuses(&self) -> Vec<ProviderStatus> {
-		let handles: Vec<Arc<ProviderHandle>> = {
-			let state = self.inner.read().await;
-			state.providers.values().cloned().collect()
-		};
-		let mut statuses = Vec::with_capacity(handles.len());
+			let Some(joined) = fetches.join_next().await else {
+				break;
+			};
+			// Fetches are never aborted, so a join error can only carry a panic.
+			let (handle, outcome) =
+				joined.unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()));
+			let registration = &handle.registration;
 
-		for handle in handles {
-			statuses.push(handle.status().await);
+			match outcome {
+				Ok(_) => report
+					.ready
+					.push((registration.tenant_id.clone(), registration.provider_id.clone())),
+				Err(err) => report.failed.push(WarmUpFailure {
+					tenant_id: registration.tenant_id.clone(),
+					provider_id: registration.provider_id.clone(),
+					error: err.to_string(),
+				}),
+			}
 		}
 
-		statuses
-	}
+		report.ready.sort_unstable();
+		report.failed.sort_unstable_by(|a, b| {
+			a.tenant_id.cmp(&b.tenant_id).then_with(|| a.provider_id.cmp(&b.provider_id))
+		});
 
-	/// Persist snapshots for every provider when persistence is configured.
-	pub async fn persist_all(&self) -> Result<()> {
-		#[cfg(feature = "redis")]
-		{
-			if let Some(persistence) = &self.config.persistence {
-				let handles: Vec<Arc<ProviderHandle>> = {
-					let state = self.inner.read().await;
+		tracing::info!(
+			ready = report.ready.len(),
+			failed = report.failed.len(),
+			"registry warmed up"
+		);
 
-					state.providers.values().cloned().collect()
-				};
-				let mut snapshots = Vec::new();
+		report
+	}
 
-				for handle in handles {
-					if let Some(snapshot) = handle.manager.persistent_snapshot().await? {
-						snapshots.push(snapshot);
-					}
-				}
+	/// Restore cached entries from persistence for all active registrations.
+	pub async fn restore_from_persistence(&self) -> Result<()> {
+		if let Some(store) = &self.config.snapshot_store {
+			let handles = self.handles();
 
-				persistence.persist(&snapshots).await?;
+			for handle in handles {
+				if let Some(snapshot) = store
+					.load(&handle.registration.tenant_id, &handle.registration.provider_id)
+					.await?
+				{
+					handle.manager.restore_snapshot(snapshot).await?;
+				}
 			}
 		}
 
 		Ok(())
 	}
 
-	/// Restore cached entries from persistence for all active registrations.
-	pub async fn restore_from_persistence(&self) -> Result<()> {
-		#[cfg(feature = "redis")]
-		{
-			if let Some(persistence) = &self.config.persistence {
-				let handles: Vec<Arc<ProviderHandle>> = {
-					let state = self.inner.read().await;
+	// Publish an invalidation already applied locally to the other registries on the bus.
+	async fn announce(&self, target: InvalidationTarget) -> Result<()> {
+		let Some(bus) = &self.config.invalidation_bus else {
+			return Ok(());
+		};
 
-					state.providers.values().cloned().collect()
-				};
+		bus.publish(&Invalidation { origin: self.inner.origin.clone(), target }).await
+	}
 
-				for handle in handles {
-					if let Some(snapshot) = persistence
-						.load(&handle.registration.tenant_id, &handle.registration.provider_id)
-						.await?
-					{
-						handle.manager.restore_snapshot(snapshot).await?;
-					}
+	fn listen_for_invalidations(&self) {
+		let Some(bus) = &self.config.invalidation_bus else {
+			return;
+		};
+
+		self.inner.invalidation_listener.get_or_init(|| {
+			let mut invalidations = bus.subscribe();
+			// Holding the state weakly lets the registry drop, which aborts this task.
+			let state = Arc::downgrade(&self.inner);
+
+			tokio::spawn(async move {
+				loop {
+					let invalidation = match invalidations.recv().await {
+						Ok(invalidation) => invalidation,
+						Err(RecvError::Lagged(missed)) => {
+							tracing::warn!(
+								missed,
+								"missed invalidations; invalidating every provider"
+							);
+
+							Invalidation { origin: String::new(), target: InvalidationTarget::All }
+						},
+						Err(RecvError::Closed) => return,
+					};
+					let Some(state) = state.upgrade() else {
+						return;
+					};
+
+					state.apply_invalidation(&invalidation).await;
 				}
-			}
+			})
+			.abort_handle()
+		});
+	}
+
+	fn emit(&self, tenant_id: &str, provider_id: &str, kind: CacheEventKind) {
+		// Send only fails when nobody is subscribed, which is not an error.
+		if self.inner.events.receiver_count() > 0 {
+			let _ = self.inner.events.send(CacheEvent::new(tenant_id, provider_id, kind));
+		}
+	}
+
+	fn handles(&self) -> Vec<Arc<ProviderHandle>> {
+		self.inner.providers.iter().map(|entry| entry.value().clone()).collect()
+	}
+
+	fn ensure_host_allowed(&self, url: &Url) -> Result<()> {
+		let Some(host) = url.host_str() else {
+			return Ok(());
+		};
+		if !self.config.host_allowlist().allows(host) {
+			return Err(Error::Security(format!(
+				"Host '{host}' is not in the registry allowlist."
+			)));
 		}
 
 		Ok(())
 	}
+
+	fn handle(&self, tenant_id: &str, provider_id: &str) -> Result<Arc<ProviderHandle>> {
+		let key = TenantProviderKey::new(tenant_id, provider_id);
+
+		self.inner.providers.get(&key).map(|entry| entry.value().clone()).ok_or_else(|| {
+			Error::NotRegistered {
+				tenant: tenant_id.to_string(),
+				provider: provider_id.to_string(),
+			}
+		})
+	}
 }
 impl Default for Registry {
 	fn default() -> Self {
@@ -715,6 +3309,33 @@ impl Default for Registry {
 	}
 }
 
+/// Most recent refresh failure of a provider, as reported in [`ProviderStatus::last_error`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderError {
+	/// Human-readable description of the failure, as given by [`Error::redacted`].
+	pub message: String,
+	/// Stable error code, matching [`Error::code`].
+	pub code: String,
+	/// UTC timestamp when the refresh failed.
+	pub occurred_at: DateTime<Utc>,
+	/// Upstream HTTP status, when the failure was an error response.
+	pub http_status: Option<u16>,
+}
+impl ProviderError {
+	/// Summarise `err` as observed at `occurred_at`.
+	pub fn from_error(err: &Error, occurred_at: DateTime<Utc>) -> Self {
+		// Status reports already name the provider, so the message omits the context wrapper.
+		let root = err.root();
+		let http_status = match root {
+			Error::HttpStatus { status, .. } => Some(status.as_u16()),
+			Error::Reqwest(err) => err.status().map(|status| status.as_u16()),
+			_ => None,
+		};
+
+		Self { message: root.redacted(), code: err.code().into(), occurred_at, http_status }
+	}
+}
+
 /// Status projection for a provider, aligned with the OpenAPI contract.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ProviderStatus {
@@ -732,6 +3353,17 @@ pub struct ProviderStatus {
 	pub expires_at: Option<DateTime<Utc>>,
 	/// Consecutive error count observed during refresh attempts.
 	pub error_count: u32,
+	/// Most recent refresh error, absent once a refresh succeeds.
+	pub last_error: Option<ProviderError>,
+	/// Current age of the cached payload in seconds, including the upstream `Age` it arrived with.
+	pub age_seconds: Option<u64>,
+	/// Circuit breaker state, absent when the registration does not configure one.
+	pub circuit_state: Option<CircuitState>,
+	/// Timestamp at which an open circuit starts admitting trial fetches.
+	pub circuit_open_until: Option<DateTime<Utc>>,
+	/// Milliseconds from registration until the provider first held usable keys, fetched or
+	/// restored; absent until then.
+	pub time_to_ready_ms: Option<u64>,
 	/// Ratio of cache hits to total requests.
 	#[cfg(feature = "metrics")]
 	pub hit_rate: f64,
@@ -743,6 +3375,19 @@ pub struct ProviderStatus {
 	pub metrics: Vec<StatusMetric>,
 }
 impl ProviderStatus {
+	/// Render the status as a JSON value in `format`.
+	///
+	/// Under [`StatusFormat::V1`] this is one element of the envelope's `statuses` array.
+	pub fn to_json(&self, format: StatusFormat) -> Result<serde_json::Value> {
+		let mut value = serde_json::to_value(self)?;
+
+		if format == StatusFormat::V1 {
+			value["state"] = self.state.as_str().into();
+		}
+
+		Ok(value)
+	}
+
 	#[cfg(feature = "metrics")]
 	fn from_components(
 		registration: &IdentityProviderRegistration,
@@ -753,6 +3398,8 @@ impl ProviderStatus {
 		let mut next_refresh = None;
 		let mut expires_at = None;
 		let mut error_count = 0;
+		let mut last_error = None;
+		let mut age_seconds = None;
 		let state = match &snapshot.state {
 			CacheState::Empty => ProviderState::Empty,
 			CacheState::Loading => ProviderState::Loading,
@@ -761,6 +3408,8 @@ impl ProviderStatus {
 				next_refresh = snapshot.to_datetime(payload.next_refresh_at);
 				expires_at = snapshot.to_datetime(payload.expires_at);
 				error_count = payload.error_count;
+				last_error = payload.last_error.clone();
+				age_seconds = Some(payload.policy.age(clock::system_now()).as_secs());
 				ProviderState::Ready
 			},
 			CacheState::Refreshing(payload) => {
@@ -768,9 +3417,17 @@ impl ProviderStatus {
 				next_refresh = snapshot.to_datetime(payload.next_refresh_at);
 				expires_at = snapshot.to_datetime(payload.expires_at);
 				error_count = payload.error_count;
+				last_error = payload.last_error.clone();
+				age_seconds = Some(payload.policy.age(clock::system_now()).as_secs());
 				ProviderState::Refreshing
 			},
 		};
+		let circuit_state = snapshot.circuit.map(|circuit| circuit.state);
+		let time_to_ready_ms = snapshot.time_to_ready.map(|elapsed| elapsed.as_millis() as u64);
+		let circuit_open_until = snapshot
+			.circuit
+			.and_then(|circuit| circuit.open_until)
+			.and_then(|until| snapshot.to_datetime(until));
 		let tenant = &registration.tenant_id;
 		let provider = &registration.provider_id;
 		let mut status_metrics = vec![
@@ -793,6 +3450,24 @@ impl ProviderStatus {
 				tenant,
 				provider,
 			),
+			StatusMetric::new(
+				"jwks_cache_refresh_aborted_total",
+				metrics.refresh_aborts as f64,
+				tenant,
+				provider,
+			),
+			StatusMetric::new(
+				"jwks_cache_connections_reused_total",
+				metrics.connections_reused as f64,
+				tenant,
+				provider,
+			),
+			StatusMetric::new(
+				"jwks_cache_connections_opened_total",
+				metrics.connections_opened as f64,
+				tenant,
+				provider,
+			),
 		];
 
 		if let Some(last_micros) = metrics.last_refresh_micros {
@@ -812,6 +3487,11 @@ impl ProviderStatus {
 			next_refresh,
 			expires_at,
 			error_count,
+			last_error,
+			age_seconds,
+			circuit_state,
+			circuit_open_until,
+			time_to_ready_ms,
 			hit_rate: metrics.hit_rate(),
 			stale_serve_ratio: metrics.stale_ratio(),
 			metrics: status_metrics,
@@ -827,6 +3507,8 @@ impl ProviderStatus {
 		let mut next_refresh = None;
 		let mut expires_at = None;
 		let mut error_count = 0;
+		let mut last_error = None;
+		let mut age_seconds = None;
 		let state = match &snapshot.state {
 			CacheState::Empty => ProviderState::Empty,
 			CacheState::Loading => ProviderState::Loading,
@@ -835,6 +3517,8 @@ impl ProviderStatus {
 				next_refresh = snapshot.to_datetime(payload.next_refresh_at);
 				expires_at = snapshot.to_datetime(payload.expires_at);
 				error_count = payload.error_count;
+				last_error = payload.last_error.clone();
+				age_seconds = Some(payload.policy.age(clock::system_now()).as_secs());
 				ProviderState::Ready
 			},
 			CacheState::Refreshing(payload) => {
@@ -842,9 +3526,17 @@ impl ProviderStatus {
 				next_refresh = snapshot.to_datetime(payload.next_refresh_at);
 				expires_at = snapshot.to_datetime(payload.expires_at);
 				error_count = payload.error_count;
+				last_error = payload.last_error.clone();
+				age_seconds = Some(payload.policy.age(clock::system_now()).as_secs());
 				ProviderState::Refreshing
 			},
 		};
+		let circuit_state = snapshot.circuit.map(|circuit| circuit.state);
+		let time_to_ready_ms = snapshot.time_to_ready.map(|elapsed| elapsed.as_millis() as u64);
+		let circuit_open_until = snapshot
+			.circuit
+			.and_then(|circuit| circuit.open_until)
+			.and_then(|until| snapshot.to_datetime(until));
 
 		Self {
 			tenant_id: registration.tenant_id.clone(),
@@ -854,10 +3546,198 @@ impl ProviderStatus {
 			next_refresh,
 			expires_at,
 			error_count,
+			last_error,
+			age_seconds,
+			circuit_state,
+			circuit_open_until,
+			time_to_ready_ms,
+		}
+	}
+}
+
+/// Criteria selecting the statuses returned by [`Registry::statuses_page`].
+///
+/// The default filter matches every provider.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatusFilter {
+	/// Only providers of this tenant.
+	pub tenant_id: Option<String>,
+	/// Only providers in this lifecycle state.
+	pub state: Option<ProviderState>,
+	/// Only providers whose last refresh failed (`error_count > 0`).
+	pub failing: bool,
+}
+impl StatusFilter {
+	/// Match every provider.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Only match providers of `tenant_id`.
+	pub fn with_tenant(mut self, tenant_id: impl Into<String>) -> Self {
+		self.tenant_id = Some(tenant_id.into());
+
+		self
+	}
+
+	/// Only match providers in `state`.
+	pub fn with_state(mut self, state: ProviderState) -> Self {
+		self.state = Some(state);
+
+		self
+	}
+
+	/// Only match providers with consecutive refresh errors.
+	pub fn failing_only(mut self) -> Self {
+		self.failing = true;
+
+		self
+	}
+
+	/// Whether `status` satisfies every criterion.
+	pub fn matches(&self, status: &ProviderStatus) -> bool {
+		self.tenant_id.as_ref().is_none_or(|tenant| *tenant == status.tenant_id)
+			&& self.state.is_none_or(|state| state == status.state)
+			&& (!self.failing || status.error_count > 0)
+	}
+}
+
+/// One page of provider statuses returned by [`Registry::statuses_page`].
+#[derive(Clone, Debug)]
+pub struct StatusPage {
+	/// Matching statuses on this page, ordered by tenant, then provider.
+	pub statuses: Vec<ProviderStatus>,
+	/// Number of providers matching the filter across all pages.
+	pub total: usize,
+	/// Offset of the next page, absent on the last one.
+	pub next_offset: Option<usize>,
+}
+
+/// Redacted diagnostic snapshot of one provider, meant for attaching to vendor support tickets.
+///
+/// The client identity's private key, `extra_headers` values, and the credentials and query
+/// strings of every configured URL are redacted; captured exchanges and error messages are already
+/// redacted when recorded.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SupportBundle {
+	/// Version of this crate that produced the bundle.
+	pub crate_version: String,
+	/// UTC timestamp when the bundle was generated.
+	pub generated_at: DateTime<Utc>,
+	/// Effective registration after registry defaults were applied.
+	pub registration: IdentityProviderRegistration,
+	/// Current status, including derived metrics when the `metrics` feature is enabled.
+	pub status: ProviderStatus,
+	/// Raw telemetry counters of the provider.
+	#[cfg(feature = "metrics")]
+	pub metrics: ProviderMetricsSnapshot,
+	/// Samples recorded after recent refreshes, oldest first.
+	pub history: Vec<StatusSample>,
+	/// Most recent upstream fetch attempts, oldest first.
+	pub exchanges: Vec<CapturedExchange>,
+}
+
+/// Cross-provider summary suited to a single health widget or readiness probe.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AggregateStatus {
+	/// Number of registered providers.
+	pub total_providers: usize,
+	/// Provider counts keyed by lifecycle state.
+	pub by_state: StateCounts,
+	/// Sum of consecutive refresh errors across all providers.
+	pub total_error_count: u64,
+	/// Longest time any provider has been serving a payload past its expiry.
+	pub worst_staleness: Duration,
+	/// Providers with the most consecutive errors, worst first.
+	pub top_failing: Vec<FailingProvider>,
+	/// Time from creating the registry until every registered provider first held usable keys.
+	///
+	/// Absent while any provider has never been ready, or when none is registered.
+	pub time_to_all_ready: Option<Duration>,
+}
+
+/// Health verdict for a provider or a whole registry.
+///
+/// A provider is unhealthy when it cannot serve keys: nothing is cached yet, or its keys expired
+/// past the `stale_while_error` window while refreshes fail. It is degraded while it serves keys
+/// but its refreshes fail or its circuit breaker is not closed. Providers whose keys merely
+/// expired without a failed refresh are healthy, as the next resolve fetches them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+	/// Keys are cached and refreshing normally.
+	#[default]
+	Healthy,
+	/// Keys are served, but refreshes are failing.
+	Degraded,
+	/// No keys can be served.
+	Unhealthy,
+}
+
+/// Outcome of [`Registry::health`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthReport {
+	/// Overall verdict.
+	pub status: HealthStatus,
+	/// Number of healthy providers.
+	pub healthy: usize,
+	/// Number of degraded providers.
+	pub degraded: usize,
+	/// Number of unhealthy providers.
+	pub unhealthy: usize,
+	/// Degraded and unhealthy providers, worst first.
+	pub worst: Vec<ProviderHealth>,
+}
+impl HealthReport {
+	/// HTTP status for a `/healthz` response: `503` when unhealthy, `200` otherwise.
+	pub fn status_code(&self) -> StatusCode {
+		match self.status {
+			HealthStatus::Healthy | HealthStatus::Degraded => StatusCode::OK,
+			HealthStatus::Unhealthy => StatusCode::SERVICE_UNAVAILABLE,
 		}
 	}
 }
 
+/// Provider reported in [`HealthReport::worst`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderHealth {
+	/// Tenant identifier that owns the provider.
+	pub tenant_id: String,
+	/// Provider identifier unique within the tenant.
+	pub provider_id: String,
+	/// Health verdict for the provider.
+	pub status: HealthStatus,
+	/// Why the provider is not healthy.
+	pub reason: String,
+	/// Consecutive error count observed during refresh attempts.
+	pub error_count: u32,
+}
+
+/// Provider counts per [`ProviderState`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateCounts {
+	/// Providers with no cached payload.
+	pub empty: usize,
+	/// Providers performing their initial fetch.
+	pub loading: usize,
+	/// Providers serving a cached payload.
+	pub ready: usize,
+	/// Providers serving while a refresh is in progress.
+	pub refreshing: usize,
+}
+
+/// Provider reported in [`AggregateStatus::top_failing`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailingProvider {
+	/// Tenant identifier that owns the provider.
+	pub tenant_id: String,
+	/// Provider identifier unique within the tenant.
+	pub provider_id: String,
+	/// Consecutive error count observed during refresh attempts.
+	pub error_count: u32,
+}
+
 /// Metric sample used in provider status responses.
 #[cfg(feature = "metrics")]
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -866,14 +3746,14 @@ pub struct StatusMetric {
 	pub name: String,
 	/// Numeric value captured for the metric.
 	pub value: f64,
-	/// Additional labels enriching the metric sample.
+	/// Additional labels enriching the metric sample, ordered by name.
 	#[serde(default)]
-	pub labels: HashMap<String, String>,
+	pub labels: BTreeMap<String, String>,
 }
 #[cfg(feature = "metrics")]
 impl StatusMetric {
 	fn new(name: impl Into<String>, value: f64, tenant: &str, provider: &str) -> Self {
-		let mut labels = HashMap::with_capacity(2);
+		let mut labels = BTreeMap::new();
 
 		labels.insert("tenant".into(), tenant.into());
 		labels.insert("provider".into(), provider.into());
@@ -885,33 +3765,139 @@ impl StatusMetric {
 #[derive(Debug)]
 struct RegistryConfig {
 	require_https: bool,
+	block_private_networks: bool,
 	default_refresh_early: Duration,
 	default_stale_while_error: Duration,
 	allowed_domains: Vec<String>,
-	#[cfg(feature = "redis")]
-	persistence: Option<RedisPersistence>,
+	allowlist_mode: Option<AllowlistMode>,
+	status_history_capacity: usize,
+	exchange_capture_capacity: usize,
+	event_capacity: usize,
+	max_resident_providers: Option<usize>,
+	resolve_timeout: Option<Duration>,
+	rotation_hooks: Vec<KeyRotationHook>,
+	key_activation: Option<Arc<dyn KeyActivation>>,
+	http_client: Option<Client>,
+	proxy: Option<ProxySettings>,
+	resolver: Option<SharedResolver>,
+	client_config: HttpClientConfig,
+	snapshot_store: Option<Arc<dyn SnapshotStore>>,
+	restore_policy: RestorePolicy,
+	write_through: bool,
+	refresh_lock: Option<Arc<dyn RefreshLock>>,
+	invalidation_bus: Option<Arc<dyn InvalidationBus>>,
+	status_format: StatusFormat,
+	rate_limiter: Option<Arc<HostRateLimiter>>,
+	scheduler: Option<Arc<RefreshScheduler>>,
+	#[cfg(feature = "zstd")]
+	parsed_jwks: Option<Arc<ParsedJwksCache>>,
+}
+impl RegistryConfig {
+	fn allowlist_mode(&self) -> AllowlistMode {
+		match self.allowlist_mode {
+			Some(mode) => mode,
+			None if self.allowed_domains.is_empty() => AllowlistMode::AllowAll,
+			None => AllowlistMode::List,
+		}
+	}
+
+	fn host_allowlist(&self) -> HostAllowlist {
+		HostAllowlist::new(self.allowlist_mode(), self.allowed_domains.clone())
+	}
 }
 impl Default for RegistryConfig {
 	fn default() -> Self {
 		Self {
 			require_https: true,
+			block_private_networks: false,
 			default_refresh_early: DEFAULT_REFRESH_EARLY,
 			default_stale_while_error: DEFAULT_STALE_WHILE_ERROR,
 			allowed_domains: Vec::new(),
-			#[cfg(feature = "redis")]
-			persistence: None,
+			allowlist_mode: None,
+			status_history_capacity: DEFAULT_STATUS_HISTORY_CAPACITY,
+			exchange_capture_capacity: DEFAULT_EXCHANGE_CAPTURE_CAPACITY,
+			event_capacity: DEFAULT_EVENT_CAPACITY,
+			max_resident_providers: None,
+			resolve_timeout: None,
+			rotation_hooks: Vec::new(),
+			key_activation: None,
+			http_client: None,
+			proxy: None,
+			resolver: None,
+			client_config: HttpClientConfig::default(),
+			snapshot_store: None,
+			restore_policy: RestorePolicy::default(),
+			write_through: false,
+			refresh_lock: None,
+			invalidation_bus: None,
+			status_format: StatusFormat::default(),
+			rate_limiter: None,
+			scheduler: None,
+			#[cfg(feature = "zstd")]
+			parsed_jwks: None,
 		}
 	}
 }
 
+// Provider built by `Registry::prepare`, not yet visible to resolves.
+#[derive(Debug)]
+struct PreparedProvider {
+	source: Arc<IdentityProviderRegistration>,
+	key: TenantProviderKey,
+	manager: CacheManager,
+	tasks: TaskSet,
+	// Cache state was taken over from the replaced manager, so there is no snapshot to restore.
+	preserved: bool,
+}
+
 #[derive(Debug)]
 struct ProviderHandle {
+	// Registration as supplied by the caller, before registry defaults; used to diff reloads.
+	source: Arc<IdentityProviderRegistration>,
 	registration: Arc<IdentityProviderRegistration>,
 	manager: CacheManager,
+	// Background refreshes spawned by `manager`; the manager only holds a weak handle.
+	tasks: TaskSet,
+	// Flips to `true` once the snapshot restore chosen by `RestorePolicy` has finished.
+	restored: watch::Receiver<bool>,
 	#[cfg(feature = "metrics")]
 	metrics: Arc<ProviderMetrics>,
 }
 impl ProviderHandle {
+	fn abort_refreshes(&self) {
+		self.observe_aborted(self.tasks.abort_all());
+	}
+
+	async fn shutdown(&self) {
+		self.observe_aborted(self.tasks.shutdown().await);
+	}
+
+	async fn drain(&self, deadline: Instant) -> (usize, usize) {
+		let (drained, aborted) = self.tasks.drain(deadline).await;
+
+		self.observe_aborted(aborted);
+
+		(drained, aborted)
+	}
+
+	fn observe_aborted(&self, aborted: usize) {
+		if aborted == 0 {
+			return;
+		}
+
+		let tenant = &self.registration.tenant_id;
+		let provider = &self.registration.provider_id;
+
+		tracing::debug!(tenant = %tenant, provider = %provider, aborted, "aborted background refreshes");
+
+		#[cfg(feature = "metrics")]
+		{
+			metrics::record_refresh_aborted(tenant, provider, aborted as u64);
+
+			self.metrics.record_refresh_aborted(aborted as u64);
+		}
+	}
+
 	async fn status(&self) -> ProviderStatus {
 		let snapshot = self.manager.snapshot().await;
 		#[cfg(feature = "metrics")]
@@ -927,105 +3913,157 @@ impl ProviderHandle {
 	}
 }
 
-#[derive(Debug)]
-struct RegistryState {
-	// TODO: Consider replacing the RwLock<HashMap> with DashMap if contention becomes measurable.
-	providers: HashMap<TenantProviderKey, Arc<ProviderHandle>>,
+impl Drop for ProviderHandle {
+	fn drop(&mut self) {
+		self.abort_refreshes();
+	}
 }
 
-#[cfg(feature = "redis")]
-#[derive(Clone, Debug)]
-struct RedisPersistence {
-	client: redis::Client,
-	namespace: Arc<str>,
+#[derive(Debug)]
+struct RegistryState {
+	// Sharded map so concurrent `resolve` calls across tenants do not serialise on one lock.
+	providers: DashMap<TenantProviderKey, Arc<ProviderHandle>>,
+	// Providers owned by the most recent configuration reload.
+	config_managed: Mutex<HashSet<TenantProviderKey>>,
+	events: broadcast::Sender<CacheEvent>,
+	// Serialises `persist_all` and write-through persists against snapshot deletion in
+	// `unregister`.
+	persistence: Arc<Mutex<()>>,
+	// Identifies this registry's own messages on the invalidation bus.
+	origin: String,
+	invalidation_listener: OnceLock<AbortHandle>,
+	// Resident managers reference the set in turn, so it is cleared on drop to free them.
+	resident: Option<Arc<ResidentSet<CacheManager>>>,
+	created_at: Instant,
 }
-#[cfg(feature = "redis")]
-impl RedisPersistence {
-	fn new(client: redis::Client) -> Self {
-		Self { client, namespace: Arc::from("jwks-cache") }
-	}
-
-	async fn persist(&self, snapshots: &[PersistentSnapshot]) -> Result<()> {
-		if snapshots.is_empty() {
-			return Ok(());
+impl RegistryState {
+	async fn apply_invalidation(&self, invalidation: &Invalidation) {
+		if invalidation.origin == self.origin {
+			return;
 		}
 
-		let mut conn = self.client.get_multiplexed_async_connection().await?;
+		let handles: Vec<Arc<ProviderHandle>> = match &invalidation.target {
+			InvalidationTarget::Provider { tenant_id, provider_id } => self
+				.providers
+				.get(&TenantProviderKey::new(tenant_id, provider_id))
+				.map(|entry| entry.value().clone())
+				.into_iter()
+				.collect(),
+			InvalidationTarget::All =>
+				self.providers.iter().map(|entry| entry.value().clone()).collect(),
+		};
 
-		for snapshot in snapshots {
-			let key = self.key(&snapshot.tenant_id, &snapshot.provider_id);
-			let payload = serde_json::to_string(snapshot)?;
-			let ttl = (snapshot.expires_at - Utc::now())
-				.to_std()
-				.unwrap_or_else(|_| Duration::from_secs(1));
-			let ttl_secs = ttl.as_secs().max(1);
+		tracing::debug!(
+			origin = %invalidation.origin,
+			target = ?invalidation.target,
+			providers = handles.len(),
+			"applying remote invalidation"
+		);
 
-			conn.set_ex::<_, _, ()>(key, payload, ttl_secs).await?;
+		for handle in handles {
+			handle.manager.invalidate().await;
+		}
+	}
+}
+impl Drop for RegistryState {
+	fn drop(&mut self) {
+		if let Some(listener) = self.invalidation_listener.get() {
+			listener.abort();
+		}
+		if let Some(resident) = &self.resident {
+			resident.clear();
 		}
-
-		Ok(())
 	}
+}
 
-	async fn load(&self, tenant: &str, provider: &str) -> Result<Option<PersistentSnapshot>> {
-		let mut conn = self.client.get_multiplexed_async_connection().await?;
-		let key = self.key(tenant, provider);
-		let value: Option<String> = conn.get(key).await?;
+fn redact_registration(
+	registration: &IdentityProviderRegistration,
+) -> IdentityProviderRegistration {
+	let mut registration = registration.clone();
 
-		if let Some(json) = value {
-			let snapshot: PersistentSnapshot = serde_json::from_str(&json)?;
+	registration.jwks_url = capture::redact_url(&registration.jwks_url);
 
-			Ok(Some(snapshot))
-		} else {
-			Ok(None)
+	for url in &mut registration.mirror_urls {
+		*url = capture::redact_url(url);
+	}
+	if let Some(discovery) = &mut registration.discovery {
+		discovery.issuer = capture::redact_url(&discovery.issuer);
+	}
+	// Configured headers carry gateway credentials, so only their names are kept.
+	for value in registration.extra_headers.values_mut() {
+		*value = capture::REDACTED.to_owned();
+	}
+	if let Some(identity) = &mut registration.client_identity {
+		identity.private_key = capture::REDACTED.to_owned();
+	}
+	if let Some(proxy) = &mut registration.proxy {
+		for url in [&mut proxy.http_proxy, &mut proxy.https_proxy].into_iter().flatten() {
+			*url = capture::redact_url(url);
 		}
 	}
 
-	fn key(&self, tenant: &str, provider: &str) -> String {
-		format!("{}:{tenant}:{provider}", self.namespace)
-	}
+	registration
 }
 
-fn random_within(min: Duration, max: Duration) -> Duration {
-	if max <= min {
-		return max;
+fn check_rsa_parameters(n: &str, e: &str) -> std::result::Result<(), &'static str> {
+	let decode = |value: &str| BASE64_URL_SAFE_NO_PAD.decode(value.trim_end_matches('='));
+	let (Ok(modulus), Ok(exponent)) = (decode(n), decode(e)) else {
+		return Err("has RSA parameters that are not valid base64url.");
+	};
+
+	if modulus.iter().all(|byte| *byte == 0) {
+		return Err("has an empty RSA modulus.");
+	}
+
+	let exponent = exponent.iter().skip_while(|byte| **byte == 0).copied().collect::<Vec<_>>();
+
+	if exponent.last().is_none_or(|byte| byte % 2 == 0) || exponent == [1] {
+		return Err("has an RSA exponent that is even or below 3.");
 	}
-	SMALL_RNG.with(|cell| {
-		let mut rng = cell.borrow_mut();
-		let nanos = max.as_nanos() - min.as_nanos();
-		let jitter = rng.random_range(0..=nanos.min(u64::MAX as u128));
 
-		min + Duration::from_nanos(jitter as u64)
-	})
+	Ok(())
 }
 
 fn default_true() -> bool {
 	true
 }
 
-fn default_refresh_early() -> Duration {
-	DEFAULT_REFRESH_EARLY
+fn default_min_ttl() -> TtlSeconds {
+	TtlSeconds::from_secs(MIN_TTL_FLOOR.as_secs())
+}
+
+fn default_max_ttl() -> TtlSeconds {
+	TtlSeconds::from_secs(DEFAULT_MAX_TTL.as_secs())
 }
 
-fn default_stale_while_error() -> Duration {
-	DEFAULT_STALE_WHILE_ERROR
+fn default_max_response_bytes() -> ByteSize {
+	ByteSize::from_bytes(DEFAULT_MAX_RESPONSE_BYTES)
 }
 
-fn default_min_ttl() -> Duration {
-	MIN_TTL_FLOOR
+fn default_min_keys() -> usize {
+	1
 }
 
-fn default_max_ttl() -> Duration {
-	DEFAULT_MAX_TTL
+fn default_missing_kid_ttl() -> Duration {
+	DEFAULT_MISSING_KID_TTL
 }
 
-fn default_max_response_bytes() -> u64 {
-	DEFAULT_MAX_RESPONSE_BYTES
+fn default_clock_jump_threshold() -> Duration {
+	DEFAULT_CLOCK_JUMP_THRESHOLD
 }
 
 fn default_max_redirects() -> u8 {
 	3
 }
 
+fn default_discovery_min_ttl() -> TtlSeconds {
+	TtlSeconds::from_secs(DEFAULT_DISCOVERY_MIN_TTL.as_secs())
+}
+
+fn default_discovery_max_ttl() -> TtlSeconds {
+	TtlSeconds::from_secs(DEFAULT_DISCOVERY_MAX_TTL.as_secs())
+}
+
 fn default_prefetch_jitter() -> Duration {
 	DEFAULT_PREFETCH_JITTER
 }
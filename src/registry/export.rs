@@ -0,0 +1,85 @@
+//! Incremental JSON encoding for status exports.
+//!
+//! Each provider status is serialised and, optionally, gzip-compressed on its own, so callers
+//! can hand the chunks to an async writer or a response body without buffering the document.
+
+// std
+#[cfg(feature = "gzip")] use std::{io::Write, mem};
+// crates.io
+#[cfg(feature = "gzip")] use flate2::{Compression, write::GzEncoder};
+// self
+use crate::{
+	_prelude::*,
+	registry::{ProviderStatus, StatusFormat},
+};
+
+// Byte encoding applied to every chunk of a status document.
+pub(crate) enum ChunkEncoding {
+	Identity,
+	#[cfg(feature = "gzip")]
+	Gzip(GzEncoder<Vec<u8>>),
+}
+impl ChunkEncoding {
+	#[cfg(feature = "gzip")]
+	pub(crate) fn gzip() -> Self {
+		Self::Gzip(GzEncoder::new(Vec::new(), Compression::default()))
+	}
+
+	fn encode(&mut self, bytes: Vec<u8>) -> Result<Vec<u8>> {
+		match self {
+			Self::Identity => Ok(bytes),
+			#[cfg(feature = "gzip")]
+			Self::Gzip(encoder) => {
+				encoder.write_all(&bytes)?;
+
+				// Hand out whatever the compressor has emitted so far.
+				Ok(mem::take(encoder.get_mut()))
+			},
+		}
+	}
+
+	fn finish(self) -> Result<Vec<u8>> {
+		match self {
+			Self::Identity => Ok(Vec::new()),
+			#[cfg(feature = "gzip")]
+			Self::Gzip(encoder) => Ok(encoder.finish()?),
+		}
+	}
+}
+
+// A JSON document holding one array of statuses, produced a chunk at a time.
+//
+// `open` writes everything up to the array, `push` appends one status, and `close` writes the
+// rest of the document and flushes the encoding.
+pub(crate) struct StatusDocument {
+	format: StatusFormat,
+	encoding: ChunkEncoding,
+	pushed: usize,
+}
+impl StatusDocument {
+	pub(crate) fn new(format: StatusFormat, encoding: ChunkEncoding) -> Self {
+		Self { format, encoding, pushed: 0 }
+	}
+
+	pub(crate) fn open(&mut self, prefix: &[u8]) -> Result<Vec<u8>> {
+		self.encoding.encode(prefix.to_vec())
+	}
+
+	pub(crate) fn push(&mut self, status: &ProviderStatus) -> Result<Vec<u8>> {
+		let mut bytes = if self.pushed == 0 { Vec::new() } else { b",".to_vec() };
+
+		serde_json::to_writer(&mut bytes, &status.to_json(self.format)?)?;
+
+		self.pushed += 1;
+
+		self.encoding.encode(bytes)
+	}
+
+	pub(crate) fn close(mut self, suffix: &[u8]) -> Result<Vec<u8>> {
+		let mut bytes = self.encoding.encode(suffix.to_vec())?;
+
+		bytes.extend(self.encoding.finish()?);
+
+		Ok(bytes)
+	}
+}
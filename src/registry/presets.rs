@@ -0,0 +1,258 @@
+//! Registration presets for hosted identity providers.
+
+// self
+use super::*;
+
+/// Minimum TTL applied by [`IdentityProviderRegistration::cognito`]; Cognito does not rotate user
+/// pool keys on its own.
+pub const COGNITO_MIN_TTL: Duration = Duration::from_secs(60 * 60);
+/// Minimum TTL applied by [`IdentityProviderRegistration::azure_ad`]; Microsoft may roll signing
+/// keys without notice.
+pub const AZURE_AD_MIN_TTL: Duration = Duration::from_secs(60 * 5);
+/// Host serving Microsoft Entra ID (Azure AD) signing keys.
+pub const AZURE_AD_HOST: &str = "login.microsoftonline.com";
+
+impl IdentityProviderRegistration {
+	/// Construct a registration for an Amazon Cognito user pool.
+	///
+	/// Fetches `https://cognito-idp.{region}.amazonaws.com/{user_pool_id}/.well-known/jwks.json`,
+	/// allows only that host, and caches keys for [`COGNITO_MIN_TTL`] to [`DEFAULT_MAX_TTL`].
+	/// `user_pool_id` must belong to `region`, as in `us-east-1_AbCdEf123`.
+	pub fn cognito(
+		tenant_id: impl Into<String>,
+		provider_id: impl Into<String>,
+		region: &str,
+		user_pool_id: &str,
+	) -> Result<Self> {
+		if region.is_empty()
+			|| !region.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+		{
+			return Err(Error::Validation {
+				field: "region",
+				reason: "Must be an AWS region such as `us-east-1`.".into(),
+			});
+		}
+
+		let valid_pool = user_pool_id
+			.strip_prefix(region)
+			.and_then(|rest| rest.strip_prefix('_'))
+			.is_some_and(|id| !id.is_empty() && id.bytes().all(|b| b.is_ascii_alphanumeric()));
+
+		if !valid_pool {
+			return Err(Error::Validation {
+				field: "user_pool_id",
+				reason: format!("Must look like `{region}_AbCdEf123`."),
+			});
+		}
+
+		let host = format!("cognito-idp.{region}.amazonaws.com");
+
+		Self::preset(
+			tenant_id,
+			provider_id,
+			&format!("https://{host}/{user_pool_id}/.well-known/jwks.json"),
+			host,
+			COGNITO_MIN_TTL,
+		)
+	}
+
+	/// Construct a registration for a Microsoft Entra ID (Azure AD) directory.
+	///
+	/// `directory` is the directory's GUID or verified domain, or `common`, `organizations`, or
+	/// `consumers` for multi-tenant applications. Fetches the v2.0 signing keys from
+	/// [`AZURE_AD_HOST`], allows only that host, and caches keys for [`AZURE_AD_MIN_TTL`] to
+	/// [`DEFAULT_MAX_TTL`].
+	pub fn azure_ad(
+		tenant_id: impl Into<String>,
+		provider_id: impl Into<String>,
+		directory: &str,
+	) -> Result<Self> {
+		if directory.split('.').any(str::is_empty)
+			|| !directory.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'.')
+		{
+			return Err(Error::Validation {
+				field: "directory",
+				reason: "Must be a directory GUID, a verified domain, or `common`.".into(),
+			});
+		}
+
+		Self::preset(
+			tenant_id,
+			provider_id,
+			&format!("https://{AZURE_AD_HOST}/{directory}/discovery/v2.0/keys"),
+			AZURE_AD_HOST.into(),
+			AZURE_AD_MIN_TTL,
+		)
+	}
+
+	/// Construct a registration for a Keycloak realm.
+	///
+	/// `base_url` is the server's public URL, including the `/auth` prefix on servers that still
+	/// use it, and the JWKS endpoint is derived as
+	/// `{base_url}/realms/{realm}/protocol/openid-connect/certs`. Only the server's host is
+	/// allowed. Use [`Self::keycloak_discovery`] to follow the realm's discovery document instead.
+	pub fn keycloak(
+		tenant_id: impl Into<String>,
+		provider_id: impl Into<String>,
+		base_url: &str,
+		realm: &str,
+	) -> Result<Self> {
+		let mut jwks_url = keycloak_issuer(base_url, realm)?;
+
+		jwks_url.path_segments_mut().map_err(|_| keycloak_base_error())?.extend([
+			"protocol",
+			"openid-connect",
+			"certs",
+		]);
+
+		let host = jwks_url.host_str().unwrap_or_default().to_owned();
+
+		Self::preset(tenant_id, provider_id, jwks_url.as_str(), host, MIN_TTL_FLOOR)
+	}
+
+	/// Construct a registration that discovers a Keycloak realm's JWKS endpoint.
+	///
+	/// Like [`Self::keycloak`], but the JWKS location is read from the realm's
+	/// `/.well-known/openid-configuration` and followed when it moves. The advertised issuer must
+	/// equal `{base_url}/realms/{realm}`, so `base_url` has to be the server's frontend URL.
+	pub fn keycloak_discovery(
+		tenant_id: impl Into<String>,
+		provider_id: impl Into<String>,
+		base_url: &str,
+		realm: &str,
+	) -> Result<Self> {
+		let issuer = keycloak_issuer(base_url, realm)?;
+		let mut registration = Self::from_issuer(tenant_id, provider_id, issuer.as_str())?;
+
+		registration.allowed_domains = issuer.host_str().map(str::to_owned).into_iter().collect();
+
+		Ok(registration)
+	}
+
+	// Shared shape of the hosted-provider presets: HTTPS only, a single-host allowlist, and a
+	// provider-specific TTL floor.
+	fn preset(
+		tenant_id: impl Into<String>,
+		provider_id: impl Into<String>,
+		jwks_url: &str,
+		host: String,
+		min_ttl: Duration,
+	) -> Result<Self> {
+		let mut registration = Self::new(tenant_id, provider_id, jwks_url)?;
+
+		registration.allowed_domains = vec![host];
+		registration.min_ttl = TtlSeconds::from_secs(min_ttl.as_secs());
+		registration.max_ttl = TtlSeconds::from_secs(DEFAULT_MAX_TTL.as_secs());
+
+		Ok(registration)
+	}
+}
+
+// Realm issuer URL `{base_url}/realms/{realm}`, with `realm` percent-encoded as one segment.
+fn keycloak_issuer(base_url: &str, realm: &str) -> Result<Url> {
+	let mut issuer = Url::parse(base_url)?;
+
+	if issuer
+		.path_segments()
+		.is_some_and(|mut segments| segments.any(|segment| segment == "realms"))
+	{
+		return Err(Error::Validation {
+			field: "base_url",
+			reason: "Pass the Keycloak server URL without `/realms/...`.".into(),
+		});
+	}
+	if realm.is_empty() {
+		return Err(Error::Validation { field: "realm", reason: "Must not be empty.".into() });
+	}
+
+	issuer
+		.path_segments_mut()
+		.map_err(|_| keycloak_base_error())?
+		.pop_if_empty()
+		.extend(["realms", realm]);
+
+	Ok(issuer)
+}
+
+fn keycloak_base_error() -> Error {
+	Error::Validation { field: "base_url", reason: "Must be an HTTP(S) server URL.".into() }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn presets_build_validated_registrations() {
+		let cognito = IdentityProviderRegistration::cognito(
+			"tenant-a",
+			"cognito",
+			"eu-west-1",
+			"eu-west-1_AbC123",
+		)
+		.expect("cognito preset");
+
+		assert_eq!(
+			cognito.jwks_url.as_str(),
+			"https://cognito-idp.eu-west-1.amazonaws.com/eu-west-1_AbC123/.well-known/jwks.json"
+		);
+		assert_eq!(cognito.allowed_domains, ["cognito-idp.eu-west-1.amazonaws.com"]);
+		assert_eq!(cognito.min_ttl.as_duration(), COGNITO_MIN_TTL);
+		cognito.validate().expect("valid cognito registration");
+
+		let azure = IdentityProviderRegistration::azure_ad("tenant-a", "entra", "contoso.com")
+			.expect("azure preset");
+
+		assert_eq!(
+			azure.jwks_url.as_str(),
+			"https://login.microsoftonline.com/contoso.com/discovery/v2.0/keys"
+		);
+		azure.validate().expect("valid azure registration");
+
+		assert!(matches!(
+			IdentityProviderRegistration::cognito("t", "p", "eu-west-1", "us-east-1_AbC123"),
+			Err(Error::Validation { field: "user_pool_id", .. })
+		));
+		assert!(matches!(
+			IdentityProviderRegistration::azure_ad("t", "p", "contoso.com/../evil"),
+			Err(Error::Validation { field: "directory", .. })
+		));
+
+		let keycloak = IdentityProviderRegistration::keycloak(
+			"tenant-a",
+			"kc",
+			"https://sso.example.com/auth/",
+			"my realm",
+		)
+		.expect("keycloak preset");
+
+		assert_eq!(
+			keycloak.jwks_url.as_str(),
+			"https://sso.example.com/auth/realms/my%20realm/protocol/openid-connect/certs"
+		);
+		assert_eq!(keycloak.allowed_domains, ["sso.example.com"]);
+		keycloak.validate().expect("valid keycloak registration");
+
+		let discovered = IdentityProviderRegistration::keycloak_discovery(
+			"tenant-a",
+			"kc",
+			"https://sso.example.com",
+			"main",
+		)
+		.expect("keycloak discovery preset");
+
+		assert_eq!(
+			discovered.discovery.expect("discovery settings").issuer.as_str(),
+			"https://sso.example.com/realms/main"
+		);
+		assert!(matches!(
+			IdentityProviderRegistration::keycloak(
+				"t",
+				"p",
+				"https://sso.example.com/realms/main",
+				"main"
+			),
+			Err(Error::Validation { field: "base_url", .. })
+		));
+	}
+}
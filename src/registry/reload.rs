@@ -0,0 +1,565 @@
+//! Configuration file loading and diff-based reconciliation of registrations.
+
+// std
+use std::{
+	collections::{HashMap, HashSet},
+	fs,
+	path::{Path, PathBuf},
+};
+// crates.io
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+// self
+use super::*;
+
+/// Registration set loaded from a configuration file.
+///
+/// `${VAR}` references in string values are expanded from the environment while loading; see
+/// [`crate::config::interpolate_env`].
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RegistrationFile {
+	/// Providers that should be registered after the reload.
+	#[serde(default)]
+	pub registrations: Vec<IdentityProviderRegistration>,
+}
+impl RegistrationFile {
+	/// Read and parse a registration file.
+	///
+	/// The format follows the extension: `.toml` requires the `toml` feature, `.yaml` and `.yml`
+	/// require the `yaml` feature, and anything else is parsed as JSON.
+	pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+		let path = path.as_ref();
+		let raw = fs::read(path)?;
+
+		Self::parse(path, &raw)
+	}
+
+	fn parse(path: &Path, raw: &[u8]) -> Result<Self> {
+		parse_config(path, raw)
+	}
+}
+
+/// Registry settings and registrations loaded together from one configuration file.
+///
+/// Files use the same formats and `${VAR}` interpolation as [`RegistrationFile`], and duration
+/// fields accept `humantime` strings such as `"90s"` or `"5m"`:
+///
+/// ```toml
+/// [registry]
+/// default_refresh_early = "2m"
+/// allowed_domains = ["${IDP_DOMAIN}"]
+///
+/// [[registrations]]
+/// tenant_id = "acme"
+/// provider_id = "okta"
+/// jwks_url = "https://${IDP_DOMAIN}/oauth2/v1/keys"
+/// stale_while_error = "10m"
+/// ```
+///
+/// A `RegistryConfigFile` is also a valid [`RegistrationFile`], so [`Registry::reload_from_path`]
+/// can reconcile later edits to its registrations. Changes to `registry` take effect only when
+/// the registry is built again.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RegistryConfigFile {
+	/// Registry-wide settings.
+	#[serde(default)]
+	pub registry: RegistrySettings,
+	/// Providers registered once the registry is built.
+	#[serde(default)]
+	pub registrations: Vec<IdentityProviderRegistration>,
+}
+impl RegistryConfigFile {
+	/// Read and parse a configuration file; see [`RegistrationFile::from_path`] for the formats.
+	pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+		let path = path.as_ref();
+		let raw = fs::read(path)?;
+
+		parse_config(path, &raw)
+	}
+
+	/// Build a registry from the settings and register every provider.
+	///
+	/// Registrations are applied through [`Registry::apply_registrations`], so a failing entry
+	/// registers nothing. Use [`RegistrySettings::apply`] instead to add hooks to the builder.
+	pub async fn build(self) -> Result<Registry> {
+		let registry = self.registry.apply(Registry::builder())?.build();
+
+		registry
+			.apply_registrations(RegistrationFile { registrations: self.registrations })
+			.await?;
+
+		Ok(registry)
+	}
+}
+
+/// Serialisable subset of [`RegistryBuilder`] options.
+///
+/// Unset fields keep the builder's defaults.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RegistrySettings {
+	/// See [`RegistryBuilder::require_https`].
+	pub require_https: Option<bool>,
+	/// See [`RegistryBuilder::block_private_networks`].
+	pub block_private_networks: Option<bool>,
+	/// See [`RegistryBuilder::allowlist_mode`].
+	pub allowlist_mode: Option<AllowlistMode>,
+	/// See [`RegistryBuilder::allowed_domains`].
+	#[serde(deserialize_with = "crate::security::deserialize_allowed_domains")]
+	pub allowed_domains: Vec<String>,
+	/// See [`RegistryBuilder::default_refresh_early`].
+	#[serde(with = "crate::config::optional_duration")]
+	pub default_refresh_early: Option<Duration>,
+	/// See [`RegistryBuilder::default_stale_while_error`].
+	#[serde(with = "crate::config::optional_duration")]
+	pub default_stale_while_error: Option<Duration>,
+	/// See [`RegistryBuilder::resolve_timeout`].
+	#[serde(with = "crate::config::optional_duration")]
+	pub resolve_timeout: Option<Duration>,
+	/// See [`RegistryBuilder::status_history_capacity`].
+	pub status_history_capacity: Option<usize>,
+	/// See [`RegistryBuilder::exchange_capture_capacity`].
+	pub exchange_capture_capacity: Option<usize>,
+	/// See [`RegistryBuilder::event_capacity`].
+	pub event_capacity: Option<usize>,
+	/// See [`RegistryBuilder::max_resident_providers`].
+	pub max_resident_providers: Option<usize>,
+	/// See [`RegistryBuilder::outbound_rate_limit`].
+	pub outbound_rate_limit: Option<RateLimitPolicy>,
+	/// See [`RegistryBuilder::refresh_schedule`].
+	pub refresh_schedule: Option<RefreshSchedulePolicy>,
+	/// See [`RegistryBuilder::with_proxy`].
+	pub proxy: Option<ProxySettings>,
+	/// See [`RegistryBuilder::http_client_config`].
+	pub http_client: Option<HttpClientConfig>,
+	/// See [`RegistryBuilder::status_format`].
+	pub status_format: Option<StatusFormat>,
+	/// See [`RegistryBuilder::restore_policy`].
+	pub restore_policy: Option<RestorePolicy>,
+	/// See [`RegistryBuilder::write_through`].
+	pub write_through: Option<bool>,
+	/// Redis server persisting snapshots; see [`RegistryBuilder::with_redis_client`].
+	#[cfg(feature = "redis")]
+	pub redis_url: Option<String>,
+	/// See [`RegistryBuilder::redis_namespace`]; requires `redis_url`.
+	#[cfg(feature = "redis")]
+	pub redis_namespace: Option<String>,
+}
+impl RegistrySettings {
+	/// Apply every set option to `builder`, validating the nested policies.
+	pub fn apply(&self, mut builder: RegistryBuilder) -> Result<RegistryBuilder> {
+		if let Some(require_https) = self.require_https {
+			builder = builder.require_https(require_https);
+		}
+		if let Some(block) = self.block_private_networks {
+			builder = builder.block_private_networks(block);
+		}
+		if let Some(mode) = self.allowlist_mode {
+			builder = builder.allowlist_mode(mode);
+		}
+		if !self.allowed_domains.is_empty() {
+			builder = builder.allowed_domains(self.allowed_domains.iter().cloned());
+		}
+		if let Some(value) = self.default_refresh_early {
+			builder = builder.default_refresh_early(value);
+		}
+		if let Some(value) = self.default_stale_while_error {
+			builder = builder.default_stale_while_error(value);
+		}
+		if let Some(timeout) = self.resolve_timeout {
+			builder = builder.resolve_timeout(timeout);
+		}
+		if let Some(capacity) = self.status_history_capacity {
+			builder = builder.status_history_capacity(capacity);
+		}
+		if let Some(capacity) = self.exchange_capture_capacity {
+			builder = builder.exchange_capture_capacity(capacity);
+		}
+		if let Some(capacity) = self.event_capacity {
+			builder = builder.event_capacity(capacity);
+		}
+		if let Some(limit) = self.max_resident_providers {
+			builder = builder.max_resident_providers(limit);
+		}
+		if let Some(policy) = &self.outbound_rate_limit {
+			policy.validate()?;
+
+			builder = builder.outbound_rate_limit(policy.clone());
+		}
+		if let Some(policy) = &self.refresh_schedule {
+			policy.validate()?;
+
+			builder = builder.refresh_schedule(policy.clone());
+		}
+		if let Some(proxy) = &self.proxy {
+			proxy.validate()?;
+
+			builder = builder.with_proxy(proxy.clone());
+		}
+		if let Some(config) = &self.http_client {
+			builder = builder.http_client_config(config.clone());
+		}
+		if let Some(format) = self.status_format {
+			builder = builder.status_format(format);
+		}
+		if let Some(policy) = self.restore_policy {
+			builder = builder.restore_policy(policy);
+		}
+		if let Some(enabled) = self.write_through {
+			builder = builder.write_through(enabled);
+		}
+
+		#[cfg(feature = "redis")]
+		match (&self.redis_url, &self.redis_namespace) {
+			(Some(url), namespace) => {
+				builder = builder.with_redis_client(redis::Client::open(url.as_str())?);
+
+				if let Some(namespace) = namespace {
+					builder = builder.redis_namespace(namespace);
+				}
+			},
+			(None, Some(_)) =>
+				return Err(Error::Validation {
+					field: "redis_namespace",
+					reason: "Requires `redis_url`.".into(),
+				}),
+			(None, None) => {},
+		}
+
+		Ok(builder)
+	}
+}
+
+/// Changes applied by [`Registry::apply_registrations`].
+///
+/// Providers are listed as `(tenant_id, provider_id)` pairs in sorted order.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReloadReport {
+	/// Providers registered for the first time.
+	pub added: Vec<(String, String)>,
+	/// Providers re-registered because their configuration changed.
+	pub updated: Vec<(String, String)>,
+	/// Config-managed providers removed because they disappeared from the file.
+	pub removed: Vec<(String, String)>,
+	/// Number of providers whose configuration was unchanged.
+	pub unchanged: usize,
+}
+
+impl Registry {
+	/// Load a [`RegistrationFile`] from `path` and reconcile the registry against it.
+	///
+	/// See [`Self::apply_registrations`] for how additions, updates, and removals are applied.
+	pub async fn load_config(&self, path: impl AsRef<Path>) -> Result<ReloadReport> {
+		self.apply_registrations(RegistrationFile::from_path(path)?).await
+	}
+
+	/// Same as [`Self::load_config`], named for call sites that re-apply a file already loaded.
+	pub async fn reload_from_path(&self, path: impl AsRef<Path>) -> Result<ReloadReport> {
+		self.load_config(path).await
+	}
+
+	/// Reconcile the registry against a desired registration set.
+	///
+	/// Every new or changed entry is validated and prepared, including HTTP clients and discovery
+	/// fetches, before anything is applied, so a bad entry leaves the current registrations
+	/// untouched. New providers are then registered, changed ones are re-registered, and providers
+	/// installed by a previous reload that are no longer listed are unregistered. Providers
+	/// registered directly through [`Registry::register`] are never removed by a reload, even while
+	/// the file lists them unchanged; one the file changes is re-registered by the reload and
+	/// managed by it from then on.
+	///
+	/// Installing a prepared provider cannot fail, so every addition and update lands once staging
+	/// succeeds; an inline snapshot restore that fails is logged and the provider starts cold.
+	pub async fn apply_registrations(&self, file: RegistrationFile) -> Result<ReloadReport> {
+		let mut desired = HashMap::with_capacity(file.registrations.len());
+
+		for registration in file.registrations {
+			registration.validate()?;
+
+			let key = TenantProviderKey::new(&registration.tenant_id, &registration.provider_id);
+
+			if desired.contains_key(&key) {
+				return Err(Error::Validation {
+					field: "registrations",
+					reason: format!(
+						"Duplicate registration for tenant '{}' and provider '{}'.",
+						key.tenant_id, key.provider_id
+					),
+				});
+			}
+
+			desired.insert(key, registration);
+		}
+
+		// Held for the whole reconciliation so concurrent reloads apply one at a time.
+		let mut managed = self.inner.config_managed.lock().await;
+		let mut report = ReloadReport::default();
+		let desired_keys: HashSet<TenantProviderKey> = desired.keys().cloned().collect();
+
+		let mut staged = Vec::new();
+
+		for (key, registration) in desired {
+			let current = self.inner.providers.get(&key).map(|entry| entry.value().source.clone());
+
+			match current {
+				None => staged.push((self.prepare(registration).await?, key, false)),
+				Some(source) if same_registration(&source, &registration)? => report.unchanged += 1,
+				Some(_) => staged.push((self.prepare(registration).await?, key, true)),
+			}
+		}
+
+		// Installing cannot fail, so once staging succeeds every addition and update lands.
+		for (prepared, key, replaces) in staged {
+			self.install(prepared).await;
+
+			let pair = (key.tenant_id.clone(), key.provider_id.clone());
+
+			if replaces {
+				report.updated.push(pair);
+			} else {
+				report.added.push(pair);
+			}
+
+			// Only providers this reload installed become config-managed; a direct registration
+			// the file merely lists unchanged stays the caller's.
+			managed.insert(key);
+		}
+
+		let stale: Vec<TenantProviderKey> = managed.difference(&desired_keys).cloned().collect();
+
+		// Forget each removed provider as it goes, so a failure leaves the rest for the next
+		// reload.
+		for key in stale {
+			if self.unregister(&key.tenant_id, &key.provider_id).await? {
+				report.removed.push((key.tenant_id.clone(), key.provider_id.clone()));
+			}
+
+			managed.remove(&key);
+		}
+
+		report.added.sort();
+		report.updated.sort();
+		report.removed.sort();
+
+		tracing::info!(
+			added = report.added.len(),
+			updated = report.updated.len(),
+			removed = report.removed.len(),
+			unchanged = report.unchanged,
+			"registrations reloaded"
+		);
+
+		Ok(report)
+	}
+
+	/// Poll `path` every `interval` and reconcile the registry whenever its contents change.
+	///
+	/// The file as it exists when the watch starts counts as already applied, so load it with
+	/// [`Self::reload_from_path`] first. Failures are logged and leave the current registrations in
+	/// place. A file that fails to parse or validate is retried only once it changes again; other
+	/// failures, such as a discovery fetch hitting an unreachable identity provider, are retried
+	/// on the next tick.
+	pub fn watch_config(
+		&self,
+		path: impl Into<PathBuf>,
+		interval: Duration,
+	) -> tokio::task::JoinHandle<()> {
+		let registry = self.clone();
+		let path = path.into();
+		let mut applied = fs::read(&path).ok();
+
+		tokio::spawn(async move {
+			let mut ticker = tokio::time::interval(interval);
+
+			ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+			loop {
+				ticker.tick().await;
+
+				let raw = match fs::read(&path) {
+					Ok(raw) => raw,
+					Err(err) => {
+						tracing::warn!(error = %err, path = %path.display(), "config watch could not read file");
+
+						continue;
+					},
+				};
+
+				if applied.as_ref() == Some(&raw) {
+					continue;
+				}
+
+				// A file that is itself invalid fails the same way until it changes, while
+				// transient failures, such as an unreachable identity provider, are retried.
+				let (outcome, settled) = match RegistrationFile::parse(&path, &raw) {
+					Ok(file) => {
+						let outcome = registry.apply_registrations(file).await;
+						let settled = outcome.as_ref().map_or_else(is_config_error, |_| true);
+
+						(outcome, settled)
+					},
+					Err(err) => (Err(err), true),
+				};
+
+				match outcome {
+					Ok(report) => {
+						tracing::debug!(?report, path = %path.display(), "config change applied")
+					},
+					Err(err) => tracing::warn!(
+						error = %err,
+						path = %path.display(),
+						"config change rejected; keeping current registrations"
+					),
+				}
+
+				if settled {
+					applied = Some(raw);
+				}
+			}
+		})
+	}
+
+	/// Reload registrations from `path` each time `triggers` yields.
+	///
+	/// This is the pipeline behind [`Self::install_sighup_reload`]; pass any other stream, such as
+	/// a channel fed by an admin endpoint, to drive it differently. Reload failures are logged and
+	/// leave the current registrations in place. The task ends when `triggers` does.
+	pub fn reload_on<S>(&self, path: impl Into<PathBuf>, triggers: S) -> tokio::task::JoinHandle<()>
+	where
+		S: Stream<Item = ()> + Send + 'static,
+	{
+		let registry = self.clone();
+		let path = path.into();
+
+		tokio::spawn(async move {
+			let mut triggers = pin::pin!(triggers);
+
+			while triggers.next().await.is_some() {
+				match registry.reload_from_path(&path).await {
+					Ok(report) => {
+						tracing::debug!(?report, path = %path.display(), "triggered reload applied")
+					},
+					Err(err) => tracing::warn!(
+						error = %err,
+						path = %path.display(),
+						"triggered reload failed; keeping current registrations"
+					),
+				}
+			}
+		})
+	}
+
+	/// Reload registrations from `path` whenever the process receives `SIGHUP`.
+	///
+	/// Reload failures are logged and leave the current registrations in place.
+	#[cfg(all(unix, feature = "unix"))]
+	pub fn install_sighup_reload(
+		&self,
+		path: impl Into<PathBuf>,
+	) -> Result<tokio::task::JoinHandle<()>> {
+		let hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
+		let triggers = stream::unfold(hangup, |mut hangup| async move {
+			hangup.recv().await.map(|()| ((), hangup))
+		});
+
+		Ok(self.reload_on(path, triggers))
+	}
+}
+
+// Whether applying a parsed file failed because of its contents rather than its environment.
+fn is_config_error(err: &Error) -> bool {
+	matches!(err.root(), Error::Validation { .. } | Error::Security(_) | Error::Url(_))
+}
+
+// Parse `raw` in the format implied by `path`, expanding `${VAR}` references in every string value
+// before deserialising into `T`.
+fn parse_config<T>(path: &Path, raw: &[u8]) -> Result<T>
+where
+	T: DeserializeOwned,
+{
+	match path.extension().and_then(|extension| extension.to_str()) {
+		#[cfg(feature = "toml")]
+		Some("toml") => {
+			let raw = std::str::from_utf8(raw).map_err(|err| Error::Validation {
+				field: "registrations",
+				reason: format!("TOML registration files must be UTF-8: {err}."),
+			})?;
+			let mut value = toml::from_str::<toml::Value>(raw)?;
+
+			interpolate_toml(&mut value)?;
+
+			Ok(value.try_into()?)
+		},
+		#[cfg(not(feature = "toml"))]
+		Some("toml") => Err(unsupported_format("TOML", "toml")),
+		#[cfg(feature = "yaml")]
+		Some("yaml" | "yml") => {
+			let mut value = serde_yaml::from_slice::<serde_yaml::Value>(raw)?;
+
+			interpolate_yaml(&mut value)?;
+
+			Ok(serde_yaml::from_value(value)?)
+		},
+		#[cfg(not(feature = "yaml"))]
+		Some("yaml" | "yml") => Err(unsupported_format("YAML", "yaml")),
+		_ => {
+			let mut value = serde_json::from_slice::<serde_json::Value>(raw)?;
+
+			interpolate_json(&mut value)?;
+
+			Ok(serde_json::from_value(value)?)
+		},
+	}
+}
+
+fn interpolate_json(value: &mut serde_json::Value) -> Result<()> {
+	match value {
+		serde_json::Value::String(text) => *text = crate::config::interpolate_env(text)?,
+		serde_json::Value::Array(items) => items.iter_mut().try_for_each(interpolate_json)?,
+		serde_json::Value::Object(fields) => fields.values_mut().try_for_each(interpolate_json)?,
+		_ => {},
+	}
+
+	Ok(())
+}
+
+#[cfg(feature = "toml")]
+fn interpolate_toml(value: &mut toml::Value) -> Result<()> {
+	match value {
+		toml::Value::String(text) => *text = crate::config::interpolate_env(text)?,
+		toml::Value::Array(items) => items.iter_mut().try_for_each(interpolate_toml)?,
+		toml::Value::Table(fields) =>
+			fields.iter_mut().try_for_each(|(_, value)| interpolate_toml(value))?,
+		_ => {},
+	}
+
+	Ok(())
+}
+
+#[cfg(feature = "yaml")]
+fn interpolate_yaml(value: &mut serde_yaml::Value) -> Result<()> {
+	match value {
+		serde_yaml::Value::String(text) => *text = crate::config::interpolate_env(text)?,
+		serde_yaml::Value::Sequence(items) => items.iter_mut().try_for_each(interpolate_yaml)?,
+		serde_yaml::Value::Mapping(fields) =>
+			fields.iter_mut().try_for_each(|(_, value)| interpolate_yaml(value))?,
+		serde_yaml::Value::Tagged(tagged) => interpolate_yaml(&mut tagged.value)?,
+		_ => {},
+	}
+
+	Ok(())
+}
+
+fn same_registration(
+	current: &IdentityProviderRegistration,
+	desired: &IdentityProviderRegistration,
+) -> Result<bool> {
+	Ok(serde_json::to_value(current)? == serde_json::to_value(desired)?)
+}
+
+#[cfg(any(not(feature = "toml"), not(feature = "yaml")))]
+fn unsupported_format(format: &str, feature: &str) -> Error {
+	Error::Validation {
+		field: "registrations",
+		reason: format!("Loading {format} registration files requires the `{feature}` feature."),
+	}
+}
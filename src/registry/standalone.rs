@@ -0,0 +1,151 @@
+//! Single-provider cache facade for embedding without a [`Registry`].
+
+// self
+use super::*;
+
+/// Tenant and provider identifier used by [`JwksCache`] registrations and statuses.
+pub const STANDALONE_ID: &str = "default";
+
+/// Common settings for a [`JwksCache`].
+///
+/// Anything not covered here can be set on an [`IdentityProviderRegistration`] passed to
+/// [`JwksCache::from_registration`].
+#[derive(Clone, Debug)]
+pub struct JwksCacheOptions {
+	/// Reject plain-HTTP JWKS URLs.
+	pub require_https: bool,
+	/// Lead time before expiry at which keys are refreshed in the background.
+	pub refresh_early: Duration,
+	/// How long cached keys are still served after refreshes start failing.
+	pub stale_while_error: Duration,
+	/// Maximum time a lookup waits on an upstream fetch; `None` waits for it to finish.
+	pub resolve_timeout: Option<Duration>,
+	/// Retry policy for upstream fetches.
+	pub retry_policy: RetryPolicy,
+}
+impl JwksCacheOptions {
+	/// Allow or reject plain-HTTP JWKS URLs.
+	pub fn with_require_https(mut self, require_https: bool) -> Self {
+		self.require_https = require_https;
+
+		self
+	}
+
+	/// Refresh keys `lead` before they expire.
+	pub fn with_refresh_early(mut self, lead: Duration) -> Self {
+		self.refresh_early = lead;
+
+		self
+	}
+
+	/// Keep serving cached keys for `window` after refreshes start failing.
+	pub fn with_stale_while_error(mut self, window: Duration) -> Self {
+		self.stale_while_error = window;
+
+		self
+	}
+
+	/// Wait at most `timeout` for an upstream fetch.
+	pub fn with_resolve_timeout(mut self, timeout: Duration) -> Self {
+		self.resolve_timeout = Some(timeout);
+
+		self
+	}
+
+	/// Replace the retry policy for upstream fetches.
+	pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+		self.retry_policy = policy;
+
+		self
+	}
+}
+impl Default for JwksCacheOptions {
+	fn default() -> Self {
+		Self {
+			require_https: true,
+			refresh_early: DEFAULT_REFRESH_EARLY,
+			stale_while_error: DEFAULT_STALE_WHILE_ERROR,
+			resolve_timeout: None,
+			retry_policy: RetryPolicy::default(),
+		}
+	}
+}
+
+/// JWKS cache for a single identity provider, without tenants or a [`Registry`].
+///
+/// Clones share the same cache. Background refreshes stop once the last clone is dropped.
+#[derive(Clone, Debug)]
+pub struct JwksCache {
+	manager: CacheManager,
+	// Owns background refreshes; dropping the last clone aborts them.
+	_tasks: Arc<TaskSet>,
+}
+impl JwksCache {
+	/// Cache the JWKS published at `url`.
+	pub fn new(url: impl AsRef<str>, options: JwksCacheOptions) -> Result<Self> {
+		let mut registration =
+			IdentityProviderRegistration::new(STANDALONE_ID, STANDALONE_ID, url)?
+				.with_require_https(options.require_https);
+
+		registration.refresh_early = Some(options.refresh_early);
+		registration.stale_while_error = Some(options.stale_while_error);
+		registration.retry_policy = options.retry_policy;
+
+		Self::build(registration, options.resolve_timeout)
+	}
+
+	/// Cache keys for a fully specified registration.
+	///
+	/// Must be called within a Tokio runtime when the registration sets `keepalive_interval`.
+	pub fn from_registration(registration: IdentityProviderRegistration) -> Result<Self> {
+		Self::build(registration, None)
+	}
+
+	fn build(
+		mut registration: IdentityProviderRegistration,
+		resolve_timeout: Option<Duration>,
+	) -> Result<Self> {
+		registration.normalize_allowed_domains();
+
+		let tasks = Arc::new(TaskSet::new());
+		let manager = CacheManager::new(registration)?
+			.with_resolve_timeout(resolve_timeout)
+			.with_task_handle(tasks.handle());
+
+		manager.start_keepalive();
+
+		Ok(Self { manager, _tasks: tasks })
+	}
+
+	/// Current key set, fetched from upstream when the cache is empty or expired.
+	pub async fn get(&self) -> Result<Arc<JwkSet>> {
+		self.manager.resolve(None).await
+	}
+
+	/// Key with the given `kid`, revalidating once when it is unknown.
+	pub async fn get_key(&self, kid: &str) -> Result<Arc<Jwk>> {
+		self.manager.resolve_key(kid).await
+	}
+
+	/// Revalidate against upstream now and wait for the outcome.
+	pub async fn refresh(&self) -> Result<()> {
+		self.manager.revalidate().await
+	}
+
+	/// Lifecycle state, expiry, and error counters of the cached keys.
+	pub async fn status(&self) -> ProviderStatus {
+		let registration = self.manager.effective_registration();
+		let snapshot = self.manager.snapshot().await;
+
+		#[cfg(feature = "metrics")]
+		let status = {
+			let metrics = self.manager.metrics().snapshot();
+
+			ProviderStatus::from_components(&registration, snapshot, metrics)
+		};
+		#[cfg(not(feature = "metrics"))]
+		let status = ProviderStatus::from_components(&registration, snapshot);
+
+		status
+	}
+}
@@ -1,22 +1,43 @@
-//! Security utilities covering HTTPS enforcement, domain allowlists, and SPKI pinning.
+//! Security utilities covering HTTPS enforcement, domain allowlists, SPKI pinning, and mutual TLS
+//! client identities.
 //!
 //! # Threat Model
 //! These helpers assume upstream TLS validation has already succeeded and focus on defending the
 //! cache pipeline against downgrade attempts (HTTP redirects), host header confusion, and
-//! certificate substitution by validating SPKI fingerprints.
+//! certificate substitution by validating SPKI fingerprints. Pins are checked during the TLS
+//! handshake by the verifier installed through [`tls_config`], after the usual chain validation
+//! against the bundled web PKI roots and any per-provider trusted roots.
+//!
+//! Registrations that fetch tenant-supplied URLs can also block private networks: IP literals are
+//! checked with [`enforce_public_host`], and hostnames resolve through [`PublicResolver`].
 
 // std
 use std::{
 	collections::HashSet,
 	fmt::{Debug, Formatter, Result as FmtResult},
+	net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
 };
 // crates.io
 use base64::prelude::*;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use rustls::{
+	ClientConfig, DigitallySignedStruct, Error as TlsError, RootCertStore, SignatureScheme,
+	client::{
+		WebPkiServerVerifier,
+		danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+	},
+	crypto,
+	pki_types::{
+		CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName, UnixTime, pem::PemObject,
+	},
+	sign::CertifiedKey,
+};
 use serde::{Deserialize, Serialize, de::Deserializer};
 use sha2::{Digest, Sha256};
-use url::Url;
+use tokio::net::lookup_host;
+use url::{Host, Url};
 // self
-use crate::_prelude::*;
+use crate::{_prelude::*, http::resolve::SharedResolver, registry::AllowlistMode};
 
 /// SHA-256 fingerprint of a Subject Public Key Info (SPKI) structure.
 #[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -73,6 +94,64 @@ impl From<SpkiFingerprint> for String {
 	}
 }
 
+/// Client certificate presented to providers that require mutual TLS.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientIdentity {
+	/// PEM-encoded certificate chain, starting with the client certificate.
+	pub certificate_chain: String,
+	/// PEM-encoded PKCS#8 private key of the client certificate.
+	pub private_key: String,
+}
+impl ClientIdentity {
+	/// Build an identity from PEM documents, rejecting keys that do not match the certificate.
+	pub fn from_pem(
+		certificate_chain: impl Into<String>,
+		private_key: impl Into<String>,
+	) -> Result<Self> {
+		let identity =
+			Self { certificate_chain: certificate_chain.into(), private_key: private_key.into() };
+
+		identity.validate()?;
+
+		Ok(identity)
+	}
+
+	/// Check that both documents parse and that the key belongs to the first certificate.
+	pub fn validate(&self) -> Result<()> {
+		let (chain, key) = self.decode()?;
+
+		CertifiedKey::from_der(chain, key, &crypto::ring::default_provider()).map_err(|err| {
+			Error::Validation {
+				field: "client_identity",
+				reason: format!("Private key does not match the certificate: {err}."),
+			}
+		})?;
+
+		Ok(())
+	}
+
+	fn decode(&self) -> Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)> {
+		let chain = parse_certificates(&self.certificate_chain, "client_identity")?;
+		let key =
+			PrivatePkcs8KeyDer::from_pem_slice(self.private_key.as_bytes()).map_err(|err| {
+				Error::Validation {
+					field: "client_identity",
+					reason: format!("Private key must be a PEM-encoded PKCS#8 key: {err}."),
+				}
+			})?;
+
+		Ok((chain, key.into()))
+	}
+}
+impl Debug for ClientIdentity {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("ClientIdentity")
+			.field("certificate_chain", &self.certificate_chain)
+			.field("private_key", &"[redacted]")
+			.finish()
+	}
+}
+
 /// Canonicalise a DNS name by trimming whitespace, removing any trailing dot, and lowercasing.
 pub fn canonicalize_dns_name(value: &str) -> Option<String> {
 	let trimmed = value.trim();
@@ -124,6 +203,114 @@ pub fn enforce_https(url: &Url) -> Result<()> {
 	}
 }
 
+/// Whether `ip` is publicly routable.
+///
+/// Loopback, private, shared (carrier-grade NAT), link-local, unique-local, site-local,
+/// unspecified, "this network", benchmarking, reserved, broadcast, multicast, and documentation
+/// addresses are not. IPv4 addresses embedded in IPv4-mapped, NAT64 (`64:ff9b::/96`), and 6to4
+/// (`2002::/16`) IPv6 addresses are checked as IPv4.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+	match ip {
+		IpAddr::V4(ip) => {
+			let [first, second, ..] = ip.octets();
+			let this_network = first == 0;
+			let shared = first == 100 && (64..128).contains(&second);
+			let benchmarking = first == 198 && (18..20).contains(&second);
+			let reserved = first >= 240;
+
+			!(ip.is_loopback()
+				|| ip.is_private()
+				|| this_network
+				|| shared || benchmarking
+				|| reserved || ip.is_link_local()
+				|| ip.is_unspecified()
+				|| ip.is_broadcast()
+				|| ip.is_multicast()
+				|| ip.is_documentation())
+		},
+		IpAddr::V6(ip) => match embedded_ipv4(ip) {
+			Some(embedded) => is_public_ip(IpAddr::V4(embedded)),
+			None => {
+				let site_local = ip.segments()[0] & 0xffc0 == 0xfec0;
+
+				!(ip.is_loopback()
+					|| ip.is_unspecified()
+					|| ip.is_multicast()
+					|| ip.is_unique_local()
+					|| ip.is_unicast_link_local()
+					|| site_local)
+			},
+		},
+	}
+}
+
+// The IPv4 address an IPv6 address reaches, when it is an IPv4-mapped, NAT64, or 6to4 address.
+fn embedded_ipv4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+	let octets = ip.octets();
+
+	match ip.segments() {
+		[0x64, 0xff9b, 0, 0, 0, 0, ..] =>
+			Some(Ipv4Addr::new(octets[12], octets[13], octets[14], octets[15])),
+		[0x2002, ..] => Some(Ipv4Addr::new(octets[2], octets[3], octets[4], octets[5])),
+		_ => ip.to_ipv4_mapped(),
+	}
+}
+
+/// Reject `url` when its host is an IP literal outside the public ranges.
+///
+/// Hostnames pass; [`PublicResolver`] checks the addresses they resolve to when connecting.
+pub fn enforce_public_host(url: &Url) -> Result<()> {
+	let ip = match url.host() {
+		Some(Host::Ipv4(ip)) => IpAddr::V4(ip),
+		Some(Host::Ipv6(ip)) => IpAddr::V6(ip),
+		_ => return Ok(()),
+	};
+
+	if is_public_ip(ip) {
+		Ok(())
+	} else {
+		Err(Error::Security(format!("Upstream URL {url} targets the non-public address {ip}.")))
+	}
+}
+
+/// DNS resolver that only returns publicly routable addresses.
+///
+/// Installed on clients built for registrations that block private networks. Connections use only
+/// the addresses returned here, so a hostname re-pointed at an internal address after validation
+/// still cannot reach it. Addresses come from the system resolver unless an inner resolver, such
+/// as the registry's [`dns_resolver`](crate::RegistryBuilder::dns_resolver), is wrapped.
+#[derive(Clone, Debug, Default)]
+pub struct PublicResolver {
+	inner: Option<SharedResolver>,
+}
+impl PublicResolver {
+	/// Filter the addresses returned by `inner`, or by the system resolver when `None`.
+	pub fn new(inner: Option<SharedResolver>) -> Self {
+		Self { inner }
+	}
+}
+impl Resolve for PublicResolver {
+	fn resolve(&self, name: Name) -> Resolving {
+		let inner = self.inner.clone();
+
+		Box::pin(async move {
+			let host = name.as_str().to_owned();
+			let addrs: Vec<SocketAddr> = match inner {
+				Some(inner) => inner.resolve(name).await?.collect(),
+				None => lookup_host((host.as_str(), 0)).await?.collect(),
+			};
+			let public =
+				addrs.into_iter().filter(|addr| is_public_ip(addr.ip())).collect::<Vec<_>>();
+
+			if public.is_empty() {
+				return Err(format!("{host} resolves only to non-public addresses").into());
+			}
+
+			Ok(Box::new(public.into_iter()) as Addrs)
+		})
+	}
+}
+
 #[inline]
 fn matches_allowlist(host: &str, domain: &str) -> bool {
 	if host == domain {
@@ -163,6 +350,37 @@ pub fn host_is_allowed(host: &str, allowed_domains: &[String]) -> bool {
 	})
 }
 
+/// Registry-wide host allowlist, enforced on top of each registration's `allowed_domains`.
+///
+/// Built from [`RegistryBuilder::allowed_domains`](crate::RegistryBuilder::allowed_domains) and
+/// [`RegistryBuilder::allowlist_mode`](crate::RegistryBuilder::allowlist_mode); the default
+/// accepts every host.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HostAllowlist {
+	mode: AllowlistMode,
+	domains: Vec<String>,
+}
+impl HostAllowlist {
+	/// Allowlist applying `mode` to `domains`.
+	pub fn new(mode: AllowlistMode, domains: Vec<String>) -> Self {
+		Self { mode, domains: normalize_allowlist(domains) }
+	}
+
+	/// Whether `host` passes the allowlist.
+	pub fn allows(&self, host: &str) -> bool {
+		match self.mode {
+			AllowlistMode::AllowAll => true,
+			AllowlistMode::DenyAll => false,
+			AllowlistMode::List => !self.domains.is_empty() && host_is_allowed(host, &self.domains),
+		}
+	}
+}
+impl Default for HostAllowlist {
+	fn default() -> Self {
+		Self::new(AllowlistMode::AllowAll, Vec::new())
+	}
+}
+
 /// Compute the SHA-256 fingerprint of a DER-encoded SPKI payload.
 pub fn fingerprint_spki(spki_der: &[u8]) -> [u8; 32] {
 	let digest = Sha256::digest(spki_der);
@@ -211,6 +429,144 @@ where
 	))
 }
 
+/// Parse every certificate in a PEM document, requiring at least one.
+pub fn parse_pem_certificates(pem: &str) -> Result<Vec<CertificateDer<'static>>> {
+	parse_certificates(pem, "trusted_roots")
+}
+
+/// Build a rustls client configuration trusting the bundled web PKI roots plus `trusted_roots`.
+///
+/// When `pins` is non-empty, handshakes whose chain matches none of them are rejected. Pins are
+/// matched after chain validation against the SPKI of every presented certificate, so pinning an
+/// intermediate CA is supported. `identity` is presented when the server requests a client
+/// certificate. Pass the result to `reqwest::ClientBuilder::use_preconfigured_tls` to apply the
+/// same settings to an injected client.
+pub fn tls_config(
+	trusted_roots: &[CertificateDer<'static>],
+	pins: &[SpkiFingerprint],
+	identity: Option<&ClientIdentity>,
+) -> Result<ClientConfig> {
+	let provider = Arc::new(crypto::ring::default_provider());
+	let mut roots = RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.to_vec() };
+
+	for root in trusted_roots {
+		roots.add(root.clone()).map_err(|err| Error::Validation {
+			field: "trusted_roots",
+			reason: format!("Certificate is not a valid trust anchor: {err}."),
+		})?;
+	}
+
+	let verifier = WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+		.build()
+		.map_err(|err| Error::Security(format!("Failed to build TLS verifier: {err}.")))?;
+	let builder = ClientConfig::builder_with_provider(provider)
+		.with_safe_default_protocol_versions()
+		.map_err(|err| Error::Security(format!("Failed to configure TLS: {err}.")))?;
+	let config = if pins.is_empty() {
+		builder.with_webpki_verifier(verifier)
+	} else {
+		builder
+			.dangerous()
+			.with_custom_certificate_verifier(Arc::new(PinnedCertVerifier::new(verifier, pins)))
+	};
+
+	match identity {
+		Some(identity) => {
+			let (chain, key) = identity.decode()?;
+
+			config.with_client_auth_cert(chain, key).map_err(|err| Error::Validation {
+				field: "client_identity",
+				reason: format!("Private key does not match the certificate: {err}."),
+			})
+		},
+		None => Ok(config.with_no_client_auth()),
+	}
+}
+
+fn parse_certificates(pem: &str, field: &'static str) -> Result<Vec<CertificateDer<'static>>> {
+	let certificates = CertificateDer::pem_slice_iter(pem.as_bytes())
+		.collect::<std::result::Result<Vec<_>, _>>()
+		.map_err(|err| Error::Validation {
+			field,
+			reason: format!("Failed to parse PEM certificate: {err}."),
+		})?;
+
+	if certificates.is_empty() {
+		return Err(Error::Validation {
+			field,
+			reason: "PEM document contains no certificates.".into(),
+		});
+	}
+
+	Ok(certificates)
+}
+
+#[derive(Debug)]
+struct PinnedCertVerifier {
+	inner: Arc<WebPkiServerVerifier>,
+	pins: Vec<SpkiFingerprint>,
+}
+impl PinnedCertVerifier {
+	fn new(inner: Arc<WebPkiServerVerifier>, pins: &[SpkiFingerprint]) -> Self {
+		Self { inner, pins: pins.to_vec() }
+	}
+}
+impl ServerCertVerifier for PinnedCertVerifier {
+	fn verify_server_cert(
+		&self,
+		end_entity: &CertificateDer<'_>,
+		intermediates: &[CertificateDer<'_>],
+		server_name: &ServerName<'_>,
+		ocsp_response: &[u8],
+		now: UnixTime,
+	) -> std::result::Result<ServerCertVerified, TlsError> {
+		let verified = self.inner.verify_server_cert(
+			end_entity,
+			intermediates,
+			server_name,
+			ocsp_response,
+			now,
+		)?;
+		let mut presented = Vec::with_capacity(intermediates.len() + 1);
+
+		// Parsing does not depend on the certificate's position, so intermediates go through the
+		// same end-entity parser.
+		for certificate in std::iter::once(end_entity).chain(intermediates) {
+			let certificate = webpki::EndEntityCert::try_from(certificate)
+				.map_err(|err| TlsError::General(format!("Failed to parse certificate: {err}.")))?;
+
+			presented.push(certificate.subject_public_key_info());
+		}
+
+		verify_spki_pins(presented.iter().map(|spki| spki.as_ref()), &self.pins)
+			.map_err(|err| TlsError::General(err.to_string()))?;
+
+		Ok(verified)
+	}
+
+	fn verify_tls12_signature(
+		&self,
+		message: &[u8],
+		cert: &CertificateDer<'_>,
+		dss: &DigitallySignedStruct,
+	) -> std::result::Result<HandshakeSignatureValid, TlsError> {
+		self.inner.verify_tls12_signature(message, cert, dss)
+	}
+
+	fn verify_tls13_signature(
+		&self,
+		message: &[u8],
+		cert: &CertificateDer<'_>,
+		dss: &DigitallySignedStruct,
+	) -> std::result::Result<HandshakeSignatureValid, TlsError> {
+		self.inner.verify_tls13_signature(message, cert, dss)
+	}
+
+	fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+		self.inner.supported_verify_schemes()
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -234,6 +590,57 @@ mod tests {
 		assert!(err.is_err());
 	}
 
+	#[test]
+	fn only_publicly_routable_addresses_are_public() {
+		for ip in [
+			"93.184.216.34",
+			"2606:2800:220:1::1",
+			"::ffff:93.184.216.34",
+			"64:ff9b::5db8:d822",
+			"2002:5db8:d822::1",
+		] {
+			assert!(is_public_ip(ip.parse().unwrap()), "{ip} should be public");
+		}
+		for ip in [
+			"127.0.0.1",
+			"10.1.2.3",
+			"172.20.0.1",
+			"192.168.1.1",
+			"100.64.0.1",
+			"169.254.169.254",
+			"0.0.0.0",
+			"255.255.255.255",
+			"224.0.0.1",
+			"::1",
+			"::",
+			"fd00::1",
+			"fe80::1",
+			"::ffff:10.0.0.1",
+			"0.1.2.3",
+			"198.18.0.1",
+			"198.19.255.254",
+			"240.0.0.1",
+			"64:ff9b::a9fe:a9fe",
+			"64:ff9b::7f00:1",
+			"2002:c0a8:101::1",
+			"2002:a00:1::",
+			"fec0::1",
+			"feff::1",
+		] {
+			assert!(!is_public_ip(ip.parse().unwrap()), "{ip} should not be public");
+		}
+
+		let literal = |url: &str| enforce_public_host(&Url::parse(url).unwrap());
+
+		assert!(
+			literal("https://idp.internal/jwks").is_ok(),
+			"hostnames are checked on resolution"
+		);
+		assert!(literal("https://93.184.216.34/jwks").is_ok());
+		assert!(matches!(literal("http://169.254.169.254/latest"), Err(Error::Security(_))));
+		assert!(matches!(literal("https://[::1]:8443/jwks"), Err(Error::Security(_))));
+	}
+
 	#[test]
 	fn host_allowlist_handles_case_and_trailing_dot() {
 		let domains = normalize_allowlist(vec!["Example.COM.".into()]);
@@ -260,4 +667,56 @@ mod tests {
 		let http = Url::parse("http://example.com/jwks").unwrap();
 		assert!(enforce_https(&http).is_err());
 	}
+
+	#[test]
+	fn pinned_verifier_checks_presented_spki_after_chain_validation() {
+		let ca = CertificateDer::from(include_bytes!("../tests/fixtures/tls/ca.der").as_slice());
+		let leaf =
+			CertificateDer::from(include_bytes!("../tests/fixtures/tls/leaf.der").as_slice());
+		let mut roots = RootCertStore::empty();
+
+		roots.add(ca).expect("test CA must be a valid trust anchor");
+
+		let inner = WebPkiServerVerifier::builder_with_provider(
+			Arc::new(roots),
+			Arc::new(crypto::ring::default_provider()),
+		)
+		.build()
+		.expect("verifier must build");
+		let spki = webpki::EndEntityCert::try_from(&leaf)
+			.expect("leaf must parse")
+			.subject_public_key_info();
+		let leaf_pin =
+			SpkiFingerprint::from_b64(&BASE64_STANDARD.encode(fingerprint_spki(spki.as_ref())))
+				.expect("valid fingerprint");
+		let other_pin = SpkiFingerprint::from_b64(&BASE64_STANDARD.encode([7u8; 32]))
+			.expect("valid fingerprint");
+		let verify = |pins: &[SpkiFingerprint]| {
+			PinnedCertVerifier::new(inner.clone(), pins).verify_server_cert(
+				&leaf,
+				&[],
+				&ServerName::try_from("idp.example.test").expect("valid server name"),
+				&[],
+				UnixTime::now(),
+			)
+		};
+
+		assert!(verify(&[other_pin.clone(), leaf_pin]).is_ok());
+		assert!(matches!(verify(&[other_pin]), Err(TlsError::General(_))));
+	}
+
+	#[test]
+	fn client_identity_requires_matching_key_and_redacts_it() {
+		let certificate = include_str!("../tests/fixtures/tls/client.pem");
+		let key = include_str!("../tests/fixtures/tls/client.key");
+		let identity = ClientIdentity::from_pem(certificate, key).expect("matching identity");
+
+		assert!(!format!("{identity:?}").contains("PRIVATE KEY"));
+		assert!(tls_config(&[], &[], Some(&identity)).is_ok());
+
+		let mismatched =
+			ClientIdentity::from_pem(certificate, include_str!("../tests/fixtures/tls/leaf.key"));
+
+		assert!(matches!(mismatched, Err(Error::Validation { field: "client_identity", .. })));
+	}
 }
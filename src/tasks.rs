@@ -0,0 +1,261 @@
+//! Background task ownership tied to a provider's lifetime.
+//!
+//! Background refreshes are spawned into a [`TaskSet`] owned by the provider's entry in the
+//! [`Registry`](crate::Registry) rather than detached onto the runtime. Unregistering the provider
+//! or dropping the last registry clone drops the set, which aborts every task still running, so
+//! tests and short-lived tools do not leak refreshes that keep talking to identity providers after
+//! nobody can read their results.
+//!
+//! Long-running loops such as keepalive and recovery probes are spawned as daemons, so a graceful
+//! drain can stop them immediately while it waits for one-shot refreshes to finish.
+
+// std
+use std::{
+	future::Future,
+	mem,
+	sync::{Mutex, Weak},
+};
+// crates.io
+use tokio::{task::JoinSet, time};
+// self
+use crate::_prelude::*;
+
+/// Owning set of background tasks; dropping it aborts every task still running.
+#[derive(Debug, Default)]
+pub struct TaskSet {
+	tasks: Arc<Mutex<Tasks>>,
+}
+impl TaskSet {
+	/// Create an empty task set.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Non-owning handle for spawning into this set.
+	pub fn handle(&self) -> TaskHandle {
+		TaskHandle { tasks: Arc::downgrade(&self.tasks) }
+	}
+
+	/// Number of one-shot tasks that have not finished yet; daemons are not counted.
+	pub fn len(&self) -> usize {
+		let mut tasks = lock(&self.tasks);
+
+		tasks.reap();
+
+		tasks.oneshots.len()
+	}
+
+	/// Whether every spawned one-shot task has finished.
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Abort every running task without waiting, returning how many one-shot tasks were still
+	/// running.
+	pub fn abort_all(&self) -> usize {
+		let (mut oneshots, _daemons) = lock(&self.tasks).take();
+
+		reap(&mut oneshots);
+
+		// Dropping the detached sets aborts their tasks, so they are not counted twice.
+		oneshots.len()
+	}
+
+	/// Abort every running task and wait for them to stop, returning how many one-shot tasks were
+	/// aborted.
+	pub async fn shutdown(&self) -> usize {
+		let (mut oneshots, mut daemons) = lock(&self.tasks).take();
+
+		reap(&mut oneshots);
+
+		let running = oneshots.len();
+
+		oneshots.shutdown().await;
+		daemons.shutdown().await;
+
+		running
+	}
+
+	/// Stop accepting tasks, abort daemons, and wait until `deadline` for the other tasks.
+	///
+	/// Tasks still running at the deadline are aborted. Returns how many tasks finished on their
+	/// own and how many were aborted, daemons included.
+	pub async fn drain(&self, deadline: Instant) -> (usize, usize) {
+		let (mut oneshots, mut daemons) = {
+			let mut tasks = lock(&self.tasks);
+
+			tasks.closed = true;
+
+			tasks.take()
+		};
+
+		reap(&mut daemons);
+
+		let mut aborted = daemons.len();
+		let mut drained = 0;
+
+		daemons.shutdown().await;
+
+		let joined = time::timeout_at(deadline, async {
+			while oneshots.join_next().await.is_some() {
+				drained += 1;
+			}
+		})
+		.await;
+
+		if joined.is_err() {
+			aborted += oneshots.len();
+
+			oneshots.shutdown().await;
+		}
+
+		(drained, aborted)
+	}
+}
+
+#[derive(Debug, Default)]
+struct Tasks {
+	oneshots: JoinSet<()>,
+	daemons: JoinSet<()>,
+	// Set by `TaskSet::drain`; later spawns are dropped.
+	closed: bool,
+}
+impl Tasks {
+	fn reap(&mut self) {
+		reap(&mut self.oneshots);
+		reap(&mut self.daemons);
+	}
+
+	fn take(&mut self) -> (JoinSet<()>, JoinSet<()>) {
+		(mem::take(&mut self.oneshots), mem::take(&mut self.daemons))
+	}
+}
+
+/// Weak reference to a [`TaskSet`] held by the tasks' own owners.
+///
+/// Spawned tasks usually capture a clone of whatever holds the handle, so a strong reference here
+/// would keep the set, and therefore the tasks, alive forever.
+#[derive(Clone, Debug)]
+pub struct TaskHandle {
+	tasks: Weak<Mutex<Tasks>>,
+}
+impl TaskHandle {
+	/// Spawn `task` into the owning set, dropping it when the set no longer exists or is draining.
+	pub fn spawn<F>(&self, task: F)
+	where
+		F: Future<Output = ()> + Send + 'static,
+	{
+		self.spawn_into(task, false);
+	}
+
+	/// Spawn a long-running `task` that a drain aborts instead of waiting for.
+	pub fn spawn_daemon<F>(&self, task: F)
+	where
+		F: Future<Output = ()> + Send + 'static,
+	{
+		self.spawn_into(task, true);
+	}
+
+	fn spawn_into<F>(&self, task: F, daemon: bool)
+	where
+		F: Future<Output = ()> + Send + 'static,
+	{
+		let Some(tasks) = self.tasks.upgrade() else {
+			tracing::debug!("task set dropped; skipping background task");
+
+			return;
+		};
+		let mut tasks = lock(&tasks);
+
+		if tasks.closed {
+			tracing::debug!("task set draining; skipping background task");
+
+			return;
+		}
+
+		tasks.reap();
+
+		if daemon {
+			tasks.daemons.spawn(task);
+		} else {
+			tasks.oneshots.spawn(task);
+		}
+	}
+}
+
+fn lock(tasks: &Mutex<Tasks>) -> std::sync::MutexGuard<'_, Tasks> {
+	// A panic while holding the lock cannot leave the set inconsistent, so ignore poisoning.
+	tasks.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// Finished tasks keep their output in the set until joined; drop them so it stays bounded.
+fn reap(tasks: &mut JoinSet<()>) {
+	while tasks.try_join_next().is_some() {}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn dropping_the_set_aborts_running_tasks() {
+		let set = TaskSet::new();
+		let handle = set.handle();
+		let (sender, receiver) = tokio::sync::oneshot::channel::<()>();
+
+		handle.spawn(async move {
+			let _sender = sender;
+
+			std::future::pending::<()>().await;
+		});
+
+		assert_eq!(set.len(), 1);
+
+		drop(set);
+
+		// The sender is dropped once the task is aborted.
+		assert!(receiver.await.is_err());
+
+		handle.spawn(async {});
+	}
+
+	#[tokio::test]
+	async fn drain_waits_for_oneshots_and_aborts_daemons() {
+		let set = TaskSet::new();
+		let handle = set.handle();
+		let (sender, receiver) = tokio::sync::oneshot::channel();
+
+		handle.spawn_daemon(std::future::pending());
+		handle.spawn(async move {
+			time::sleep(Duration::from_millis(20)).await;
+
+			let _ = sender.send(());
+		});
+		handle.spawn(std::future::pending());
+
+		let (drained, aborted) = set.drain(Instant::now() + Duration::from_millis(200)).await;
+
+		assert_eq!((drained, aborted), (1, 2));
+		assert!(receiver.await.is_ok(), "the short task should have completed");
+
+		handle.spawn(std::future::pending());
+
+		assert!(set.is_empty(), "a drained set accepts no new tasks");
+	}
+
+	#[tokio::test]
+	async fn abort_all_counts_running_tasks_once() {
+		let set = TaskSet::new();
+		let handle = set.handle();
+
+		handle.spawn_daemon(std::future::pending());
+		handle.spawn(std::future::pending());
+		handle.spawn(async {});
+		tokio::task::yield_now().await;
+
+		assert_eq!(set.len(), 1, "daemons are not counted");
+		assert_eq!(set.abort_all(), 1);
+		assert_eq!(set.abort_all(), 0);
+		assert!(set.is_empty());
+	}
+}
@@ -0,0 +1,276 @@
+//! In-process identity provider for testing code built on the cache.
+//!
+//! [`MockJwksProvider`] serves a JWKS over plain HTTP on a local port, signs tokens with its
+//! current key, rotates keys on demand, and can be told to answer the next requests with a
+//! `304`, an error status, or a delay:
+//!
+//! ```no_run
+//! use jwks_cache::{Registry, test_util::{MockJwksProvider, MockResponse}};
+//!
+//! # async fn demo() -> jwks_cache::Result<()> {
+//! let idp = MockJwksProvider::start().await;
+//! let registry = Registry::builder().require_https(false).build();
+//!
+//! idp.register(&registry, "tenant-a", "idp").await?;
+//!
+//! let token = idp.sign(&serde_json::json!({ "sub": "alice", "exp": 4_102_444_800_u64 }))?;
+//!
+//! idp.enqueue(MockResponse::Status(503));
+//! # Ok(())
+//! # }
+//! ```
+
+// std
+use std::{
+	collections::VecDeque,
+	sync::{Mutex, MutexGuard},
+};
+// crates.io
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use jsonwebtoken::{Algorithm, EncodingKey, Header, jwk::JwkSet};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use url::Url;
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate, matchers};
+// self
+use crate::{
+	_prelude::*,
+	jitter,
+	registry::{IdentityProviderRegistration, Registry},
+};
+
+/// Path the mock serves its JWKS from.
+pub const MOCK_JWKS_PATH: &str = "/.well-known/jwks.json";
+/// `max-age` advertised by the mock until [`MockJwksProvider::set_max_age`] changes it.
+pub const DEFAULT_MOCK_MAX_AGE: Duration = Duration::from_secs(300);
+
+/// One-shot answer queued with [`MockJwksProvider::enqueue`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MockResponse {
+	/// Answer `304 Not Modified`, whatever the request's validators.
+	NotModified,
+	/// Answer with this status code and an empty body.
+	Status(u16),
+	/// Serve the current JWKS after waiting this long.
+	Delayed(Duration),
+}
+
+/// Local JWKS endpoint backed by a `wiremock` server.
+///
+/// Keys are `HS256` secrets, so tokens from [`Self::sign`] verify against the served set without
+/// any key generation dependency. Responses carry an `ETag` that changes with the key set, and a
+/// request whose `If-None-Match` matches it gets a `304`. The mock serves plain HTTP, so the
+/// registry it is used with must be built with `require_https(false)`.
+pub struct MockJwksProvider {
+	server: MockServer,
+	state: Arc<Mutex<MockState>>,
+}
+impl MockJwksProvider {
+	/// Start a provider serving a single freshly generated key.
+	pub async fn start() -> Self {
+		let seed = jitter::unique_token();
+		let key = MockKey::generate(&seed, 0);
+		let state = Arc::new(Mutex::new(MockState {
+			seed,
+			generation: 0,
+			signing: Some(key.clone()),
+			jwks: JwkSet { keys: Vec::new() },
+			max_age: DEFAULT_MOCK_MAX_AGE,
+			queue: VecDeque::new(),
+		}));
+
+		lock(&state).publish(vec![key]);
+
+		let server = MockServer::start().await;
+
+		Mock::given(matchers::method("GET"))
+			.and(matchers::path(MOCK_JWKS_PATH))
+			.respond_with(MockResponder(state.clone()))
+			.mount(&server)
+			.await;
+
+		Self { server, state }
+	}
+
+	/// URL of the served JWKS.
+	pub fn jwks_url(&self) -> Url {
+		Url::parse(&format!("{}{MOCK_JWKS_PATH}", self.server.uri()))
+			.expect("mock server URI must be a valid URL")
+	}
+
+	/// Registration pointing at this provider, with HTTPS enforcement disabled.
+	pub fn registration(
+		&self,
+		tenant_id: impl Into<String>,
+		provider_id: impl Into<String>,
+	) -> Result<IdentityProviderRegistration> {
+		Ok(IdentityProviderRegistration::new(tenant_id, provider_id, self.jwks_url().as_str())?
+			.with_require_https(false))
+	}
+
+	/// Register this provider with `registry` under the given identifiers.
+	pub async fn register(
+		&self,
+		registry: &Registry,
+		tenant_id: impl Into<String>,
+		provider_id: impl Into<String>,
+	) -> Result<Arc<IdentityProviderRegistration>> {
+		registry.register(self.registration(tenant_id, provider_id)?).await
+	}
+
+	/// Key id of the current signing key, or `None` after [`Self::set_jwks`].
+	pub fn current_kid(&self) -> Option<String> {
+		lock(&self.state).signing.as_ref().map(|key| key.kid.clone())
+	}
+
+	/// Sign `claims` as an `HS256` token carrying the current key's `kid`.
+	///
+	/// Fails after [`Self::set_jwks`] replaced the generated keys, until the next [`Self::rotate`].
+	pub fn sign<T>(&self, claims: &T) -> Result<String>
+	where
+		T: Serialize,
+	{
+		let key = lock(&self.state).signing.clone().ok_or_else(|| Error::Validation {
+			field: "jwks",
+			reason: "The served key set was replaced; rotate to sign again.".into(),
+		})?;
+		let header = Header { kid: Some(key.kid), ..Header::new(Algorithm::HS256) };
+
+		Ok(jsonwebtoken::encode(&header, claims, &EncodingKey::from_secret(&key.secret))?)
+	}
+
+	/// Start signing with a new key, returning its `kid`.
+	///
+	/// The previous signing key stays published next to the new one, so tokens issued before the
+	/// rotation keep verifying, as with a real provider's overlap window. Keys from earlier
+	/// rotations are dropped.
+	pub fn rotate(&self) -> String {
+		let mut state = lock(&self.state);
+
+		state.generation += 1;
+
+		let key = MockKey::generate(&state.seed, state.generation);
+		let kid = key.kid.clone();
+		let keys = state.signing.replace(key.clone()).into_iter().chain([key]).collect();
+
+		state.publish(keys);
+
+		kid
+	}
+
+	/// Serve `jwks` verbatim from now on.
+	pub fn set_jwks(&self, jwks: JwkSet) {
+		let mut state = lock(&self.state);
+
+		state.generation += 1;
+		state.signing = None;
+		state.jwks = jwks;
+	}
+
+	/// Currently served key set.
+	pub fn jwks(&self) -> JwkSet {
+		lock(&self.state).jwks.clone()
+	}
+
+	/// Advertise `max_age` in the `Cache-Control` header of successful responses.
+	pub fn set_max_age(&self, max_age: Duration) {
+		lock(&self.state).max_age = max_age;
+	}
+
+	/// Answer the next request with `response` instead of the key set.
+	///
+	/// Queued responses are used in order, one per request, before normal serving resumes.
+	pub fn enqueue(&self, response: MockResponse) {
+		lock(&self.state).queue.push_back(response);
+	}
+
+	/// Number of JWKS requests received so far.
+	pub async fn request_count(&self) -> usize {
+		self.server.received_requests().await.map(|requests| requests.len()).unwrap_or_default()
+	}
+}
+
+struct MockState {
+	seed: String,
+	generation: u64,
+	signing: Option<MockKey>,
+	jwks: JwkSet,
+	max_age: Duration,
+	queue: VecDeque<MockResponse>,
+}
+impl MockState {
+	fn publish(&mut self, keys: Vec<MockKey>) {
+		let keys = keys
+			.into_iter()
+			.map(|key| {
+				serde_json::json!({
+					"kty": "oct",
+					"use": "sig",
+					"alg": "HS256",
+					"kid": key.kid,
+					"k": BASE64_URL_SAFE_NO_PAD.encode(&key.secret),
+				})
+			})
+			.collect::<Vec<_>>();
+
+		self.jwks = serde_json::from_value(serde_json::json!({ "keys": keys }))
+			.expect("generated keys must form a valid JWKS");
+	}
+
+	fn etag(&self) -> String {
+		format!("\"mock-{}\"", self.generation)
+	}
+}
+
+#[derive(Clone)]
+struct MockKey {
+	kid: String,
+	secret: Vec<u8>,
+}
+impl MockKey {
+	// Secrets only need to differ between mocks and rotations, not to resist attack.
+	fn generate(seed: &str, generation: u64) -> Self {
+		let secret = Sha256::digest(format!("{seed}/{generation}")).to_vec();
+
+		Self { kid: format!("mock-{generation}-{}", hex_prefix(&secret)), secret }
+	}
+}
+
+struct MockResponder(Arc<Mutex<MockState>>);
+impl Respond for MockResponder {
+	fn respond(&self, request: &Request) -> ResponseTemplate {
+		let mut state = lock(&self.0);
+		let queued = state.queue.pop_front();
+		let etag = state.etag();
+		let fresh = |delay: Duration| {
+			ResponseTemplate::new(200)
+				.set_body_json(&state.jwks)
+				.insert_header("etag", etag.as_str())
+				.insert_header(
+					"cache-control",
+					format!("public, max-age={}", state.max_age.as_secs()).as_str(),
+				)
+				.set_delay(delay)
+		};
+
+		match queued {
+			Some(MockResponse::NotModified) => ResponseTemplate::new(304),
+			Some(MockResponse::Status(status)) => ResponseTemplate::new(status),
+			Some(MockResponse::Delayed(delay)) => fresh(delay),
+			None if request
+				.headers
+				.get("if-none-match")
+				.is_some_and(|value| value.as_bytes() == etag.as_bytes()) =>
+				ResponseTemplate::new(304).insert_header("etag", etag.as_str()),
+			None => fresh(Duration::ZERO),
+		}
+	}
+}
+
+fn lock(state: &Mutex<MockState>) -> MutexGuard<'_, MockState> {
+	state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn hex_prefix(bytes: &[u8]) -> String {
+	bytes.iter().take(4).map(|byte| format!("{byte:02x}")).collect()
+}
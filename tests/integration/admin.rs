@@ -0,0 +1,155 @@
+//! Integration coverage for the admin HTTP router.
+
+// crates.io
+use axum::{
+	body::Body,
+	extract::Request,
+	http::StatusCode,
+	middleware::{self, Next},
+	response::{IntoResponse, Response},
+};
+use jwks_cache::{ProviderState, Registry, Result, admin};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+use wiremock::{
+	Mock, MockServer, ResponseTemplate,
+	matchers::{method, path},
+};
+
+async fn require_token(request: Request, next: Next) -> Response {
+	match request.headers().get("x-admin-token") {
+		Some(token) if token == "s3cr3t" => next.run(request).await,
+		_ => StatusCode::UNAUTHORIZED.into_response(),
+	}
+}
+
+async fn call(app: &axum::Router, method: &str, uri: &str, body: Option<Value>) -> (u16, Value) {
+	let request = Request::builder()
+		.method(method)
+		.uri(uri)
+		.header("x-admin-token", "s3cr3t")
+		.header("content-type", "application/json")
+		.body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+		.expect("request");
+	let response = app.clone().oneshot(request).await.expect("call");
+	let status = response.status().as_u16();
+	let body = axum::body::to_bytes(response.into_body(), 64 * 1_024).await.expect("body");
+
+	(status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn admin_router_manages_providers_behind_the_auth_layer() -> Result<()> {
+	let server = MockServer::start().await;
+
+	Mock::given(method("GET"))
+		.and(path("/jwks.json"))
+		.respond_with(
+			ResponseTemplate::new(200)
+				.set_body_json(json!({ "keys": [] }))
+				.insert_header("cache-control", "public, max-age=300"),
+		)
+		.mount(&server)
+		.await;
+
+	let registry = Registry::builder().require_https(false).build();
+	let app = admin::router(registry.clone(), middleware::from_fn(require_token));
+
+	let unauthenticated =
+		Request::builder().uri("/providers").body(Body::empty()).expect("request");
+	let response = app.clone().oneshot(unauthenticated).await.expect("call");
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+	let registration = json!({
+		"tenant_id": "tenant",
+		"provider_id": "idp",
+		"jwks_url": format!("{}/jwks.json?access=s3cr3t-query", server.uri()),
+		"require_https": false,
+		"min_keys": 0,
+	});
+	let (status, body) = call(&app, "POST", "/providers", Some(registration)).await;
+	assert_eq!(status, 201, "{body}");
+	assert_eq!(body["tenant_id"], "tenant");
+
+	let (status, _) = call(&app, "POST", "/providers/tenant/idp/refresh", None).await;
+	assert_eq!(status, 202);
+	assert_eq!(registry.provider_status("tenant", "idp").await?.state, ProviderState::Ready);
+
+	let (status, body) = call(&app, "GET", "/providers/tenant/idp/exchanges", None).await;
+	assert_eq!(status, 200);
+	assert_eq!(body[0]["method"], "GET");
+	assert_eq!(body[0]["status"], 200);
+	assert!(!body.to_string().contains("s3cr3t-query"), "query string leaked: {body}");
+
+	let (status, body) = call(&app, "GET", "/providers?tenant_id=tenant&state=Ready", None).await;
+	assert_eq!(status, 200);
+	assert_eq!((body["total"].clone(), body["next_offset"].clone()), (json!(1), Value::Null));
+	assert_eq!(body["statuses"][0]["provider_id"], "idp");
+
+	let (status, _) =
+		call(&app, "POST", "/providers/tenant/idp/invalidate?purge_snapshot=true", None).await;
+	assert_eq!(status, 204);
+
+	let (status, body) = call(&app, "GET", "/providers/tenant/idp", None).await;
+	assert_eq!(status, 200);
+	assert_eq!(body["state"], "Empty");
+
+	let (status, body) = call(&app, "GET", "/providers/tenant/missing", None).await;
+	assert_eq!(status, 404);
+	assert_eq!(body["code"], "JWKS_CACHE_NOT_REGISTERED");
+
+	Ok(())
+}
+
+#[cfg(feature = "gzip")]
+#[tokio::test]
+async fn admin_listing_is_gzip_compressed_when_accepted() -> Result<()> {
+	use std::io::Read;
+
+	use jwks_cache::IdentityProviderRegistration;
+
+	let registry = Registry::builder().build();
+
+	for provider_id in ["alpha", "beta"] {
+		registry
+			.register(
+				IdentityProviderRegistration::new(
+					"tenant",
+					provider_id,
+					format!("https://{provider_id}.example.com/jwks.json"),
+				)
+				.expect("registration"),
+			)
+			.await?;
+	}
+
+	let app = admin::router(registry, tower_layer::Identity::new());
+	let list = |accept_encoding: &'static str| {
+		Request::builder()
+			.uri("/providers?limit=1")
+			.header("accept-encoding", accept_encoding)
+			.body(Body::empty())
+			.expect("request")
+	};
+
+	let response = app.clone().oneshot(list("br;q=1.0, gzip;q=0.5")).await.expect("call");
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.headers()["content-encoding"], "gzip");
+	assert_eq!(response.headers()["vary"], "accept-encoding");
+
+	let compressed = axum::body::to_bytes(response.into_body(), 64 * 1_024).await.expect("body");
+	let mut decoded = String::new();
+	flate2::read::GzDecoder::new(&compressed[..]).read_to_string(&mut decoded)?;
+	let body: Value = serde_json::from_str(&decoded)?;
+	assert_eq!((body["total"].clone(), body["next_offset"].clone()), (json!(2), json!(1)));
+	assert_eq!(body["statuses"][0]["provider_id"], "alpha");
+
+	let response = app.oneshot(list("gzip;q=0")).await.expect("call");
+	assert!(response.headers().get("content-encoding").is_none());
+
+	let plain = axum::body::to_bytes(response.into_body(), 64 * 1_024).await.expect("body");
+	let body: Value = serde_json::from_slice(&plain)?;
+	assert_eq!(body["statuses"].as_array().map(Vec::len), Some(1));
+
+	Ok(())
+}
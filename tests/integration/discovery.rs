@@ -0,0 +1,200 @@
+//! Integration coverage for OpenID discovery-backed registrations.
+
+// crates.io
+use jwks_cache::{
+	CacheEventKind, Error, IdentityProviderRegistration, Registry, Result, TtlSeconds,
+};
+use url::Url;
+use wiremock::{
+	Mock, MockServer, ResponseTemplate,
+	matchers::{method, path},
+};
+
+const JWKS: &str = r#"{
+    "keys": [
+        {
+            "kty": "RSA",
+            "alg": "RS256",
+            "use": "sig",
+            "kid": "discovered",
+            "n": "AQIDBAUGBwgJCgsMDQ4PEBESExQVFhcYGRobHB0eHyAhIiMkJSYnKCkqKywtLi8wMTIzNDU2Nzg5Ojs8PT4_QEFCQ0RFRkdISUpLTE1OT1BRUlNUVVZXWFlaW1xdXl9gYWJjZGVmZ2hpamtsbW5vcHFyc3R1dnd4eXp7fH1-f4A",
+            "e": "AQAB"
+        }
+    ]
+}"#;
+
+#[tokio::test]
+async fn registration_resolves_jwks_uri_from_discovery() -> Result<()> {
+	let _ = tracing_subscriber::fmt::try_init();
+
+	let server = MockServer::start().await;
+	let base = Url::parse(&server.uri()).expect("mock url");
+	let host = base.host_str().expect("host present").to_ascii_lowercase();
+	let issuer = base.join("/realms/acme").expect("issuer url");
+	let jwks_uri = base.join("/realms/acme/protocol/keys").expect("jwks url");
+	let document = serde_json::json!({
+		"issuer": issuer.as_str(),
+		"jwks_uri": jwks_uri.as_str(),
+		"token_endpoint": base.join("/realms/acme/token").expect("token url").as_str(),
+	});
+
+	Mock::given(method("GET"))
+		.and(path("/realms/acme/.well-known/openid-configuration"))
+		.respond_with(
+			ResponseTemplate::new(200)
+				.set_body_json(document)
+				.insert_header("cache-control", "public, max-age=600"),
+		)
+		.expect(1)
+		.mount(&server)
+		.await;
+	Mock::given(method("GET"))
+		.and(path("/realms/acme/protocol/keys"))
+		.respond_with(
+			ResponseTemplate::new(200)
+				.set_body_string(JWKS)
+				.insert_header("cache-control", "public, max-age=60")
+				.insert_header("content-type", "application/json"),
+		)
+		.expect(1..)
+		.mount(&server)
+		.await;
+
+	let registry = Registry::builder().require_https(false).add_allowed_domain(host).build();
+	let mut registration = IdentityProviderRegistration::from_issuer("tenant", "oidc", issuer)?;
+
+	registration.require_https = false;
+
+	let effective = registry.register(registration).await?;
+
+	assert_eq!(effective.jwks_url, jwks_uri);
+
+	let jwks = registry.resolve("tenant", "oidc", Some("discovered")).await?;
+
+	assert!(jwks.find("discovered").is_some());
+
+	let metadata =
+		registry.discovery_document("tenant", "oidc").await?.expect("discovery document cached");
+
+	assert_eq!(metadata.jwks_uri, jwks_uri);
+	assert!(metadata.additional.contains_key("token_endpoint"));
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn discovery_rejects_mismatched_issuer() -> Result<()> {
+	let server = MockServer::start().await;
+	let base = Url::parse(&server.uri()).expect("mock url");
+	let host = base.host_str().expect("host present").to_ascii_lowercase();
+
+	Mock::given(method("GET"))
+		.and(path("/.well-known/openid-configuration"))
+		.respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+			"issuer": "https://attacker.example.com",
+			"jwks_uri": base.join("/keys").expect("jwks url").as_str(),
+		})))
+		.mount(&server)
+		.await;
+
+	let registry = Registry::builder().require_https(false).add_allowed_domain(host).build();
+	let mut registration = IdentityProviderRegistration::from_issuer("tenant", "oidc", base)?;
+
+	registration.require_https = false;
+
+	let err = registry.register(registration).await.expect_err("issuer mismatch must fail");
+
+	assert!(matches!(err, Error::Validation { field: "discovery.issuer", .. }));
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn moved_jwks_uri_is_followed_after_allowlist_revalidation() -> Result<()> {
+	let server = MockServer::start().await;
+	let base = Url::parse(&server.uri()).expect("mock url");
+	let host = base.host_str().expect("host present").to_ascii_lowercase();
+	let original = base.join("/keys/v1").expect("jwks url");
+	let moved = base.join("/keys/v2").expect("jwks url");
+	let document = |jwks_uri: &str| {
+		ResponseTemplate::new(200).set_body_json(serde_json::json!({
+			"issuer": base.as_str(),
+			"jwks_uri": jwks_uri,
+		}))
+	};
+
+	Mock::given(method("GET"))
+		.and(path("/.well-known/openid-configuration"))
+		.respond_with(document(original.as_str()))
+		.up_to_n_times(1)
+		.mount(&server)
+		.await;
+	Mock::given(method("GET"))
+		.and(path("/.well-known/openid-configuration"))
+		.respond_with(document(moved.as_str()))
+		.up_to_n_times(1)
+		.mount(&server)
+		.await;
+	Mock::given(method("GET"))
+		.and(path("/.well-known/openid-configuration"))
+		.respond_with(document("http://keys.attacker.example.com/jwks"))
+		.mount(&server)
+		.await;
+
+	Mock::given(method("GET"))
+		.and(path("/keys/v1"))
+		.respond_with(ResponseTemplate::new(404))
+		.expect(0)
+		.mount(&server)
+		.await;
+	Mock::given(method("GET"))
+		.and(path("/keys/v2"))
+		.respond_with(
+			ResponseTemplate::new(200)
+				.set_body_string(JWKS)
+				.insert_header("cache-control", "public, max-age=600")
+				.insert_header("content-type", "application/json"),
+		)
+		.expect(2)
+		.mount(&server)
+		.await;
+
+	let registry = Registry::builder().require_https(false).add_allowed_domain(host).build();
+	let mut events = registry.subscribe();
+	let mut registration = IdentityProviderRegistration::from_issuer("tenant", "oidc", base)?;
+
+	registration.require_https = false;
+
+	if let Some(discovery) = registration.discovery.as_mut() {
+		discovery.min_ttl = TtlSeconds::from_secs(0);
+		discovery.max_ttl = TtlSeconds::from_secs(0);
+	}
+
+	assert_eq!(registry.register(registration).await?.jwks_url, original);
+
+	// The discovery document expires immediately, so the first fetch already sees the move.
+	registry.resolve("tenant", "oidc", None).await?;
+
+	assert_eq!(registry.effective_registration("tenant", "oidc").await?.jwks_url, moved);
+
+	registry.force_refresh("tenant", "oidc").await?;
+
+	assert_eq!(registry.effective_registration("tenant", "oidc").await?.jwks_url, moved);
+
+	let mut changed = None;
+	let mut rejected = None;
+
+	while let Ok(event) = events.try_recv() {
+		match event.kind {
+			CacheEventKind::JwksUriChanged { previous, current } =>
+				changed = Some((previous, current)),
+			CacheEventKind::JwksUriRejected { advertised, .. } => rejected = Some(advertised),
+			_ => {},
+		}
+	}
+
+	assert_eq!(changed, Some((original.to_string(), moved.to_string())));
+	assert_eq!(rejected.as_deref(), Some("http://keys.attacker.example.com/jwks"));
+
+	Ok(())
+}
@@ -0,0 +1,127 @@
+//! Integration coverage for the tonic `CacheControl` server.
+
+// crates.io
+use jwks_cache::{
+	Registry,
+	grpc::{
+		CacheControlService, ERROR_CODE_METADATA,
+		proto::{
+			ListStatusesRequest, ProviderRef, ProviderState, RefreshRequest, RegisterRequest,
+			ResolveRequest, cache_control_client::CacheControlClient,
+		},
+	},
+};
+use serde_json::{Value, json};
+use tokio::net::TcpListener;
+use tonic::{
+	Code,
+	transport::{Channel, Server, server::TcpIncoming},
+};
+use wiremock::{
+	Mock, MockServer, ResponseTemplate,
+	matchers::{method, path},
+};
+
+async fn serve(registry: Registry) -> CacheControlClient<Channel> {
+	let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+	let addr = listener.local_addr().expect("local address");
+
+	tokio::spawn(
+		Server::builder()
+			.add_service(CacheControlService::new(registry).into_server())
+			.serve_with_incoming(TcpIncoming::from(listener)),
+	);
+
+	CacheControlClient::connect(format!("http://{addr}")).await.expect("connect")
+}
+
+fn provider() -> ProviderRef {
+	ProviderRef { tenant_id: "tenant".into(), provider_id: "idp".into() }
+}
+
+#[tokio::test]
+async fn grpc_server_manages_providers_through_a_tonic_client() {
+	let server = MockServer::start().await;
+
+	Mock::given(method("GET"))
+		.and(path("/jwks.json"))
+		.respond_with(
+			ResponseTemplate::new(200)
+				.set_body_json(json!({ "keys": [] }))
+				.insert_header("cache-control", "public, max-age=300"),
+		)
+		.mount(&server)
+		.await;
+
+	let registry = Registry::builder().require_https(false).build();
+	let mut client = serve(registry.clone()).await;
+	let registration = json!({
+		"tenant_id": "tenant",
+		"provider_id": "idp",
+		"jwks_url": format!("{}/jwks.json", server.uri()),
+		"require_https": false,
+		"min_keys": 0,
+	});
+
+	let status = client
+		.register(RegisterRequest { registration_json: registration.to_string() })
+		.await
+		.expect("register")
+		.into_inner();
+	assert_eq!((status.tenant_id.as_str(), status.provider_id.as_str()), ("tenant", "idp"));
+
+	let jwks = client
+		.resolve(ResolveRequest { provider: Some(provider()), kid: None })
+		.await
+		.expect("resolve")
+		.into_inner();
+	let jwks: Value = serde_json::from_str(&jwks.jwks_json).expect("jwks json");
+	assert_eq!(jwks, json!({ "keys": [] }));
+
+	let status = client
+		.refresh(RefreshRequest { provider: Some(provider()), force: true })
+		.await
+		.expect("refresh")
+		.into_inner();
+	assert_eq!(status.state(), ProviderState::Ready);
+	assert!(status.last_refresh.is_some());
+
+	let status = client.status(provider()).await.expect("status").into_inner();
+	assert_eq!(status.state(), ProviderState::Ready);
+	assert_eq!(status.error_count, 0);
+
+	let page = client
+		.list_statuses(ListStatusesRequest {
+			state: ProviderState::Ready.into(),
+			..Default::default()
+		})
+		.await
+		.expect("list statuses")
+		.into_inner();
+	assert_eq!(page.total, 1);
+	assert_eq!(page.statuses[0].provider_id, "idp");
+	assert_eq!(page.next_offset, None);
+
+	let removed = client.unregister(provider()).await.expect("unregister").into_inner();
+	assert!(removed.removed);
+	assert!(registry.provider_status("tenant", "idp").await.is_err());
+
+	let missing = client.status(provider()).await.expect_err("unregistered provider");
+	assert_eq!(missing.code(), Code::NotFound);
+	assert_eq!(
+		missing.metadata().get(ERROR_CODE_METADATA).and_then(|code| code.to_str().ok()),
+		Some("JWKS_CACHE_NOT_REGISTERED"),
+	);
+
+	let invalid = client
+		.register(RegisterRequest { registration_json: "{".into() })
+		.await
+		.expect_err("malformed registration");
+	assert_eq!(invalid.code(), Code::InvalidArgument);
+
+	let invalid = client
+		.resolve(ResolveRequest { provider: None, kid: None })
+		.await
+		.expect_err("missing provider");
+	assert_eq!(invalid.code(), Code::InvalidArgument);
+}
@@ -1,4 +1,13 @@
 //! Integration test harness for the JWKS cache library.
 
+#[cfg(feature = "admin-api")] mod admin;
+mod discovery;
+#[cfg(feature = "grpc")] mod grpc;
 mod jwks_refresh;
+#[cfg(feature = "jwks-cache-axum")] mod middleware;
 mod multi_tenant;
+mod persistence;
+mod reload;
+mod standalone;
+#[cfg(feature = "test-util")] mod test_util;
+mod tls;
@@ -0,0 +1,146 @@
+//! Integration coverage for the axum extractor and tower layer.
+
+// std
+use std::time::{SystemTime, UNIX_EPOCH};
+// crates.io
+use axum::{Router, body::Body, http::Request, routing::get};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use jsonwebtoken::{Algorithm, EncodingKey, Header, Validation};
+use jwks_cache::{
+	IdentityProviderRegistration, Registry, Result,
+	middleware::{JwtAuthLayer, StaticProvider, ValidatedClaims},
+};
+use serde::{Deserialize, Serialize};
+use tower::ServiceExt;
+use wiremock::{
+	Mock, MockServer, ResponseTemplate,
+	matchers::{method, path},
+};
+
+const SECRET: &[u8] = b"integration-test-hmac-secret-0123456789";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+	sub: String,
+	exp: u64,
+}
+
+async fn me(ValidatedClaims(claims): ValidatedClaims<Claims>) -> String {
+	claims.sub
+}
+
+fn token(kid: &str) -> String {
+	let mut header = Header::new(Algorithm::HS256);
+	let exp = SystemTime::now().duration_since(UNIX_EPOCH).expect("clock").as_secs() + 600;
+
+	header.kid = Some(kid.into());
+
+	jsonwebtoken::encode(
+		&header,
+		&Claims { sub: "user-1".into(), exp },
+		&EncodingKey::from_secret(SECRET),
+	)
+	.expect("sign token")
+}
+
+#[tokio::test]
+async fn layer_verifies_bearer_tokens_and_extracts_claims() -> Result<()> {
+	let server = MockServer::start().await;
+	let jwks = serde_json::json!({
+		"keys": [{ "kty": "oct", "alg": "HS256", "kid": "hmac", "k": URL_SAFE_NO_PAD.encode(SECRET) }]
+	});
+
+	Mock::given(method("GET"))
+		.and(path("/jwks.json"))
+		.respond_with(
+			ResponseTemplate::new(200)
+				.set_body_json(jwks)
+				.insert_header("cache-control", "public, max-age=300"),
+		)
+		.mount(&server)
+		.await;
+
+	let registry = Registry::builder().require_https(false).build();
+	let registration =
+		IdentityProviderRegistration::new("tenant", "idp", format!("{}/jwks.json", server.uri()))?
+			.with_require_https(false);
+
+	registry.register(registration).await?;
+
+	let app = Router::new().route("/me", get(me)).layer(JwtAuthLayer::new(
+		registry,
+		StaticProvider::new("tenant", "idp"),
+		Validation::new(Algorithm::HS256),
+	));
+	let request = |authorization: Option<String>| {
+		let mut builder = Request::builder().uri("/me");
+
+		if let Some(value) = authorization {
+			builder = builder.header("authorization", value);
+		}
+
+		builder.body(Body::empty()).expect("request")
+	};
+
+	let response = app
+		.clone()
+		.oneshot(request(Some(format!("Bearer {}", token("hmac")))))
+		.await
+		.expect("call");
+	assert_eq!(response.status(), 200);
+	let body = axum::body::to_bytes(response.into_body(), 1_024).await.expect("body");
+	assert_eq!(&body[..], b"user-1");
+
+	let response = app.clone().oneshot(request(None)).await.expect("call");
+	assert_eq!(response.status(), 401);
+	assert!(response.headers().contains_key("www-authenticate"));
+
+	let response =
+		app.oneshot(request(Some(format!("Bearer {}", token("unknown"))))).await.expect("call");
+	assert_eq!(response.status(), 401);
+	assert_eq!(response.headers()["www-authenticate"], "Bearer error=\"invalid_token\"");
+	let body = axum::body::to_bytes(response.into_body(), 1_024).await.expect("body");
+	assert_eq!(&body[..], b"Invalid token.");
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn rejections_do_not_leak_upstream_details() -> Result<()> {
+	let server = MockServer::start().await;
+
+	Mock::given(method("GET"))
+		.and(path("/jwks.json"))
+		.respond_with(ResponseTemplate::new(500).set_body_string("upstream stack trace"))
+		.mount(&server)
+		.await;
+
+	let registry = Registry::builder().require_https(false).build();
+	let mut registration =
+		IdentityProviderRegistration::new("tenant", "idp", format!("{}/jwks.json", server.uri()))?
+			.with_require_https(false);
+
+	registration.retry_policy.max_retries = 0;
+	registry.register(registration).await?;
+
+	let app = Router::new().route("/me", get(me)).layer(JwtAuthLayer::new(
+		registry,
+		StaticProvider::new("tenant", "idp"),
+		Validation::new(Algorithm::HS256),
+	));
+	let request = Request::builder()
+		.uri("/me")
+		.header("authorization", format!("Bearer {}", token("hmac")))
+		.body(Body::empty())
+		.expect("request");
+	let response = app.oneshot(request).await.expect("call");
+
+	assert_eq!(response.status(), 503);
+	assert!(!response.headers().contains_key("www-authenticate"));
+
+	let body = axum::body::to_bytes(response.into_body(), 1_024).await.expect("body");
+
+	assert_eq!(&body[..], b"Signing keys are temporarily unavailable.");
+
+	Ok(())
+}
@@ -0,0 +1,979 @@
+//! Integration coverage for pluggable snapshot persistence.
+
+// std
+use std::{sync::Arc, time::Duration};
+// crates.io
+use jwks_cache::{
+	CacheEventKind, Error, IdentityProviderRegistration, InvalidationBus, InvalidationTarget,
+	MemoryInvalidationBus, MemoryRefreshLock, MemorySnapshotStore, PersistentSnapshot,
+	ProviderState, RefreshLock, Registry, RestorePolicy, Result, SNAPSHOT_VERSION, ShutdownOptions,
+	SnapshotKey, SnapshotStore,
+};
+use tokio::time;
+use wiremock::{
+	Mock, MockServer, ResponseTemplate,
+	matchers::{header, method, path},
+};
+
+const JWKS_BODY: &str = r#"{
+    "keys": [
+        {
+            "kty": "RSA",
+            "alg": "RS256",
+            "use": "sig",
+            "kid": "persisted",
+            "n": "AQIDBAUGBwgJCgsMDQ4PEBESExQVFhcYGRobHB0eHyAhIiMkJSYnKCkqKywtLi8wMTIzNDU2Nzg5Ojs8PT4_QEFCQ0RFRkdISUpLTE1OT1BRUlNUVVZXWFlaW1xdXl9gYWJjZGVmZ2hpamtsbW5vcHFyc3R1dnd4eXp7fH1-f4A",
+            "e": "AQAB"
+        }
+    ]
+}"#;
+
+#[tokio::test]
+async fn custom_store_warm_starts_new_registry() -> Result<()> {
+	let _ = tracing_subscriber::fmt::try_init();
+
+	let server = MockServer::start().await;
+	let jwks_path = "/.well-known/jwks.json";
+
+	Mock::given(method("GET"))
+		.and(path(jwks_path))
+		.respond_with(
+			ResponseTemplate::new(200)
+				.set_body_string(JWKS_BODY)
+				.insert_header("content-type", "application/json")
+				.insert_header("cache-control", "public, max-age=600"),
+		)
+		.expect(1)
+		.mount(&server)
+		.await;
+
+	let store = Arc::new(MemorySnapshotStore::new());
+	let registration = IdentityProviderRegistration::new(
+		"tenant-a",
+		"auth0",
+		format!("{}{}", server.uri(), jwks_path),
+	)
+	.expect("registration")
+	.with_require_https(false);
+
+	let first = Registry::builder().require_https(false).with_snapshot_store(store.clone()).build();
+	first.register(registration.clone()).await?;
+	first.resolve("tenant-a", "auth0", None).await?;
+	first.persist_all().await?;
+
+	assert_eq!(store.list().await?, vec![SnapshotKey::new("tenant-a", "auth0")]);
+
+	// A second registry sharing the store must serve from the snapshot without refetching.
+	let second =
+		Registry::builder().require_https(false).with_snapshot_store(store.clone()).build();
+	second.register(registration).await?;
+	let jwks = second.resolve("tenant-a", "auth0", Some("persisted")).await?;
+	assert!(jwks.find("persisted").is_some());
+
+	assert!(second.unregister("tenant-a", "auth0").await?);
+
+	server.verify().await;
+	Ok(())
+}
+
+#[tokio::test]
+async fn unregister_deletes_persisted_snapshot() -> Result<()> {
+	let server = MockServer::start().await;
+	let jwks_path = "/.well-known/jwks.json";
+
+	Mock::given(method("GET"))
+		.and(path(jwks_path))
+		.respond_with(
+			ResponseTemplate::new(200)
+				.set_body_string(JWKS_BODY)
+				.insert_header("cache-control", "public, max-age=600"),
+		)
+		.mount(&server)
+		.await;
+
+	let store = Arc::new(MemorySnapshotStore::new());
+	let registration = IdentityProviderRegistration::new(
+		"tenant-a",
+		"auth0",
+		format!("{}{}", server.uri(), jwks_path),
+	)
+	.expect("registration")
+	.with_require_https(false);
+	let registry =
+		Registry::builder().require_https(false).with_snapshot_store(store.clone()).build();
+
+	registry.register(registration).await?;
+	registry.resolve("tenant-a", "auth0", None).await?;
+	registry.persist_all().await?;
+
+	assert!(store.load("tenant-a", "auth0").await?.is_some());
+	assert!(registry.unregister("tenant-a", "auth0").await?);
+	assert!(store.list().await?.is_empty(), "unregister should delete the persisted snapshot");
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn invalidate_forces_refetch_and_purges_snapshot() -> Result<()> {
+	let server = MockServer::start().await;
+	let jwks_path = "/.well-known/jwks.json";
+
+	Mock::given(method("GET"))
+		.and(path(jwks_path))
+		.respond_with(
+			ResponseTemplate::new(200)
+				.set_body_string(JWKS_BODY)
+				.insert_header("content-type", "application/json")
+				.insert_header("cache-control", "public, max-age=600"),
+		)
+		.expect(3)
+		.mount(&server)
+		.await;
+
+	let store = Arc::new(MemorySnapshotStore::new());
+	let registration = IdentityProviderRegistration::new(
+		"tenant-a",
+		"auth0",
+		format!("{}{}", server.uri(), jwks_path),
+	)
+	.expect("registration")
+	.with_require_https(false);
+	let registry =
+		Registry::builder().require_https(false).with_snapshot_store(store.clone()).build();
+
+	registry.register(registration).await?;
+	registry.resolve("tenant-a", "auth0", None).await?;
+	registry.persist_all().await?;
+	registry.invalidate("tenant-a", "auth0", true).await?;
+
+	assert!(store.list().await?.is_empty(), "invalidate should purge the persisted snapshot");
+
+	registry.resolve("tenant-a", "auth0", None).await?;
+	registry.persist_all().await?;
+
+	assert_eq!(registry.invalidate_all(false).await?, 1);
+	assert_eq!(store.list().await?.len(), 1, "snapshots are kept without purge_snapshot");
+
+	registry.resolve("tenant-a", "auth0", None).await?;
+
+	server.verify().await;
+	Ok(())
+}
+
+#[tokio::test]
+async fn restore_revalidates_immediately_with_persisted_validators() -> Result<()> {
+	let server = MockServer::start().await;
+	let jwks_path = "/.well-known/jwks.json";
+
+	Mock::given(method("GET"))
+		.and(path(jwks_path))
+		.and(header("if-none-match", "\"v1\""))
+		.respond_with(
+			ResponseTemplate::new(304)
+				.insert_header("etag", "\"v1\"")
+				.insert_header("cache-control", "public, max-age=600"),
+		)
+		.expect(1)
+		.mount(&server)
+		.await;
+	Mock::given(method("GET"))
+		.and(path(jwks_path))
+		.respond_with(
+			ResponseTemplate::new(200)
+				.set_body_string(JWKS_BODY)
+				.insert_header("etag", "\"v1\"")
+				.insert_header("cache-control", "public, max-age=600"),
+		)
+		.expect(1)
+		.mount(&server)
+		.await;
+
+	let store = Arc::new(MemorySnapshotStore::new());
+	let registration = IdentityProviderRegistration::new(
+		"tenant-a",
+		"auth0",
+		format!("{}{}", server.uri(), jwks_path),
+	)
+	.expect("registration")
+	.with_require_https(false);
+	let first = Registry::builder().require_https(false).with_snapshot_store(store.clone()).build();
+
+	first.register(registration.clone()).await?;
+	first.resolve("tenant-a", "auth0", None).await?;
+	first.persist_all().await?;
+
+	let second =
+		Registry::builder().require_https(false).with_snapshot_store(store.clone()).build();
+
+	// Registration restores the snapshot; restoring again must not start a second revalidation.
+	second.register(registration).await?;
+	second.restore_from_persistence().await?;
+
+	// The conditional revalidation runs in the background; wait for it to reach the server.
+	for _ in 0..50 {
+		if server.received_requests().await.unwrap_or_default().len() >= 2 {
+			break;
+		}
+
+		tokio::time::sleep(Duration::from_millis(20)).await;
+	}
+
+	let jwks = second.resolve("tenant-a", "auth0", Some("persisted")).await?;
+
+	assert!(jwks.find("persisted").is_some());
+
+	server.verify().await;
+	Ok(())
+}
+
+#[tokio::test]
+async fn scoped_registry_aborts_background_refreshes() -> Result<()> {
+	let server = MockServer::start().await;
+	let jwks_path = "/.well-known/jwks.json";
+
+	Mock::given(method("GET"))
+		.and(path(jwks_path))
+		.and(header("if-none-match", "\"v1\""))
+		.respond_with(ResponseTemplate::new(304).set_delay(Duration::from_secs(30)))
+		.mount(&server)
+		.await;
+	Mock::given(method("GET"))
+		.and(path(jwks_path))
+		.respond_with(
+			ResponseTemplate::new(200)
+				.set_body_string(JWKS_BODY)
+				.insert_header("etag", "\"v1\"")
+				.insert_header("cache-control", "public, max-age=600"),
+		)
+		.mount(&server)
+		.await;
+
+	let store = Arc::new(MemorySnapshotStore::new());
+	let registration = IdentityProviderRegistration::new(
+		"tenant-a",
+		"auth0",
+		format!("{}{}", server.uri(), jwks_path),
+	)
+	.expect("registration")
+	.with_require_https(false);
+	let seed = Registry::builder().require_https(false).with_snapshot_store(store.clone()).build();
+
+	seed.register(registration.clone()).await?;
+	seed.resolve("tenant-a", "auth0", None).await?;
+	seed.persist_all().await?;
+
+	let started = std::time::Instant::now();
+	let leaked = Registry::builder()
+		.require_https(false)
+		.with_snapshot_store(store)
+		.run(|registry| async move {
+			// Restoring the snapshot starts a background revalidation that stalls upstream.
+			registry.register(registration).await?;
+
+			assert_eq!(registry.background_tasks(), 1);
+
+			Ok::<_, Error>(registry)
+		})
+		.await?;
+
+	assert_eq!(leaked.background_tasks(), 0);
+	assert!(started.elapsed() < Duration::from_secs(2), "stalled refresh was not aborted");
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn aborted_background_refreshes_leave_the_entry_ready() -> Result<()> {
+	let server = MockServer::start().await;
+	let jwks_path = "/.well-known/jwks.json";
+
+	Mock::given(method("GET"))
+		.and(path(jwks_path))
+		.and(header("if-none-match", "\"v1\""))
+		.respond_with(ResponseTemplate::new(304).set_delay(Duration::from_secs(30)))
+		.mount(&server)
+		.await;
+	Mock::given(method("GET"))
+		.and(path(jwks_path))
+		.respond_with(
+			ResponseTemplate::new(200)
+				.set_body_string(JWKS_BODY)
+				.insert_header("etag", "\"v1\"")
+				.insert_header("cache-control", "public, max-age=600"),
+		)
+		.mount(&server)
+		.await;
+
+	let registration = IdentityProviderRegistration::new(
+		"tenant-a",
+		"auth0",
+		format!("{}{}", server.uri(), jwks_path),
+	)
+	.expect("registration")
+	.with_require_https(false)
+	// Due for refresh as soon as it is cached, so `refresh` spawns a background fetch.
+	.with_refresh_early(Duration::from_secs(600));
+	let registry = Registry::builder().require_https(false).build();
+
+	registry.register(registration).await?;
+	registry.resolve("tenant-a", "auth0", None).await?;
+	registry.refresh("tenant-a", "auth0").await?;
+
+	assert_eq!(
+		registry.provider_status("tenant-a", "auth0").await?.state,
+		ProviderState::Refreshing
+	);
+
+	registry.shutdown().await;
+
+	assert_eq!(
+		registry.provider_status("tenant-a", "auth0").await?.state,
+		ProviderState::Ready,
+		"an aborted refresh should not leave the entry refreshing"
+	);
+
+	registry.shutdown_gracefully(&ShutdownOptions::default()).await?;
+	registry.refresh("tenant-a", "auth0").await?;
+
+	assert_eq!(registry.background_tasks(), 0);
+	assert_eq!(
+		registry.provider_status("tenant-a", "auth0").await?.state,
+		ProviderState::Ready,
+		"a rejected spawn should not leave the entry refreshing"
+	);
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn unregister_aborts_provider_refreshes() -> Result<()> {
+	let server = MockServer::start().await;
+	let jwks_path = "/.well-known/jwks.json";
+
+	Mock::given(method("GET"))
+		.and(path(jwks_path))
+		.and(header("if-none-match", "\"v1\""))
+		.respond_with(ResponseTemplate::new(304).set_delay(Duration::from_secs(30)))
+		.mount(&server)
+		.await;
+	Mock::given(method("GET"))
+		.and(path(jwks_path))
+		.respond_with(
+			ResponseTemplate::new(200)
+				.set_body_string(JWKS_BODY)
+				.insert_header("etag", "\"v1\"")
+				.insert_header("cache-control", "public, max-age=600"),
+		)
+		.mount(&server)
+		.await;
+
+	let store = Arc::new(MemorySnapshotStore::new());
+	let mut registration = IdentityProviderRegistration::new(
+		"tenant-a",
+		"auth0",
+		format!("{}{}", server.uri(), jwks_path),
+	)
+	.expect("registration")
+	.with_require_https(false);
+
+	// Without the abort, the stalled revalidation would time out and report a failure quickly.
+	registration.retry_policy.max_retries = 0;
+	registration.retry_policy.attempt_timeout = Duration::from_millis(200);
+	registration.retry_policy.deadline = Duration::from_millis(200);
+
+	let seed = Registry::builder().require_https(false).with_snapshot_store(store.clone()).build();
+
+	seed.register(registration.clone()).await?;
+	seed.resolve("tenant-a", "auth0", None).await?;
+	seed.persist_all().await?;
+
+	let registry = Registry::builder().require_https(false).with_snapshot_store(store).build();
+	let mut events = registry.subscribe();
+
+	registry.register(registration).await?;
+
+	assert_eq!(registry.background_tasks(), 1);
+	assert!(registry.unregister("tenant-a", "auth0").await?);
+	assert_eq!(registry.background_tasks(), 0);
+
+	tokio::time::sleep(Duration::from_millis(500)).await;
+
+	while let Ok(event) = events.try_recv() {
+		assert!(
+			!matches!(
+				event.kind,
+				CacheEventKind::RefreshSucceeded | CacheEventKind::RefreshFailed { .. }
+			),
+			"aborted refresh reported {:?}",
+			event.kind
+		);
+	}
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn unregister_during_refresh_skips_commit_persistence_and_events() -> Result<()> {
+	let server = MockServer::start().await;
+	let jwks_path = "/.well-known/jwks.json";
+
+	Mock::given(method("GET"))
+		.and(path(jwks_path))
+		.respond_with(
+			ResponseTemplate::new(200)
+				.set_body_string(JWKS_BODY)
+				.insert_header("cache-control", "public, max-age=600")
+				.set_delay(Duration::from_millis(300)),
+		)
+		.mount(&server)
+		.await;
+
+	let store = Arc::new(MemorySnapshotStore::new());
+	let registration = IdentityProviderRegistration::new(
+		"tenant-a",
+		"auth0",
+		format!("{}{}", server.uri(), jwks_path),
+	)
+	.expect("registration")
+	.with_require_https(false);
+	let registry =
+		Registry::builder().require_https(false).with_snapshot_store(store.clone()).build();
+	let mut events = registry.subscribe();
+
+	registry.register(registration).await?;
+
+	let resolving = {
+		let registry = registry.clone();
+
+		tokio::spawn(async move { registry.resolve("tenant-a", "auth0", None).await })
+	};
+
+	tokio::time::sleep(Duration::from_millis(50)).await;
+
+	assert!(registry.unregister("tenant-a", "auth0").await?);
+
+	// The caller still receives the keys it was waiting for.
+	assert_eq!(resolving.await.expect("resolve task")?.keys.len(), 1);
+
+	registry.persist_all().await?;
+
+	assert!(store.load("tenant-a", "auth0").await?.is_none());
+
+	let mut kinds = Vec::new();
+
+	while let Ok(event) = events.try_recv() {
+		kinds.push(event.kind);
+	}
+
+	assert_eq!(kinds.last(), Some(&CacheEventKind::ProviderUnregistered), "{kinds:?}");
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn restore_policy_controls_warm_starts() -> Result<()> {
+	let server = MockServer::start().await;
+	let jwks_path = "/.well-known/jwks.json";
+
+	Mock::given(method("GET"))
+		.and(path(jwks_path))
+		.respond_with(
+			ResponseTemplate::new(200)
+				.set_body_string(JWKS_BODY)
+				.insert_header("content-type", "application/json")
+				.insert_header("cache-control", "public, max-age=600"),
+		)
+		.expect(2)
+		.mount(&server)
+		.await;
+
+	let store = Arc::new(MemorySnapshotStore::new());
+	let registration = IdentityProviderRegistration::new(
+		"tenant-a",
+		"auth0",
+		format!("{}{}", server.uri(), jwks_path),
+	)
+	.expect("registration")
+	.with_require_https(false);
+
+	let seed = Registry::builder().require_https(false).with_snapshot_store(store.clone()).build();
+	seed.register(registration.clone()).await?;
+	seed.resolve("tenant-a", "auth0", None).await?;
+	seed.persist_all().await?;
+
+	// A background restore must be complete once `wait_ready` returns, without refetching.
+	let background = Registry::builder()
+		.require_https(false)
+		.with_snapshot_store(store.clone())
+		.restore_policy(RestorePolicy::Background)
+		.build();
+	background.register(registration.clone()).await?;
+	background.wait_ready().await;
+	let jwks = background.resolve("tenant-a", "auth0", Some("persisted")).await?;
+	assert!(jwks.find("persisted").is_some());
+
+	// Skipping the restore leaves the provider cold, so the first resolve fetches.
+	let skip = Registry::builder()
+		.require_https(false)
+		.with_snapshot_store(store.clone())
+		.restore_policy(RestorePolicy::Skip)
+		.build();
+	skip.register(registration).await?;
+	skip.wait_ready().await;
+	let status = skip.provider_status("tenant-a", "auth0").await?;
+	assert!(status.expires_at.is_none(), "skip must not restore the snapshot");
+	skip.resolve("tenant-a", "auth0", Some("persisted")).await?;
+
+	server.verify().await;
+	Ok(())
+}
+
+#[tokio::test]
+async fn snapshots_from_older_and_newer_releases_still_restore() -> Result<()> {
+	let server = MockServer::start().await;
+	let jwks_path = "/.well-known/jwks.json";
+
+	Mock::given(method("GET"))
+		.and(path(jwks_path))
+		.respond_with(
+			ResponseTemplate::new(200)
+				.set_body_string(JWKS_BODY)
+				.insert_header("content-type", "application/json")
+				.insert_header("cache-control", "public, max-age=600"),
+		)
+		.expect(1)
+		.mount(&server)
+		.await;
+
+	let seed_store = Arc::new(MemorySnapshotStore::new());
+	let registration = IdentityProviderRegistration::new(
+		"tenant-a",
+		"auth0",
+		format!("{}{}", server.uri(), jwks_path),
+	)
+	.expect("registration")
+	.with_require_https(false);
+	let seed =
+		Registry::builder().require_https(false).with_snapshot_store(seed_store.clone()).build();
+	seed.register(registration.clone()).await?;
+	seed.resolve("tenant-a", "auth0", None).await?;
+	seed.persist_all().await?;
+
+	let current = seed_store.load("tenant-a", "auth0").await?.expect("persisted snapshot");
+	assert_eq!(current.version, SNAPSHOT_VERSION);
+
+	let current = serde_json::to_value(&current)?;
+	let mut legacy = current.clone();
+	legacy.as_object_mut().expect("object").remove("version");
+	let legacy = PersistentSnapshot::from_json(&legacy.to_string())?;
+	assert_eq!(legacy.version, SNAPSHOT_VERSION, "unversioned snapshots are migrated");
+
+	let mut newer = current.clone();
+	newer["version"] = (SNAPSHOT_VERSION + 1).into();
+	newer["added_later"] = "ignored".into();
+	let newer = PersistentSnapshot::from_json(&newer.to_string())?;
+	assert_eq!(newer.version, SNAPSHOT_VERSION + 1);
+
+	let mut incompatible = current;
+	incompatible["version"] = (SNAPSHOT_VERSION + 1).into();
+	incompatible.as_object_mut().expect("object").remove("jwks_json");
+	assert!(matches!(
+		PersistentSnapshot::from_json(&incompatible.to_string()),
+		Err(Error::Validation { field: "version", .. })
+	));
+
+	// The migrated snapshot warm-starts a new registry without refetching.
+	let store = Arc::new(MemorySnapshotStore::new());
+	store.persist(&[legacy]).await?;
+	let restored = Registry::builder().require_https(false).with_snapshot_store(store).build();
+	restored.register(registration).await?;
+	let jwks = restored.resolve("tenant-a", "auth0", Some("persisted")).await?;
+	assert!(jwks.find("persisted").is_some());
+
+	server.verify().await;
+	Ok(())
+}
+
+#[tokio::test]
+async fn write_through_persists_every_successful_refresh() -> Result<()> {
+	let server = MockServer::start().await;
+	let jwks_path = "/.well-known/jwks.json";
+
+	Mock::given(method("GET"))
+		.and(path(jwks_path))
+		.respond_with(
+			ResponseTemplate::new(200)
+				.set_body_string(JWKS_BODY)
+				.insert_header("content-type", "application/json")
+				.insert_header("cache-control", "public, max-age=600"),
+		)
+		.expect(2)
+		.mount(&server)
+		.await;
+
+	let registration = IdentityProviderRegistration::new(
+		"tenant-a",
+		"auth0",
+		format!("{}{}", server.uri(), jwks_path),
+	)
+	.expect("registration")
+	.with_require_https(false);
+
+	// Without write-through, snapshots wait for `persist_all`.
+	let periodic_store = Arc::new(MemorySnapshotStore::new());
+	let periodic = Registry::builder()
+		.require_https(false)
+		.with_snapshot_store(periodic_store.clone())
+		.build();
+	periodic.register(registration.clone()).await?;
+	periodic.resolve("tenant-a", "auth0", None).await?;
+
+	let store = Arc::new(MemorySnapshotStore::new());
+	let registry = Registry::builder()
+		.require_https(false)
+		.with_snapshot_store(store.clone())
+		.write_through(true)
+		.build();
+	registry.register(registration).await?;
+	registry.resolve("tenant-a", "auth0", None).await?;
+
+	// The write runs in the background; wait for it to land.
+	for _ in 0..50 {
+		if !store.list().await?.is_empty() {
+			break;
+		}
+
+		tokio::time::sleep(Duration::from_millis(20)).await;
+	}
+
+	assert_eq!(store.list().await?, vec![SnapshotKey::new("tenant-a", "auth0")]);
+	assert!(periodic_store.list().await?.is_empty());
+
+	assert!(registry.unregister("tenant-a", "auth0").await?);
+	assert!(store.list().await?.is_empty());
+
+	server.verify().await;
+	Ok(())
+}
+
+#[tokio::test]
+async fn refresh_lock_lets_one_replica_fetch_for_all() -> Result<()> {
+	let server = MockServer::start().await;
+	let jwks_path = "/.well-known/jwks.json";
+
+	Mock::given(method("GET"))
+		.and(path(jwks_path))
+		.respond_with(
+			ResponseTemplate::new(200)
+				.set_body_string(JWKS_BODY)
+				.insert_header("content-type", "application/json")
+				.insert_header("cache-control", "public, max-age=600")
+				.set_delay(Duration::from_millis(200)),
+		)
+		.expect(2)
+		.mount(&server)
+		.await;
+
+	let registration = IdentityProviderRegistration::new(
+		"tenant-a",
+		"auth0",
+		format!("{}{}", server.uri(), jwks_path),
+	)
+	.expect("registration")
+	.with_require_https(false);
+	let store = Arc::new(MemorySnapshotStore::new());
+	let lock = Arc::new(MemoryRefreshLock::new().with_lease_duration(Duration::from_millis(500)));
+	let replica = || {
+		Registry::builder()
+			.require_https(false)
+			.with_snapshot_store(store.clone())
+			.with_refresh_lock(lock.clone())
+			.build()
+	};
+	let (first, second) = (replica(), replica());
+
+	first.register(registration.clone()).await?;
+	second.register(registration.clone()).await?;
+
+	// One replica fetches while the other adopts the snapshot it persists.
+	let (a, b) = tokio::join!(
+		first.resolve("tenant-a", "auth0", Some("persisted")),
+		second.resolve("tenant-a", "auth0", Some("persisted")),
+	);
+	assert!(a?.find("persisted").is_some());
+	assert!(b?.find("persisted").is_some());
+	assert_eq!(server.received_requests().await.unwrap_or_default().len(), 1);
+
+	// A holder that died with the lease only delays the next refresh until the lease expires.
+	let store = Arc::new(MemorySnapshotStore::new());
+	let orphaned = lock.try_acquire("tenant-a", "auth0").await?;
+	assert!(orphaned.is_some());
+	let survivor = Registry::builder()
+		.require_https(false)
+		.with_snapshot_store(store.clone())
+		.with_refresh_lock(lock.clone())
+		.build();
+	survivor.register(registration.clone()).await?;
+	let jwks = survivor.resolve("tenant-a", "auth0", Some("persisted")).await?;
+	assert!(jwks.find("persisted").is_some());
+	assert_eq!(store.list().await?, vec![SnapshotKey::new("tenant-a", "auth0")]);
+
+	let unshared = Registry::builder().require_https(false).with_refresh_lock(lock).build();
+	assert!(matches!(
+		unshared.register(registration).await,
+		Err(Error::Validation { field: "refresh_lock", .. })
+	));
+
+	server.verify().await;
+	Ok(())
+}
+
+#[tokio::test]
+async fn forced_revalidations_fetch_instead_of_adopting_shared_snapshots() -> Result<()> {
+	let server = MockServer::start().await;
+	let jwks_path = "/.well-known/jwks.json";
+	let rotated = JWKS_BODY.replace("\"persisted\"", "\"rotated\"");
+
+	Mock::given(method("GET"))
+		.and(path(jwks_path))
+		.respond_with(
+			ResponseTemplate::new(200)
+				.set_body_string(JWKS_BODY)
+				.insert_header("content-type", "application/json")
+				.insert_header("cache-control", "public, max-age=600"),
+		)
+		.up_to_n_times(2)
+		.mount(&server)
+		.await;
+	Mock::given(method("GET"))
+		.and(path(jwks_path))
+		.respond_with(
+			ResponseTemplate::new(200)
+				.set_body_string(rotated)
+				.insert_header("content-type", "application/json")
+				.insert_header("cache-control", "public, max-age=600"),
+		)
+		.mount(&server)
+		.await;
+
+	let registration = IdentityProviderRegistration::new(
+		"tenant-a",
+		"auth0",
+		format!("{}{}", server.uri(), jwks_path),
+	)
+	.expect("registration")
+	.with_require_https(false);
+	let store = Arc::new(MemorySnapshotStore::new());
+	let lock = Arc::new(MemoryRefreshLock::new());
+	let replica = || {
+		Registry::builder()
+			.require_https(false)
+			.with_snapshot_store(store.clone())
+			.with_refresh_lock(lock.clone())
+			.build()
+	};
+	let (first, second) = (replica(), replica());
+
+	first.register(registration.clone()).await?;
+	second.register(registration).await?;
+	second.resolve("tenant-a", "auth0", Some("persisted")).await?;
+
+	// The shared snapshot is now fresher than `second`'s cache but predates the rotation.
+	first.force_refresh("tenant-a", "auth0").await?;
+	assert_eq!(server.received_requests().await.unwrap_or_default().len(), 2);
+
+	let key = second.resolve_key("tenant-a", "auth0", "rotated").await?;
+	assert_eq!(key.common.key_id.as_deref(), Some("rotated"));
+	assert_eq!(server.received_requests().await.unwrap_or_default().len(), 3);
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn invalidations_propagate_across_replicas() -> Result<()> {
+	let server = MockServer::start().await;
+	let jwks_path = "/.well-known/jwks.json";
+
+	Mock::given(method("GET"))
+		.and(path(jwks_path))
+		.respond_with(
+			ResponseTemplate::new(200)
+				.set_body_string(JWKS_BODY)
+				.insert_header("content-type", "application/json")
+				.insert_header("cache-control", "public, max-age=600"),
+		)
+		.expect(4)
+		.mount(&server)
+		.await;
+
+	let registration = IdentityProviderRegistration::new(
+		"tenant-a",
+		"auth0",
+		format!("{}{}", server.uri(), jwks_path),
+	)
+	.expect("registration")
+	.with_require_https(false);
+	let bus = Arc::new(MemoryInvalidationBus::new());
+	let replica =
+		|| Registry::builder().require_https(false).with_invalidation_bus(bus.clone()).build();
+	let (first, second) = (replica(), replica());
+
+	first.register(registration.clone()).await?;
+	second.register(registration).await?;
+	first.resolve("tenant-a", "auth0", Some("persisted")).await?;
+	second.resolve("tenant-a", "auth0", Some("persisted")).await?;
+
+	let mut events = second.subscribe();
+	first.invalidate("tenant-a", "auth0", false).await?;
+	let event = time::timeout(Duration::from_secs(1), events.recv())
+		.await
+		.expect("remote invalidation")
+		.expect("event");
+	assert_eq!(event.kind, CacheEventKind::Invalidated);
+
+	// Both replicas refetch after the invalidation.
+	second.resolve("tenant-a", "auth0", Some("persisted")).await?;
+	first.resolve("tenant-a", "auth0", Some("persisted")).await?;
+
+	let mut remote = bus.subscribe();
+	second.invalidate_all(false).await?;
+	let invalidation = remote.recv().await.expect("published invalidation");
+	assert_eq!(invalidation.target, InvalidationTarget::All);
+
+	server.verify().await;
+	Ok(())
+}
+
+#[tokio::test]
+async fn cold_providers_are_evicted_and_rehydrated_from_snapshots() -> Result<()> {
+	let server = MockServer::start().await;
+	let jwks_path = "/.well-known/jwks.json";
+
+	Mock::given(method("GET"))
+		.and(path(jwks_path))
+		.respond_with(
+			ResponseTemplate::new(200)
+				.set_body_string(JWKS_BODY)
+				.insert_header("content-type", "application/json")
+				.insert_header("cache-control", "public, max-age=600"),
+		)
+		.expect(2)
+		.mount(&server)
+		.await;
+
+	let store = Arc::new(MemorySnapshotStore::new());
+	let registry = Registry::builder()
+		.require_https(false)
+		.with_snapshot_store(store.clone())
+		.max_resident_providers(1)
+		.build();
+
+	for tenant in ["tenant-a", "tenant-b"] {
+		registry
+			.register(
+				IdentityProviderRegistration::new(
+					tenant,
+					"auth0",
+					format!("{}{}", server.uri(), jwks_path),
+				)
+				.expect("registration")
+				.with_require_https(false),
+			)
+			.await?;
+	}
+
+	let mut events = registry.subscribe();
+	let mut evicted = async || loop {
+		let event = time::timeout(Duration::from_secs(1), events.recv())
+			.await
+			.expect("eviction")
+			.expect("event");
+
+		if event.kind == CacheEventKind::Evicted {
+			return event.tenant_id;
+		}
+	};
+
+	registry.resolve("tenant-a", "auth0", Some("persisted")).await?;
+	registry.resolve("tenant-b", "auth0", Some("persisted")).await?;
+	assert_eq!(evicted().await, "tenant-a");
+
+	let status = registry.provider_status("tenant-a", "auth0").await?;
+	assert_eq!(status.state, ProviderState::Empty);
+	assert!(store.load("tenant-a", "auth0").await?.is_some(), "evictions persist first");
+
+	// The evicted provider comes back from the snapshot store without another fetch.
+	let jwks = registry.resolve("tenant-a", "auth0", Some("persisted")).await?;
+	assert!(jwks.find("persisted").is_some());
+	assert_eq!(evicted().await, "tenant-b");
+	assert_eq!(server.received_requests().await.unwrap_or_default().len(), 2);
+
+	server.verify().await;
+	Ok(())
+}
+
+#[tokio::test]
+async fn graceful_shutdown_drains_refreshes_and_persists() -> Result<()> {
+	let server = MockServer::start().await;
+	let jwks_path = "/.well-known/jwks.json";
+
+	Mock::given(method("GET"))
+		.and(path(jwks_path))
+		.respond_with(
+			ResponseTemplate::new(200)
+				.set_body_string(JWKS_BODY)
+				.insert_header("etag", "\"v1\"")
+				.insert_header("cache-control", "public, max-age=600"),
+		)
+		.up_to_n_times(1)
+		.mount(&server)
+		.await;
+	Mock::given(method("GET"))
+		.and(path(jwks_path))
+		.respond_with(
+			ResponseTemplate::new(200)
+				.set_body_string(JWKS_BODY)
+				.insert_header("etag", "\"v2\"")
+				.insert_header("cache-control", "public, max-age=600")
+				.set_delay(Duration::from_millis(200)),
+		)
+		.mount(&server)
+		.await;
+
+	let store = Arc::new(MemorySnapshotStore::new());
+	let registration = IdentityProviderRegistration::new(
+		"tenant-a",
+		"auth0",
+		format!("{}{}", server.uri(), jwks_path),
+	)
+	.expect("registration")
+	.with_require_https(false)
+	.with_keepalive(Duration::from_secs(60))
+	// Due for refresh as soon as it is cached, so `refresh` spawns a background fetch.
+	.with_refresh_early(Duration::from_secs(600));
+	let registry =
+		Registry::builder().require_https(false).with_snapshot_store(store.clone()).build();
+
+	registry.register(registration).await?;
+	registry.resolve("tenant-a", "auth0", None).await?;
+	registry.refresh("tenant-a", "auth0").await?;
+
+	let report = registry
+		.shutdown_gracefully(&ShutdownOptions::default().with_drain_timeout(Duration::from_secs(5)))
+		.await?;
+
+	assert_eq!(report.drained, 1, "the running refresh should finish");
+	assert_eq!(report.aborted, 1, "the keepalive probe should be aborted");
+	assert!(report.persisted);
+	assert_eq!(registry.background_tasks(), 0);
+
+	let snapshot = store.load("tenant-a", "auth0").await?.expect("snapshot persisted");
+
+	assert_eq!(snapshot.etag.as_deref(), Some("\"v2\""), "the drained refresh should be persisted");
+
+	registry.refresh("tenant-a", "auth0").await?;
+
+	assert_eq!(registry.background_tasks(), 0, "no refreshes are scheduled after shutdown");
+
+	Ok(())
+}
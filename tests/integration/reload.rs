@@ -0,0 +1,445 @@
+//! Integration coverage for configuration reloads.
+
+// std
+use std::{fs, path::PathBuf, sync::Arc};
+// crates.io
+use async_trait::async_trait;
+use jwks_cache::{
+	Error, IdentityProviderRegistration, PersistentSnapshot, RegistrationFile, Registry, Result,
+	SnapshotKey, SnapshotStore,
+};
+use wiremock::{
+	Mock, MockServer, ResponseTemplate,
+	matchers::{method, path},
+};
+
+fn registration(tenant: &str, provider: &str) -> IdentityProviderRegistration {
+	IdentityProviderRegistration::new(
+		tenant,
+		provider,
+		format!("https://{tenant}.example.com/.well-known/jwks.json"),
+	)
+	.expect("registration")
+}
+
+fn write_config(name: &str, registrations: &[IdentityProviderRegistration]) -> PathBuf {
+	let path = std::env::temp_dir().join(format!("jwks-cache-{name}-{}.json", std::process::id()));
+	let body = serde_json::json!({ "registrations": registrations });
+
+	fs::write(&path, serde_json::to_vec_pretty(&body).expect("serialize")).expect("write config");
+
+	path
+}
+
+#[tokio::test]
+async fn reload_diffs_against_previous_file() -> Result<()> {
+	let registry = Registry::new();
+
+	registry.register(registration("manual", "idp")).await?;
+
+	let path =
+		write_config("diff", &[registration("tenant-a", "idp"), registration("tenant-b", "idp")]);
+	let report = registry.reload_from_path(&path).await?;
+
+	assert_eq!(
+		report.added,
+		vec![("tenant-a".into(), "idp".into()), ("tenant-b".into(), "idp".into())]
+	);
+
+	let mut changed = registration("tenant-a", "idp");
+
+	changed.missing_kid_ttl = std::time::Duration::from_secs(5);
+
+	let path = write_config("diff", &[changed, registration("tenant-c", "idp")]);
+	let report = registry.reload_from_path(&path).await?;
+
+	assert_eq!(report.added, vec![("tenant-c".into(), "idp".into())]);
+	assert_eq!(report.updated, vec![("tenant-a".into(), "idp".into())]);
+	assert_eq!(report.removed, vec![("tenant-b".into(), "idp".into())]);
+
+	let report = registry.reload_from_path(&path).await?;
+
+	assert_eq!(report.unchanged, 2);
+	assert!(report.added.is_empty() && report.updated.is_empty() && report.removed.is_empty());
+	assert!(
+		registry.effective_registration("manual", "idp").await.is_ok(),
+		"providers registered outside the config file must survive reloads"
+	);
+
+	let _ = fs::remove_file(path);
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn reload_never_adopts_direct_registrations() -> Result<()> {
+	let registry = Registry::new();
+
+	registry.register(registration("manual", "idp")).await?;
+
+	let path = write_config("adopt", &[registration("manual", "idp")]);
+	let report = registry.reload_from_path(&path).await?;
+
+	assert_eq!(report.unchanged, 1);
+
+	let path = write_config("adopt", &[]);
+	let report = registry.reload_from_path(&path).await?;
+
+	assert!(report.removed.is_empty(), "{report:?}");
+	assert!(
+		registry.effective_registration("manual", "idp").await.is_ok(),
+		"a file listing a direct registration unchanged must not take it over"
+	);
+
+	let _ = fs::remove_file(path);
+
+	Ok(())
+}
+
+// Store whose reads always fail, as when the backing service is unreachable.
+#[derive(Debug)]
+struct UnreadableStore;
+#[async_trait]
+impl SnapshotStore for UnreadableStore {
+	async fn persist(&self, _: &[PersistentSnapshot]) -> Result<()> {
+		Ok(())
+	}
+
+	async fn load(&self, _: &str, _: &str) -> Result<Option<PersistentSnapshot>> {
+		Err(Error::Cache("snapshot store offline".into()))
+	}
+
+	async fn delete(&self, _: &str, _: &str) -> Result<bool> {
+		Ok(false)
+	}
+
+	async fn list(&self) -> Result<Vec<SnapshotKey>> {
+		Ok(Vec::new())
+	}
+}
+
+#[tokio::test]
+async fn reload_applies_every_entry_when_snapshot_restores_fail() -> Result<()> {
+	let registry = Registry::builder().with_snapshot_store(Arc::new(UnreadableStore)).build();
+	let path = write_config("restore", &[registration("tenant-old", "idp")]);
+
+	registry.load_config(&path).await?;
+
+	let path =
+		write_config("restore", &[registration("tenant-a", "idp"), registration("tenant-b", "idp")]);
+	let report = registry.load_config(&path).await?;
+
+	assert_eq!(
+		report.added,
+		vec![("tenant-a".into(), "idp".into()), ("tenant-b".into(), "idp".into())]
+	);
+	assert_eq!(report.removed, vec![("tenant-old".into(), "idp".into())]);
+
+	// Both installed providers are still managed, so an empty file removes them.
+	let path = write_config("restore", &[]);
+	let report = registry.load_config(&path).await?;
+
+	assert_eq!(report.removed.len(), 2);
+	assert!(registry.all_statuses().await.is_empty());
+
+	let _ = fs::remove_file(path);
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn reload_rejects_invalid_file_without_applying() -> Result<()> {
+	let registry = Registry::new();
+	let mut invalid = registration("tenant-b", "idp");
+
+	invalid.max_redirects = 42;
+
+	let path = write_config("invalid", &[registration("tenant-a", "idp"), invalid]);
+	let err = registry.reload_from_path(&path).await.expect_err("invalid registration");
+
+	assert!(matches!(err, Error::Validation { field: "max_redirects", .. }));
+	assert!(registry.all_statuses().await.is_empty(), "nothing should be applied");
+
+	let _ = fs::remove_file(path);
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn reload_prepares_every_entry_before_applying() -> Result<()> {
+	let registry = Registry::new();
+	// Nothing listens on port 1, so discovery fails while the entry is prepared.
+	let unreachable = IdentityProviderRegistration::from_issuer(
+		"tenant-b",
+		"idp",
+		url::Url::parse("https://127.0.0.1:1/").expect("issuer"),
+	)?;
+	let path = write_config("prepare", &[registration("tenant-a", "idp"), unreachable]);
+
+	registry.reload_from_path(&path).await.expect_err("discovery fails");
+
+	assert!(registry.all_statuses().await.is_empty(), "nothing should be applied");
+
+	let _ = fs::remove_file(path);
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn watch_config_applies_changed_files() -> Result<()> {
+	let registry = Registry::new();
+	let path = write_config("watch", &[registration("tenant-a", "idp")]);
+
+	registry.reload_from_path(&path).await?;
+
+	let task = registry.watch_config(&path, std::time::Duration::from_millis(20));
+	let mut invalid = registration("tenant-c", "idp");
+
+	invalid.max_redirects = 42;
+
+	write_config("watch", &[registration("tenant-a", "idp"), registration("tenant-b", "idp")]);
+
+	for _ in 0..50 {
+		if registry.effective_registration("tenant-b", "idp").await.is_ok() {
+			break;
+		}
+
+		tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+	}
+
+	assert!(registry.effective_registration("tenant-b", "idp").await.is_ok());
+
+	write_config("watch", &[invalid]);
+	tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+	assert!(
+		registry.effective_registration("tenant-b", "idp").await.is_ok(),
+		"a rejected change keeps the current registrations"
+	);
+	assert!(registry.effective_registration("tenant-c", "idp").await.is_err());
+
+	task.abort();
+
+	let _ = fs::remove_file(path);
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn watch_config_retries_transient_failures() -> Result<()> {
+	let server = MockServer::start().await;
+	let issuer = format!("{}/realms/acme", server.uri());
+
+	Mock::given(method("GET"))
+		.and(path("/realms/acme/.well-known/openid-configuration"))
+		.respond_with(ResponseTemplate::new(503))
+		.mount(&server)
+		.await;
+
+	let registry = Registry::builder().require_https(false).build();
+	let config = write_config("retry", &[]);
+
+	registry.reload_from_path(&config).await?;
+
+	let task = registry.watch_config(&config, std::time::Duration::from_millis(20));
+	let mut discovered = IdentityProviderRegistration::from_issuer("tenant-a", "oidc", &issuer)?;
+
+	discovered.require_https = false;
+	discovered.retry_policy.max_retries = 0;
+
+	write_config("retry", &[discovered]);
+	tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+	assert!(registry.effective_registration("tenant-a", "oidc").await.is_err());
+
+	// The identity provider recovers without the file changing again.
+	server.reset().await;
+	Mock::given(method("GET"))
+		.and(path("/realms/acme/.well-known/openid-configuration"))
+		.respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+			"issuer": issuer,
+			"jwks_uri": format!("{issuer}/protocol/keys"),
+		})))
+		.mount(&server)
+		.await;
+
+	for _ in 0..50 {
+		if registry.effective_registration("tenant-a", "oidc").await.is_ok() {
+			break;
+		}
+
+		tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+	}
+
+	assert!(registry.effective_registration("tenant-a", "oidc").await.is_ok());
+
+	task.abort();
+
+	let _ = fs::remove_file(config);
+
+	Ok(())
+}
+
+#[test]
+fn example_registration_file_is_valid() -> Result<()> {
+	let file = RegistrationFile::from_path(
+		PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples/registrations.json"),
+	)?;
+
+	assert_eq!(file.registrations.len(), 3);
+
+	for registration in &file.registrations {
+		registration.validate()?;
+	}
+
+	Ok(())
+}
+
+#[cfg(all(feature = "toml", feature = "yaml"))]
+#[tokio::test]
+async fn reload_parses_toml_and_yaml_by_extension() -> Result<()> {
+	let registry = Registry::new();
+	let toml_path =
+		std::env::temp_dir().join(format!("jwks-cache-formats-{}.toml", std::process::id()));
+	let yaml_path =
+		std::env::temp_dir().join(format!("jwks-cache-formats-{}.yaml", std::process::id()));
+
+	fs::write(
+		&toml_path,
+		r#"
+[[registrations]]
+tenant_id = "tenant-a"
+provider_id = "idp"
+jwks_url = "https://tenant-a.example.com/.well-known/jwks.json"
+min_keys = 2
+"#,
+	)?;
+	fs::write(
+		&yaml_path,
+		r#"
+registrations:
+  - tenant_id: tenant-b
+    provider_id: idp
+    jwks_url: https://tenant-b.example.com/.well-known/jwks.json
+"#,
+	)?;
+
+	let report = registry.reload_from_path(&toml_path).await?;
+
+	assert_eq!(report.added, vec![("tenant-a".into(), "idp".into())]);
+	assert_eq!(registry.effective_registration("tenant-a", "idp").await?.min_keys, 2);
+
+	let report = registry.reload_from_path(&yaml_path).await?;
+
+	assert_eq!(report.added, vec![("tenant-b".into(), "idp".into())]);
+	assert_eq!(report.removed, vec![("tenant-a".into(), "idp".into())]);
+
+	fs::write(&toml_path, "registrations = 42")?;
+
+	let err = registry.reload_from_path(&toml_path).await.expect_err("malformed TOML");
+
+	assert_eq!(err.code(), "JWKS_CACHE_INVALID_CONFIG");
+
+	let _ = fs::remove_file(toml_path);
+	let _ = fs::remove_file(yaml_path);
+
+	Ok(())
+}
+
+#[cfg(all(feature = "toml", feature = "yaml"))]
+#[tokio::test]
+async fn config_file_interpolates_env_and_parses_humantime() -> Result<()> {
+	use std::time::Duration;
+
+	use jwks_cache::{RegistryConfigFile, StatusFormat};
+
+	// Cargo sets `CARGO_PKG_NAME` for test binaries, so it is always available here.
+	let toml_path =
+		std::env::temp_dir().join(format!("jwks-cache-profile-{}.toml", std::process::id()));
+	let yaml_path =
+		std::env::temp_dir().join(format!("jwks-cache-profile-{}.yaml", std::process::id()));
+
+	fs::write(
+		&toml_path,
+		r#"
+[registry]
+default_refresh_early = "3m"
+status_format = "v1"
+allowed_domains = ["${CARGO_PKG_NAME}.example.com"]
+
+[[registrations]]
+tenant_id = "tenant-a"
+provider_id = "idp"
+jwks_url = "https://${CARGO_PKG_NAME}.example.com/${JWKS_PATH:-keys}"
+stale_while_error = "10m"
+prefetch_jitter = "1s 500ms"
+"#,
+	)?;
+
+	let config = RegistryConfigFile::from_path(&toml_path)?;
+
+	assert_eq!(config.registry.default_refresh_early, Some(Duration::from_secs(180)));
+
+	let registry = config.build().await?;
+	let registration = registry.effective_registration("tenant-a", "idp").await?;
+
+	assert_eq!(registry.status_format(), StatusFormat::V1);
+	assert_eq!(registration.jwks_url.as_str(), "https://jwks-cache.example.com/keys");
+	assert_eq!(registration.stale_while_error, Some(Duration::from_secs(600)));
+	assert_eq!(registration.prefetch_jitter, Duration::from_millis(1_500));
+	assert_eq!(registry.reload_from_path(&toml_path).await?.unchanged, 1);
+
+	fs::write(
+		&yaml_path,
+		r#"
+registrations:
+  - tenant_id: tenant-b
+    provider_id: idp
+    jwks_url: https://${JWKS_CACHE_UNSET_TEST_HOST}/keys
+"#,
+	)?;
+
+	let err = RegistryConfigFile::from_path(&yaml_path).expect_err("unset variable");
+
+	assert!(matches!(err, Error::Validation { field: "env", .. }), "{err:?}");
+
+	let _ = fs::remove_file(toml_path);
+	let _ = fs::remove_file(yaml_path);
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn reload_triggers_apply_the_file() -> Result<()> {
+	let registry = Registry::new();
+	let path = write_config("trigger", &[registration("tenant-a", "idp")]);
+	let (trigger, triggers) = tokio::sync::mpsc::unbounded_channel();
+	let task = registry.reload_on(
+		&path,
+		futures_util::stream::unfold(triggers, |mut triggers| async move {
+			triggers.recv().await.map(|()| ((), triggers))
+		}),
+	);
+
+	assert!(registry.effective_registration("tenant-a", "idp").await.is_err());
+
+	trigger.send(()).expect("send trigger");
+
+	for _ in 0..50 {
+		if registry.effective_registration("tenant-a", "idp").await.is_ok() {
+			break;
+		}
+
+		tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+	}
+
+	assert!(registry.effective_registration("tenant-a", "idp").await.is_ok());
+
+	// Closing the trigger stream ends the reload task.
+	drop(trigger);
+	task.await.expect("reload task");
+
+	let _ = fs::remove_file(path);
+
+	Ok(())
+}
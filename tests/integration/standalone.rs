@@ -0,0 +1,66 @@
+//! Integration coverage for the single-provider cache facade.
+
+// crates.io
+use jwks_cache::{Error, JwksCache, JwksCacheOptions, ProviderState, Result, STANDALONE_ID};
+use wiremock::{
+	Mock, MockServer, ResponseTemplate,
+	matchers::{method, path},
+};
+
+const JWKS_BODY: &str = r#"{
+    "keys": [
+        {
+            "kty": "RSA",
+            "alg": "RS256",
+            "use": "sig",
+            "kid": "primary",
+            "n": "AQIDBAUGBwgJCgsMDQ4PEBESExQVFhcYGRobHB0eHyAhIiMkJSYnKCkqKywtLi8wMTIzNDU2Nzg5Ojs8PT4_QEFCQ0RFRkdISUpLTE1OT1BRUlNUVVZXWFlaW1xdXl9gYWJjZGVmZ2hpamtsbW5vcHFyc3R1dnd4eXp7fH1-f4A",
+            "e": "AQAB"
+        }
+    ]
+}"#;
+
+#[tokio::test]
+async fn standalone_cache_serves_keys_without_a_registry() -> Result<()> {
+	let server = MockServer::start().await;
+
+	Mock::given(method("GET"))
+		.and(path("/jwks.json"))
+		.respond_with(
+			ResponseTemplate::new(200)
+				.set_body_string(JWKS_BODY)
+				.insert_header("content-type", "application/json")
+				.insert_header("cache-control", "public, max-age=60"),
+		)
+		.expect(2)
+		.mount(&server)
+		.await;
+
+	let cache = JwksCache::new(
+		format!("{}/jwks.json", server.uri()),
+		JwksCacheOptions::default().with_require_https(false),
+	)?;
+	let keys = cache.get().await?;
+
+	assert_eq!(keys.keys.len(), 1);
+
+	let key = cache.clone().get_key("primary").await?;
+
+	assert_eq!(key.common.key_id.as_deref(), Some("primary"));
+
+	cache.refresh().await?;
+
+	let status = cache.status().await;
+
+	assert_eq!(status.state, ProviderState::Ready);
+	assert_eq!(status.tenant_id, STANDALONE_ID);
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn standalone_cache_rejects_plain_http_by_default() {
+	let result = JwksCache::new("http://idp.example.com/jwks.json", JwksCacheOptions::default());
+
+	assert!(matches!(result, Err(Error::Security(_))));
+}
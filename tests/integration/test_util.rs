@@ -0,0 +1,57 @@
+//! Integration coverage for the `test-util` mock provider.
+
+// std
+use std::time::Duration;
+// crates.io
+use jsonwebtoken::{Algorithm, Validation};
+use jwks_cache::{
+	Registry, Result,
+	test_util::{MockJwksProvider, MockResponse},
+};
+use serde_json::Value;
+
+async fn verify(registry: &Registry, token: &str) -> Result<Value> {
+	let kid = jsonwebtoken::decode_header(token)?.kid.expect("mock tokens carry a kid");
+	let key = registry.decoding_key("tenant-a", "idp", &kid).await?;
+	let mut validation = Validation::new(Algorithm::HS256);
+
+	validation.required_spec_claims.clear();
+
+	Ok(jsonwebtoken::decode::<Value>(token, &key, &validation)?.claims)
+}
+
+#[tokio::test]
+async fn mock_provider_signs_rotates_and_injects_failures() -> Result<()> {
+	let idp = MockJwksProvider::start().await;
+	let registry = Registry::builder().require_https(false).build();
+
+	idp.register(&registry, "tenant-a", "idp").await?;
+
+	let before = idp.sign(&serde_json::json!({ "sub": "alice" }))?;
+
+	assert_eq!(verify(&registry, &before).await?["sub"], "alice");
+	assert_eq!(idp.request_count().await, 1);
+
+	// An unknown kid forces a revalidation; the first attempt fails and the retry succeeds.
+	let rotated = idp.rotate();
+
+	idp.enqueue(MockResponse::Status(503));
+
+	let after = idp.sign(&serde_json::json!({ "sub": "bob" }))?;
+
+	assert_eq!(jsonwebtoken::decode_header(&after)?.kid.as_deref(), Some(rotated.as_str()));
+	assert_eq!(verify(&registry, &after).await?["sub"], "bob");
+	assert_eq!(verify(&registry, &before).await?["sub"], "alice", "previous key stays published");
+	assert_eq!(idp.request_count().await, 3);
+	assert_eq!(idp.jwks().keys.len(), 2);
+
+	idp.enqueue(MockResponse::Delayed(Duration::from_millis(50)));
+	idp.enqueue(MockResponse::NotModified);
+	registry.force_refresh("tenant-a", "idp").await?;
+	registry.force_refresh("tenant-a", "idp").await?;
+
+	assert_eq!(idp.request_count().await, 5);
+	assert_eq!(registry.resolve("tenant-a", "idp", None).await?.keys.len(), 2);
+
+	Ok(())
+}
@@ -0,0 +1,173 @@
+//! Integration tests for per-provider TLS trust, SPKI pinning, and mutual TLS.
+
+// std
+use std::sync::Arc;
+// crates.io
+use base64::{Engine, engine::general_purpose::STANDARD};
+use jwks_cache::{
+	IdentityProviderRegistration, Registry, Result,
+	security::{self, ClientIdentity, SpkiFingerprint},
+};
+use rustls::{
+	RootCertStore, ServerConfig,
+	crypto::ring,
+	pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+	server::WebPkiClientVerifier,
+};
+use tokio::{
+	io::{AsyncReadExt, AsyncWriteExt},
+	net::TcpListener,
+};
+use tokio_rustls::TlsAcceptor;
+
+const CA_PEM: &str = include_str!("../fixtures/tls/ca.pem");
+const LEAF_DER: &[u8] = include_bytes!("../fixtures/tls/leaf.der");
+const LEAF_KEY: &str = include_str!("../fixtures/tls/leaf.key");
+const CLIENT_CA_PEM: &str = include_str!("../fixtures/tls/client-ca.pem");
+const CLIENT_PEM: &str = include_str!("../fixtures/tls/client.pem");
+const CLIENT_KEY: &str = include_str!("../fixtures/tls/client.key");
+const JWKS_BODY: &str = r#"{"keys":[{"kty":"oct","kid":"private-ca","k":"c2VjcmV0"}]}"#;
+
+// Serve the JWKS over TLS with a certificate issued by the private test CA, optionally requiring a
+// client certificate issued by the client test CA.
+async fn spawn_private_ca_server(require_client_cert: bool) -> String {
+	let provider = Arc::new(ring::default_provider());
+	let key = PrivateKeyDer::from_pem_slice(LEAF_KEY.as_bytes()).expect("valid test key");
+	let builder = ServerConfig::builder_with_provider(provider.clone())
+		.with_safe_default_protocol_versions()
+		.expect("protocol versions");
+	let builder = if require_client_cert {
+		let mut roots = RootCertStore::empty();
+
+		roots
+			.add(CertificateDer::from_pem_slice(CLIENT_CA_PEM.as_bytes()).expect("client CA"))
+			.expect("client CA trust anchor");
+
+		builder.with_client_cert_verifier(
+			WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+				.build()
+				.expect("client verifier"),
+		)
+	} else {
+		builder.with_no_client_auth()
+	};
+	let config = builder
+		.with_single_cert(vec![CertificateDer::from(LEAF_DER)], key)
+		.expect("server certificate");
+	let acceptor = TlsAcceptor::from(Arc::new(config));
+	let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind listener");
+	let port = listener.local_addr().expect("local address").port();
+
+	tokio::spawn(async move {
+		while let Ok((stream, _)) = listener.accept().await {
+			let acceptor = acceptor.clone();
+
+			tokio::spawn(async move {
+				// Failed handshakes are expected when the client rejects the certificate.
+				let Ok(mut stream) = acceptor.accept(stream).await else {
+					return;
+				};
+				let mut request = Vec::new();
+				let mut buffer = [0; 1024];
+
+				while !request.ends_with(b"\r\n\r\n") {
+					match stream.read(&mut buffer).await {
+						Ok(0) | Err(_) => return,
+						Ok(read) => request.extend_from_slice(&buffer[..read]),
+					}
+				}
+
+				let response = format!(
+					"HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncache-control: max-age=300\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{JWKS_BODY}",
+					JWKS_BODY.len()
+				);
+				let _ = stream.write_all(response.as_bytes()).await;
+				let _ = stream.shutdown().await;
+			});
+		}
+	});
+
+	format!("https://localhost:{port}/jwks.json")
+}
+
+fn leaf_pin() -> SpkiFingerprint {
+	let leaf = CertificateDer::from(LEAF_DER);
+	let spki =
+		webpki::EndEntityCert::try_from(&leaf).expect("leaf parses").subject_public_key_info();
+
+	SpkiFingerprint::from_b64(&STANDARD.encode(security::fingerprint_spki(spki.as_ref())))
+		.expect("valid fingerprint")
+}
+
+#[tokio::test]
+async fn private_ca_roots_are_trusted_per_registration() -> Result<()> {
+	let jwks_url = spawn_private_ca_server(false).await;
+	let registry = Registry::builder().build();
+
+	registry
+		.register(IdentityProviderRegistration::new("tenant-a", "public-roots", &jwks_url)?)
+		.await?;
+	registry
+		.register(
+			IdentityProviderRegistration::new("tenant-a", "private-ca", &jwks_url)?
+				.with_trusted_root(CA_PEM),
+		)
+		.await?;
+
+	assert!(registry.resolve("tenant-a", "public-roots", None).await.is_err());
+
+	let jwks = registry.resolve("tenant-a", "private-ca", None).await?;
+
+	assert_eq!(jwks.keys[0].common.key_id.as_deref(), Some("private-ca"));
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn spki_pins_are_enforced_during_the_handshake() -> Result<()> {
+	let jwks_url = spawn_private_ca_server(false).await;
+	let registry = Registry::builder().build();
+	let mut pinned = IdentityProviderRegistration::new("tenant-a", "pinned", &jwks_url)?
+		.with_trusted_root(CA_PEM);
+	let mut mismatched = IdentityProviderRegistration::new("tenant-a", "mismatched", &jwks_url)?
+		.with_trusted_root(CA_PEM);
+
+	pinned.pinned_spki = vec![leaf_pin()];
+	mismatched.pinned_spki = vec![SpkiFingerprint::from_b64(&STANDARD.encode([7u8; 32]))?];
+
+	registry.register(pinned).await?;
+	registry.register(mismatched).await?;
+
+	assert!(registry.resolve("tenant-a", "pinned", None).await.is_ok());
+	assert!(registry.resolve("tenant-a", "mismatched", None).await.is_err());
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn client_identities_satisfy_mutual_tls() -> Result<()> {
+	let jwks_url = spawn_private_ca_server(true).await;
+	let registry = Registry::builder().build();
+
+	registry
+		.register(
+			IdentityProviderRegistration::new("tenant-a", "anonymous", &jwks_url)?
+				.with_trusted_root(CA_PEM),
+		)
+		.await?;
+	registry
+		.register(
+			IdentityProviderRegistration::new("tenant-a", "mtls", &jwks_url)?
+				.with_trusted_root(CA_PEM)
+				.with_client_identity(ClientIdentity::from_pem(CLIENT_PEM, CLIENT_KEY)?),
+		)
+		.await?;
+
+	assert!(registry.resolve("tenant-a", "anonymous", None).await.is_err());
+
+	let jwks = registry.resolve("tenant-a", "mtls", None).await?;
+
+	assert_eq!(jwks.keys[0].common.key_id.as_deref(), Some("private-ca"));
+
+	Ok(())
+}